@@ -0,0 +1,72 @@
+/* The C interface to one-lie, declared from src/ffi.rs, which documents each
+ * function in full. Build the crate as a cdylib or staticlib and link it.
+ *
+ *     OneLieGame *game = one_lie_new(0, 100, 1);
+ *     int64_t guess;
+ *     char question[32];
+ *     while (one_lie_result(game, NULL) == ONE_LIE_ONGOING) {
+ *         one_lie_best_question(game, &guess);
+ *         snprintf(question, sizeof question, "%lld", (long long)guess);
+ *         one_lie_guess(game, question);
+ *     }
+ *     one_lie_free(game);
+ */
+
+#ifndef ONE_LIE_H
+#define ONE_LIE_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Answers. To a guess, yes means the number is less than it. */
+#define ONE_LIE_YES 0
+#define ONE_LIE_NO 1
+#define ONE_LIE_EQUAL 2
+#define ONE_LIE_PASS 3
+/* A call failed, for the reason one_lie_error gives. */
+#define ONE_LIE_ERROR (-1)
+
+/* How a game stands. */
+#define ONE_LIE_ONGOING 0
+#define ONE_LIE_FINISHED 1
+#define ONE_LIE_IMPOSSIBLE 2
+#define ONE_LIE_LOST 3
+
+typedef struct OneLieGame OneLieGame;
+
+/* A game of guessing a number in lower..limit with up to lies lies, answered by
+ * the adversary, or NULL if there are fewer than two numbers or more than 1000
+ * lies. */
+OneLieGame *one_lie_new(int64_t lower, int64_t limit, uint32_t lies);
+void one_lie_free(OneLieGame *game);
+/* Plays under a variant such as "three-way", before the first question,
+ * returning 0 if it exists. */
+int one_lie_set_variant(OneLieGame *game, const char *variant);
+/* Has "adversarial", "minimax", "expected" or "random" answer from now on, the
+ * random opponent picking its number and lies from seed. Returns 0 if it exists. */
+int one_lie_set_opponent(OneLieGame *game, const char *name, uint64_t seed);
+
+/* Asks the adversary a question, such as "37" or "=42", returning its answer. */
+int one_lie_guess(OneLieGame *game, const char *question);
+/* Records your own answer to a question, returning 0 if it fits the rules. */
+int one_lie_respond(OneLieGame *game, const char *question, int response);
+/* How the game stands, writing the number to number, if not NULL, once found. */
+int one_lie_result(const OneLieGame *game, int64_t *number);
+/* Writes the best number to guess next, returning how many more questions it
+ * guarantees finishing in. */
+int one_lie_best_question(OneLieGame *game, int64_t *guess);
+/* What the number could still be, and the questions and answers so far, as
+ * JSON text valid until the next call returning text. */
+const char *one_lie_possibilities(OneLieGame *game);
+const char *one_lie_history(OneLieGame *game);
+/* Why the last failed call on game did, valid until the next call. */
+const char *one_lie_error(const OneLieGame *game);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
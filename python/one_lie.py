@@ -0,0 +1,156 @@
+"""Python access to the one-lie engine, for scripting experiments.
+
+The engine runs in-process: this module loads the library built as a cdylib
+with ctypes and calls the C interface include/one_lie.h declares, so the lie
+logic, the opponents and the solver are the engine's own. It needs only the
+standard library and the built library, named with `library`, with the
+ONE_LIE_LIBRARY environment variable, or found in target/release beside this
+directory. Build it from the crate with
+
+    cargo rustc --lib --release --crate-type cdylib
+
+    >>> game = Game(100, lies=1)
+    >>> game.guess("50")
+    'Low'
+    >>> game.best_question()
+    '28'
+    >>> game.possibilities()  # doctest: +ELLIPSIS
+    [{'range': {'lower': 50, 'higher': 100}, 'lies': [0]}, ...]
+"""
+
+import ctypes
+import json
+import os
+import sys
+
+YES, NO, EQUAL, PASS, ERROR = 0, 1, 2, 3, -1
+ONGOING, FINISHED, IMPOSSIBLE, LOST = 0, 1, 2, 3
+
+# Answers as respond takes them, and the letters transcripts end moves with
+RESPONSES = {
+    "yes": YES, "y": YES, "low": YES, "l": YES,
+    "no": NO, "n": NO, "high": NO, "h": NO,
+    "equal": EQUAL, "e": EQUAL,
+    "pass": PASS, "p": PASS,
+}
+
+_library = None
+
+
+class EngineError(Exception):
+    """A call the engine refused, with its reason."""
+
+
+def load(library=None):
+    """Loads the one-lie library, once, from `library` if given."""
+    global _library
+    if _library is not None:
+        return _library
+    if library is None:
+        library = os.environ.get("ONE_LIE_LIBRARY")
+    if library is None:
+        name = {"darwin": "libone_lie.dylib", "win32": "one_lie.dll"}.get(sys.platform, "libone_lie.so")
+        library = os.path.join(os.path.dirname(os.path.abspath(__file__)), "..", "target", "release", name)
+    lib = ctypes.CDLL(library)
+    game = ctypes.c_void_p
+    for name, result, arguments in [
+        ("one_lie_new", game, [ctypes.c_int64, ctypes.c_int64, ctypes.c_uint32]),
+        ("one_lie_free", None, [game]),
+        ("one_lie_set_variant", ctypes.c_int, [game, ctypes.c_char_p]),
+        ("one_lie_set_opponent", ctypes.c_int, [game, ctypes.c_char_p, ctypes.c_uint64]),
+        ("one_lie_guess", ctypes.c_int, [game, ctypes.c_char_p]),
+        ("one_lie_respond", ctypes.c_int, [game, ctypes.c_char_p, ctypes.c_int]),
+        ("one_lie_result", ctypes.c_int, [game, ctypes.POINTER(ctypes.c_int64)]),
+        ("one_lie_best_question", ctypes.c_int, [game, ctypes.POINTER(ctypes.c_int64)]),
+        ("one_lie_possibilities", ctypes.c_char_p, [game]),
+        ("one_lie_history", ctypes.c_char_p, [game]),
+        ("one_lie_error", ctypes.c_char_p, [game]),
+    ]:
+        function = getattr(lib, name)
+        function.restype = result
+        function.argtypes = arguments
+    _library = lib
+    return lib
+
+
+class Game:
+    """A game of guessing a number at least `lower` and less than `limit`, with
+    up to `lies` lies, under the `variant` named as on the command line. Any
+    `moves` already made are written as in transcripts, such as "50L".
+
+    `opponent` answers guess: adversarial, minimax, expected, or random, which
+    picks its number and its lies from `seed`. best_question asks the solver.
+    """
+
+    def __init__(self, limit, lower=0, lies=1, variant="standard", moves=(),
+                 opponent="adversarial", seed=0, library=None):
+        self._lib = load(library)
+        self._game = self._lib.one_lie_new(lower, limit, lies)
+        if not self._game:
+            raise EngineError("A game needs at least two numbers and at most 1000 lies")
+        self._check(self._lib.one_lie_set_variant(self._game, variant.encode()))
+        self._check(self._lib.one_lie_set_opponent(self._game, opponent.encode(), seed))
+        for move in moves:
+            self.respond(move[:-1], move[-1])
+
+    def _check(self, code):
+        if code == ERROR:
+            raise EngineError(self._lib.one_lie_error(self._game).decode())
+        return code
+
+    def guess(self, question):
+        """Asks the opponent `question`, such as 37 or "=42", returning its answer:
+        Low or High for "less than" questions, Low, Equal or High for
+        comparisons, and Yes or No for the rest."""
+        self._check(self._lib.one_lie_guess(self._game, str(question).encode()))
+        return self.history()[-1][1]
+
+    def respond(self, question, response):
+        """Records your own answer to `question`, such as "low", "high", "y" or "n"."""
+        code = RESPONSES.get(str(response).lower())
+        if code is None:
+            raise EngineError("{} isn't an answer".format(response))
+        self._check(self._lib.one_lie_respond(self._game, str(question).encode(), code))
+
+    def best_question(self):
+        """The question the solver would ask next, or None once the game is over."""
+        if self.status() != "Ongoing":
+            return None
+        guess = ctypes.c_int64()
+        self._check(self._lib.one_lie_best_question(self._game, ctypes.byref(guess)))
+        return str(guess.value)
+
+    def possibilities(self):
+        """What the number could still be: each range, with the questions that
+        were lies if the number is in it."""
+        return json.loads(self._lib.one_lie_possibilities(self._game))
+
+    def history(self):
+        """The questions asked so far, each with its answer."""
+        return json.loads(self._lib.one_lie_history(self._game))
+
+    def status(self):
+        """Ongoing, Finished, Impossible or Lost."""
+        return ["Ongoing", "Finished", "Impossible", "Lost"][self._lib.one_lie_result(self._game, None)]
+
+    def finished(self):
+        """The number, once it is pinned down, or else None."""
+        number = ctypes.c_int64()
+        if self._lib.one_lie_result(self._game, ctypes.byref(number)) == FINISHED:
+            return number.value
+        return None
+
+    def close(self):
+        """Frees the game, as leaving a with block does."""
+        if getattr(self, "_game", None):
+            self._lib.one_lie_free(self._game)
+            self._game = None
+
+    def __del__(self):
+        self.close()
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *exc):
+        self.close()
@@ -0,0 +1,305 @@
+//! An HTTP API for playing games against the computer's opponent, for web
+//! frontends and other services. Each game is kept on the server, under an id
+//! handed out when it is created:
+//!
+//! ```text
+//!   POST   /games                      Starts a game, replying with its id. The
+//!                                      body may give its limit, lower, lies and
+//!                                      variant, or else it is the command line's
+//!   GET    /games/{id}                 The game so far, and how it stands
+//!   POST   /games/{id}/guess           Asks the body's question, as in
+//!                                      {"question": "=42"}, replying with the answer
+//!   GET    /games/{id}/possibilities   What the number could still be
+//!   DELETE /games/{id}                 Forgets the game
+//! ```
+//!
+//! Every reply is a JSON object, with an error field saying what went wrong if
+//! the status isn't 200 or 201. Requests are handled one at a time, as the
+//! opponents share one search. A game left unplayed for an hour is forgotten, as
+//! is the one played least recently once there are 1000.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use crate::engine::Rules;
+use crate::json::{possibilities_to_json, response_to_json, Json, ToJson};
+use crate::setup::{make_opponent, OpponentSetup};
+use crate::{GameResult, GameState, Opponent, Question, Variant};
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: String,
+}
+
+// How long a request may take to arrive, how long each of its lines may be, and
+// how many headers it may have, so a client can't hold the server up or fill its
+// memory with a request that never ends.
+const REQUEST_TIME: Duration = Duration::from_secs(10);
+const MAX_LINE: u64 = 8192;
+const MAX_HEADERS: usize = 100;
+
+// The next line of the request, with its line ending, or empty at the end.
+fn read_line(reader: &mut BufReader<&TcpStream>) -> Result<String, String> {
+    let mut line = String::new();
+    reader.by_ref().take(MAX_LINE).read_line(&mut line).map_err(|err| err.to_string())?;
+    if line.len() as u64 == MAX_LINE && !line.ends_with('\n') {
+        return Err(format!("A line of the request is over {} bytes", MAX_LINE));
+    }
+    Ok(line)
+}
+
+pub fn read_request(stream: &TcpStream) -> Result<Request, String> {
+    let started = Instant::now();
+    let mut reader = BufReader::new(stream);
+    let line = read_line(&mut reader)?;
+    let mut words = line.split_whitespace();
+    let (method, path) = match (words.next(), words.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return Err("Not an HTTP request".to_string()),
+    };
+    let mut length = 0;
+    for headers in 0.. {
+        let line = read_line(&mut reader)?;
+        if line.is_empty() {
+            return Err("The connection closed during the headers".to_string());
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if headers == MAX_HEADERS {
+            return Err(format!("The request has over {} headers", MAX_HEADERS));
+        }
+        // Each read has its own timeout, but a client could still send one byte at a time
+        if started.elapsed() > REQUEST_TIME {
+            return Err("The request took too long to arrive".to_string());
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().map_err(|_| format!("Bad Content-Length {}", value.trim()))?;
+            }
+        }
+    }
+    // Nothing the API is sent needs more than this
+    if length > 1 << 20 {
+        return Err("The body is too long".to_string());
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|err| err.to_string())?;
+    let body = String::from_utf8(body).map_err(|_| "The body isn't UTF-8".to_string())?;
+    // Any query string is ignored
+    let path = path.split('?').next().unwrap_or_default().to_string();
+    Ok(Request { method, path, body })
+}
+
+// The page web serves at /, which plays through the API.
+const PAGE: &str = include_str!("web.html");
+
+pub fn write_response(mut stream: &TcpStream, status: u16, content_type: &str, body: &str) {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Conflict",
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        content_type,
+        body.len(),
+        body
+    );
+}
+
+// A reply's status and body, or an error's status and why.
+type Reply = Result<(u16, Json), (u16, String)>;
+
+fn bad_request(err: impl ToString) -> (u16, String) {
+    (400, err.to_string())
+}
+
+/// The request's body, as an object, with no body at all taken as an empty one.
+pub fn body_json(request: &Request) -> Result<Json, (u16, String)> {
+    if request.body.trim().is_empty() {
+        return Ok(Json::Object(vec![]));
+    }
+    match Json::parse(&request.body).map_err(bad_request)? {
+        json @ Json::Object(_) => Ok(json),
+        _ => Err(bad_request("The body must be a JSON object")),
+    }
+}
+
+// How many games are kept at once, and how long one may go unplayed before it is
+// forgotten, so clients that never delete their games can't fill the memory.
+const MAX_SESSIONS: usize = 1000;
+const SESSION_IDLE: Duration = Duration::from_secs(60 * 60);
+
+struct Session {
+    game: GameState,
+    opponent: Box<dyn Opponent>,
+    // When a request last used the game
+    used: Instant,
+}
+
+struct Api {
+    rules: Rules,
+    setup: OpponentSetup,
+    sessions: BTreeMap<u64, Session>,
+    next_id: u64,
+}
+
+impl Api {
+    fn session(&mut self, id: &str) -> Result<(u64, &mut Session), (u16, String)> {
+        let not_found = || (404, format!("There's no game {}", id));
+        let id = id.parse().map_err(|_| not_found())?;
+        let session = self.sessions.get_mut(&id).ok_or_else(not_found)?;
+        session.used = Instant::now();
+        Ok((id, session))
+    }
+
+    // Makes room for another game, forgetting those left unplayed too long, and
+    // the least recently played if there are still too many.
+    fn make_room(&mut self) {
+        self.sessions.retain(|_, session| session.used.elapsed() < SESSION_IDLE);
+        if self.sessions.len() >= MAX_SESSIONS {
+            let oldest = self.sessions.iter().min_by_key(|(_, session)| session.used).map(|(&id, _)| id);
+            self.sessions.retain(|&id, _| Some(id) != oldest);
+        }
+    }
+
+    fn create(&mut self, body: &Json) -> Reply {
+        let mut rules = self.rules;
+        if let Some(limit) = body.get("limit") {
+            rules.limit = limit.as_number().map_err(bad_request)?;
+        }
+        if let Some(lower) = body.get("lower") {
+            rules.lower = lower.as_number().map_err(bad_request)?;
+        }
+        if let Some(lies) = body.get("lies") {
+            rules.lies = lies.as_u64().map_err(bad_request)? as usize;
+        }
+        if let Some(variant) = body.get("variant") {
+            rules.variant = Variant::parse(variant.as_str().map_err(bad_request)?).map_err(bad_request)?;
+        }
+        rules.validate().map_err(bad_request)?;
+        rules.check_players(None, Some(self.setup.kind)).map_err(bad_request)?;
+        let game = rules.game();
+        let mut setup = self.setup.clone();
+        setup.resolve(&game).map_err(bad_request)?;
+        let opponent = make_opponent(&game, &setup);
+        let id = self.next_id;
+        self.next_id += 1;
+        let json = Json::object(vec![
+            ("id", Json::Int(i128::from(id))),
+            ("rules", Json::Str(format!("{}, {}", game.describe(), game.describe_range()))),
+            ("game", game.to_json()),
+            ("status", game.status().to_json()),
+        ]);
+        self.make_room();
+        self.sessions.insert(id, Session { game, opponent, used: Instant::now() });
+        Ok((201, json))
+    }
+
+    fn guess(&mut self, id: &str, body: &Json) -> Reply {
+        let (_, session) = self.session(id)?;
+        let game = &mut session.game;
+        let question = match body.field("question").map_err(bad_request)? {
+            Json::Int(guess) => game.guess(*guess),
+            json => match json.as_str().map_err(bad_request)?.parse::<Question>() {
+                // A number on its own asks whatever a guess asks in this game
+                Ok(Question::Less(guess)) => game.guess(guess),
+                Ok(question) => question,
+                Err(err) => return Err(bad_request(err)),
+            },
+        };
+        if game.status() != GameResult::Ongoing {
+            return Err((409, "The game is over".to_string()));
+        }
+        game.check_question(&question).map_err(bad_request)?;
+        let response = session.opponent.respond(game, &question);
+        game.ask(question.clone(), response).map_err(bad_request)?;
+        Ok((200, Json::object(vec![
+            ("question", Json::Int(game.history().len() as i128 - 1)),
+            ("guess", question.to_json()),
+            ("response", response_to_json(&question, response)),
+            ("answer", Json::Str(question.describe_answer(response))),
+            ("possibilities", possibilities_to_json(game)),
+            ("status", game.status().to_json()),
+        ])))
+    }
+
+    fn handle(&mut self, request: &Request) -> Reply {
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("POST", ["games"]) => self.create(&body_json(request)?),
+            ("GET", ["games", id]) => {
+                let (id, session) = self.session(id)?;
+                Ok((200, Json::object(vec![
+                    ("id", Json::Int(i128::from(id))),
+                    ("game", session.game.to_json()),
+                    ("status", session.game.status().to_json()),
+                ])))
+            }
+            ("DELETE", ["games", id]) => {
+                let (id, _) = self.session(id)?;
+                self.sessions.remove(&id);
+                Ok((200, Json::object(vec![("id", Json::Int(i128::from(id)))])))
+            }
+            ("POST", ["games", id, "guess"]) => self.guess(id, &body_json(request)?),
+            ("GET", ["games", id, "possibilities"]) => {
+                let (_, session) = self.session(id)?;
+                Ok((200, Json::object(vec![
+                    ("possibilities", possibilities_to_json(&session.game)),
+                    ("status", session.game.status().to_json()),
+                ])))
+            }
+            (_, ["games"] | ["games", _] | ["games", _, "guess" | "possibilities"]) => {
+                Err((405, format!("{} can't be used on {}", request.method, request.path)))
+            }
+            _ => Err((404, format!("There's nothing at {}", request.path))),
+        }
+    }
+}
+
+/// Listens on `port`, starting games under `rules` against `setup`'s opponent
+/// unless a request asks for others, and if `page`, serving the page to play them
+/// at /. Runs until the server is stopped.
+pub fn serve_api(rules: Rules, setup: OpponentSetup, port: u16, page: bool) -> Result<(), String> {
+    let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|err| format!("Couldn't listen on port {}: {}", port, err))?;
+    println!("Listening on port {} for games with {}, {}", port, rules.game().describe(), rules.game().describe_range());
+    if page {
+        println!("Play at http://localhost:{}/", port);
+    }
+    let mut api = Api { rules, setup, sessions: BTreeMap::new(), next_id: 1 };
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                println!("Couldn't accept a connection: {}", err);
+                continue;
+            }
+        };
+        // So a client that never finishes its request can't hold up everyone after it
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+        let request = read_request(&stream);
+        if let (true, Ok(Request { ref method, ref path, .. })) = (page, &request) {
+            if method == "GET" && path == "/" {
+                write_response(&stream, 200, "text/html; charset=utf-8", PAGE);
+                continue;
+            }
+        }
+        let json = match request.map_err(bad_request).and_then(|request| api.handle(&request)) {
+            Ok((status, json)) => (status, json),
+            Err((status, err)) => (status, Json::object(vec![("error", Json::Str(err))])),
+        };
+        write_response(&stream, json.0, "application/json", &format!("{}\n", json.1));
+    }
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! Non-adaptive play, where every question is asked before any answer comes back.
+//!
+//! This is searching with lies as coding theory sees it. Each number's truthful
+//! answers to the questions form a codeword, and the answers the opponent gives
+//! are that codeword with up to `max_lies` of its letters changed. The questions
+//! pin down the secret whatever the answers exactly when no two numbers' codewords
+//! can be changed into the same answers, and decoding is finding the one codeword
+//! the answers could have come from.
+
+use std::cmp::min;
+
+use crate::game::{GameState, Number, Question, Range, Response, Variant};
+
+/// Two numbers that some answers to a batch of questions, each with no more lies
+/// than the game allows, would fit equally well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Confusion {
+    pub first: Number,
+    pub second: Number,
+    /// How many of the questions the two numbers answer differently, told truthfully.
+    pub distance: usize,
+}
+
+/// The closest two numbers that the answers to `questions`, asked all at once at
+/// the start of `game`, can fail to tell apart, or None if every way the opponent
+/// can answer them pins down the secret. Each question should have passed
+/// [`GameState::check_question`], and the game should have a fixed number of lies.
+pub fn confusion(game: &GameState, questions: &[Question]) -> Option<Confusion> {
+    let range = game.range();
+    let mut bounds: Vec<Number> = questions.iter()
+        .flat_map(|question| question.bounds(range))
+        .filter(|&bound| bound > range.lower && bound < range.higher)
+        .collect();
+    bounds.push(range.lower);
+    bounds.push(range.higher);
+    bounds.sort();
+    bounds.dedup();
+    // Numbers between the same two bounds answer every question alike, so only one
+    // of each piece needs a codeword, unless there are two to confuse
+    let mut codewords: Vec<(Number, Vec<Response>)> = vec![];
+    for pair in bounds.windows(2) {
+        let piece = Range::new(pair[0], pair[1]);
+        if let (Some(first), Some(second)) = (game.nth(piece, 0), game.nth(piece, 1)) {
+            return Some(Confusion { first, second, distance: 0 });
+        }
+        if let Some(number) = game.nth(piece, 0) {
+            codewords.push((number, questions.iter().map(|question| question.truth(number)).collect()));
+        }
+    }
+    let mut closest: Option<Confusion> = None;
+    for (index, (first, first_answers)) in codewords.iter().enumerate() {
+        for (second, second_answers) in &codewords[index + 1..] {
+            let distance = first_answers.iter().zip(second_answers).filter(|(a, b)| a != b).count();
+            if closest.is_some_and(|closest| closest.distance <= distance) {
+                continue;
+            }
+            if can_meet(game, questions, first_answers, second_answers) {
+                closest = Some(Confusion { first: *first, second: *second, distance });
+            }
+        }
+    }
+    closest
+}
+
+// Whether some answers are within the allowed lies of both codewords. For each
+// number of lies told against the first, this tracks the fewest that must then be
+// told against the second, question by question.
+fn can_meet(game: &GameState, questions: &[Question], first: &[Response], second: &[Response]) -> bool {
+    let max_lies = game.max_lies();
+    let mut fewest: Vec<usize> = (0..=max_lies).map(|lies| if lies == 0 { 0 } else { usize::MAX }).collect();
+    for (index, ((question, &first_truth), &second_truth)) in questions.iter().zip(first).zip(second).enumerate() {
+        if first_truth == second_truth {
+            continue;
+        }
+        // In truthful-last games the last of the batch is the last answer
+        let last_truthful = game.variant() == Variant::TruthfulLast && index + 1 == questions.len();
+        let may_lie = |truth: Response| game.variant().allows_lie(truth) && game.in_lie_window(index) && !last_truthful;
+        let cost = |truth: Response, response: Response| match (truth == response, may_lie(truth)) {
+            (true, _) => Some(0),
+            (false, true) => Some(1),
+            (false, false) => None,
+        };
+        let mut next = vec![usize::MAX; max_lies + 1];
+        for &response in question.responses() {
+            if let (Some(first_cost), Some(second_cost)) = (cost(first_truth, response), cost(second_truth, response)) {
+                for (lies, &against_second) in fewest.iter().enumerate() {
+                    let told = lies + first_cost;
+                    if against_second != usize::MAX && told <= max_lies {
+                        next[told] = min(next[told], against_second + second_cost);
+                    }
+                }
+            }
+        }
+        fewest = next;
+    }
+    fewest.iter().any(|&lies| lies <= max_lies)
+}
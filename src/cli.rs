@@ -0,0 +1,1441 @@
+// Parses the command line into a Command, reporting any mistake as an error
+// message for main to print alongside the usage text.
+
+use std::fs;
+use std::str::FromStr;
+use std::time::Duration;
+
+use one_lie::continuous::Continuum;
+use one_lie::cost::CostModel;
+use one_lie::domain::{domain, Domain};
+use one_lie::engine::{describable, Rules};
+use one_lie::evolve::Evolution;
+use one_lie::frontend::Output;
+use one_lie::game::CONFIDENCE;
+use one_lie::grid::Grid;
+use one_lie::mastermind::{Code, Mastermind};
+use one_lie::plugin::{self, Plugin};
+use one_lie::setup::{read_model, read_script, OpponentKind, OpponentSetup, EXPLORATION, PLAYOUTS};
+use one_lie::tournament::{self, Tournament};
+use one_lie::twenty::ItemDatabase;
+use one_lie::value::value_function;
+use one_lie::words::Wordlist;
+use one_lie::{CandidateSet, Count, GameState, Number, Prior, Variant};
+
+pub const USAGE: &str = "\
+Usage: one-lie [play] [LIMIT] [OPTIONS]
+       one-lie solve [LIMIT] [OPTIONS]
+       one-lie simulate [LIMIT] [OPTIONS]
+       one-lie analyze <TRANSCRIPT>
+       one-lie replay <TRANSCRIPT>
+       one-lie bound <LIMIT> [--lies K] [--questions Q]
+       one-lie benchmark [--limits A,B,...] [OPTIONS]
+       one-lie tournament [--config FILE] [OPTIONS]
+       one-lie evolve [--population N] [--generations G] [OPTIONS]
+       one-lie train [--out FILE] [--episodes N] [OPTIONS]
+       one-lie export <LIMIT> [--lies K] [--out FILE]
+       one-lie verify <STRATEGY>
+       one-lie tree <LIMIT> [--lies K] [--depth D]
+       one-lie table [--lies K] [--questions Q]
+       one-lie mastermind [--pegs N] [--colours C] [OPTIONS]
+       one-lie twenty <DATABASE> [OPTIONS]
+       one-lie words <WORDLIST> [OPTIONS]
+       one-lie bisect <A..B> [--domain NAME] [OPTIONS]
+       one-lie engine [--rpc] [OPTIONS]
+       one-lie serve [LIMIT] [--port P] [--websocket] [OPTIONS]
+       one-lie api [LIMIT] [--port P] [OPTIONS]
+       one-lie web [LIMIT] [--port P] [OPTIONS]
+       one-lie grpc [--port P] [OPTIONS]
+
+Subcommands:
+  play       Guess the opponent's number (the default)
+  solve      Think of a number and let the computer find it
+  simulate   Have the computer guess against an automated opponent, over many games
+  analyze    Compare each question of a recorded game with the best one
+  replay     Step through a recorded game
+  bound      Work out how many questions a perfect guesser needs
+  benchmark  Compare the greedy guesser's value functions over a range of limits
+  tournament Play every guesser against every opponent over many games and
+             limits, tabulating how each pairing went
+  evolve     Breed value functions for the greedy guesser by self-play
+  train      Teach an opponent to answer by playing against itself, for
+             --opponent learned:FILE
+  export     Write out the perfect guesser's whole strategy as JSON
+  verify     Check an exported strategy against every secret and placement of
+             lies, listing any it fails on
+  tree       Draw the perfect guesser's questions as a tree
+  table      Tabulate the most numbers each number of questions can handle
+  mastermind Guess a code of coloured pegs, from feedback that may lie
+  twenty     Guess an item from a list, asking which attributes it has
+  words      Guess a word from a list, asking which words it comes before
+  bisect     Let the computer find a date, version or other value by asking
+             which values it comes before, as when finding the first bad build
+  engine     Speak a text protocol on standard input and output, so GUIs and
+             scripts can have the computer ask questions or answer them, or
+             JSON-RPC with --rpc
+  serve      Host games over TCP between two people, the first to connect
+             answering and the second guessing
+  api        Serve an HTTP API for starting games against the opponent and
+             asking it questions, each game kept on the server
+  web        Serve the api along with a page to play in a browser
+  grpc       Serve the game API as gRPC, as proto/one_lie.proto describes, over
+             HTTP/2 without TLS, to this machine only
+
+Options:
+  LIMIT              The number is at least 0 and less than LIMIT, which is at
+                     least 2 and may end in k, m or g for thousands, millions or
+                     billions, as in 1k [default: 10]
+  --limit LIMIT      The same as giving LIMIT
+  --range A..B       The number is at least A and less than B, instead of LIMIT
+  --candidates FILE  The number is one of those listed in FILE, instead of LIMIT,
+                     or for bisect one of the values listed
+  --weights FILE     The number is one of those in FILE, each line a number and how
+                     likely it is relative to the others, instead of LIMIT
+  --prior geometric:R
+                     Each number is R times as likely as the one below it, for R
+                     from 0 to 1
+  --lies K           The opponent may lie up to K times [default: 1]
+  --mode MODE        lies, or noisy to lie on each answer independently [default: lies]
+  --p P              The chance of each lie in noisy mode [default: 0.1]
+  --confidence C     How sure a noisy game must be of one number to finish, from
+                     0.5 to below 1, such as 0.999 [default: 0.99]
+  --variant NAME     standard, half-lie for lies that can only falsely say yes,
+                     such as less than, three-way for guesses answered less,
+                     equal or greater, truthful-last for a last answer that is
+                     never a lie, or directional for up to K false yeses and K
+                     false nos [default: standard]
+  --opponent NAME    adversarial, honest, random, minimax, expected or human, or
+                     for mastermind, twenty and words adversarial or random,
+                     and for engine adversarial, minimax or expected. Or
+                     cmd:PROGRAM for another program, as in cmd:./my_bot,
+                     spoken to as engine is, learned:FILE for the opponent
+                     train wrote to FILE, script:FILE for one that answers as
+                     the script in FILE says, or the name of a plugin in
+                     --strategy-path [default: adversarial]
+  --value NAME       simple, better or berlekamp, or an expression over the
+                     position, as in truth_len * log2(total) + lie_len
+                     [default: better]
+  --seed N           Seed for the opponent's random choices
+  --secret N         The number the honest or random opponent picks, or for
+                     mastermind the code, such as 1123, for twenty and words the
+                     item or word, or for bisect the value, which the random
+                     opponent then answers about instead of you
+  --lie-at A,B       The questions the honest opponent lies on, counting from 0
+  --max-questions N  Lose if the number isn't found within N questions
+  --lie-window M     Only the first M answers may be lies
+  --passes N         Let the opponent refuse to answer up to N questions, which
+                     tells the guesser nothing but uses up a question
+  --final-guess      Only win by announcing the number once it is pinned down,
+                     which the opponent may lie to deny
+  --guesser NAME     greedy, minimax, mcts for Monte Carlo tree search, or the
+                     name of a plugin in --strategy-path, for solve, simulate,
+                     engine and grpc [default: greedy]
+  --playouts N       How many games the mcts guesser plays out for each
+                     question [default: 200]
+  --exploration C    How much the mcts guesser favours questions it has tried
+                     little, in questions [default: 1]
+  --strategy-path DIR
+                     Where to find plugins: shared libraries exporting
+                     one_lie_respond to be opponents, one_lie_choose to be
+                     guessers, or both, each named by its file, as in sly for
+                     libsly.so
+  --time-limit T     Stop the minimax opponent, the minimax guesser and hints
+                     searching after T, such as 2s or 500ms, and use the best
+                     question or answer found so far
+  --threads N        Search on N threads, for the minimax opponent, the minimax
+                     guesser, hints and bound [default: 1]
+  --cost MODEL       Have each question cost log, log2 of the size of its guess
+                     plus 2, or as listed in a FILE of guesses and costs, others
+                     costing 1. The minimax guesser and bound then keep the
+                     worst-case total cost lowest, for small games
+  --games N          How many games to simulate, summarised unless N is 1 [default: 1,
+                     100 for benchmark, 20 a pairing at each limit for
+                     tournament, or for evolve 10 against the random opponent
+                     for each value function at each limit]
+  --csv FILE         Write a row about each simulated game to a CSV file
+  --limits A,B,...   The limits to benchmark [default: 10,30,100,300,1000], to
+                     play a tournament or evolve at [default: 10,30,100], or to
+                     train at [default: 8,16,32]
+  --guessers A,B,... The guessers in a tournament, each as --guesser names it, or
+                     greedy:VALUE for the greedy guesser with --value VALUE
+                     [default: greedy,minimax]
+  --opponents A,B,...
+                     The opponents in a tournament, each as --opponent names it
+                     [default: adversarial,honest,random]
+  --config FILE      Read a tournament's options from FILE, a TOML file of keys
+                     named as the options are, as in games = 50 or
+                     guessers = [\"greedy\", \"minimax\"], with the command line
+                     winning over it
+  --population N     How many value functions each generation of evolve has
+                     [default: 16]
+  --generations G    How many generations evolve breeds [default: 20]
+  --ratings FILE     Keep the Elo ratings of a tournament's guessers and opponents
+                     in FILE, carrying them on from any earlier tournaments
+  --out FILE         Where to export the strategy [default: standard output], or
+                     to keep the model train learns, carrying on from any
+                     model already there [default: model.bin]
+  --episodes N       How many games train plays against itself [default: 20000]
+  --depth D          How many questions down to draw the tree [default: all]
+  --questions Q      The most questions to tabulate [default: 16], or for bound
+                     just whether Q questions are enough, settled by a
+                     proof-number search
+  --domain NAME      What bisect searches: integer, date as in 2024-03-15, char,
+                     or version as in 1.2.3, which needs --candidates
+                     [default: integer]
+  --pegs N           How many pegs a mastermind code has [default: 4]
+  --colours C        How many colours each peg may be, from 2 to 9 [default: 6]
+  --record FILE      Write a transcript of the game when it ends
+  --resume FILE      Continue a saved game, for play
+  --show-state       Show what is still possible after every answer
+  --output FORMAT    text, or json for one JSON object per event [default: text]
+  --tui              Play full screen, redrawn after every answer, for play
+  --gui              Play in the browser, with a slider for guesses, for play
+  --batch            Ask every question before hearing any answers, for play
+  --delay D          Hear the answer to each question only after D more, for play
+                     [default: 0]
+  --moving           Let the number move up or down by one between questions, for
+                     play
+  --corner W         Win a --moving game by cornering the number within W numbers
+                     in a row [default: 3, and 3 more for each lie]
+  --targets N        Guess N numbers at once, with the lies shared between them,
+                     for play [default: 1]
+  --dims D           1, or 2 to guess a point on a grid by asking about x or y,
+                     for play, where LIMIT gives the grid's size as 10 or 10x20
+                     [default: 1]
+  --epsilon E        Guess a real number at least 0 and less than LIMIT to within
+                     E, for play, where LIMIT may be a real such as 2.5
+  --port P           The TCP port to serve games on [default: 7777, 8080 for api
+                     and web, or 50051 for grpc]
+  --websocket        Serve games over WebSockets, sending JSON, for browsers
+  --rpc              Speak JSON-RPC 2.0 for engine instead, one request a line
+  --no-color         Print without colours, as does setting NO_COLOR
+";
+
+const SUBCOMMANDS: &[&str] = &["play", "solve", "simulate", "analyze", "replay", "bound", "benchmark", "tournament", "evolve", "train", "export", "verify", "tree", "table", "mastermind", "twenty", "words", "bisect", "engine", "serve", "api", "web", "grpc"];
+
+// Each option, with the subcommands that accept it.
+const OPTIONS: &[(&str, &[&str])] = &[
+    ("--limit", &["play", "solve", "simulate", "bound", "export", "tree", "serve", "api", "web"]),
+    ("--lies", &["play", "solve", "simulate", "bound", "benchmark", "tournament", "evolve", "train", "export", "tree", "table", "mastermind", "twenty", "words", "bisect", "serve", "api", "web"]),
+    ("--mode", &["play", "solve", "simulate"]),
+    ("--p", &["play", "solve", "simulate"]),
+    ("--confidence", &["play", "solve", "simulate"]),
+    ("--variant", &["play", "solve", "simulate", "bound", "serve", "api", "web"]),
+    ("--opponent", &["play", "solve", "simulate", "benchmark", "mastermind", "twenty", "words", "engine", "api", "web", "grpc"]),
+    ("--value", &["play", "solve", "simulate", "tournament", "twenty", "words", "bisect", "engine", "api", "web", "grpc"]),
+    ("--seed", &["play", "solve", "simulate", "benchmark", "tournament", "evolve", "train", "mastermind", "twenty", "words", "bisect", "api", "web", "grpc"]),
+    ("--secret", &["play", "solve", "simulate", "mastermind", "twenty", "words", "bisect", "api", "web", "grpc"]),
+    ("--lie-at", &["play", "solve", "simulate", "api", "web", "grpc"]),
+    ("--guesser", &["solve", "simulate", "engine", "grpc"]),
+    ("--playouts", &["solve", "simulate", "tournament", "engine", "grpc"]),
+    ("--exploration", &["solve", "simulate", "tournament", "engine", "grpc"]),
+    ("--time-limit", &["play", "solve", "simulate", "tournament", "engine", "api", "web", "grpc"]),
+    ("--threads", &["play", "solve", "simulate", "bound", "tournament", "engine", "api", "web", "grpc"]),
+    ("--cost", &["solve", "simulate", "bound"]),
+    ("--max-questions", &["play", "solve", "simulate", "serve"]),
+    ("--lie-window", &["play", "solve", "simulate"]),
+    ("--passes", &["play", "solve", "simulate"]),
+    ("--range", &["play", "solve", "simulate", "serve", "api", "web"]),
+    ("--candidates", &["play", "solve", "simulate", "bisect"]),
+    ("--weights", &["play", "solve", "simulate"]),
+    ("--prior", &["play", "solve", "simulate"]),
+    ("--games", &["simulate", "benchmark", "tournament", "evolve"]),
+    ("--csv", &["simulate"]),
+    ("--limits", &["benchmark", "tournament", "evolve", "train"]),
+    ("--guessers", &["tournament"]),
+    ("--opponents", &["tournament"]),
+    ("--config", &["tournament"]),
+    ("--ratings", &["tournament"]),
+    ("--population", &["evolve"]),
+    ("--generations", &["evolve"]),
+    ("--out", &["export", "train"]),
+    ("--episodes", &["train"]),
+    ("--depth", &["tree"]),
+    ("--questions", &["bound", "table"]),
+    ("--domain", &["bisect"]),
+    ("--pegs", &["mastermind"]),
+    ("--colours", &["mastermind"]),
+    ("--record", &["play", "solve", "simulate"]),
+    ("--resume", &["play"]),
+    ("--delay", &["play"]),
+    ("--corner", &["play"]),
+    ("--targets", &["play"]),
+    ("--dims", &["play"]),
+    ("--epsilon", &["play"]),
+    ("--port", &["serve", "api", "web", "grpc"]),
+    ("--strategy-path", &["play", "solve", "simulate", "tournament", "engine", "api", "web", "grpc"]),
+    ("--output", &["play", "solve", "simulate"]),
+];
+
+// Options that take no value, with the subcommands that accept them.
+const SWITCHES: &[(&str, &[&str])] = &[
+    ("--show-state", &["play", "solve", "simulate", "mastermind", "twenty", "words"]),
+    ("--tui", &["play"]),
+    ("--gui", &["play"]),
+    ("--batch", &["play"]),
+    ("--moving", &["play"]),
+    ("--final-guess", &["play", "solve", "simulate"]),
+    ("--websocket", &["serve"]),
+    ("--rpc", &["engine"]),
+    ("--no-color", &["play", "solve", "simulate", "analyze", "replay", "bound", "benchmark", "tournament", "evolve", "train", "export", "verify", "tree", "table", "mastermind", "twenty", "words", "bisect", "engine", "serve", "api", "web", "grpc"]),
+];
+
+pub enum Command {
+    // A real number, numbered by the cell it is in, with the opponent set up as for
+    // numbers.
+    Continuous {
+        continuum: Continuum,
+        game: GameState,
+        setup: OpponentSetup,
+        show_state: bool,
+    },
+    // A point on a grid, numbered row by row, with the opponent set up as for numbers.
+    Grid {
+        grid: Grid,
+        game: GameState,
+        setup: OpponentSetup,
+        show_state: bool,
+    },
+    Play {
+        game: GameState,
+        setup: OpponentSetup,
+        record: Option<String>,
+        show_state: bool,
+        output: Output,
+        batch: bool,
+        // How many secrets to guess at once, sharing one budget of lies.
+        targets: usize,
+    },
+    Resume {
+        path: String,
+        record: Option<String>,
+        show_state: bool,
+        output: Output,
+    },
+    // Solving and simulating differ only in who answers by default, and in that
+    // simulations may play many games.
+    Solve {
+        game: GameState,
+        setup: OpponentSetup,
+        guesser: String,
+        games: u64,
+        csv: Option<String>,
+        record: Option<String>,
+        show_state: bool,
+        output: Output,
+    },
+    Analyze {
+        path: String,
+    },
+    Replay {
+        path: String,
+    },
+    Bound {
+        upper_limit: Number,
+        max_lies: usize,
+        variant: Variant,
+        threads: usize,
+        // Each question's cost, when the bound is on the total cost instead
+        cost: Option<CostModel>,
+        // The number of questions to check are enough, rather than finding the fewest
+        questions: Option<usize>,
+    },
+    // The setup is for the opponent the mean number of questions is measured against.
+    Benchmark {
+        limits: Vec<Number>,
+        max_lies: usize,
+        games: u64,
+        setup: OpponentSetup,
+    },
+    Tournament(Tournament),
+    Evolve(Evolution),
+    Train {
+        out: String,
+        limits: Vec<Number>,
+        max_lies: usize,
+        episodes: u64,
+        seed: Option<u64>,
+    },
+    Export {
+        upper_limit: Number,
+        max_lies: usize,
+        out: Option<String>,
+    },
+    Verify {
+        path: String,
+    },
+    Tree {
+        upper_limit: Number,
+        max_lies: usize,
+        depth: usize,
+    },
+    Table {
+        max_lies: usize,
+        max_questions: usize,
+    },
+    // The opponent lies at random if `random`, and otherwise adversarially.
+    Mastermind {
+        game: Mastermind,
+        random: bool,
+        seed: Option<u64>,
+        secret: Option<Code>,
+        show_state: bool,
+    },
+    // Items are numbered as the database lists them, and the secret is one of them.
+    Twenty {
+        database: ItemDatabase,
+        max_lies: usize,
+        random: bool,
+        seed: Option<u64>,
+        secret: Option<usize>,
+        value: String,
+        show_state: bool,
+    },
+    // Words are numbered by their place in the sorted list.
+    Words {
+        wordlist: Wordlist,
+        max_lies: usize,
+        random: bool,
+        seed: Option<u64>,
+        secret: Option<Number>,
+        value: String,
+        show_state: bool,
+    },
+    // The game is over the domain's numbers for its values. With a secret, the
+    // random opponent answers instead of the player.
+    Bisect {
+        domain: Box<dyn Domain>,
+        game: GameState,
+        secret: Option<Number>,
+        seed: Option<u64>,
+        value: String,
+    },
+    // The opponent answers from the position alone, as the engine keeps nothing
+    // between positions.
+    Engine {
+        setup: OpponentSetup,
+        guesser: String,
+        rpc: bool,
+    },
+    Serve {
+        game: GameState,
+        port: u16,
+        websocket: bool,
+    },
+    Api {
+        rules: Rules,
+        setup: OpponentSetup,
+        port: u16,
+        page: bool,
+    },
+    // Each game's rules come with the request that starts it.
+    Grpc {
+        setup: OpponentSetup,
+        guesser: String,
+        port: u16,
+    },
+    Help,
+}
+
+impl Command {
+    pub fn output(&self) -> Option<Output> {
+        match *self {
+            Command::Play { output, .. } | Command::Resume { output, .. } | Command::Solve { output, .. } => {
+                Some(output)
+            }
+            _ => None,
+        }
+    }
+}
+
+// Program and plugin opponents, and plugin guessers, are told the game as the
+// engine protocol's setposition, so can only play games it describes.
+fn check_program(setup: &OpponentSetup, guesser: Option<&str>, game: &GameState) -> Result<(), String> {
+    let what = match (setup.kind, guesser) {
+        (OpponentKind::Program, _) => "--opponent cmd:",
+        (OpponentKind::Plugin, _) => "A plugin opponent",
+        (_, Some(guesser)) if !matches!(guesser, "greedy" | "minimax" | "mcts") => "A plugin guesser",
+        _ => return Ok(()),
+    };
+    if !describable(game) {
+        return Err(format!(
+            "{} only learns the limits, lies and variant, so can't be used with --mode noisy, --candidates, --weights, \
+             --prior, --lie-window, --passes, --final-guess, --delay or --moving",
+            what
+        ));
+    }
+    Ok(())
+}
+
+// Numbers separated by commas or whitespace, one per line in the simplest case.
+fn read_candidates(path: &str) -> Result<CandidateSet, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Couldn't read {}: {}", path, err))?;
+    let members = text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|member| !member.is_empty())
+        .map(|member| member.parse().map_err(|_| format!("{} lists {}, which isn't a number", path, member)))
+        .collect::<Result<Vec<Number>, String>>()?;
+    if members.is_empty() {
+        return Err(format!("{} lists no numbers", path));
+    }
+    Ok(CandidateSet::new(members))
+}
+
+// Lines of a number and its `what`, such as its weight, separated by a comma or
+// whitespace.
+fn read_pairs(path: &str, what: &str) -> Result<Vec<(Number, f64)>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Couldn't read {}: {}", path, err))?;
+    let pairs = text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let fields: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|field| !field.is_empty())
+                .collect();
+            match fields.as_slice() {
+                [number, value] => match (number.parse(), value.parse()) {
+                    (Ok(number), Ok(value)) => Ok((number, value)),
+                    _ => Err(format!("{} has a line {}, which isn't a number and a {}", path, line, what)),
+                },
+                _ => Err(format!("{} has a line {}, which isn't a number and a {}", path, line, what)),
+            }
+        })
+        .collect::<Result<Vec<(Number, f64)>, String>>()?;
+    if pairs.is_empty() {
+        return Err(format!("{} lists no numbers", path));
+    }
+    Ok(pairs)
+}
+
+// Values of a domain separated by commas or whitespace, as for read_candidates.
+fn read_values(path: &str, domain: &dyn Domain) -> Result<CandidateSet, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Couldn't read {}: {}", path, err))?;
+    let members = text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|member| !member.is_empty())
+        .map(|member| domain.parse(member).map_err(|err| format!("{} in {}", err, path)))
+        .collect::<Result<Vec<Number>, String>>()?;
+    if members.len() < 2 {
+        return Err(format!("{} lists fewer than two {}s", path, domain.name()));
+    }
+    Ok(CandidateSet::new(members))
+}
+
+fn read_weights(path: &str) -> Result<Prior, String> {
+    Prior::weights(read_pairs(path, "weight")?).map_err(|err| format!("{} in {}", err, path))
+}
+
+fn read_costs(path: &str) -> Result<CostModel, String> {
+    CostModel::table(read_pairs(path, "cost")?).map_err(|err| format!("{} in {}", err, path))
+}
+
+// A number such as 1500, or with a suffix for thousands, millions or billions,
+// as in 1k, 2m or 3g. None if it isn't a number or is too large for one.
+fn parse_number(text: &str) -> Option<Number> {
+    let (digits, scale) = match text.char_indices().last()? {
+        (end, 'k') | (end, 'K') => (&text[..end], 1_000),
+        (end, 'm') | (end, 'M') => (&text[..end], 1_000_000),
+        (end, 'g') | (end, 'G') => (&text[..end], 1_000_000_000),
+        _ => (text, 1),
+    };
+    digits.parse::<Number>().ok()?.checked_mul(scale)
+}
+
+// Games need two numbers or more, or there'd be nothing to ask about.
+fn check_limits(lower: Number, upper: Number, given: &str) -> Result<(), String> {
+    if lower >= upper {
+        Err(format!("{} has no numbers in it", given))
+    } else if upper.checked_sub(lower).is_some_and(|size| size < 2) {
+        Err(format!("{} has only the number {} in it, so there's nothing to guess", given, lower))
+    } else {
+        Ok(())
+    }
+}
+
+// A duration such as 2s, 1.5s or 500ms, or a plain number of seconds.
+fn parse_duration(text: &str) -> Option<Duration> {
+    let (number, scale) = if let Some(millis) = text.strip_suffix("ms") {
+        (millis, 0.001)
+    } else if let Some(minutes) = text.strip_suffix('m') {
+        (minutes, 60.0)
+    } else {
+        (text.strip_suffix('s').unwrap_or(text), 1.0)
+    };
+    let seconds = number.parse::<f64>().ok()? * scale;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+struct Options {
+    values: Vec<(String, String)>,
+    switches: Vec<String>,
+    positionals: Vec<String>,
+}
+
+impl Options {
+    fn has(&self, name: &str) -> bool {
+        self.switches.iter().any(|switch| switch == name)
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.values.iter().rev()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn parse<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(value) => value.parse()
+                .map(Some)
+                .map_err(|_| format!("{} can't be {}", name, value)),
+        }
+    }
+
+    // Limits count up from 0, so games over negative numbers need --range.
+    fn limit(&self, default: Option<Number>) -> Result<Number, String> {
+        let given = match (self.get("--limit"), self.positionals.as_slice()) {
+            (Some(_), [positional, ..]) => return Err(format!("Unexpected argument {}, as --limit was given", positional)),
+            (Some(limit), []) => limit,
+            (None, [limit]) => limit.as_str(),
+            (None, []) => return default.ok_or_else(|| "Missing LIMIT".to_string()),
+            (None, [_, extra, ..]) => return Err(format!("Unexpected argument {}", extra)),
+        };
+        let limit = parse_number(given).ok_or_else(|| format!("LIMIT can't be {}, it should be a number such as 1000 or 1k", given))?;
+        if limit < 0 {
+            return Err(format!("LIMIT can't be {}, use --range for negative numbers", given));
+        }
+        check_limits(0, limit, &format!("LIMIT {}", given))?;
+        Ok(limit)
+    }
+
+    // The numbers to guess among, from --range LOWER..LIMIT, or else from 0 up to LIMIT.
+    fn range(&self, default: Option<Number>) -> Result<(Number, Number), String> {
+        let range = match self.get("--range") {
+            None => return Ok((0, self.limit(default)?)),
+            Some(range) => range,
+        };
+        if self.get("--limit").is_some() || !self.positionals.is_empty() {
+            return Err("Give either --range or LIMIT, not both".to_string());
+        }
+        let bad_range = || format!("--range must look like 100..1000 or 1k..2k, not {}", range);
+        let (lower, upper) = range.split_once("..").ok_or_else(bad_range)?;
+        let lower = parse_number(lower).ok_or_else(bad_range)?;
+        let upper = parse_number(upper).ok_or_else(bad_range)?;
+        check_limits(lower, upper, &format!("--range {}", range))?;
+        Ok((lower, upper))
+    }
+
+    fn limits(&self, default: &[Number]) -> Result<Vec<Number>, String> {
+        match self.get("--limits") {
+            None => Ok(default.to_vec()),
+            Some(limits) => limits.split(',')
+                .map(|limit| {
+                    let parsed = parse_number(limit).ok_or_else(|| format!("--limits can't include {}", limit))?;
+                    check_limits(0, parsed, &format!("--limits {}", limit))?;
+                    Ok(parsed)
+                })
+                .collect(),
+        }
+    }
+
+    fn path(&self, name: &str) -> Result<String, String> {
+        match self.positionals.as_slice() {
+            [] => Err(format!("Missing {}", name)),
+            [path] => Ok(path.clone()),
+            _ => Err(format!("Unexpected argument {}", self.positionals[1])),
+        }
+    }
+
+    fn variant(&self) -> Result<Variant, String> {
+        match self.get("--variant") {
+            None => Ok(Variant::Standard),
+            Some(name) => Ok(Variant::parse(name)?),
+        }
+    }
+
+    fn game(&self) -> Result<GameState, String> {
+        let (lower_limit, upper_limit) = self.range(Some(10))?;
+        let max_lies = self.parse("--lies")?.unwrap_or(1);
+        let game = match self.get("--mode").unwrap_or("lies") {
+            "lies" => GameState::new(upper_limit, max_lies),
+            "noisy" => {
+                let lie_probability = self.parse("--p")?.unwrap_or(0.1);
+                if !(0.0..0.5).contains(&lie_probability) {
+                    return Err("--p must be at least 0 and less than 0.5".to_string());
+                }
+                let confidence = self.parse("--confidence")?.unwrap_or(CONFIDENCE);
+                if !(0.5..1.0).contains(&confidence) {
+                    return Err("--confidence must be at least 0.5 and less than 1".to_string());
+                }
+                GameState::noisy(upper_limit, lie_probability).with_confidence(confidence)
+            }
+            mode => return Err(format!("--mode must be lies or noisy, not {}", mode)),
+        };
+        if game.lie_probability().is_none() && self.get("--confidence").is_some() {
+            return Err("--confidence is for noisy games".to_string());
+        }
+        let variant = self.variant()?;
+        if variant != Variant::Standard && game.lie_probability().is_some() {
+            return Err("--variant is for games with a fixed number of lies".to_string());
+        }
+        let rules = Rules { lower: lower_limit, limit: upper_limit, lies: max_lies, variant };
+        rules.validate()?;
+        rules.check_players(self.get("--guesser"), self.get("--opponent").and_then(OpponentKind::from_name))?;
+        let game = if variant == Variant::Directional {
+            // --lies is how many go each way
+            game.with_max_lies(2 * max_lies).with_variant(variant)
+        } else {
+            game.with_variant(variant)
+        };
+        let game = match self.parse("--lie-window")? {
+            None => game,
+            Some(_) if game.lie_probability().is_some() => {
+                return Err("--lie-window is for games with a fixed number of lies".to_string());
+            }
+            Some(window) => game.with_lie_window(window),
+        };
+        let game = match self.parse("--passes")? {
+            None | Some(0) => game,
+            Some(_) if game.variant() == Variant::TruthfulLast => {
+                return Err("--passes can't be used with --variant truthful-last".to_string());
+            }
+            Some(passes) => game.with_passes(passes),
+        };
+        let game = if self.has("--final-guess") {
+            if game.lie_probability().is_some() || game.variant() == Variant::TruthfulLast {
+                return Err("--final-guess is for games with a fixed number of lies, and not --variant truthful-last".to_string());
+            }
+            if self.get("--guesser") == Some("minimax") {
+                return Err("--final-guess can't be used with the minimax guesser".to_string());
+            }
+            if let Some("minimax" | "expected") = self.get("--opponent") {
+                return Err("--final-guess can't be used with the minimax or expected opponent".to_string());
+            }
+            game.with_final_guess()
+        } else {
+            game
+        };
+        let prior = match (self.get("--weights"), self.get("--prior")) {
+            (None, None) => None,
+            (Some(path), None) => Some(read_weights(path)?),
+            (None, Some(spec)) => match Prior::parse(spec)? {
+                Prior::Weights { .. } => return Err("--prior weights:... is written --weights FILE".to_string()),
+                prior => Some(prior),
+            },
+            (Some(_), Some(_)) => return Err("Give either --weights or --prior, not both".to_string()),
+        };
+        if prior.is_some() && game.lie_probability().is_some() {
+            return Err("--weights and --prior are for games with a fixed number of lies".to_string());
+        }
+        let candidates = match (self.get("--candidates"), prior.as_ref().and_then(Prior::numbers)) {
+            (None, None) => None,
+            (None, Some(numbers)) => Some(CandidateSet::new(numbers)),
+            (Some(path), None) if prior.is_none() => Some(read_candidates(path)?),
+            (Some(_), _) => return Err("--candidates can't be used with --weights or --prior".to_string()),
+        };
+        let game = match candidates {
+            None => game.with_lower_limit(lower_limit),
+            Some(candidate_set) => {
+                if self.get("--range").is_some() || self.get("--limit").is_some() || !self.positionals.is_empty() {
+                    return Err("Give only one of --candidates, --weights, --range and LIMIT".to_string());
+                }
+                game.with_candidates(candidate_set)
+            }
+        };
+        let game = match prior {
+            None => game,
+            Some(prior) => game.with_prior(prior),
+        };
+        match self.parse("--max-questions")? {
+            None => Ok(game),
+            Some(0) => Err("--max-questions must be at least 1".to_string()),
+            Some(max_questions) => Ok(game.with_max_questions(max_questions)),
+        }
+    }
+
+    fn setup(&self, default_kind: OpponentKind) -> Result<OpponentSetup, String> {
+        let (kind, program) = match self.get("--opponent") {
+            None => (default_kind, None),
+            Some(name) => match name.strip_prefix("cmd:") {
+                Some(program) if program.trim().is_empty() => {
+                    return Err("--opponent cmd: needs a program to run, as in cmd:./my_bot".to_string());
+                }
+                Some(program) => (OpponentKind::Program, Some(program.to_string())),
+                None if name.starts_with("learned:") => {
+                    let path = &name["learned:".len()..];
+                    let max_lies = read_model(path)?.max_lies();
+                    if self.parse("--lies")?.unwrap_or(1) != max_lies {
+                        return Err(format!("{} was trained with up to {} lies, so needs --lies {}", path, max_lies, max_lies));
+                    }
+                    (OpponentKind::Learned, Some(path.to_string()))
+                }
+                None if name.starts_with("script:") => {
+                    let path = &name["script:".len()..];
+                    read_script(path)?;
+                    (OpponentKind::Script, Some(path.to_string()))
+                }
+                None => match (OpponentKind::from_name(name), self.get("--strategy-path")) {
+                    (Some(kind), _) => (kind, None),
+                    (None, Some(dir)) => {
+                        let path = plugin::find(dir, name)?;
+                        if !Plugin::load(&path)?.responds() {
+                            return Err(format!("The plugin {} has no one_lie_respond, so can't be an opponent", path));
+                        }
+                        (OpponentKind::Plugin, Some(path))
+                    }
+                    (None, None) => {
+                        return Err(format!(
+                            "--opponent must be adversarial, honest, random, minimax, expected, human, cmd:PROGRAM, learned:FILE, \
+                             script:FILE or a plugin in --strategy-path, not {}",
+                            name
+                        ))
+                    }
+                },
+            },
+        };
+        let value = self.value()?;
+        let lie_positions = match self.get("--lie-at") {
+            None => None,
+            Some(positions) => Some(positions.split(',')
+                .map(|position| position.parse().map_err(|_| format!("--lie-at can't be {}", positions)))
+                .collect::<Result<Vec<usize>, String>>()?),
+        };
+        Ok(OpponentSetup {
+            kind,
+            secret: self.parse("--secret")?,
+            lie_positions,
+            seed: self.parse("--seed")?,
+            value,
+            time_limit: match self.get("--time-limit") {
+                None => None,
+                Some(text) => Some(parse_duration(text).ok_or_else(|| format!("--time-limit can't be {}", text))?),
+            },
+            threads: self.threads()?,
+            cost: None,
+            program,
+            strategy_path: self.get("--strategy-path").map(str::to_string),
+            playouts: match self.parse("--playouts")? {
+                None => PLAYOUTS,
+                Some(0) => return Err("--playouts must be at least 1".to_string()),
+                Some(playouts) => playouts,
+            },
+            exploration: match self.parse::<f64>("--exploration")? {
+                None => EXPLORATION,
+                Some(exploration) if exploration >= 0.0 => exploration,
+                Some(exploration) => return Err(format!("--exploration can't be {}, as it must be at least 0", exploration)),
+            },
+        })
+    }
+
+    // For games played on the usual engine but asked about in their own terms,
+    // where only options about the opponent carry over. `option` is the one that
+    // chose the game, and `what` how to name it.
+    fn only_opponent_options(&self, option: &str, what: &str) -> Result<(), String> {
+        let allowed = [option, "--lies", "--opponent", "--value", "--seed", "--secret", "--lie-at", "--show-state", "--no-color"];
+        let used = self.values.iter().map(|(name, _)| name).chain(&self.switches);
+        match used.into_iter().find(|name| !allowed.contains(&name.as_str())) {
+            Some(name) => Err(format!("{} can't be used with {}", what, name)),
+            None => Ok(()),
+        }
+    }
+
+    // The opponent setup for those games, with the secret read in the game's own
+    // terms rather than as a number.
+    fn simple_setup(&mut self, secret: Option<Number>, what: &str) -> Result<OpponentSetup, String> {
+        self.values.retain(|(name, _)| name != "--secret");
+        let mut setup = self.setup(OpponentKind::Adversarial)?;
+        if let OpponentKind::Minimax | OpponentKind::Expected | OpponentKind::Human = setup.kind {
+            return Err(format!("{} needs the adversarial, honest or random opponent", what));
+        }
+        setup.secret = secret;
+        Ok(setup)
+    }
+
+    fn threads(&self) -> Result<usize, String> {
+        match self.parse("--threads")? {
+            None => Ok(1),
+            Some(0) => Err("--threads must be at least 1".to_string()),
+            Some(threads) => Ok(threads),
+        }
+    }
+
+    fn output(&self) -> Result<Output, String> {
+        match (self.get("--output").unwrap_or("text"), self.has("--tui"), self.has("--gui")) {
+            (_, true, true) => Err("Give either --tui or --gui, not both".to_string()),
+            ("text", false, false) => Ok(Output::Text),
+            ("text", true, false) => Ok(Output::Tui),
+            ("text", false, true) => Ok(Output::Gui),
+            ("json", false, false) => Ok(Output::Json),
+            ("json", _, _) => Err("--tui and --gui can't be used with --output json".to_string()),
+            (output, _, _) => Err(format!("--output must be text or json, not {}", output)),
+        }
+    }
+
+    // The cost model, checked against the game, as the cost search only plays the
+    // standard and half-lie games without a lie window.
+    fn cost(&self, game: &GameState) -> Result<Option<CostModel>, String> {
+        let cost = match self.get("--cost") {
+            None => return Ok(None),
+            Some("log") => CostModel::Log,
+            Some(path) => read_costs(path)?,
+        };
+        if game.lie_probability().is_some() || game.lie_window().is_some() {
+            return Err("--cost is for games with a fixed number of lies and no --lie-window".to_string());
+        }
+        if let Variant::ThreeWay | Variant::TruthfulLast | Variant::Directional = game.variant() {
+            return Err(format!("--cost can't be used with --variant {}", game.variant()));
+        }
+        if game.final_guess() {
+            return Err("--cost can't be used with --final-guess".to_string());
+        }
+        Ok(Some(cost))
+    }
+
+    fn value(&self) -> Result<String, String> {
+        let value = self.get("--value").unwrap_or("better").to_string();
+        if let Err(err) = value_function(&value) {
+            return Err(format!("--value {}", err));
+        }
+        Ok(value)
+    }
+
+    // Whether the opponent in games other than guessing numbers is the random one,
+    // the only other being the adversary.
+    fn random_opponent(&self, subcommand: &str) -> Result<bool, String> {
+        match self.get("--opponent").unwrap_or("adversarial") {
+            "adversarial" => Ok(false),
+            "random" => Ok(true),
+            name => Err(format!("--opponent for {} must be adversarial or random, not {}", subcommand, name)),
+        }
+    }
+
+    fn guesser(&self) -> Result<String, String> {
+        match self.get("--guesser").unwrap_or("greedy") {
+            guesser @ ("greedy" | "minimax" | "mcts") => Ok(guesser.to_string()),
+            guesser => match self.get("--strategy-path") {
+                Some(dir) => {
+                    let path = plugin::find(dir, guesser)?;
+                    if !Plugin::load(&path)?.chooses() {
+                        return Err(format!("The plugin {} has no one_lie_choose, so can't be a guesser", path));
+                    }
+                    Ok(guesser.to_string())
+                }
+                None => Err(format!("--guesser must be greedy, minimax, mcts or a plugin in --strategy-path, not {}", guesser)),
+            },
+        }
+    }
+}
+
+/// Parses the arguments after the program name, returning the command and whether
+/// colour was turned off. Without a subcommand, play is assumed.
+pub fn parse(args: Vec<String>) -> Result<(Command, bool), String> {
+    let mut args = args.into_iter().peekable();
+    let subcommand = match args.peek() {
+        Some(arg) if SUBCOMMANDS.contains(&arg.as_str()) => args.next().unwrap(),
+        _ => "play".to_string(),
+    };
+    let mut options = Options {
+        values: vec![],
+        switches: vec![],
+        positionals: vec![],
+    };
+    while let Some(arg) = args.next() {
+        if arg == "help" || arg == "--help" || arg == "-h" {
+            return Ok((Command::Help, false));
+        }
+        if !arg.starts_with("--") {
+            options.positionals.push(arg);
+            continue;
+        }
+        let switch = SWITCHES.iter().find(|&&(name, _)| name == arg);
+        let &(_, accepted_by) = OPTIONS.iter()
+            .chain(switch)
+            .find(|&&(name, _)| name == arg)
+            .ok_or_else(|| format!("Unknown option {}", arg))?;
+        if !accepted_by.contains(&subcommand.as_str()) {
+            return Err(format!("{} doesn't take {}", subcommand, arg));
+        }
+        if switch.is_some() {
+            options.switches.push(arg);
+            continue;
+        }
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        options.values.push((arg, value));
+    }
+    let record = options.get("--record").map(str::to_string);
+    let show_state = options.has("--show-state");
+    let output = options.output()?;
+    let command: Result<Command, String> = match subcommand.as_str() {
+        "play" if options.get("--epsilon").is_some() => {
+            options.only_opponent_options("--epsilon", "--epsilon")?;
+            let limit = match options.positionals.as_slice() {
+                [] => "1",
+                [limit] => limit.as_str(),
+                [_, extra, ..] => return Err(format!("Unexpected argument {}", extra)),
+            };
+            let limit = limit.parse::<f64>().map_err(|_| format!("LIMIT can't be {}, it should be a number such as 2.5", limit))?;
+            let continuum = Continuum::new(limit, options.parse("--epsilon")?.unwrap_or(0.0)).map_err(|err| err.to_string())?;
+            // Secrets are reals, so aren't read as the secret number is
+            let secret = match options.parse::<f64>("--secret")? {
+                Some(secret) if (0.0..limit).contains(&secret) => Some(continuum.cell(secret)),
+                Some(_) => return Err("--secret must be at least 0 and less than LIMIT".to_string()),
+                None => None,
+            };
+            let setup = options.simple_setup(secret, "--epsilon")?;
+            Ok(Command::Continuous {
+                continuum,
+                game: continuum.game(options.parse("--lies")?.unwrap_or(1)),
+                setup,
+                show_state,
+            })
+        }
+        "play" if options.get("--dims").is_some_and(|dims| dims != "1") => {
+            if options.get("--dims") != Some("2") {
+                return Err(format!("--dims must be 1 or 2, not {}", options.get("--dims").unwrap_or_default()));
+            }
+            options.only_opponent_options("--dims", "--dims 2")?;
+            let size = match options.positionals.as_slice() {
+                [] => "10",
+                [size] => size.as_str(),
+                [_, extra, ..] => return Err(format!("Unexpected argument {}", extra)),
+            };
+            let bad_size = || format!("The grid's size can't be {}, it should look like 10 or 10x20", size);
+            let (width, height) = size.split_once('x').unwrap_or((size, size));
+            let width = parse_number(width).ok_or_else(bad_size)?;
+            let height = parse_number(height).ok_or_else(bad_size)?;
+            let grid = Grid::new(width, height).map_err(|err| err.to_string())?;
+            // Points are written x,y, so aren't read as the secret number is
+            let secret = options.get("--secret").map(|secret| grid.parse_point(secret)).transpose().map_err(|err| err.to_string())?;
+            let setup = options.simple_setup(secret, "--dims 2")?;
+            Ok(Command::Grid {
+                grid,
+                game: grid.game(options.parse("--lies")?.unwrap_or(1)),
+                setup,
+                show_state,
+            })
+        }
+        "play" => match options.get("--resume") {
+            Some(_) if options.has("--batch") => Err("--batch can't be used with --resume".to_string()),
+            Some(path) => Ok(Command::Resume {
+                path: path.to_string(),
+                record,
+                show_state,
+                output,
+            }),
+            None => {
+                let game = options.game()?;
+                let batch = options.has("--batch");
+                if batch && (output != Output::Text || show_state) {
+                    return Err("--batch prints plain text, so can't be used with --tui, --gui, --output or --show-state".to_string());
+                }
+                if batch && game.lie_probability().is_some() {
+                    return Err("--batch is for games with a fixed number of lies".to_string());
+                }
+                if batch && game.variant() == Variant::Directional {
+                    return Err("--batch can't be used with --variant directional".to_string());
+                }
+                if batch && (game.final_guess() || game.max_passes() > 0) {
+                    return Err("--batch can't be used with --final-guess or --passes".to_string());
+                }
+                let game = match options.parse("--delay")? {
+                    None => game,
+                    Some(_) if batch => return Err("--batch already hears every answer at the end, so can't be used with --delay".to_string()),
+                    Some(delay) => game.with_delay(delay),
+                };
+                let setup = options.setup(OpponentKind::Adversarial)?;
+                let game = if options.has("--moving") {
+                    if batch || game.delay() > 0 {
+                        return Err("--moving can't be used with --batch or --delay".to_string());
+                    }
+                    if game.lie_probability().is_some() || game.candidate_set().is_some() || game.prior().is_some() {
+                        return Err("--moving is for games over every number in a range, with a fixed number of lies".to_string());
+                    }
+                    if game.variant() == Variant::TruthfulLast || game.final_guess() {
+                        return Err("--moving can't be used with --variant truthful-last or --final-guess".to_string());
+                    }
+                    if let OpponentKind::Minimax | OpponentKind::Expected = setup.kind {
+                        return Err("--moving can't be used with the minimax or expected opponent, which assume the number stays put".to_string());
+                    }
+                    let width = options.parse("--corner")?.unwrap_or(3 * (game.max_lies() as Count + 1));
+                    if width == 0 {
+                        return Err("--corner must be at least 1".to_string());
+                    }
+                    game.with_moving_target(width)
+                } else if options.get("--corner").is_some() {
+                    return Err("--corner is for games with --moving".to_string());
+                } else {
+                    game
+                };
+                let targets = options.parse("--targets")?.unwrap_or(1);
+                if targets == 0 {
+                    return Err("--targets must be at least 1".to_string());
+                }
+                if targets > 1 {
+                    if batch || game.delay() > 0 || game.moving_target().is_some() || game.lie_window().is_some() {
+                        return Err("--targets can't be used with --batch, --delay, --moving or --lie-window".to_string());
+                    }
+                    if game.final_guess() || game.max_passes() > 0 {
+                        return Err("--targets can't be used with --final-guess or --passes".to_string());
+                    }
+                    if let Variant::TruthfulLast | Variant::Directional = game.variant() {
+                        return Err(format!("--targets can't be used with --variant {}", game.variant()));
+                    }
+                    if game.lie_probability().is_some() || game.max_questions().is_some() {
+                        return Err("--targets is for games with a fixed number of lies and no --max-questions".to_string());
+                    }
+                    if record.is_some() || output != Output::Text {
+                        return Err("--targets prints plain text, so can't be used with --record, --tui, --gui or --output".to_string());
+                    }
+                    if setup.kind != OpponentKind::Adversarial && setup.kind != OpponentKind::Honest {
+                        return Err("--targets needs the adversarial or honest opponent".to_string());
+                    }
+                    if setup.secret.is_some() {
+                        return Err("--secret can't choose several secrets, so can't be used with --targets".to_string());
+                    }
+                }
+                if output == Output::Gui && setup.kind == OpponentKind::Human {
+                    return Err("--gui needs an automated opponent, as the page has no one to answer it".to_string());
+                }
+                check_program(&setup, None, &game)?;
+                Ok(Command::Play {
+                    game,
+                    setup,
+                    record,
+                    show_state,
+                    output,
+                    batch,
+                    targets,
+                })
+            }
+        },
+        "solve" => {
+            let game = options.game()?;
+            let mut setup = options.setup(OpponentKind::Human)?;
+            setup.cost = options.cost(&game)?;
+            let guesser = options.guesser()?;
+            check_program(&setup, Some(&guesser), &game)?;
+            Ok(Command::Solve {
+                game,
+                setup,
+                guesser,
+                games: 1,
+                csv: None,
+                record,
+                show_state,
+                output,
+            })
+        }
+        "simulate" => {
+            let game = options.game()?;
+            let mut setup = options.setup(OpponentKind::Random)?;
+            setup.cost = options.cost(&game)?;
+            let guesser = options.guesser()?;
+            check_program(&setup, Some(&guesser), &game)?;
+            if setup.kind == OpponentKind::Human {
+                return Err("simulate needs an automated opponent".to_string());
+            }
+            let games = options.parse("--games")?.unwrap_or(1);
+            if games == 0 {
+                return Err("--games must be at least 1".to_string());
+            }
+            let shows_games = ["--record", "--output"].iter().any(|&name| options.get(name).is_some())
+                || options.has("--show-state");
+            let csv = options.get("--csv").map(str::to_string);
+            if (games > 1 || csv.is_some()) && shows_games {
+                return Err("--record, --output and --show-state need --games 1 and no --csv".to_string());
+            }
+            Ok(Command::Solve {
+                game,
+                setup,
+                guesser,
+                games,
+                csv,
+                record,
+                show_state,
+                output,
+            })
+        }
+        "analyze" => Ok(Command::Analyze { path: options.path("TRANSCRIPT")? }),
+        "replay" => Ok(Command::Replay { path: options.path("TRANSCRIPT")? }),
+        "bound" => match options.variant()? {
+            Variant::Directional => Err("bound can't solve games with --variant directional".to_string()),
+            variant => {
+                let upper_limit = options.limit(None)?;
+                let max_lies = options.parse("--lies")?.unwrap_or(1);
+                let cost = options.cost(&GameState::new(upper_limit, max_lies).with_variant(variant))?;
+                let questions = options.parse("--questions")?;
+                if cost.is_some() && questions.is_some() {
+                    return Err("bound can't check --questions against a --cost".to_string());
+                }
+                Ok(Command::Bound {
+                    upper_limit,
+                    max_lies,
+                    variant,
+                    threads: options.threads()?,
+                    cost,
+                    questions,
+                })
+            }
+        },
+        "benchmark" => {
+            let setup = options.setup(OpponentKind::Random)?;
+            if setup.kind == OpponentKind::Human {
+                return Err("benchmark needs an automated opponent".to_string());
+            }
+            Ok(Command::Benchmark {
+                limits: options.limits(&[10, 30, 100, 300, 1000])?,
+                max_lies: options.parse("--lies")?.unwrap_or(1),
+                games: options.parse("--games")?.unwrap_or(100),
+                setup,
+            })
+        }
+        "tournament" => {
+            if let Some(positional) = options.positionals.first() {
+                return Err(format!("Unexpected argument {}", positional));
+            }
+            if let Some(path) = options.get("--config").map(str::to_string) {
+                let text = fs::read_to_string(&path).map_err(|err| format!("Couldn't read {}: {}", path, err))?;
+                let mut values = tournament::config_options(&text).map_err(|err| format!("{} in {}", err, path))?;
+                for (option, _) in &values {
+                    let accepted = OPTIONS.iter().any(|&(name, accepted_by)| name == option && accepted_by.contains(&"tournament"));
+                    if !accepted || option == "--config" {
+                        return Err(format!("{} can't set {}", path, &option[2..]));
+                    }
+                }
+                // The command line's options come last, so win
+                values.append(&mut options.values);
+                options.values = values;
+            }
+            let guessers_given = options.get("--guessers").unwrap_or("greedy,minimax").to_string();
+            let mut guessers = vec![];
+            for name in tournament::split_list(&guessers_given) {
+                match name.split_once(':') {
+                    Some(("greedy", value)) => {
+                        value_function(value).map_err(|err| format!("--guessers greedy:{} {}", value, err))?;
+                    }
+                    _ => {
+                        options.values.push(("--guesser".to_string(), name.to_string()));
+                        options.guesser()?;
+                        options.values.pop();
+                    }
+                }
+                guessers.push(name.to_string());
+            }
+            let opponents_given = options.get("--opponents").unwrap_or("adversarial,honest,random").to_string();
+            let mut opponents = vec![];
+            for name in opponents_given.split(',').map(str::trim) {
+                options.values.push(("--opponent".to_string(), name.to_string()));
+                let setup = options.setup(OpponentKind::Random)?;
+                options.values.pop();
+                if setup.kind == OpponentKind::Human {
+                    return Err("tournament needs automated opponents".to_string());
+                }
+                opponents.push((name.to_string(), setup));
+            }
+            Ok(Command::Tournament(Tournament {
+                guessers,
+                opponents,
+                limits: options.limits(&[10, 30, 100])?,
+                max_lies: options.parse("--lies")?.unwrap_or(1),
+                games: options.parse("--games")?.unwrap_or(20),
+                setup: options.setup(OpponentKind::Random)?,
+                ratings: options.get("--ratings").map(str::to_string),
+            }))
+        }
+        "evolve" => {
+            if let Some(positional) = options.positionals.first() {
+                return Err(format!("Unexpected argument {}", positional));
+            }
+            let population = options.parse("--population")?.unwrap_or(16);
+            if population < 2 {
+                return Err("--population must be at least 2, to have something to breed".to_string());
+            }
+            let generations = options.parse("--generations")?.unwrap_or(20);
+            if generations == 0 {
+                return Err("--generations must be at least 1".to_string());
+            }
+            Ok(Command::Evolve(Evolution {
+                limits: options.limits(&[10, 30, 100])?,
+                max_lies: options.parse("--lies")?.unwrap_or(1),
+                population,
+                generations,
+                games: options.parse("--games")?.unwrap_or(10),
+                setup: options.setup(OpponentKind::Random)?,
+            }))
+        }
+        "train" => match options.positionals.first() {
+            Some(positional) => Err(format!("Unexpected argument {}", positional)),
+            None => Ok(Command::Train {
+                out: options.get("--out").unwrap_or("model.bin").to_string(),
+                limits: options.limits(&[8, 16, 32])?,
+                max_lies: options.parse("--lies")?.unwrap_or(1),
+                episodes: options.parse("--episodes")?.unwrap_or(20000),
+                seed: options.parse("--seed")?,
+            }),
+        },
+        "export" => Ok(Command::Export {
+            upper_limit: options.limit(None)?,
+            max_lies: options.parse("--lies")?.unwrap_or(1),
+            out: options.get("--out").map(str::to_string),
+        }),
+        "verify" => Ok(Command::Verify { path: options.path("STRATEGY")? }),
+        "tree" => Ok(Command::Tree {
+            upper_limit: options.limit(None)?,
+            max_lies: options.parse("--lies")?.unwrap_or(1),
+            depth: options.parse("--depth")?.unwrap_or(usize::MAX),
+        }),
+        "table" => match options.positionals.first() {
+            Some(positional) => Err(format!("Unexpected argument {}", positional)),
+            None => Ok(Command::Table {
+                max_lies: options.parse("--lies")?.unwrap_or(1),
+                max_questions: options.parse("--questions")?.unwrap_or(16),
+            }),
+        },
+        "mastermind" => {
+            if let Some(positional) = options.positionals.first() {
+                return Err(format!("Unexpected argument {}", positional));
+            }
+            let game = Mastermind::new(
+                options.parse("--pegs")?.unwrap_or(4),
+                options.parse("--colours")?.unwrap_or(6),
+                options.parse("--lies")?.unwrap_or(1),
+            )
+            .map_err(|err| err.to_string())?;
+            let random = options.random_opponent(&subcommand)?;
+            let secret = match options.get("--secret") {
+                Some(_) if !random => return Err("--secret is for the random opponent".to_string()),
+                Some(secret) => Some(game.parse_code(secret).map_err(|err| format!("--secret can't be {}: {}", secret, err))?),
+                None => None,
+            };
+            Ok(Command::Mastermind {
+                game,
+                random,
+                seed: options.parse("--seed")?,
+                secret,
+                show_state,
+            })
+        }
+        "twenty" => {
+            let path = options.positionals.first().ok_or_else(|| "Missing DATABASE".to_string())?;
+            if let Some(extra) = options.positionals.get(1) {
+                return Err(format!("Unexpected argument {}", extra));
+            }
+            let text = fs::read_to_string(path).map_err(|err| format!("Couldn't read {}: {}", path, err))?;
+            let database = ItemDatabase::parse(&text).map_err(|err| format!("{} in {}", err, path))?;
+            let random = options.random_opponent(&subcommand)?;
+            let secret = match options.get("--secret") {
+                Some(_) if !random => return Err("--secret is for the random opponent".to_string()),
+                Some(secret) => Some(
+                    database.items()
+                        .iter()
+                        .position(|item| item.eq_ignore_ascii_case(secret))
+                        .ok_or_else(|| format!("--secret can't be {}, which {} doesn't list", secret, path))?,
+                ),
+                None => None,
+            };
+            let value = options.value()?;
+            Ok(Command::Twenty {
+                database,
+                max_lies: options.parse("--lies")?.unwrap_or(1),
+                random,
+                seed: options.parse("--seed")?,
+                secret,
+                value,
+                show_state,
+            })
+        }
+        "words" => {
+            let path = options.positionals.first().ok_or_else(|| "Missing WORDLIST".to_string())?;
+            if let Some(extra) = options.positionals.get(1) {
+                return Err(format!("Unexpected argument {}", extra));
+            }
+            let text = fs::read_to_string(path).map_err(|err| format!("Couldn't read {}: {}", path, err))?;
+            let wordlist = Wordlist::parse(&text).map_err(|err| format!("{} in {}", err, path))?;
+            let random = options.random_opponent(&subcommand)?;
+            let secret = match options.get("--secret") {
+                Some(_) if !random => return Err("--secret is for the random opponent".to_string()),
+                Some(secret) => Some(
+                    wordlist.find(secret).ok_or_else(|| format!("--secret can't be {}, which {} doesn't list", secret, path))?,
+                ),
+                None => None,
+            };
+            Ok(Command::Words {
+                wordlist,
+                max_lies: options.parse("--lies")?.unwrap_or(1),
+                random,
+                seed: options.parse("--seed")?,
+                secret,
+                value: options.value()?,
+                show_state,
+            })
+        }
+        "bisect" => {
+            let name = options.get("--domain").unwrap_or("integer");
+            let domain = domain(name).ok_or_else(|| format!("--domain must be integer, date, char or version, not {}", name))?;
+            let max_lies = options.parse("--lies")?.unwrap_or(1);
+            let game = match (options.get("--candidates"), options.positionals.as_slice()) {
+                (Some(path), []) => GameState::new(0, max_lies).with_candidates(read_values(path, &*domain)?),
+                (None, [range]) if domain.dense() => {
+                    let range = domain.parse_range(range).map_err(|err| err.to_string())?;
+                    if range.higher - range.lower < 2 {
+                        return Err(format!("The range must hold at least two {}s", domain.name()));
+                    }
+                    GameState::new(range.higher, max_lies).with_lower_limit(range.lower)
+                }
+                (None, [_]) => return Err(format!("{}s don't all follow one another, so list them with --candidates", domain.name())),
+                (None, []) => return Err("Missing A..B".to_string()),
+                (Some(_), [_, ..]) => return Err("Give either A..B or --candidates, not both".to_string()),
+                (None, [_, extra, ..]) => return Err(format!("Unexpected argument {}", extra)),
+            };
+            let secret = match options.get("--secret") {
+                None => None,
+                Some(secret) => match domain.parse(secret) {
+                    Ok(secret) if game.allows(secret) => Some(secret),
+                    _ => return Err(format!("--secret must be one of the {}s being searched, not {}", domain.name(), secret)),
+                },
+            };
+            Ok(Command::Bisect {
+                domain,
+                game,
+                secret,
+                seed: options.parse("--seed")?,
+                value: options.value()?,
+            })
+        }
+        "engine" => {
+            if let Some(positional) = options.positionals.first() {
+                return Err(format!("Unexpected argument {}", positional));
+            }
+            let setup = options.setup(OpponentKind::Adversarial)?;
+            if let OpponentKind::Honest | OpponentKind::Random | OpponentKind::Human = setup.kind {
+                return Err("The engine's opponent must be adversarial, minimax, expected, cmd:PROGRAM or a plugin".to_string());
+            }
+            Ok(Command::Engine { setup, guesser: options.guesser()?, rpc: options.has("--rpc") })
+        }
+        "serve" => Ok(Command::Serve {
+            game: options.game()?,
+            port: options.parse("--port")?.unwrap_or(7777),
+            websocket: options.has("--websocket"),
+        }),
+        "api" | "web" => {
+            let (lower, limit) = options.range(Some(10))?;
+            let setup = options.setup(OpponentKind::Adversarial)?;
+            if setup.kind == OpponentKind::Human {
+                return Err("The API's opponent can't be human, as there's no one at the server to answer".to_string());
+            }
+            let rules = Rules { lower, limit, lies: options.parse("--lies")?.unwrap_or(1), variant: options.variant()? };
+            rules.validate()?;
+            rules.check_players(None, Some(setup.kind))?;
+            Ok(Command::Api { rules, setup, port: options.parse("--port")?.unwrap_or(8080), page: subcommand == "web" })
+        }
+        "grpc" => {
+            if let Some(positional) = options.positionals.first() {
+                return Err(format!("Unexpected argument {}", positional));
+            }
+            let setup = options.setup(OpponentKind::Adversarial)?;
+            if setup.kind == OpponentKind::Human {
+                return Err("The gRPC server's opponent can't be human, as there's no one at the server to answer".to_string());
+            }
+            Ok(Command::Grpc { setup, guesser: options.guesser()?, port: options.parse("--port")?.unwrap_or(50051) })
+        }
+        _ => unreachable!(),
+    };
+    Ok((command?, options.has("--no-color")))
+}
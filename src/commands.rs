@@ -0,0 +1,368 @@
+//! What the player can type at the prompt while guessing: a number to ask about,
+//! or one of a few commands. Batches of questions, asked before any answers, games
+//! with several secrets, points on a grid, real numbers, Mastermind, twenty
+//! questions and words have commands of their own.
+
+use crate::grid::Axis;
+use crate::{Number, Question};
+
+pub const HELP: &str = "\
+Type a number to ask whether the secret is less than it, = and a number to ask
+whether it is exactly that number, a range like 3..7 to ask whether it is at
+least 3 and less than 7, several ranges and numbers like 3..7,10,15..20 to ask
+whether it is in any of them, bit 3 to ask whether bit 3 of it is set, odd to
+ask whether it is odd, or one of the commands below. In three-way games a number
+asks whether the secret is less than, equal to or greater than it instead.
+  announce <n> Say the number is n, which final-guess games are only won by
+  hint         Suggest the best question
+  status       Show which numbers are still possible
+  history      List the questions so far with their answers
+  wait         Hear the next answer without asking anything, when answers
+               are delayed, which uses up a question
+  undo         Take back the last question
+  save <file>  Save the game, to continue later with --resume <file>
+  resign       Give up, and hear what the number could have been
+  quit         Stop without finishing, as when the input runs out
+  help         Show this list";
+
+pub enum PlayCommand {
+    Ask(Question),
+    Announce(Number),
+    Hint,
+    Status,
+    History,
+    Wait,
+    Undo,
+    Save(String),
+    Resign,
+    Quit,
+    Help,
+}
+
+impl PlayCommand {
+    pub fn parse(input: &str) -> Result<PlayCommand, String> {
+        let (word, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let rest = rest.trim();
+        let command = match word.to_lowercase().as_str() {
+            "announce" => {
+                return rest.parse::<Number>()
+                    .map(PlayCommand::Announce)
+                    .map_err(|_| "announce needs the number, as in announce 42".to_string());
+            }
+            "hint" => PlayCommand::Hint,
+            "status" => PlayCommand::Status,
+            "history" => PlayCommand::History,
+            "wait" => PlayCommand::Wait,
+            "undo" => PlayCommand::Undo,
+            "save" if rest.is_empty() => return Err("save needs a file to save to, as in save game.json".to_string()),
+            "save" => return Ok(PlayCommand::Save(rest.to_string())),
+            "resign" => PlayCommand::Resign,
+            "quit" | "exit" => PlayCommand::Quit,
+            "help" | "?" => PlayCommand::Help,
+            _ => {
+                return input.parse::<Question>()
+                    .map(PlayCommand::Ask)
+                    .map_err(|_| format!("{} isn't a number or a command, type help to see the commands", input));
+            }
+        };
+        if rest.is_empty() {
+            Ok(command)
+        } else {
+            Err(format!("{} takes nothing after it", word))
+        }
+    }
+}
+
+pub const BATCH_HELP: &str = "\
+Type questions as you would while playing, one per line, to add them to the
+batch. None is answered until you are done. Or type one of:
+  check        Say whether the batch always finds the number, however the
+               opponent lies
+  list         List the questions in the batch
+  undo         Take the last question out of the batch
+  done         Hear the answers to the whole batch
+  quit         Stop without hearing any answers
+  help         Show this list";
+
+/// What the player can type while putting a batch of questions together.
+pub enum BatchCommand {
+    Ask(Question),
+    Check,
+    List,
+    Undo,
+    Done,
+    Quit,
+    Help,
+}
+
+impl BatchCommand {
+    pub fn parse(input: &str) -> Result<BatchCommand, String> {
+        match input.trim().to_lowercase().as_str() {
+            "check" => Ok(BatchCommand::Check),
+            "list" => Ok(BatchCommand::List),
+            "undo" => Ok(BatchCommand::Undo),
+            "done" => Ok(BatchCommand::Done),
+            "quit" | "exit" => Ok(BatchCommand::Quit),
+            "help" | "?" => Ok(BatchCommand::Help),
+            _ => input.parse::<Question>()
+                .map(BatchCommand::Ask)
+                .map_err(|_| format!("{} isn't a question or a command, type help to see the commands", input)),
+        }
+    }
+}
+
+pub const MULTI_HELP: &str = "\
+Type which number to ask about, a colon and then a question as you would while
+playing, as in 0:50 to ask whether number 0 is less than 50, or 1:=7 to ask
+whether number 1 is exactly 7. Or type one of:
+  status       Show which numbers are still possible for each secret
+  history      List the questions so far with their answers
+  undo         Take back the last question
+  resign       Give up, and hear what the numbers could have been
+  quit         Stop without finishing, as when the input runs out
+  help         Show this list";
+
+/// What the player can type while guessing several secrets at once.
+pub enum MultiCommand {
+    Ask(usize, Question),
+    Status,
+    History,
+    Undo,
+    Resign,
+    Quit,
+    Help,
+}
+
+impl MultiCommand {
+    pub fn parse(input: &str) -> Result<MultiCommand, String> {
+        match input.trim().to_lowercase().as_str() {
+            "status" => return Ok(MultiCommand::Status),
+            "history" => return Ok(MultiCommand::History),
+            "undo" => return Ok(MultiCommand::Undo),
+            "resign" => return Ok(MultiCommand::Resign),
+            "quit" | "exit" => return Ok(MultiCommand::Quit),
+            "help" | "?" => return Ok(MultiCommand::Help),
+            _ => {}
+        }
+        let bad_input = || format!("{} isn't a question or a command, type help to see the commands", input);
+        let (target, question) = input.split_once(':').ok_or_else(bad_input)?;
+        let target = target.trim().parse::<usize>().map_err(|_| bad_input())?;
+        let question = question.trim().parse::<Question>().map_err(|_| bad_input())?;
+        Ok(MultiCommand::Ask(target, question))
+    }
+}
+
+pub const GRID_HELP: &str = "\
+Type x<3 to ask whether the point is left of column 3, or y<3 whether it is above
+row 3, or one of:
+  hint         Suggest the best question
+  status       Show which points are still possible
+  history      List the questions so far with their answers
+  undo         Take back the last question
+  resign       Give up, and hear what the point could have been
+  quit         Stop without finishing, as when the input runs out
+  help         Show this list";
+
+/// What the player can type while guessing a point on a grid.
+pub enum GridCommand {
+    Ask(Axis, Number),
+    Hint,
+    Status,
+    History,
+    Undo,
+    Resign,
+    Quit,
+    Help,
+}
+
+impl GridCommand {
+    pub fn parse(input: &str) -> Result<GridCommand, String> {
+        let input = input.trim().to_lowercase();
+        match input.as_str() {
+            "hint" => return Ok(GridCommand::Hint),
+            "status" => return Ok(GridCommand::Status),
+            "history" => return Ok(GridCommand::History),
+            "undo" => return Ok(GridCommand::Undo),
+            "resign" => return Ok(GridCommand::Resign),
+            "quit" | "exit" => return Ok(GridCommand::Quit),
+            "help" | "?" => return Ok(GridCommand::Help),
+            _ => {}
+        }
+        let bad_input = || format!("{} isn't a question or a command, type help to see the commands", input);
+        let (axis, threshold) = input.split_once('<').ok_or_else(bad_input)?;
+        let axis = match axis.trim() {
+            "x" => Axis::X,
+            "y" => Axis::Y,
+            _ => return Err(bad_input()),
+        };
+        let threshold = threshold.trim().parse::<Number>().map_err(|_| bad_input())?;
+        Ok(GridCommand::Ask(axis, threshold))
+    }
+}
+
+pub const REAL_HELP: &str = "\
+Type a number such as 0.35 to ask whether the secret is less than it, which is
+rounded to the precision being guessed to, or one of:
+  hint         Suggest the best question
+  status       Show where the secret could still be
+  history      List the questions so far with their answers
+  undo         Take back the last question
+  resign       Give up, and hear where the secret could have been
+  quit         Stop without finishing, as when the input runs out
+  help         Show this list";
+
+/// What the player can type while guessing a real number.
+pub enum RealCommand {
+    Ask(f64),
+    Hint,
+    Status,
+    History,
+    Undo,
+    Resign,
+    Quit,
+    Help,
+}
+
+impl RealCommand {
+    pub fn parse(input: &str) -> Result<RealCommand, String> {
+        match input.trim().to_lowercase().as_str() {
+            "hint" => Ok(RealCommand::Hint),
+            "status" => Ok(RealCommand::Status),
+            "history" => Ok(RealCommand::History),
+            "undo" => Ok(RealCommand::Undo),
+            "resign" => Ok(RealCommand::Resign),
+            "quit" | "exit" => Ok(RealCommand::Quit),
+            "help" | "?" => Ok(RealCommand::Help),
+            number => number.trim_start_matches('<')
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite())
+                .map(RealCommand::Ask)
+                .ok_or_else(|| format!("{} isn't a number or a command, type help to see the commands", input.trim())),
+        }
+    }
+}
+
+pub const MASTERMIND_HELP: &str = "\
+Type a code to guess it, one digit for each peg's colour, as in 1123, or one of:
+  hint         Suggest the best guess
+  status       Show which codes are still possible
+  history      List the guesses so far with their feedback
+  undo         Take back the last guess
+  resign       Give up, and hear what the code could have been
+  quit         Stop without finishing, as when the input runs out
+  help         Show this list";
+
+/// What the player can type while playing Mastermind. Guesses are kept as typed,
+/// since only the game knows how many pegs and colours a code has.
+pub enum MastermindCommand {
+    Guess(String),
+    Hint,
+    Status,
+    History,
+    Undo,
+    Resign,
+    Quit,
+    Help,
+}
+
+impl MastermindCommand {
+    pub fn parse(input: &str) -> MastermindCommand {
+        match input.trim().to_lowercase().as_str() {
+            "hint" => MastermindCommand::Hint,
+            "status" => MastermindCommand::Status,
+            "history" => MastermindCommand::History,
+            "undo" => MastermindCommand::Undo,
+            "resign" => MastermindCommand::Resign,
+            "quit" | "exit" => MastermindCommand::Quit,
+            "help" | "?" => MastermindCommand::Help,
+            guess => MastermindCommand::Guess(guess.to_string()),
+        }
+    }
+}
+
+pub const TWENTY_HELP: &str = "\
+Type an attribute to ask whether the secret has it, or one of:
+  attributes   List the attributes there are to ask about
+  hint         Suggest the best attribute to ask about
+  status       Show which items are still possible
+  history      List the questions so far with their answers
+  undo         Take back the last question
+  resign       Give up, and hear what the item could have been
+  quit         Stop without finishing, as when the input runs out
+  help         Show this list";
+
+/// What the player can type while playing twenty questions. Attributes are kept
+/// as typed, since only the database knows which there are.
+pub enum TwentyCommand {
+    Ask(String),
+    Attributes,
+    Hint,
+    Status,
+    History,
+    Undo,
+    Resign,
+    Quit,
+    Help,
+}
+
+impl TwentyCommand {
+    pub fn parse(input: &str) -> TwentyCommand {
+        match input.trim().to_lowercase().as_str() {
+            "attributes" => TwentyCommand::Attributes,
+            "hint" => TwentyCommand::Hint,
+            "status" => TwentyCommand::Status,
+            "history" => TwentyCommand::History,
+            "undo" => TwentyCommand::Undo,
+            "resign" => TwentyCommand::Resign,
+            "quit" | "exit" => TwentyCommand::Quit,
+            "help" | "?" => TwentyCommand::Help,
+            _ => TwentyCommand::Ask(input.trim().to_string()),
+        }
+    }
+}
+
+pub const WORDS_HELP: &str = "\
+Type a word to ask whether the secret comes before it in dictionary order, which
+needn't be in the list, = and a word to ask whether it is exactly that word, or
+one of:
+  hint         Suggest the best word to ask about
+  status       Show which words are still possible
+  history      List the questions so far with their answers
+  undo         Take back the last question
+  resign       Give up, and hear what the word could have been
+  quit         Stop without finishing, as when the input runs out
+  help         Show this list";
+
+/// What the player can type while guessing a word. Words are kept as typed, as
+/// the list decides where they fall.
+pub enum WordsCommand {
+    Before(String),
+    Exactly(String),
+    Hint,
+    Status,
+    History,
+    Undo,
+    Resign,
+    Quit,
+    Help,
+}
+
+impl WordsCommand {
+    pub fn parse(input: &str) -> WordsCommand {
+        match input.trim() {
+            "hint" => WordsCommand::Hint,
+            "status" => WordsCommand::Status,
+            "history" => WordsCommand::History,
+            "undo" => WordsCommand::Undo,
+            "resign" => WordsCommand::Resign,
+            "quit" | "exit" => WordsCommand::Quit,
+            "help" | "?" => WordsCommand::Help,
+            word => match word.strip_prefix('=') {
+                Some(word) => WordsCommand::Exactly(word.trim().to_string()),
+                None => WordsCommand::Before(word.to_string()),
+            },
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Guessing a real number in `[0, limit)` to within `epsilon`.
+//!
+//! The interval is cut into cells `epsilon` wide, numbered from 0, and questions
+//! ask whether the secret is less than a boundary between cells, so the game is an
+//! ordinary [`GameState`] over the cells. It finishes once only one cell is left,
+//! which is when everything still consistent lies within `epsilon`. Thresholds
+//! between boundaries are rounded to the nearest one, which changes no answer by
+//! more than the precision asked for.
+
+use crate::game::{GameError, GameState, Number, Question, Range};
+
+/// The most cells a game may have, so that every boundary is exact in f64.
+pub const MAX_CELLS: Number = 1 << 53;
+
+/// `[0, limit)` cut into cells `epsilon` wide, the last perhaps narrower.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Continuum {
+    limit: f64,
+    epsilon: f64,
+    cells: Number,
+}
+
+impl Continuum {
+    pub fn new(limit: f64, epsilon: f64) -> Result<Continuum, GameError> {
+        if !(limit.is_finite() && limit > 0.0 && epsilon > 0.0 && epsilon < limit) {
+            return Err(GameError::InvalidRules("The limit must be above 0, and epsilon above 0 and below it".to_string()));
+        }
+        let cells = (limit / epsilon).ceil();
+        if cells > MAX_CELLS as f64 {
+            return Err(GameError::InvalidRules(format!("Epsilon must be at least the limit over {}", MAX_CELLS)));
+        }
+        Ok(Continuum { limit, epsilon, cells: cells as Number })
+    }
+    pub fn limit(&self) -> f64 {
+        self.limit
+    }
+    pub fn epsilon(&self) -> f64 {
+        self.epsilon
+    }
+    /// A game over the cells, with up to `max_lies` lies.
+    pub fn game(&self, max_lies: usize) -> GameState {
+        GameState::new(self.cells, max_lies)
+    }
+    /// The boundary nearest `x`, as the number of cells below it.
+    pub fn boundary(&self, x: f64) -> Number {
+        ((x / self.epsilon).round() as Number).clamp(0, self.cells)
+    }
+    /// Where boundary `boundary` falls.
+    pub fn at(&self, boundary: Number) -> f64 {
+        (boundary as f64 * self.epsilon).min(self.limit)
+    }
+    /// The cell holding `x`.
+    pub fn cell(&self, x: f64) -> Number {
+        ((x / self.epsilon).floor() as Number).clamp(0, self.cells - 1)
+    }
+    /// Whether the secret is less than `x`, rounded to the nearest boundary.
+    pub fn question(&self, x: f64) -> Question {
+        Question::Less(self.boundary(x))
+    }
+    /// The reals in the cells `range`.
+    pub fn interval(&self, range: Range) -> (f64, f64) {
+        (self.at(range.lower), self.at(range.higher))
+    }
+    /// How wide the span of everything still consistent is, from the lowest value
+    /// any lie hypothesis allows to the highest.
+    pub fn width(&self, game: &GameState) -> f64 {
+        let candidates = game.candidates();
+        match (candidates.ranges().first(), candidates.ranges().last()) {
+            (Some(first), Some(last)) => self.at(last.higher) - self.at(first.lower),
+            _ => 0.0,
+        }
+    }
+    /// Enough decimal places to write every boundary exactly, as 2 are for an
+    /// epsilon of 0.25, or to tell them apart when they can't be written exactly.
+    pub fn decimals(&self) -> usize {
+        (0..=15)
+            .find(|&decimals| {
+                let scaled = self.epsilon * 10f64.powi(decimals as i32);
+                (scaled - scaled.round()).abs() < 1e-9 * scaled
+            })
+            .unwrap_or((-self.epsilon.log10()).ceil().max(0.0) as usize + 1)
+    }
+    /// `x` written to [`Continuum::decimals`] places.
+    pub fn format(&self, x: f64) -> String {
+        format!("{:.*}", self.decimals(), x)
+    }
+}
@@ -0,0 +1,289 @@
+//! Questions whose cost depends on the guess, as when some tests are dearer to
+//! run than others, and a search for the guesses that keep the total cost lowest
+//! in the worst case, rather than the number of questions.
+//!
+//! The cheapest strategy can depend on exactly which numbers are left, not just
+//! how many, so positions can't be shared the way [`Solver`] shares them, and the
+//! search is only practical for small games. The question counts [`Solver`] finds
+//! bound it from below, at the cheapest question each position could ask.
+
+use std::cmp::max;
+use std::collections::HashMap;
+
+use crate::game::Response::*;
+use crate::game::{Count, GameError, GameState, Number, Question, Range, Response, Variant};
+use crate::guesser::Guesser;
+use crate::solver::Solver;
+
+/// What each question costs, by its guess.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CostModel {
+    /// log2 of the size of the guess plus 2, about the bits it takes to write
+    /// down, so a guess of 0 costs 1 and larger ones more.
+    Log,
+    /// Each listed guess costs what is listed and every other guess costs 1. Made
+    /// by [`CostModel::table`].
+    Table(Vec<(Number, f64)>),
+}
+
+impl CostModel {
+    /// Costs for single guesses, each listed once with a finite cost of 0 or more.
+    pub fn table(mut costs: Vec<(Number, f64)>) -> Result<CostModel, GameError> {
+        if let Some(&(number, cost)) = costs.iter().find(|&&(_, cost)| !(cost.is_finite() && cost >= 0.0)) {
+            return Err(GameError::InvalidRules(format!("Cost {} for {} must be a number, 0 or more", cost, number)));
+        }
+        costs.sort_by_key(|&(number, _)| number);
+        if let Some(pair) = costs.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(GameError::InvalidRules(format!("{} is given more than one cost", pair[0].0)));
+        }
+        Ok(CostModel::Table(costs))
+    }
+    /// What asking about `guess` costs.
+    pub fn cost(&self, guess: Number) -> f64 {
+        match self {
+            CostModel::Log => (guess.unsigned_abs() as f64 + 2.0).log2(),
+            CostModel::Table(costs) => match costs.binary_search_by_key(&guess, |&(number, _)| number) {
+                Ok(index) => costs[index].1,
+                Err(_) => 1.0,
+            },
+        }
+    }
+    /// What asking `question` costs: its guess's cost for guesses and questions
+    /// about one number, and 1 for the rest.
+    pub fn question_cost(&self, question: &Question) -> f64 {
+        match *question {
+            Question::Less(guess) | Question::Equal(guess) | Question::Compare(guess) => self.cost(guess),
+            _ => 1.0,
+        }
+    }
+    /// What every question in `history` cost in all.
+    pub fn total(&self, history: &[(Question, Response)]) -> f64 {
+        history.iter().map(|(question, _)| self.question_cost(question)).sum()
+    }
+    /// The cheapest guess in `range`, the lowest if several tie, or None if the
+    /// range is empty.
+    pub fn cheapest(&self, range: Range) -> Option<(Number, f64)> {
+        if range.is_empty() {
+            return None;
+        }
+        let guess = match self {
+            CostModel::Log if range.lower > 0 => range.lower,
+            CostModel::Log if range.higher <= 0 => range.higher - 1,
+            CostModel::Log => 0,
+            CostModel::Table(costs) => {
+                let start = costs.partition_point(|&(number, _)| number < range.lower);
+                let listed = costs[start..].iter().take_while(|&&(number, _)| number < range.higher);
+                // The first number that isn't listed, if there is one in the range,
+                // competes at the cost of 1
+                let mut unlisted = Some(range.lower);
+                let mut best: Option<(Number, f64)> = None;
+                for &(number, cost) in listed {
+                    if unlisted == Some(number) {
+                        unlisted = number.checked_add(1).filter(|&next| next < range.higher);
+                    }
+                    best = cheaper(best, (number, cost));
+                }
+                return match unlisted {
+                    Some(unlisted) => cheaper(best, (unlisted, 1.0)),
+                    None => best,
+                };
+            }
+        };
+        Some((guess, self.cost(guess)))
+    }
+    /// The model, for messages like "each question costing log2 of ...".
+    pub fn describe(&self) -> String {
+        match self {
+            CostModel::Log => "log2 of the size of its guess plus 2".to_string(),
+            CostModel::Table(costs) => format!("as listed for {} guesses, or 1", costs.len()),
+        }
+    }
+}
+
+// Whichever guess costs less, or the lower if they cost the same.
+fn cheaper(best: Option<(Number, f64)>, other: (Number, f64)) -> Option<(Number, f64)> {
+    match best {
+        Some(best) if best.1 < other.1 || (best.1 == other.1 && best.0 < other.0) => Some(best),
+        _ => Some(other),
+    }
+}
+
+// The live numbers, as ranges each paired with the lies they need, in order.
+type State = Vec<(Number, Number, usize)>;
+
+/// Exact worst-case search for the lowest total cost, in standard and half-lie
+/// games without a lie window.
+pub struct CostSolver {
+    model: CostModel,
+    max_lies: usize,
+    variant: Variant,
+    // The least worst-case cost from each position, and a guess that achieves it
+    table: HashMap<State, (f64, Number)>,
+    // Counts the questions each position needs, for the lower bounds
+    solver: Solver,
+}
+
+impl CostSolver {
+    pub fn new(max_lies: usize, model: CostModel) -> CostSolver {
+        CostSolver {
+            model,
+            max_lies,
+            variant: Variant::Standard,
+            table: HashMap::new(),
+            solver: Solver::new(max_lies),
+        }
+    }
+
+    /// The same solver, for games where the opponent only tells the lies `variant`
+    /// allows. Only the standard and half-lie variants can be searched.
+    pub fn with_variant(mut self, variant: Variant) -> CostSolver {
+        self.variant = variant;
+        self.solver = Solver::new(self.max_lies).with_variant(variant);
+        self
+    }
+
+    pub fn model(&self) -> &CostModel {
+        &self.model
+    }
+
+    /// The lowest total cost that guarantees finishing from this position.
+    pub fn worst_cost(&mut self, game: &GameState) -> f64 {
+        let state = self.start(game);
+        self.search(game, &state).0
+    }
+
+    /// The guess to ask about next, and the total cost (including it) it
+    /// guarantees finishing within. None once the game is over.
+    pub fn best_guess(&mut self, game: &GameState) -> Option<(Number, f64)> {
+        let state = self.start(game);
+        match self.search(game, &state) {
+            (cost, Some(guess)) => Some((guess, cost)),
+            (_, None) => None,
+        }
+    }
+
+    // The live numbers of `game`, with touching ranges that need the same lies
+    // merged.
+    fn start(&self, game: &GameState) -> State {
+        let mut state: State = vec![];
+        for (range, lies) in game.contradictions() {
+            if lies <= self.max_lies && game.count(range) > 0 {
+                push_range(&mut state, range, lies);
+            }
+        }
+        state
+    }
+
+    // The position after `response` to whether the secret is less than `guess`.
+    // The numbers it rules out gain a lie, or are dropped if they have none left
+    // or the variant forbids the lie.
+    fn split(&self, game: &GameState, state: &[(Number, Number, usize)], guess: Number, response: Response) -> State {
+        let mut next = vec![];
+        for &(lower, higher, lies) in state {
+            let below = Range::new(lower, guess.clamp(lower, higher));
+            let above = Range::new(guess.clamp(lower, higher), higher);
+            for &(part, truth) in &[(below, Yes), (above, No)] {
+                if game.count(part) == 0 {
+                    continue;
+                }
+                if truth == response {
+                    push_range(&mut next, part, lies);
+                } else if lies < self.max_lies && self.variant.allows_lie(truth) {
+                    push_range(&mut next, part, lies + 1);
+                }
+            }
+        }
+        next
+    }
+
+    // The fewest questions the position needs, each at the cheapest guess that
+    // splits its numbers, which no strategy can beat.
+    fn lower_bound(&mut self, game: &GameState, state: &[(Number, Number, usize)]) -> f64 {
+        let live = live_numbers(game, state);
+        let (first, last) = match (live.first(), live.last()) {
+            (Some(&first), Some(&last)) if first < last => (first, last),
+            _ => return 0.0,
+        };
+        let mut runs: Vec<(Count, usize)> = vec![];
+        for &(lower, higher, lies) in state {
+            let count = game.count(Range::new(lower, higher));
+            match runs.last_mut() {
+                Some(last) if last.1 == lies => last.0 += count,
+                _ => runs.push((count, lies)),
+            }
+        }
+        let cheapest = self.model.cheapest(Range::new(first + 1, last + 1)).map_or(0.0, |(_, cost)| cost);
+        self.solver.run_questions(&runs) as f64 * cheapest
+    }
+
+    // The least worst-case cost from `state`, and the guess that gets it. Each way
+    // of splitting the live numbers is tried at its cheapest guess, in order of
+    // their lower bounds, until the bounds show no other can do better.
+    fn search(&mut self, game: &GameState, state: &[(Number, Number, usize)]) -> (f64, Option<Number>) {
+        if let Some(&(cost, guess)) = self.table.get(state) {
+            return (cost, Some(guess));
+        }
+        let live = live_numbers(game, state);
+        if live.len() <= 1 {
+            return (0.0, None);
+        }
+        let mut splits = vec![];
+        for pair in live.windows(2) {
+            let (guess, cost) = self.model.cheapest(Range::new(pair[0] + 1, pair[1] + 1)).expect("Live numbers differ");
+            let low = self.split(game, state, guess, Yes);
+            let high = self.split(game, state, guess, No);
+            let bound = cost + self.lower_bound(game, &low).max(self.lower_bound(game, &high));
+            splits.push((bound, guess, cost, low, high));
+        }
+        splits.sort_by(|a, b| a.0.partial_cmp(&b.0).expect("Costs are never NaN"));
+        let mut best: Option<(f64, Number)> = None;
+        for (bound, guess, cost, low, high) in splits {
+            if best.is_some_and(|(best_cost, _)| bound >= best_cost) {
+                break;
+            }
+            let low_cost = self.search(game, &low).0;
+            if best.is_some_and(|(best_cost, _)| cost + low_cost >= best_cost) {
+                continue;
+            }
+            let total = cost + low_cost.max(self.search(game, &high).0);
+            match best {
+                Some((best_cost, _)) if best_cost <= total => {}
+                _ => best = Some((total, guess)),
+            }
+        }
+        let (cost, guess) = best.expect("A position with two numbers has a split");
+        self.table.insert(state.to_vec(), (cost, guess));
+        (cost, Some(guess))
+    }
+}
+
+fn push_range(state: &mut State, range: Range, lies: usize) {
+    if let Some(last) = state.last_mut() {
+        if last.2 == lies && last.1 == range.lower {
+            last.1 = max(last.1, range.higher);
+            return;
+        }
+    }
+    state.push((range.lower, range.higher, lies));
+}
+
+// Every live number in order.
+fn live_numbers(game: &GameState, state: &[(Number, Number, usize)]) -> Vec<Number> {
+    state.iter()
+        .flat_map(|&(lower, higher, _)| {
+            let range = Range::new(lower, higher);
+            (0..game.count(range)).map(move |index| game.nth(range, index).expect("The index is below the count"))
+        })
+        .collect()
+}
+
+/// Asks whichever guess keeps the worst-case total cost lowest.
+pub struct CheapestGuesser {
+    pub solver: CostSolver,
+}
+
+impl Guesser for CheapestGuesser {
+    fn choose(&mut self, game: &GameState) -> Number {
+        self.solver.best_guess(game).map_or(0, |(guess, _)| guess)
+    }
+}
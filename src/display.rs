@@ -0,0 +1,153 @@
+//! Pictures of a game for the terminal.
+//!
+//! [`number_line`] draws the numbers from the lower to the upper limit as a line of
+//! characters, one row per lie hypothesis, marking which numbers that hypothesis
+//! still allows. Each character stands for an equal share of the numbers, so a
+//! partly filled character means only some of its share is possible.
+//!
+//! After "is it less than 5?" is answered yes, with up to one lie:
+//!
+//! ```text
+//!       0         10
+//!     0 |     #####|
+//!     - |#####     |
+//! ```
+//!
+//! [`question_tree`] draws the perfect guesser's questions as a tree, branching
+//! on every answer.
+
+use std::cmp::{max, min};
+
+use crate::game::Dir::*;
+use crate::game::GameResult::*;
+use crate::game::{GameState, Count, Range};
+use crate::solver::Solver;
+
+// From empty to full, for drawing how much of a column is possible.
+const SHADES: [char; 5] = [' ', '.', ':', '+', '#'];
+
+// The numbers drawn in column `index` of a line `width` characters wide.
+fn column(numbers: Range, width: usize, index: usize) -> Range {
+    // Split into whole and remainder so that enormous ranges can't overflow
+    let (width, len) = (width as Count, numbers.len());
+    let edge = |index: usize| {
+        let index = index as Count;
+        numbers.offset(len / width * index + len % width * index / width)
+    };
+    Range::new(edge(index), edge(index + 1))
+}
+
+// How many numbers the secret may be are in both ranges.
+fn overlap(game: &GameState, range: Range, other: Range) -> Count {
+    game.count(range.clamp_lower(other.lower).clamp_higher(other.higher))
+}
+
+// The share of a column's numbers that `range` covers, or none for a column with
+// no numbers the secret may be.
+fn fraction(game: &GameState, range: Range, column: Range) -> f64 {
+    match game.count(column) {
+        0 => 0.0,
+        total => overlap(game, range, column) as f64 / total as f64,
+    }
+}
+
+// A character for a column `fraction` full, keeping the full and empty shades for
+// columns that are exactly full or empty.
+fn shade(fraction: f64) -> char {
+    if fraction <= 0.0 {
+        SHADES[0]
+    } else if fraction >= 1.0 {
+        SHADES[SHADES.len() - 1]
+    } else {
+        let steps = (SHADES.len() - 2) as f64;
+        SHADES[1 + (fraction * steps).min(steps - 1.0) as usize]
+    }
+}
+
+fn draw_row(label: &str, columns: &[f64]) -> String {
+    let line: String = columns.iter().map(|&fraction| shade(fraction)).collect();
+    format!("{:>5} |{}|\n", label, line)
+}
+
+/// Draws the game at most `width` characters wide, not counting the labels.
+///
+/// Games with a fixed number of lies get a row for each lie hypothesis that still
+/// has numbers, labelled with the questions it lies on, or `-` for none. Noisy
+/// games get a single row shaded by how likely each column is, relative to the
+/// likeliest.
+pub fn number_line(game: &GameState, width: usize) -> String {
+    let numbers = game.range();
+    let width = max(1, min(width as Count, numbers.len())) as usize;
+    let columns: Vec<Range> = (0..width).map(|index| column(numbers, width, index)).collect();
+    let limit = numbers.higher.to_string();
+    let mut picture = format!("{:>7}{:>width$}\n", numbers.lower, limit, width = width + 1);
+    if let Some(lie_probability) = game.lie_probability() {
+        let posterior = game.posterior(lie_probability);
+        let mass: Vec<f64> = columns.iter()
+            .map(|&column| posterior.iter()
+                .map(|&(range, probability)| overlap(game, range, column) as f64 * probability)
+                .sum())
+            .collect();
+        let most = mass.iter().cloned().fold(0.0, f64::max);
+        let relative: Vec<f64> = mass.iter()
+            .map(|&mass| if most > 0.0 { mass / most } else { 0.0 })
+            .collect();
+        picture += &draw_row("p", &relative);
+        return picture;
+    }
+    for (range, lies) in game.hypotheses() {
+        if range.is_empty() {
+            continue;
+        }
+        let label = if lies.is_empty() {
+            "-".to_string()
+        } else {
+            lies.iter().map(|lie| lie.to_string()).collect::<Vec<_>>().join(",")
+        };
+        let filled: Vec<f64> = columns.iter()
+            .map(|&column| fraction(game, range, column))
+            .collect();
+        picture += &draw_row(&label, &filled);
+    }
+    picture
+}
+
+fn describe_node(game: &GameState, solver: &mut Solver) -> String {
+    match game.status() {
+        Finished(answer) => format!("It's {}", answer),
+        Impossible => "Impossible, too many lies".to_string(),
+        Lost => "Out of questions".to_string(),
+        Ongoing => {
+            let (guess, needed) = solver.best_question(game).expect("The game is still going");
+            format!("{}: Is it less than {}? ({} to go)", game.history().len(), guess, needed)
+        }
+    }
+}
+
+fn grow(game: &GameState, solver: &mut Solver, depth: usize, indent: &str, tree: &mut String) {
+    let guess = match solver.best_question(game) {
+        Some((guess, _)) if game.status() == Ongoing => guess,
+        _ => return,
+    };
+    if depth == 0 {
+        tree.push_str(&format!("{}`- ...\n", indent));
+        return;
+    }
+    for &(answer, label, last) in &[(Low, "yes", false), (High, "no", true)] {
+        let mut child = game.clone();
+        child.store_guess(guess, answer).expect("The solver only asks legal questions");
+        let (branch, continuation) = if last { ("`- ", "   ") } else { ("|- ", "|  ") };
+        tree.push_str(&format!("{}{}{}: {}\n", indent, branch, label, describe_node(&child, solver)));
+        grow(&child, solver, depth - 1, &format!("{}{}", indent, continuation), tree);
+    }
+}
+
+/// Draws the perfect guesser's questions from `game` as a tree, with the answer
+/// "yes, it's less" first under each question, cut off `depth` questions down.
+/// Each question is numbered by its place in the game and shows how many
+/// questions, counting itself, it guarantees finishing in.
+pub fn question_tree(game: &GameState, solver: &mut Solver, depth: usize) -> String {
+    let mut tree = format!("{}\n", describe_node(game, solver));
+    grow(game, solver, depth, "", &mut tree);
+    tree
+}
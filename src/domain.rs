@@ -0,0 +1,194 @@
+//! Ordered values other than numbers, such as dates, characters and software
+//! versions, for searching them with a lying oracle, as when finding which
+//! day's build a flaky test first failed on.
+//!
+//! Each [`Domain`] numbers its values in order, so that a range of values is a
+//! range of numbers and "is it before this date?" is "is it less than this
+//! number?". The game is then an ordinary [`GameState`], and guessers split the
+//! numbers as they always do. Only reading and writing values is left to the
+//! domain, along with whether every number in a range stands for a value.
+//!
+//! [`GameState`]: crate::game::GameState
+
+use std::convert::TryFrom;
+
+use crate::game::{GameError, Number, Range};
+
+/// Values that can be numbered in order.
+pub trait Domain {
+    /// The command line name, such as `date`.
+    fn name(&self) -> &'static str;
+    /// The number of the value written `text`.
+    fn parse(&self, text: &str) -> Result<Number, GameError>;
+    /// The value numbered `number`, written as [`Domain::parse`] reads it.
+    fn format(&self, number: Number) -> String;
+    /// Whether every number between two values is a value too. If not, as with
+    /// versions, games must list the values there are, or most of the numbers
+    /// searched would be values that don't exist.
+    fn dense(&self) -> bool {
+        true
+    }
+    /// The values from `A` up to but not including `B`, written `A..B`.
+    fn parse_range(&self, text: &str) -> Result<Range, GameError> {
+        let (lower, higher) = text.split_once("..")
+            .ok_or_else(|| GameError::ParseError(format!("A range of {}s looks like A..B, not {}", self.name(), text)))?;
+        Ok(Range::new(self.parse(lower.trim())?, self.parse(higher.trim())?))
+    }
+}
+
+/// Plain numbers, as in any other game.
+pub struct Integers;
+
+impl Domain for Integers {
+    fn name(&self) -> &'static str {
+        "integer"
+    }
+    fn parse(&self, text: &str) -> Result<Number, GameError> {
+        text.parse().map_err(|_| GameError::ParseError(format!("{} isn't a number", text)))
+    }
+    fn format(&self, number: Number) -> String {
+        number.to_string()
+    }
+}
+
+/// Days, written as in 2024-03-15, numbered from 1970-01-01 on the Gregorian
+/// calendar.
+pub struct Dates;
+
+// The days from 1970-01-01 to the given date, negative for dates before it.
+// Years run from March, so that leap days fall at the end of one.
+fn days_from_civil(year: Number, month: Number, day: Number) -> Number {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// The inverse of days_from_civil, as a year, month and day.
+fn civil_from_days(days: Number) -> (Number, Number, Number) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn is_leap_year(year: Number) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+impl Domain for Dates {
+    fn name(&self) -> &'static str {
+        "date"
+    }
+    fn parse(&self, text: &str) -> Result<Number, GameError> {
+        let bad_date = || GameError::ParseError(format!("{} isn't a date like 2024-03-15", text));
+        let fields: Vec<Number> = text.splitn(3, '-')
+            .map(|field| field.parse::<Number>().ok().filter(|&field| field >= 0))
+            .collect::<Option<Vec<Number>>>()
+            .ok_or_else(bad_date)?;
+        let (year, month, day) = match *fields {
+            [year, month, day] if year <= 9999 => (year, month, day),
+            _ => return Err(bad_date()),
+        };
+        let days_in_month = match month {
+            2 if is_leap_year(year) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            1..=12 => 31,
+            _ => return Err(bad_date()),
+        };
+        if !(1..=days_in_month).contains(&day) {
+            return Err(bad_date());
+        }
+        Ok(days_from_civil(year, month, day))
+    }
+    fn format(&self, number: Number) -> String {
+        let (year, month, day) = civil_from_days(number);
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+/// Unicode characters, numbered by their code points. The numbers reserved for
+/// surrogates aren't characters, and are written U+D800 and so on.
+pub struct Chars;
+
+impl Domain for Chars {
+    fn name(&self) -> &'static str {
+        "char"
+    }
+    fn parse(&self, text: &str) -> Result<Number, GameError> {
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(Number::from(u32::from(c))),
+            _ => Err(GameError::ParseError(format!("{} isn't a single character", text))),
+        }
+    }
+    fn format(&self, number: Number) -> String {
+        match u32::try_from(number).ok().and_then(char::from_u32) {
+            Some(c) if !c.is_control() && !c.is_whitespace() => c.to_string(),
+            _ => format!("U+{:04X}", number),
+        }
+    }
+}
+
+/// Software versions of up to four parts, as in 1.2.3, ordered part by part.
+/// Versions aren't [`dense`](Domain::dense), as 1.2.3 is followed by 1.2.4 or
+/// 1.3.0 depending on what was released, so games list the versions there are.
+pub struct Versions;
+
+// Each part of a version gets this many bits of its number.
+const VERSION_PART_BITS: u32 = 24;
+
+impl Domain for Versions {
+    fn name(&self) -> &'static str {
+        "version"
+    }
+    fn parse(&self, text: &str) -> Result<Number, GameError> {
+        let bad_version = || GameError::ParseError(format!("{} isn't a version like 1.2.3", text));
+        let parts: Vec<Number> = text.trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse::<Number>().ok().filter(|&part| (0..1 << VERSION_PART_BITS).contains(&part)))
+            .collect::<Option<Vec<Number>>>()
+            .ok_or_else(bad_version)?;
+        if parts.len() > 4 {
+            return Err(bad_version());
+        }
+        Ok((0..4).fold(0, |number, index| number << VERSION_PART_BITS | parts.get(index).cloned().unwrap_or(0)))
+    }
+    fn format(&self, number: Number) -> String {
+        let mut parts: Vec<Number> = (0..4)
+            .rev()
+            .map(|index| (number >> (index * VERSION_PART_BITS)) & ((1 << VERSION_PART_BITS) - 1))
+            .collect();
+        // Written with three parts unless the fourth is used
+        if parts[3] == 0 {
+            parts.pop();
+        }
+        parts.iter().map(|part| part.to_string()).collect::<Vec<String>>().join(".")
+    }
+    fn dense(&self) -> bool {
+        false
+    }
+}
+
+/// The command line names of every domain.
+pub const DOMAINS: [&str; 4] = ["integer", "date", "char", "version"];
+
+/// Looks up a domain by its command line name.
+pub fn domain(name: &str) -> Option<Box<dyn Domain>> {
+    match name {
+        "integer" => Some(Box::new(Integers)),
+        "date" => Some(Box::new(Dates)),
+        "char" => Some(Box::new(Chars)),
+        "version" => Some(Box::new(Versions)),
+        _ => None,
+    }
+}
@@ -0,0 +1,359 @@
+//! The engine protocol, for GUIs, scripts and arena managers to drive the computer
+//! over standard input and output the way chess GUIs drive chess engines. Each
+//! line in is one command, and each reply is a line out:
+//!
+//! ```text
+//!   isready        Replies readyok, once every command before it is done
+//!   setposition LIMIT [lower N] [lies K] [variant NAME] [moves M ...]
+//!                  Sets up a game, with any questions already asked written as
+//!                  in transcripts, such as moves 50L 25H
+//!   go             Replies bestquestion and the question to ask next, such as
+//!                  bestquestion 37, or bestquestion none once the game is over
+//!   respond Q      Replies response and Q with the answer the engine's opponent
+//!                  gives, such as response 37H, without asking it
+//!   status         Replies result and how the game stands, as transcripts write
+//!                  it, such as result Finished 3
+//!   quit           Stops, as does the input running out
+//! ```
+//!
+//! Questions and moves are written as in transcripts. A command that is malformed
+//! or can't be carried out replies error and why, leaving the position as it was.
+//! Positions are set whole each time, as in UCI, so the engine needs to remember
+//! nothing between them but its own search.
+//!
+//! The same protocol lets other programs be the opponent: each is sent the
+//! position and the question to respond to, as this engine would be.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{exit, Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use crate::game::check_rules;
+use crate::input::{Input, Terminal};
+use crate::setup::{make_guesser, make_opponent, OpponentKind, OpponentSetup};
+use crate::transcript::{format_move, format_result, parse_move};
+use crate::{GameError, GameResult, GameState, Guesser, Number, Opponent, Question, Response, Variant};
+
+/// The rules a position is played under, so that a new position under the same
+/// rules can keep the guesser and opponent, with whatever they have worked out.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    pub lower: Number,
+    pub limit: Number,
+    pub lies: usize,
+    pub variant: Variant,
+}
+
+impl Rules {
+    /// Whether the rules describe a game that can be played, whichever front end
+    /// they came from.
+    pub fn validate(&self) -> Result<(), String> {
+        check_rules(self.lower, self.limit, self.lies).map_err(|err| err.to_string())
+    }
+
+    /// Whether the guesser and opponent named can play under the rules. Directional
+    /// games are beyond the searching players, which don't track which way each
+    /// lie went.
+    pub fn check_players(&self, guesser: Option<&str>, opponent: Option<OpponentKind>) -> Result<(), String> {
+        if self.variant == Variant::Directional {
+            if guesser == Some("minimax") {
+                return Err("variant directional can't be used with the minimax guesser".to_string());
+            }
+            if let Some(OpponentKind::Minimax | OpponentKind::Expected) = opponent {
+                return Err("variant directional can't be used with the minimax or expected opponent".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn game(self) -> GameState {
+        let game = GameState::new(self.limit, self.lies).with_lower_limit(self.lower);
+        // Directional games take --lies as the lies each way, as on the command line
+        match self.variant {
+            Variant::Directional => game.with_max_lies(2 * self.lies).with_variant(self.variant),
+            variant => game.with_variant(variant),
+        }
+    }
+}
+
+/// Whether setposition can describe `game`, which it does by its limits, lies and
+/// variant alone.
+pub fn describable(game: &GameState) -> bool {
+    game.lie_probability().is_none()
+        && game.candidate_set().is_none()
+        && game.prior().is_none()
+        && game.lie_window().is_none()
+        && game.max_passes() == 0
+        && !game.final_guess()
+        && game.delay() == 0
+        && game.moving_target().is_none()
+}
+
+/// The setposition command for `game`, which must be describable.
+pub fn set_position(game: &GameState) -> String {
+    let lies = match game.variant() {
+        Variant::Directional => game.max_lies() / 2,
+        _ => game.max_lies(),
+    };
+    let mut command = format!("setposition {} lower {} lies {} variant {}", game.upper_limit(), game.lower_limit(), lies, game.variant());
+    if !game.history().is_empty() {
+        command += " moves";
+        for (question, response) in game.history() {
+            command += &format!(" {}", format_move(question, *response));
+        }
+    }
+    command
+}
+
+pub enum EngineCommand {
+    IsReady,
+    SetPosition(Rules, Vec<(Question, Response)>),
+    Go,
+    Respond(Question),
+    Status,
+    Quit,
+}
+
+impl EngineCommand {
+    pub fn parse(input: &str) -> Result<EngineCommand, String> {
+        let mut words = input.split_whitespace();
+        let command = match words.next().unwrap_or_default() {
+            "isready" => EngineCommand::IsReady,
+            "setposition" => {
+                let number = |word: Option<&str>, what: &str| {
+                    word.and_then(|word| word.parse::<Number>().ok())
+                        .ok_or_else(|| format!("setposition needs a number for {}", what))
+                };
+                let mut rules = Rules {
+                    lower: 0,
+                    limit: number(words.next(), "the limit")?,
+                    lies: 1,
+                    variant: Variant::Standard,
+                };
+                while let Some(word) = words.next() {
+                    match word {
+                        "lower" => rules.lower = number(words.next(), "lower")?,
+                        "lies" => {
+                            rules.lies = words.next()
+                                .and_then(|word| word.parse().ok())
+                                .ok_or_else(|| "setposition needs a count for lies".to_string())?
+                        }
+                        "variant" => rules.variant = Variant::parse(words.next().unwrap_or_default())?,
+                        "moves" => break,
+                        _ => return Err(format!("setposition doesn't know {}", word)),
+                    }
+                }
+                rules.validate()?;
+                let moves = words.map(parse_move).collect::<Result<Vec<_>, _>>()?;
+                return Ok(EngineCommand::SetPosition(rules, moves));
+            }
+            "go" => EngineCommand::Go,
+            "respond" => {
+                let question = words.next().ok_or_else(|| "respond needs a question, as in respond 37".to_string())?;
+                EngineCommand::Respond(question.parse()?)
+            }
+            "status" => EngineCommand::Status,
+            "quit" => EngineCommand::Quit,
+            word => return Err(format!("Unknown command {}", word)),
+        };
+        match words.next() {
+            Some(extra) => Err(format!("Unexpected {}", extra)),
+            None => Ok(command),
+        }
+    }
+}
+
+// Where the engine stands: the position set last, with a guesser and an opponent
+// made for its rules.
+struct Position {
+    rules: Rules,
+    game: GameState,
+    guesser: Box<dyn Guesser>,
+    opponent: Box<dyn Opponent>,
+}
+
+pub struct Engine {
+    setup: OpponentSetup,
+    guesser_name: String,
+    position: Option<Position>,
+}
+
+impl Engine {
+    /// The setup's opponent must answer from the position alone, as the engine
+    /// keeps no secret from one position to the next.
+    pub fn new(setup: OpponentSetup, guesser_name: String) -> Engine {
+        Engine { setup, guesser_name, position: None }
+    }
+
+    fn position(&mut self) -> Result<&mut Position, String> {
+        self.position.as_mut().ok_or_else(|| "There's no position yet, so send setposition first".to_string())
+    }
+
+    /// The replies to `command`, which mustn't be Quit.
+    pub fn run(&mut self, command: EngineCommand) -> Result<Vec<String>, String> {
+        match command {
+            EngineCommand::IsReady => Ok(vec!["readyok".to_string()]),
+            EngineCommand::SetPosition(rules, moves) => {
+                rules.check_players(Some(&self.guesser_name), Some(self.setup.kind))?;
+                let mut game = rules.game();
+                for (question, response) in moves {
+                    game.ask(question, response)?;
+                }
+                let mut setup = self.setup.clone();
+                setup.resolve(&game)?;
+                match self.position.take() {
+                    Some(position) if position.rules == rules => self.position = Some(Position { game, ..position }),
+                    _ => {
+                        self.position = Some(Position {
+                            rules,
+                            guesser: make_guesser(&game, &setup, &self.guesser_name),
+                            opponent: make_opponent(&game, &setup),
+                            game,
+                        })
+                    }
+                }
+                Ok(vec![])
+            }
+            EngineCommand::Go => {
+                let position = self.position()?;
+                match position.game.status() {
+                    GameResult::Ongoing => {
+                        let guess = position.guesser.choose(&position.game);
+                        Ok(vec![format!("bestquestion {}", position.game.guess(guess))])
+                    }
+                    _ => Ok(vec!["bestquestion none".to_string()]),
+                }
+            }
+            EngineCommand::Respond(question) => {
+                let position = self.position()?;
+                if position.game.status() != GameResult::Ongoing {
+                    return Err("The game is over, so there's nothing to answer".to_string());
+                }
+                position.game.check_question(&question)?;
+                let response = position.opponent.respond(&position.game, &question);
+                Ok(vec![format!("response {}", format_move(&question, response))])
+            }
+            EngineCommand::Status => Ok(vec![format!("result {}", format_result(self.position()?.game.status()))]),
+            EngineCommand::Quit => unreachable!("The caller stops on quit"),
+        }
+    }
+}
+
+/// An answer to `question`, as the letter a transcript ends the move with, L, H, E,
+/// Y, N or P, or the word low, high, equal, yes, no or pass.
+pub fn parse_response(question: &Question, answer: &str) -> Result<Response, GameError> {
+    let letter = match answer.to_lowercase().as_str() {
+        "l" | "low" => "L",
+        "h" | "high" => "H",
+        "e" | "equal" => "E",
+        "y" | "yes" => "Y",
+        "n" | "no" => "N",
+        "p" | "pass" => "P",
+        _ => "",
+    };
+    parse_move(&format!("{}{}", question, letter)).map(|(_, response)| response)
+}
+
+/// An outside opponent's reply to `question`, either as the engine answers, as in
+/// response 37H, or as parse_response reads it, if it's an answer the game allows.
+pub fn read_reply(game: &GameState, question: &Question, reply: &str) -> Option<Response> {
+    let answered = match reply.strip_prefix("response ") {
+        Some(answered) => parse_move(answered.trim()),
+        None => parse_response(question, reply).map(|response| (question.clone(), response)),
+    };
+    match answered {
+        Ok((answered, response)) if answered == *question && game.responses(question).contains(&response) => Some(response),
+        _ => None,
+    }
+}
+
+/// Another program as the opponent. For each question it is sent the position, as
+/// setposition, then respond and the question, and replies with the answer, either
+/// as the engine does, as in response 37H, or as parse_response reads it.
+/// The program is started when first asked, and can be one-lie engine itself. If
+/// it can't be started, stops, or replies with anything else, the game ends, as
+/// there's no one else to answer.
+pub struct ProgramOpponent {
+    command: String,
+    process: Option<(Child, ChildStdin, BufReader<ChildStdout>)>,
+}
+
+impl ProgramOpponent {
+    pub fn new(command: String) -> ProgramOpponent {
+        ProgramOpponent { command, process: None }
+    }
+
+    fn fail(&self, why: &str) -> ! {
+        eprintln!("error: The opponent {} {}", self.command, why);
+        exit(1);
+    }
+
+    // Sends `lines` and reads back one line, starting the program if need be.
+    fn exchange(&mut self, lines: &str) -> Result<String, String> {
+        if self.process.is_none() {
+            let mut words = self.command.split_whitespace();
+            let program = words.next().unwrap_or_default();
+            let mut child = Command::new(program)
+                .args(words)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|err| format!("couldn't be started: {}", err))?;
+            let stdin = child.stdin.take().expect("The program's input is piped");
+            let stdout = BufReader::new(child.stdout.take().expect("The program's output is piped"));
+            self.process = Some((child, stdin, stdout));
+        }
+        let (_, stdin, stdout) = self.process.as_mut().expect("The program was started");
+        stdin.write_all(lines.as_bytes())
+            .and_then(|()| stdin.flush())
+            .map_err(|err| format!("stopped listening: {}", err))?;
+        let mut reply = String::new();
+        match stdout.read_line(&mut reply) {
+            Ok(0) => Err("stopped without answering".to_string()),
+            Ok(_) => Ok(reply.trim().to_string()),
+            Err(err) => Err(format!("couldn't be heard: {}", err)),
+        }
+    }
+}
+
+impl Opponent for ProgramOpponent {
+    fn respond(&mut self, game: &GameState, question: &Question) -> Response {
+        let reply = self.exchange(&format!("{}\nrespond {}\n", set_position(game), question))
+            .unwrap_or_else(|err| self.fail(&err));
+        read_reply(game, question, &reply)
+            .unwrap_or_else(|| self.fail(&format!("replied {} to respond {}, which isn't an answer the game allows", reply, question)))
+    }
+}
+
+impl Drop for ProgramOpponent {
+    // Closing its input tells the program to stop, as quit would.
+    fn drop(&mut self) {
+        if let Some((mut child, stdin, _)) = self.process.take() {
+            drop(stdin);
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Speaks the engine protocol on standard input and output until told to quit or
+/// the input runs out.
+pub fn run_engine(setup: OpponentSetup, guesser_name: String) {
+    let mut engine = Engine::new(setup, guesser_name);
+    while let Some(line) = Terminal.read_line() {
+        if line.is_empty() {
+            continue;
+        }
+        let replies = match EngineCommand::parse(&line) {
+            Ok(EngineCommand::Quit) => break,
+            Ok(command) => engine.run(command),
+            Err(err) => Err(err),
+        };
+        match replies {
+            Ok(replies) => {
+                for reply in replies {
+                    println!("{}", reply);
+                }
+            }
+            Err(err) => println!("error {}", err),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Evolves value functions for the greedy guesser. Each genome weighs the lie
+//! hypotheses as sum(len * (A + B * log2(total)) ^ lies_left), so a range counts
+//! for more the more lies it has left, by a weight that may grow with the size of
+//! the game, as Berlekamp's volume does. Genomes are rated by self-play: each one's
+//! greedy guesser plays each one's adversary, and the random opponent, and those
+//! asking the fewest questions are bred into the next generation.
+
+use crate::guesser::Greedy;
+use crate::rng::Rng;
+use crate::setup::{OpponentKind, OpponentSetup};
+use crate::simulate::play_many;
+use crate::value::value_function;
+use crate::{GameState, Number};
+
+pub struct Evolution {
+    pub limits: Vec<Number>,
+    pub max_lies: usize,
+    pub population: usize,
+    pub generations: usize,
+    /// Games against the random opponent, for each genome at each limit
+    pub games: u64,
+    /// The seed, and the threads and time limit games are played with
+    pub setup: OpponentSetup,
+}
+
+#[derive(Clone, Copy)]
+struct Genome {
+    base: f64,
+    growth: f64,
+}
+
+// Genes are kept to three places, so the expression printed is the genome itself.
+fn gene(value: f64) -> f64 {
+    (value.max(0.0) * 1000.0).round() / 1000.0
+}
+
+impl Genome {
+    fn random(rng: &mut Rng) -> Genome {
+        Genome { base: gene(1.0 + 3.0 * rng.fraction()), growth: gene(2.0 * rng.fraction()) }
+    }
+
+    // Each gene from either parent, then each perhaps nudged.
+    fn child(&self, other: &Genome, rng: &mut Rng) -> Genome {
+        let mut pick = |mine: f64, theirs: f64, scale: f64| {
+            let inherited = if rng.chance(0.5) { mine } else { theirs };
+            if rng.chance(0.5) {
+                gene(inherited + scale * (rng.fraction() - 0.5))
+            } else {
+                inherited
+            }
+        };
+        Genome { base: pick(self.base, other.base, 1.0), growth: pick(self.growth, other.growth, 0.5) }
+    }
+
+    fn value(&self) -> String {
+        format!("sum(len * ({} + {} * log2(total)) ^ lies_left)", self.base, self.growth)
+    }
+}
+
+// The mean questions each of `guessers`' greedy guesser asks over every limit,
+// against each of `adversaries`' adversary once and the random opponent in the
+// games `seed` draws.
+fn fitness(evolution: &Evolution, guessers: &[String], adversaries: &[String], seed: u64) -> Result<Vec<f64>, String> {
+    let mut fitness = vec![];
+    for guesser in guessers {
+        let mut greedy = Greedy { value: value_function(guesser)? };
+        let (mut questions, mut games) = (0, 0);
+        for &upper_limit in &evolution.limits {
+            let game = GameState::new(upper_limit, evolution.max_lies);
+            let random = OpponentSetup { kind: OpponentKind::Random, seed: Some(seed), ..evolution.setup.clone() };
+            let mut played = play_many(&game, &random, &mut greedy, evolution.games)?;
+            for adversary in adversaries {
+                let setup = OpponentSetup { kind: OpponentKind::Adversarial, value: adversary.clone(), ..evolution.setup.clone() };
+                played.extend(play_many(&game, &setup, &mut greedy, 1)?);
+            }
+            questions += played.iter().map(|(finished, _)| finished.history().len()).sum::<usize>();
+            games += played.len();
+        }
+        fitness.push(questions as f64 / games as f64);
+    }
+    Ok(fitness)
+}
+
+pub fn evolve(evolution: &Evolution) -> Result<(), String> {
+    let seed = evolution.setup.seed.unwrap_or_else(|| Rng::from_time().next_u64());
+    println!(
+        "Evolving {} value functions over {} generations with {}, at limits {}, from seed {}",
+        evolution.population,
+        evolution.generations,
+        GameState::new(1, evolution.max_lies).describe(),
+        evolution.limits.iter().map(Number::to_string).collect::<Vec<_>>().join(", "),
+        seed
+    );
+    let mut rng = Rng::new(seed);
+    let mut population: Vec<Genome> = (0..evolution.population).map(|_| Genome::random(&mut rng)).collect();
+    let mut ranked = vec![];
+    for generation in 1..=evolution.generations {
+        let values: Vec<String> = population.iter().map(Genome::value).collect();
+        // Every genome in a generation plays the same games
+        let fitness = fitness(evolution, &values, &values, rng.next_u64())?;
+        ranked = population.iter().copied().zip(fitness).collect();
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let mean = ranked.iter().map(|&(_, fitness)| fitness).sum::<f64>() / ranked.len() as f64;
+        println!(
+            "Generation {}: the best asks {:.3} questions a game and the mean {:.3}, the best being {}",
+            generation,
+            ranked[0].1,
+            mean,
+            ranked[0].0.value()
+        );
+        // The best quarter lives on, and the best half breeds the rest
+        let parents = (ranked.len() / 2).max(1);
+        population = ranked.iter().take((ranked.len() / 4).max(1)).map(|&(genome, _)| genome).collect();
+        while population.len() < evolution.population {
+            let mother = ranked[rng.below(parents as u64) as usize].0;
+            let father = ranked[rng.below(parents as u64) as usize].0;
+            population.push(mother.child(&father, &mut rng));
+        }
+    }
+    let best = ranked[0].0.value();
+    println!();
+    println!("The best found, which asked {:.3} questions a game: --value '{}'", ranked[0].1, best);
+    // The built-in value functions, played against the last generation's adversaries
+    let adversaries: Vec<String> = ranked.iter().map(|(genome, _)| genome.value()).collect();
+    let built_in = ["better".to_string(), "berlekamp".to_string(), best];
+    let fitness = fitness(evolution, &built_in, &adversaries, rng.next_u64())?;
+    println!(
+        "Against the last generation, over new games: better asks {:.3}, berlekamp {:.3} and the best found {:.3}",
+        fitness[0], fitness[1], fitness[2]
+    );
+    Ok(())
+}
@@ -0,0 +1,568 @@
+//! Value functions written as arithmetic, for trying out heuristics without
+//! recompiling, as in `truth_len * log2(total) + lie_len`. An expression can use
+//! these statistics of the position:
+//!
+//! - `total`: the weight of every candidate, counted once per lie hypothesis,
+//!   which is the simple value
+//! - `truth_len`: the weight of the candidates that need no lies
+//! - `lie_len`: the weight of those that need some, so `total - truth_len`
+//! - `asked`: how many questions have been answered
+//! - `lies`: the most lies the game allows
+//!
+//! `sum(...)` adds up its argument over every lie hypothesis, where it can also
+//! use `len`, the weight of the hypothesis's range, and `lies_used` and
+//! `lies_left`, so `sum(len * 8 ^ lies_left)` weighs each range by its unused
+//! lies. Numbers, `+`, `-`, `*`, `/`, `^`, brackets, `log2`, `ln`, `min` and `max`
+//! work as usual.
+//!
+//! Comparisons, `<`, `<=`, `>`, `>=`, `==` and `!=`, are 1 if they hold and 0 if
+//! not, and `if(condition, then, else)` is `then` if `condition` is above 0.
+//!
+//! Expressions are worked out in f64, unlike the built-in value functions, with
+//! anything negative or not a number counting as 0. Brackets, minus signs and powers
+//! may nest 128 deep, and an expression may have up to 1000 numbers, names and
+//! operators, counting those of the names it uses, so one from a save file or a
+//! web page can't exhaust the stack.
+//!
+//! A [`Script`] uses the same language to answer questions, for trying out
+//! opponents. It is a list of lines, each naming an expression, as in
+//! `left = after_yes(total)`, with an expression to answer by on the last: yes, the
+//! number is less than the guess, if it is above 0, and no if not. A name stands
+//! for its expression wherever it is used after it. Scripts can also use `guess`,
+//! the number asked about, and `after_yes(...)` and `after_no(...)`, their argument
+//! worked out in the position after answering that way, so
+//!
+//! ```text
+//! # Whichever answer leaves more, or yes to a tie
+//! after_yes(total) >= after_no(total)
+//! ```
+//!
+//! answers as the simple adversary does. Everything after a `#` is a comment.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use crate::float::{ln, log2, powf};
+use crate::game::{Count, GameState, Number, Question, Response};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variable {
+    Total,
+    TruthLen,
+    LieLen,
+    Asked,
+    Lies,
+    // Only in scripts
+    Guess,
+    // Only inside sum
+    Len,
+    LiesUsed,
+    LiesLeft,
+}
+
+impl Variable {
+    fn parse(name: &str) -> Option<Variable> {
+        match name {
+            "total" => Some(Variable::Total),
+            "truth_len" => Some(Variable::TruthLen),
+            "lie_len" => Some(Variable::LieLen),
+            "asked" => Some(Variable::Asked),
+            "lies" => Some(Variable::Lies),
+            "guess" => Some(Variable::Guess),
+            "len" => Some(Variable::Len),
+            "lies_used" => Some(Variable::LiesUsed),
+            "lies_left" => Some(Variable::LiesLeft),
+            _ => None,
+        }
+    }
+
+    fn per_hypothesis(self) -> bool {
+        matches!(self, Variable::Len | Variable::LiesUsed | Variable::LiesLeft)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Number(f64),
+    Variable(Variable),
+    Negate(Box<Node>),
+    // The operator, as written
+    Binary(char, Box<Node>, Box<Node>),
+    // The comparison, as written
+    Compare(&'static str, Box<Node>, Box<Node>),
+    // The function's name, and its arguments
+    Call(&'static str, Vec<Node>),
+}
+
+// The position's statistics, those of the hypothesis being summed over, if any,
+// and the guess a script is answering, if any.
+struct Statistics {
+    total: f64,
+    truth_len: f64,
+    asked: f64,
+    lies: f64,
+    hypothesis: Option<(f64, f64, f64)>,
+    guess: Option<Number>,
+}
+
+impl Statistics {
+    fn of(game: &GameState, guess: Option<Number>) -> Statistics {
+        let (mut total, mut truth_len) = (0.0, 0.0);
+        for (range, lies) in game.hypotheses() {
+            total += game.mass(range) as f64;
+            if lies.is_empty() {
+                truth_len += game.mass(range) as f64;
+            }
+        }
+        Statistics {
+            total,
+            truth_len,
+            asked: game.history().len() as f64,
+            lies: game.max_lies() as f64,
+            hypothesis: None,
+            guess,
+        }
+    }
+}
+
+impl Node {
+    fn evaluate(&self, game: &GameState, statistics: &Statistics) -> f64 {
+        match *self {
+            Node::Number(number) => number,
+            Node::Variable(variable) => match (variable, statistics.hypothesis) {
+                (Variable::Total, _) => statistics.total,
+                (Variable::TruthLen, _) => statistics.truth_len,
+                (Variable::LieLen, _) => statistics.total - statistics.truth_len,
+                (Variable::Asked, _) => statistics.asked,
+                (Variable::Lies, _) => statistics.lies,
+                (Variable::Guess, _) => statistics.guess.expect("guess is checked to be in a script when parsed") as f64,
+                (Variable::Len, Some((len, _, _))) => len,
+                (Variable::LiesUsed, Some((_, used, _))) => used,
+                (Variable::LiesLeft, Some((_, _, left))) => left,
+                _ => unreachable!("Per hypothesis variables are checked to be inside sum when parsed"),
+            },
+            Node::Negate(ref node) => -node.evaluate(game, statistics),
+            Node::Binary(operator, ref left, ref right) => {
+                let (left, right) = (left.evaluate(game, statistics), right.evaluate(game, statistics));
+                match operator {
+                    '+' => left + right,
+                    '-' => left - right,
+                    '*' => left * right,
+                    '/' => left / right,
+                    _ => powf(left, right),
+                }
+            }
+            Node::Compare(operator, ref left, ref right) => {
+                let (left, right) = (left.evaluate(game, statistics), right.evaluate(game, statistics));
+                let holds = match operator {
+                    "<" => left < right,
+                    "<=" => left <= right,
+                    ">" => left > right,
+                    ">=" => left >= right,
+                    "==" => left == right,
+                    _ => left != right,
+                };
+                if holds { 1.0 } else { 0.0 }
+            }
+            Node::Call(name @ ("after_yes" | "after_no"), ref arguments) => {
+                let guess = statistics.guess.expect("after_yes and after_no are checked to be in a script when parsed");
+                let response = if name == "after_yes" { Response::Yes } else { Response::No };
+                let mut next = game.clone();
+                match next.ask(Question::Less(guess), response) {
+                    Ok(()) => arguments[0].evaluate(&next, &Statistics::of(&next, Some(guess))),
+                    // Only if the question can't be asked, when the answers don't matter
+                    Err(_) => 0.0,
+                }
+            }
+            Node::Call("sum", ref arguments) => game.hypotheses()
+                .map(|(range, lies)| {
+                    let hypothesis = Some((game.mass(range) as f64, lies.len() as f64, game.lies_left(lies.len()) as f64));
+                    arguments[0].evaluate(game, &Statistics { hypothesis, ..*statistics })
+                })
+                .sum(),
+            Node::Call(name, ref arguments) => {
+                let values: Vec<f64> = arguments.iter().map(|argument| argument.evaluate(game, statistics)).collect();
+                match name {
+                    "log2" => log2(values[0]),
+                    "ln" => ln(values[0]),
+                    "min" => values[0].min(values[1]),
+                    "if" if values[0] > 0.0 => values[1],
+                    "if" => values[2],
+                    _ => values[0].max(values[1]),
+                }
+            }
+        }
+    }
+}
+
+/// A value function written as arithmetic over the position's statistics.
+#[derive(Debug, Clone)]
+pub struct Expression {
+    root: Node,
+}
+
+impl Expression {
+    /// The expression's value in `game`, rounded down to a count.
+    pub fn value(&self, game: &GameState) -> Count {
+        // Negative numbers and NaN cast to 0, and those too big to count to Count::MAX
+        self.root.evaluate(game, &Statistics::of(game, None)) as Count
+    }
+}
+
+impl FromStr for Expression {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Expression, String> {
+        let mut parser = Parser::new(false);
+        parser.tokens = tokenize(text)?;
+        Ok(Expression { root: parser.whole()? })
+    }
+}
+
+/// An opponent's answers written as a script of expressions, which answers yes
+/// to whether the number is less than the guess when its last line is above 0.
+#[derive(Debug, Clone)]
+pub struct Script {
+    root: Node,
+}
+
+impl Script {
+    /// Whether the script answers yes to whether the number is less than `guess`.
+    pub fn says_yes(&self, game: &GameState, guess: Number) -> bool {
+        self.root.evaluate(game, &Statistics::of(game, Some(guess))) > 0.0
+    }
+}
+
+impl FromStr for Script {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Script, String> {
+        let mut parser = Parser::new(true);
+        let mut root = None;
+        for (index, line) in text.lines().enumerate() {
+            let on_line = |err: String| format!("Line {}: {}", index + 1, err);
+            let line = line.split('#').next().unwrap_or_default();
+            let tokens = tokenize(line).map_err(on_line)?;
+            if tokens.is_empty() {
+                continue;
+            }
+            if root.is_some() {
+                return Err(on_line("Only the last line may answer, the others naming expressions".to_string()));
+            }
+            let name = match (tokens.first(), tokens.get(1)) {
+                (Some(Token::Name(name)), Some(Token::Symbol('='))) => Some(name.clone()),
+                _ => None,
+            };
+            parser.tokens = tokens;
+            parser.position = if name.is_some() { 2 } else { 0 };
+            let node = parser.whole().map_err(on_line)?;
+            match name {
+                Some(name) => parser.define(name, node).map_err(on_line)?,
+                None => root = Some(node),
+            }
+        }
+        match root {
+            Some(root) => Ok(Script { root }),
+            None => Err("The script has no line to answer by".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(char),
+    Comparison(&'static str),
+}
+
+impl core::fmt::Display for Token {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match *self {
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Name(ref name) => write!(f, "{}", name),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+            Token::Comparison(comparison) => write!(f, "{}", comparison),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(index, c)) = chars.peek().filter(|&&(_, c)| c.is_ascii_digit() || c == '.') {
+                end = index + c.len_utf8();
+                chars.next();
+            }
+            let number = &text[start..end];
+            tokens.push(Token::Number(number.parse().map_err(|_| format!("Bad number {}", number))?));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(index, c)) = chars.peek().filter(|&&(_, c)| c.is_ascii_alphanumeric() || c == '_') {
+                end = index + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Name(text[start..end].to_string()));
+        } else if "+-*/^(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else if "<>=!".contains(c) {
+            chars.next();
+            let equals = chars.next_if(|&(_, c)| c == '=').is_some();
+            tokens.push(match (c, equals) {
+                ('<', false) => Token::Comparison("<"),
+                ('<', true) => Token::Comparison("<="),
+                ('>', false) => Token::Comparison(">"),
+                ('>', true) => Token::Comparison(">="),
+                ('=', true) => Token::Comparison("=="),
+                ('=', false) => Token::Symbol('='),
+                ('!', true) => Token::Comparison("!="),
+                _ => return Err("Unexpected !".to_string()),
+            });
+        } else {
+            return Err(format!("Unexpected {}", c));
+        }
+    }
+    Ok(tokens)
+}
+
+// Recursive descent, with ^ binding tightest and to the right, then unary minus,
+// then * and /, then + and -, then a comparison, of which there can be only one.
+// How deeply brackets, minus signs and powers may nest, and how large an
+// expression may grow, counting the expressions its names stand for. Parsing and
+// working out an expression both recurse into it, so without limits a long run of
+// ( in a script or a save file would overflow the stack.
+const MAX_DEPTH: usize = 128;
+const MAX_SIZE: usize = 1000;
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    in_sum: bool,
+    // Whether this is a script, which can use the guess
+    script: bool,
+    // The expressions a script has named so far with their sizes, the latest of a
+    // name last
+    names: Vec<(String, Node, usize)>,
+    // How many brackets, minus signs and powers the current token is inside
+    depth: usize,
+    // How large the current expression is so far, at most: a node for each of its
+    // tokens, and those of the expressions its names stand for
+    size: usize,
+}
+
+impl Parser {
+    fn new(script: bool) -> Parser {
+        Parser { tokens: vec![], position: 0, in_sum: false, script, names: vec![], depth: 0, size: 0 }
+    }
+
+    // The whole of the tokens, as one expression.
+    fn whole(&mut self) -> Result<Node, String> {
+        self.size = 0;
+        self.grow(self.tokens.len() - self.position)?;
+        let root = self.comparison()?;
+        match self.tokens.get(self.position) {
+            None => Ok(root),
+            Some(token) => Err(format!("Unexpected {}", token)),
+        }
+    }
+
+    // Names `node`, the expression just read.
+    fn define(&mut self, name: String, node: Node) -> Result<(), String> {
+        if Variable::parse(&name).is_some() {
+            return Err(format!("{} is already a statistic", name));
+        }
+        self.names.push((name, node, self.size));
+        Ok(())
+    }
+
+    fn grow(&mut self, size: usize) -> Result<(), String> {
+        self.size += size;
+        if self.size > MAX_SIZE {
+            return Err(format!("The expression is too large, at over {} parts", MAX_SIZE));
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.tokens.get(self.position) == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Symbol(found)) if found == symbol => Ok(()),
+            Some(token) => Err(format!("Expected {} but found {}", symbol, token)),
+            None => Err(format!("Expected {} but the expression ended", symbol)),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Node, String> {
+        let left = self.sum()?;
+        match self.tokens.get(self.position) {
+            Some(&Token::Comparison(operator)) => {
+                self.position += 1;
+                Ok(Node::Compare(operator, Box::new(left), Box::new(self.sum()?)))
+            }
+            _ => Ok(left),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Node, String> {
+        let mut node = self.product()?;
+        loop {
+            let operator = if self.eat('+') {
+                '+'
+            } else if self.eat('-') {
+                '-'
+            } else {
+                return Ok(node);
+            };
+            node = Node::Binary(operator, Box::new(node), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        loop {
+            let operator = if self.eat('*') {
+                '*'
+            } else if self.eat('/') {
+                '/'
+            } else {
+                return Ok(node);
+            };
+            node = Node::Binary(operator, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!("The expression is nested over {} deep", MAX_DEPTH));
+        }
+        self.depth += 1;
+        let node = self.power();
+        self.depth -= 1;
+        node
+    }
+
+    fn power(&mut self) -> Result<Node, String> {
+        if self.eat('-') {
+            return Ok(Node::Negate(Box::new(self.unary()?)));
+        }
+        let base = self.atom()?;
+        if self.eat('^') {
+            return Ok(Node::Binary('^', Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Node::Number(number)),
+            Some(Token::Symbol('(')) => {
+                let node = self.comparison()?;
+                self.expect(')')?;
+                Ok(node)
+            }
+            Some(Token::Name(name)) if self.eat('(') => self.call(&name),
+            Some(Token::Name(name)) if self.names.iter().any(|(named, _, _)| *named == name) => {
+                let (_, node, size) = self.names.iter().rev().find(|(named, _, _)| *named == name).expect("The name was found");
+                let (node, size) = (node.clone(), *size);
+                self.grow(size)?;
+                Ok(node)
+            }
+            Some(Token::Name(name)) => match Variable::parse(&name) {
+                Some(variable) if variable.per_hypothesis() && !self.in_sum => {
+                    Err(format!("{} is about one lie hypothesis, so only means something inside sum(...)", name))
+                }
+                Some(Variable::Guess) if !self.script => Err("guess is about a question, so only means something in a script".to_string()),
+                Some(variable) => Ok(Node::Variable(variable)),
+                None => Err(format!("Unknown statistic {}", name)),
+            },
+            Some(token) => Err(format!("Unexpected {}", token)),
+            None => Err("The expression ended early".to_string()),
+        }
+    }
+
+    // A call to `name`, whose opening bracket has been read.
+    fn call(&mut self, name: &str) -> Result<Node, String> {
+        let (name, arity) = match name {
+            "sum" if self.in_sum => return Err("sum can't be inside another sum".to_string()),
+            "sum" => ("sum", 1),
+            "after_yes" | "after_no" if !self.script => {
+                return Err(format!("{} is about a question, so only means something in a script", name));
+            }
+            "after_yes" => ("after_yes", 1),
+            "after_no" => ("after_no", 1),
+            "if" => ("if", 3),
+            "log2" => ("log2", 1),
+            "ln" => ("ln", 1),
+            "min" => ("min", 2),
+            "max" => ("max", 2),
+            _ => return Err(format!("Unknown function {}", name)),
+        };
+        let outside = self.in_sum;
+        // After an answer, the position is a new one, with no hypothesis picked out
+        self.in_sum = match name {
+            "sum" => true,
+            "after_yes" | "after_no" => false,
+            _ => outside,
+        };
+        let mut arguments = vec![self.comparison()?];
+        while self.eat(',') {
+            arguments.push(self.comparison()?);
+        }
+        self.in_sum = outside;
+        self.expect(')')?;
+        if arguments.len() != arity {
+            return Err(format!("{} takes {} argument{}, not {}", name, arity, if arity == 1 { "" } else { "s" }, arguments.len()));
+        }
+        Ok(Node::Call(name, arguments))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth: usize| format!("{}total{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(nested(MAX_DEPTH - 1).parse::<Expression>().is_ok());
+        assert!(nested(MAX_DEPTH + 1).parse::<Expression>().unwrap_err().contains("nested"));
+        assert!(format!("{}total", "-".repeat(MAX_DEPTH + 1)).parse::<Expression>().unwrap_err().contains("nested"));
+        // Deep enough to overflow the stack, if the limits didn't stop it first
+        assert!(nested(100_000).parse::<Expression>().is_err());
+    }
+
+    #[test]
+    fn size_is_limited() {
+        assert!(vec!["total"; 2000].join("+").parse::<Expression>().unwrap_err().contains("too large"));
+        // Each name doubles the one before, so without a limit this would need 2^60 nodes
+        let mut script = "a0 = total + total\n".to_string();
+        for index in 1..60 {
+            script += &format!("a{} = a{} + a{}\n", index, index - 1, index - 1);
+        }
+        script += "a59 > 0";
+        assert!(script.parse::<Script>().unwrap_err().contains("too large"));
+    }
+}
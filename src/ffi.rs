@@ -0,0 +1,338 @@
+//! A C interface, for embedding the engine in game clients and runtimes that can
+//! call C. `include/one_lie.h` declares it. A game is an opaque handle from
+//! [`one_lie_new`], freed with [`one_lie_free`]; numbers cross as `int64_t`,
+//! questions as text written as a player would type them, and answers and
+//! results as the small integers below, with what needs more than a number, the
+//! possibilities and the history, as JSON text. A call that fails returns
+//! [`ONE_LIE_ERROR`], and [`one_lie_error`] says why.
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+
+use crate::game::{check_rules, GameResult, GameState, Number, Question, Response, Variant};
+use crate::json::{answered_to_json, possibilities_to_json, Json};
+use crate::opponent::{Adversary, Opponent, RandomLiar};
+use crate::rng::Rng;
+use crate::solver::{ExpectedOpponent, MinimaxOpponent, Solver};
+use crate::value::{better_value, volume_bound, ValueFunction};
+
+/// Yes, which to a guess means the number is less than it.
+pub const ONE_LIE_YES: c_int = 0;
+/// No, which to a guess means the number is at least it.
+pub const ONE_LIE_NO: c_int = 1;
+/// The guess itself, which only answers a comparison.
+pub const ONE_LIE_EQUAL: c_int = 2;
+/// No answer at all, in games that allow passes.
+pub const ONE_LIE_PASS: c_int = 3;
+/// The call failed, for the reason [`one_lie_error`] gives.
+pub const ONE_LIE_ERROR: c_int = -1;
+
+/// The number isn't pinned down yet.
+pub const ONE_LIE_ONGOING: c_int = 0;
+/// The number is found.
+pub const ONE_LIE_FINISHED: c_int = 1;
+/// No number fits the answers with the lies allowed.
+pub const ONE_LIE_IMPOSSIBLE: c_int = 2;
+/// The questions ran out first.
+pub const ONE_LIE_LOST: c_int = 3;
+
+/// A game, with the opponent to answer its questions and the solver for hints.
+pub struct OneLieGame {
+    game: GameState,
+    // The lies allowed, each way in directional games
+    lies: usize,
+    opponent: Box<dyn Opponent>,
+    // The opponent's name and seed, to build it again when the variant changes
+    opponent_name: String,
+    seed: u64,
+    solver: Solver,
+    error: CString,
+    // The JSON text last returned
+    text: CString,
+}
+
+impl OneLieGame {
+    fn fail(&mut self, err: impl ToString) -> c_int {
+        self.error = c_string(err.to_string());
+        ONE_LIE_ERROR
+    }
+
+    fn build_opponent(&self, name: &str, seed: u64) -> Result<Box<dyn Opponent>, String> {
+        let game = &self.game;
+        let solver = || Solver::new(game.max_lies()).with_variant(game.variant());
+        Ok(match name {
+            "adversarial" => Box::new(Adversary { value: ValueFunction::Named(better_value) }),
+            "minimax" | "expected" if game.variant() == Variant::Directional => {
+                return Err("variant directional can't be used with the minimax or expected opponent".to_string());
+            }
+            "minimax" => Box::new(MinimaxOpponent { solver: solver() }),
+            "expected" => Box::new(ExpectedOpponent { solver: solver() }),
+            "random" => {
+                let mut rng = Rng::new(seed);
+                let range = game.range();
+                let secret = game.nth(range, rng.below_u128(game.count(range))).expect("The index is below the count");
+                Box::new(RandomLiar { secret, horizon: volume_bound(game.count(range), game.max_lies()), rng })
+            }
+            _ => return Err(format!("opponent must be adversarial, minimax, expected or random, not {}", name)),
+        })
+    }
+
+    // Returns `json` as text that lives until the next such call.
+    fn text(&mut self, json: Json) -> *const c_char {
+        self.text = c_string(json.to_string());
+        self.text.as_ptr()
+    }
+
+    // The question `question` says, with a number on its own asking whatever a guess
+    // asks in this game.
+    unsafe fn question(&self, question: *const c_char) -> Result<Question, String> {
+        let question = match text(question)?.trim().parse::<Question>()? {
+            Question::Less(guess) => self.game.guess(guess),
+            question => question,
+        };
+        if self.game.status() != GameResult::Ongoing {
+            return Err("The game is over".to_string());
+        }
+        self.game.check_question(&question)?;
+        Ok(question)
+    }
+}
+
+/// Starts a game of guessing a number at least `lower` and less than `limit`,
+/// with up to `lies` lies, answered by the adversary. Returns null if there are
+/// fewer than two numbers to guess among, or more than
+/// [`MAX_LIES`](crate::game::MAX_LIES) lies.
+#[no_mangle]
+pub extern "C" fn one_lie_new(lower: i64, limit: i64, lies: u32) -> *mut OneLieGame {
+    if check_rules(lower as Number, limit as Number, lies as usize).is_err() {
+        return ptr::null_mut();
+    }
+    let game = GameState::new(limit as Number, lies as usize).with_lower_limit(lower as Number);
+    let solver = Solver::new(game.max_lies());
+    let opponent = Box::new(Adversary { value: ValueFunction::Named(better_value) });
+    Box::into_raw(Box::new(OneLieGame {
+        game,
+        lies: lies as usize,
+        opponent,
+        opponent_name: "adversarial".to_string(),
+        seed: 0,
+        solver,
+        error: CString::default(),
+        text: CString::default(),
+    }))
+}
+
+/// Plays the game under the `variant` named as on the command line, such as
+/// `"three-way"`, before any question is asked. Directional games allow the
+/// lies given to [`one_lie_new`] each way. Returns 0 if the variant exists.
+///
+/// # Safety
+///
+/// `game` must be a live game and `variant` a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn one_lie_set_variant(game: *mut OneLieGame, variant: *const c_char) -> c_int {
+    let game = &mut *game;
+    let variant = match text(variant).and_then(|name| Ok(Variant::parse(name)?)) {
+        Ok(variant) => variant,
+        Err(err) => return game.fail(err),
+    };
+    if game.game.turns() > 0 {
+        return game.fail("The variant can only be set before the first question");
+    }
+    let lies = if variant == Variant::Directional { 2 * game.lies } else { game.lies };
+    let previous = game.game.clone();
+    game.game = game.game.clone().with_max_lies(lies).with_variant(variant);
+    match game.build_opponent(&game.opponent_name, game.seed) {
+        Ok(opponent) => {
+            game.opponent = opponent;
+            game.solver = Solver::new(game.game.max_lies()).with_variant(variant);
+            0
+        }
+        Err(err) => {
+            game.game = previous;
+            game.fail(err)
+        }
+    }
+}
+
+/// Has the opponent `name`d answer the questions asked from now on: `"adversarial"`,
+/// the default, `"minimax"`, `"expected"`, or `"random"`, which picks its number
+/// and its lies from `seed`. Returns 0 if there is such an opponent.
+///
+/// # Safety
+///
+/// `game` must be a live game and `name` a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn one_lie_set_opponent(game: *mut OneLieGame, name: *const c_char, seed: u64) -> c_int {
+    let game = &mut *game;
+    let built = text(name).and_then(|name| Ok((name, game.build_opponent(name, seed)?)));
+    match built {
+        Ok((name, opponent)) => {
+            game.opponent = opponent;
+            game.opponent_name = name.to_string();
+            game.seed = seed;
+            0
+        }
+        Err(err) => game.fail(err),
+    }
+}
+
+/// Frees a game. Null is ignored.
+///
+/// # Safety
+///
+/// `game` must be null or come from [`one_lie_new`], and not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn one_lie_free(game: *mut OneLieGame) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Asks the adversary `question`, such as `"37"` or `"=42"`, returning its answer.
+///
+/// # Safety
+///
+/// `game` must be a live game and `question` a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn one_lie_guess(game: *mut OneLieGame, question: *const c_char) -> c_int {
+    let game = &mut *game;
+    let question = match game.question(question) {
+        Ok(question) => question,
+        Err(err) => return game.fail(err),
+    };
+    let response = game.opponent.respond(&game.game, &question);
+    match game.game.ask(question, response) {
+        Ok(()) => response_code(response),
+        Err(err) => game.fail(err),
+    }
+}
+
+/// Records your own `response` to `question`, for when the game's answers come
+/// from somewhere else, returning 0 if it fits the rules.
+///
+/// # Safety
+///
+/// `game` must be a live game and `question` a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn one_lie_respond(game: *mut OneLieGame, question: *const c_char, response: c_int) -> c_int {
+    let game = &mut *game;
+    let response = match response {
+        ONE_LIE_YES => Response::Yes,
+        ONE_LIE_NO => Response::No,
+        ONE_LIE_EQUAL => Response::Equal,
+        ONE_LIE_PASS => Response::Pass,
+        _ => return game.fail(format!("{} isn't an answer", response)),
+    };
+    let question = match game.question(question) {
+        Ok(question) => question,
+        Err(err) => return game.fail(err),
+    };
+    match game.game.ask(question, response) {
+        Ok(()) => 0,
+        Err(err) => game.fail(err),
+    }
+}
+
+/// How the game stands, writing the number to `number` once it is found.
+///
+/// # Safety
+///
+/// `game` must be a live game, and `number` null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn one_lie_result(game: *const OneLieGame, number: *mut i64) -> c_int {
+    match (*game).game.status() {
+        GameResult::Ongoing => ONE_LIE_ONGOING,
+        GameResult::Finished(answer) => {
+            if !number.is_null() {
+                *number = answer as i64;
+            }
+            ONE_LIE_FINISHED
+        }
+        GameResult::Impossible => ONE_LIE_IMPOSSIBLE,
+        GameResult::Lost => ONE_LIE_LOST,
+    }
+}
+
+/// Writes the best number to guess next to `guess`, returning how many more
+/// questions it guarantees finishing in, or [`ONE_LIE_ERROR`] once the game is over.
+///
+/// # Safety
+///
+/// `game` must be a live game and `guess` writable.
+#[no_mangle]
+pub unsafe extern "C" fn one_lie_best_question(game: *mut OneLieGame, guess: *mut i64) -> c_int {
+    let game = &mut *game;
+    if game.game.status() != GameResult::Ongoing {
+        return game.fail("The game is over");
+    }
+    if game.game.variant() == Variant::Directional {
+        return game.fail("variant directional can't be used with the minimax guesser");
+    }
+    match game.solver.best_question(&game.game) {
+        Some((best, needed)) => {
+            *guess = best as i64;
+            needed as c_int
+        }
+        None => game.fail("There's no question to ask"),
+    }
+}
+
+/// What the number could still be, as a JSON array of ranges, each with the
+/// questions that were lies if the number is in it. Valid until the next call
+/// that returns text.
+///
+/// # Safety
+///
+/// `game` must be a live game.
+#[no_mangle]
+pub unsafe extern "C" fn one_lie_possibilities(game: *mut OneLieGame) -> *const c_char {
+    let game = &mut *game;
+    let possibilities = possibilities_to_json(&game.game);
+    game.text(possibilities)
+}
+
+/// The questions asked so far with their answers, as a JSON array of pairs such
+/// as `["50", "Low"]`. Valid until the next call that returns text.
+///
+/// # Safety
+///
+/// `game` must be a live game.
+#[no_mangle]
+pub unsafe extern "C" fn one_lie_history(game: *mut OneLieGame) -> *const c_char {
+    let game = &mut *game;
+    let history = game.game.history().iter().map(|(question, response)| answered_to_json(question, *response)).collect();
+    game.text(Json::Array(history))
+}
+
+/// Why the last call on `game` that failed did, valid until the next call.
+///
+/// # Safety
+///
+/// `game` must be a live game.
+#[no_mangle]
+pub unsafe extern "C" fn one_lie_error(game: *const OneLieGame) -> *const c_char {
+    (*game).error.as_ptr()
+}
+
+// The string `text` points to.
+unsafe fn text<'a>(text: *const c_char) -> Result<&'a str, String> {
+    if text.is_null() {
+        return Err("The text is null".to_string());
+    }
+    CStr::from_ptr(text).to_str().map_err(|_| "The text isn't UTF-8".to_string())
+}
+
+fn c_string(text: String) -> CString {
+    // A C string can't hold a nul, and ours never do
+    CString::new(text.replace('\0', "")).unwrap_or_default()
+}
+
+fn response_code(response: Response) -> c_int {
+    match response {
+        Response::Yes => ONE_LIE_YES,
+        Response::No => ONE_LIE_NO,
+        Response::Equal => ONE_LIE_EQUAL,
+        Response::Pass => ONE_LIE_PASS,
+    }
+}
@@ -0,0 +1,116 @@
+// The few float functions the game needs, which core doesn't have. With std they
+// are std's own; on targets without an operating system, and so without std,
+// they are worked out here, to about twelve places, which is plenty for priors
+// and value functions.
+
+#[cfg(not(target_os = "none"))]
+pub fn ln(x: f64) -> f64 {
+    x.ln()
+}
+
+#[cfg(not(target_os = "none"))]
+pub fn log2(x: f64) -> f64 {
+    x.log2()
+}
+
+#[cfg(not(target_os = "none"))]
+pub fn powf(x: f64, y: f64) -> f64 {
+    x.powf(y)
+}
+
+#[cfg(not(target_os = "none"))]
+pub fn powi(x: f64, n: i32) -> f64 {
+    x.powi(n)
+}
+
+#[cfg(target_os = "none")]
+use core::f64::consts::LN_2;
+
+// Writes x as m * 2^e with m in [1, 2), for x positive and finite, then sums
+// ln m = 2 atanh((m - 1) / (m + 1)) as a series, whose terms shrink at least
+// ninefold each.
+#[cfg(target_os = "none")]
+pub fn ln(x: f64) -> f64 {
+    if x.is_nan() || x < 0.0 {
+        return f64::NAN;
+    }
+    if x == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if x.is_infinite() {
+        return x;
+    }
+    // Subnormals are scaled up first, so their exponent bits mean what they say
+    let (x, shift) = if x < f64::MIN_POSITIVE { (x * (1u64 << 54) as f64, -54) } else { (x, 0) };
+    let bits = x.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i32 - 1023 + shift;
+    let mantissa = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+    let s = (mantissa - 1.0) / (mantissa + 1.0);
+    let (mut term, mut sum, mut k) = (s, 0.0f64, 1.0);
+    while term.abs() > 1e-18 * sum.abs() + f64::MIN_POSITIVE {
+        sum += term / k;
+        term *= s * s;
+        k += 2.0;
+    }
+    f64::from(exponent) * LN_2 + 2.0 * sum
+}
+
+#[cfg(target_os = "none")]
+pub fn log2(x: f64) -> f64 {
+    ln(x) / LN_2
+}
+
+// Writes y as k ln 2 + r with |r| at most ln 2 / 2, sums e^r as a Taylor series
+// and doubles or halves it k times.
+#[cfg(target_os = "none")]
+fn exp(y: f64) -> f64 {
+    if y.is_nan() {
+        return y;
+    }
+    if y > 710.0 {
+        return f64::INFINITY;
+    }
+    if y < -746.0 {
+        return 0.0;
+    }
+    let k = (y / LN_2 + if y < 0.0 { -0.5 } else { 0.5 }) as i32;
+    let r = y - f64::from(k) * LN_2;
+    let (mut term, mut sum, mut n) = (1.0f64, 1.0, 1.0);
+    while term.abs() > 1e-18 {
+        term *= r / n;
+        sum += term;
+        n += 1.0;
+    }
+    sum * powi(2.0, k)
+}
+
+#[cfg(target_os = "none")]
+pub fn powf(x: f64, y: f64) -> f64 {
+    if y == 0.0 {
+        1.0
+    } else if x == 0.0 {
+        if y > 0.0 { 0.0 } else { f64::INFINITY }
+    } else if x < 0.0 && y == f64::from(y as i32) {
+        powi(x, y as i32)
+    } else {
+        exp(y * ln(x))
+    }
+}
+
+// Squares and multiplies, one bit of n at a time.
+#[cfg(target_os = "none")]
+pub fn powi(x: f64, n: i32) -> f64 {
+    let (mut base, mut bits, mut result) = (x, n.unsigned_abs(), 1.0);
+    while bits > 0 {
+        if bits & 1 == 1 {
+            result *= base;
+        }
+        base *= base;
+        bits >>= 1;
+    }
+    if n < 0 {
+        1.0 / result
+    } else {
+        result
+    }
+}
@@ -0,0 +1,356 @@
+//! The ways an interactive game can be shown to the player: plain lines of text,
+//! or a full screen redrawn every turn.
+
+use std::io::{stdout, Write};
+
+use crate::display::number_line;
+use crate::game::GameResult::Ongoing;
+use crate::game::Question;
+use crate::game::Response::*;
+use crate::guesser::Median;
+use crate::input::Input;
+use crate::json::{possibilities_to_json, response_to_json, Json, ToJson};
+use crate::style::Style;
+use crate::{GameState, Guesser, Range, Solver, Variant};
+
+// The hypotheses that the last answer ruled out, as they were before it.
+fn ruled_out(game: &GameState) -> Vec<(Range, Vec<usize>)> {
+    let mut before = game.clone();
+    if before.undo().is_none() {
+        return vec![];
+    }
+    let last = before.history().len();
+    let now: Vec<Vec<usize>> = game.possibilities().into_iter()
+        .filter(|&(range, _)| !range.is_empty())
+        .map(|(_, lies)| lies)
+        .collect();
+    before.possibilities().into_iter()
+        .filter(|(range, lies)| {
+            let mut lied_last = lies.clone();
+            lied_last.push(last);
+            !range.is_empty() && !now.contains(lies) && !now.contains(&lied_last)
+        })
+        .collect()
+}
+
+fn describe_hypothesis(range: Range, lies: &[usize]) -> String {
+    if lies.is_empty() {
+        format!("    {}..{} if there were no lies", range.lower, range.higher)
+    } else {
+        format!("    {}..{} if question(s) {:?} were lies", range.lower, range.higher, lies)
+    }
+}
+
+/// Which numbers are still possible, and under which lies, with the hypotheses the
+/// last answer ruled out dimmed below them.
+pub fn survivors(game: &GameState, width: usize, style: Style) -> String {
+    let mut text = number_line(game, width);
+    if let Some(lie_probability) = game.lie_probability() {
+        let likeliest = game.posterior(lie_probability).into_iter()
+            .filter(|&(range, _)| !range.is_empty())
+            .fold(None, |best: Option<(Range, f64)>, (range, probability)| match best {
+                Some((_, best_probability)) if best_probability >= probability => best,
+                _ => Some((range, probability)),
+            });
+        if let Some((range, probability)) = likeliest {
+            text += &format!("    Most likely {}, at {:.2}%\n", range.lower, probability * 100.0);
+        }
+        return text;
+    }
+    let survivors: Vec<(Range, Vec<usize>)> = game.possibilities().into_iter()
+        .filter(|&(range, _)| !range.is_empty())
+        .collect();
+    if survivors.is_empty() {
+        text += "    Nothing is possible any more\n";
+    }
+    for (range, lies) in survivors {
+        text += &describe_hypothesis(range, &lies);
+        text += "\n";
+    }
+    for (range, lies) in ruled_out(game) {
+        text += &style.dim(&format!("{}, ruled out", describe_hypothesis(range, &lies)));
+        text += "\n";
+    }
+    text
+}
+
+/// The best next question, as the engine sees it.
+pub fn hint(game: &GameState, solver: &mut Solver) -> Option<String> {
+    if game.delay() > 0 {
+        Some("There are no hints with delayed answers, as the engine only plans with answers it has heard".to_string())
+    } else if game.moving_target().is_some() {
+        Some("There are no hints when the number moves, as the engine assumes it stays put".to_string())
+    } else if game.variant() == Variant::Directional {
+        Some("There are no hints with directional lies, as the engine doesn't track which way they went".to_string())
+    } else if game.final_guess() {
+        Some("There are no hints in final-guess games, as the engine doesn't count the announcements".to_string())
+    } else if game.lie_probability().is_some() {
+        Some(format!("Try asking about {}, which splits the probability in half", Median.choose(game)))
+    } else {
+        // Passes tell the guesser nothing, so each one left adds a question
+        solver.best_question_in_time(game).map(|question| match question.at_most {
+            Some(needed) => format!(
+                "Try asking about {}, which guarantees finishing in {} more question(s)",
+                question.guess,
+                needed + game.passes_left()
+            ),
+            None => format!(
+                "Try asking about {}, the most even split found in time, as at least {} more question(s) are needed",
+                question.guess,
+                question.at_least + game.passes_left()
+            ),
+        })
+    }
+}
+
+pub trait Frontend {
+    /// Called once before the first question.
+    fn start(&mut self, _game: &GameState) {}
+    /// Tells the player something: a hint, a result, or a mistake in their input.
+    fn say(&mut self, text: &str);
+    /// Called after each answer, once it is stored in the game.
+    fn answered(&mut self, game: &GameState);
+    /// Shows which numbers are still possible.
+    fn show_state(&mut self, game: &GameState);
+    /// Asks for the player's next question or command, or None if the input has
+    /// run out.
+    fn prompt(&mut self, game: &GameState) -> Option<String>;
+    /// Called once the game is over, after everything about the result has been said.
+    fn finish(&mut self, _game: &GameState) {}
+}
+
+/// How a game is shown as it is played.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    Text,
+    Tui,
+    Json,
+    /// A page in the browser, rather than the terminal
+    Gui,
+}
+
+/// What the last answer said, as a sentence.
+pub fn describe_answer(game: &GameState, style: Style) -> String {
+    let &(ref question, response) = game.history().last().expect("An answer was given");
+    style.response(response, &question.describe_answer(response))
+}
+
+/// Prints everything as it happens, one line after another.
+pub struct Lines {
+    pub style: Style,
+    pub input: Box<dyn Input>,
+}
+
+impl Frontend for Lines {
+    fn say(&mut self, text: &str) {
+        println!("{}", text);
+    }
+    fn answered(&mut self, game: &GameState) {
+        println!("{}", describe_answer(game, self.style));
+    }
+    fn show_state(&mut self, game: &GameState) {
+        print!("{}", survivors(game, 60, self.style));
+    }
+    fn prompt(&mut self, game: &GameState) -> Option<String> {
+        let ask = match game.variant() {
+            Variant::ThreeWay => "What number do you want to compare it with?",
+            _ => "What number do you want to know if it's less than?",
+        };
+        println!("{}: {} (help lists the other questions)", game.asked(), ask);
+        self.input.read_line()
+    }
+}
+
+/// Shows nothing, for games no one is watching.
+pub struct Silent;
+
+impl Frontend for Silent {
+    fn say(&mut self, _text: &str) {}
+    fn answered(&mut self, _game: &GameState) {}
+    fn show_state(&mut self, _game: &GameState) {}
+    fn prompt(&mut self, _game: &GameState) -> Option<String> {
+        unreachable!("Only the computer plays unwatched games")
+    }
+}
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+const WIDTH: usize = 80;
+const HISTORY_WIDTH: usize = 24;
+const HISTORY_ROWS: usize = 16;
+const MESSAGE_ROWS: usize = 4;
+
+/// Redraws the whole terminal every turn, with the questions so far beside what is
+/// still possible, and the engine's suggestion and recent messages below.
+pub struct Tui {
+    pub solver: Solver,
+    pub style: Style,
+    input: Box<dyn Input>,
+    messages: Vec<String>,
+}
+
+impl Tui {
+    pub fn new(solver: Solver, style: Style, input: Box<dyn Input>) -> Tui {
+        Tui {
+            solver,
+            style,
+            input,
+            messages: vec![],
+        }
+    }
+
+    fn draw(&mut self, game: &GameState, input_box: bool) {
+        let rule = "-".repeat(WIDTH);
+        let mut screen = CLEAR_SCREEN.to_string();
+        screen += &format!("one-lie: guess the number, with {}, {}\n", game.describe(), game.describe_range());
+        screen += &format!("{:<width$} | Possibilities\n", "Questions", width = HISTORY_WIDTH);
+        screen += &rule;
+        screen += "\n";
+        let history = game.history();
+        let shown = history.len().saturating_sub(HISTORY_ROWS);
+        // Padded before colouring, since the colour codes take no room on screen
+        let questions: Vec<String> = history.iter().enumerate().skip(shown)
+            .map(|(index, &(ref question, response))| {
+                let answer = match (question, response) {
+                    (_, Pass) => "pass",
+                    (Question::Less(_), Yes) => "less",
+                    (Question::Less(_), _) => "not less",
+                    (Question::Equal(_), Yes) => "equal",
+                    (Question::Equal(_), _) => "not equal",
+                    (Question::Within(_) | Question::Subset(_), Yes) => "in",
+                    (Question::Within(_) | Question::Subset(_), _) => "not in",
+                    (Question::Bit(_), Yes) => "set",
+                    (Question::Bit(_), _) => "clear",
+                    (Question::Compare(_), Yes) => "less",
+                    (Question::Compare(_), Equal) => "equal",
+                    (Question::Compare(_), No) => "greater",
+                };
+                let row = format!("{:>3}. {:>8}  {}", index, question.to_string(), answer);
+                self.style.response(response, &format!("{:<width$}", row, width = HISTORY_WIDTH))
+            })
+            .collect();
+        let state = survivors(game, WIDTH - HISTORY_WIDTH - 12, self.style);
+        let possibilities: Vec<&str> = state.lines().collect();
+        let blank = " ".repeat(HISTORY_WIDTH);
+        for row in 0..questions.len().max(possibilities.len()) {
+            screen += &format!(
+                "{} |{}\n",
+                questions.get(row).unwrap_or(&blank),
+                possibilities.get(row).unwrap_or(&"")
+            );
+        }
+        screen += &rule;
+        screen += "\n";
+        let evaluation = hint(game, &mut self.solver).unwrap_or_else(|| "The game is over".to_string());
+        screen += &format!("Engine: {}\n", evaluation);
+        screen += &rule;
+        screen += "\n";
+        let recent = self.messages.len().saturating_sub(MESSAGE_ROWS);
+        for message in &self.messages[recent..] {
+            screen += message;
+            screen += "\n";
+        }
+        if input_box {
+            screen += &rule;
+            screen += &format!("\n{}: Less than? > ", game.asked());
+        }
+        print!("{}", screen);
+        stdout().flush().expect("Failed to write stdout");
+    }
+}
+
+impl Frontend for Tui {
+    fn say(&mut self, text: &str) {
+        self.messages.extend(text.lines().filter(|line| !line.is_empty()).map(str::to_string));
+    }
+    fn answered(&mut self, game: &GameState) {
+        self.messages.push(describe_answer(game, self.style));
+    }
+    // The possibilities are always on screen.
+    fn show_state(&mut self, _game: &GameState) {}
+    fn prompt(&mut self, game: &GameState) -> Option<String> {
+        self.draw(game, true);
+        self.input.read_line()
+    }
+    fn finish(&mut self, game: &GameState) {
+        self.draw(game, false);
+    }
+}
+
+/// Prints one JSON object per line for everything that happens, each with an
+/// "event" field saying what it is, for programs driving the game.
+pub struct JsonLines {
+    pub solver: Solver,
+    input: Box<dyn Input>,
+}
+
+impl JsonLines {
+    pub fn new(solver: Solver, input: Box<dyn Input>) -> JsonLines {
+        JsonLines { solver, input }
+    }
+
+    fn emit(&self, event: &str, mut fields: Vec<(&str, Json)>) {
+        fields.insert(0, ("event", Json::Str(event.to_string())));
+        println!("{}", Json::object(fields));
+    }
+
+    // The engine's suggested question, and for games with a fixed number of lies,
+    // how many questions it guarantees finishing in.
+    fn evaluation(&mut self, game: &GameState) -> Json {
+        if game.status() != Ongoing || game.delay() > 0 || game.moving_target().is_some() || game.variant() == Variant::Directional
+            || game.final_guess()
+        {
+            return Json::Null;
+        }
+        if game.lie_probability().is_some() {
+            return Json::object(vec![
+                ("best_guess", Json::Int(Median.choose(game))),
+                ("questions_needed", Json::Null),
+            ]);
+        }
+        match self.solver.best_question(game) {
+            None => Json::Null,
+            Some((guess, needed)) => Json::object(vec![
+                ("best_guess", Json::Int(guess)),
+                ("questions_needed", Json::Int((needed + game.passes_left()) as i128)),
+            ]),
+        }
+    }
+}
+
+impl Frontend for JsonLines {
+    fn start(&mut self, game: &GameState) {
+        self.emit("start", vec![("game", game.to_json())]);
+    }
+    fn say(&mut self, text: &str) {
+        self.emit("message", vec![("text", Json::Str(text.trim().to_string()))]);
+    }
+    fn answered(&mut self, game: &GameState) {
+        let &(ref question, response) = game.history().last().expect("An answer was given");
+        let evaluation = self.evaluation(game);
+        self.emit("turn", vec![
+            ("question", Json::Int(game.history().len() as i128 - 1)),
+            ("guess", question.to_json()),
+            ("response", response_to_json(question, response)),
+            ("possibilities", possibilities_to_json(game)),
+            ("evaluation", evaluation),
+            ("status", game.status().to_json()),
+        ]);
+    }
+    fn show_state(&mut self, game: &GameState) {
+        self.emit("state", vec![
+            ("possibilities", possibilities_to_json(game)),
+            ("status", game.status().to_json()),
+        ]);
+    }
+    fn prompt(&mut self, game: &GameState) -> Option<String> {
+        self.emit("prompt", vec![("question", Json::Int(game.asked() as i128))]);
+        self.input.read_line()
+    }
+    fn finish(&mut self, game: &GameState) {
+        self.emit("end", vec![
+            ("status", game.status().to_json()),
+            ("questions", Json::Int(game.turns() as i128)),
+            ("game", game.to_json()),
+        ]);
+    }
+}
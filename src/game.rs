@@ -0,0 +1,1642 @@
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+use core::fmt;
+
+use crate::float::{ln, powf, powi};
+
+/// The type of the numbers being guessed, signed and wide enough for enormous games.
+pub type Number = i128;
+
+/// How many numbers there are in a range, which can be more than any one Number.
+pub type Count = u128;
+
+/// A half open range of candidate numbers, `lower..higher`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub lower: Number,
+    pub higher: Number,
+}
+
+/// An answer to "is it less than the guess?": `Low` for yes, `High` for no.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dir {
+    High,
+    Low,
+}
+
+use self::Dir::*;
+
+impl Dir {
+    pub fn opposite(self) -> Self {
+        match self {
+            High => Low,
+            Low => High,
+        }
+    }
+}
+
+impl Range {
+    pub fn new(lower: Number, higher: Number) -> Range {
+        Range { lower, higher }
+    }
+    pub fn clamp_lower(&self, clamp: Number) -> Range {
+        Range {
+            lower: max(self.lower, clamp),
+            higher: self.higher,
+        }
+    }
+    pub fn clamp_higher(&self, clamp: Number) -> Range {
+        Range {
+            lower: self.lower,
+            higher: min(self.higher, clamp),
+        }
+    }
+    pub fn len(&self) -> Count {
+        if self.higher > self.lower {
+            (self.higher as Count).wrapping_sub(self.lower as Count)
+        } else {
+            0
+        }
+    }
+    /// The number `index` places above the start of the range.
+    pub fn offset(&self, index: Count) -> Number {
+        (self.lower as Count).wrapping_add(index) as Number
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The part of the range consistent with `truth` being the real answer about `guess`.
+    pub fn restrict(&self, guess: Number, truth: Dir) -> Range {
+        match truth {
+            High => self.clamp_lower(guess),
+            Low => self.clamp_higher(guess),
+        }
+    }
+}
+
+/// A question about the secret, answered yes or no.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Question {
+    /// Is it less than this number?
+    Less(Number),
+    /// Is it exactly this number?
+    Equal(Number),
+    /// Is it in this range, at least its lower end and less than its higher one?
+    Within(Range),
+    /// Is it in any of these ranges? They are sorted and don't touch, as made by
+    /// [`Question::subset`].
+    Subset(Vec<Range>),
+    /// Is this bit of the number set? Bit 0 asks whether it's odd, and bit 127
+    /// whether it's negative, since numbers are two's complement.
+    Bit(u32),
+    /// Is it less than, equal to or greater than this number? Only asked in
+    /// three-way games, and answered `Yes` for less, `Equal` or `No` for greater.
+    Compare(Number),
+}
+
+// The highest bit of a Number, which no block of numbers sharing a bit is half of.
+const SIGN_BIT: u32 = Number::BITS - 1;
+
+// The runs of numbers in `range` whose bit `bit` gives `response`. Each run is
+// a block of 2^bit numbers, apart perhaps from the first and last.
+fn bit_runs(range: Range, bit: u32, response: Response) -> Vec<Range> {
+    let size: Number = 1 << bit;
+    let mut runs = vec![];
+    let mut start = range.lower;
+    while start < range.higher {
+        // Shifting rounds towards minus infinity, finding the block start is in
+        let end = ((start >> bit) << bit).checked_add(size).map_or(range.higher, |end| min(end, range.higher));
+        let set = (start >> bit) & 1 == 1;
+        if set == (response == Response::Yes) {
+            runs.push(Range::new(start, end));
+        }
+        start = end;
+    }
+    runs
+}
+
+/// An answer to a [`Question`], which may be a lie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Response {
+    Yes,
+    No,
+    /// The guess itself, which only answers a [`Question::Compare`].
+    Equal,
+    /// No answer at all, which rules nothing out and is never a lie, but uses up
+    /// the question. Only given in games that allow passes, no more often than
+    /// they allow.
+    Pass,
+}
+
+/// "Is it less than the guess?" is answered yes by `Low` and no by `High`.
+impl From<Dir> for Response {
+    fn from(dir: Dir) -> Response {
+        match dir {
+            Low => Response::Yes,
+            High => Response::No,
+        }
+    }
+}
+
+/// The other way round, for answers to "is it less than the guess?". An answer
+/// of `Equal` is not less, so `High`, and neither is a pass.
+impl From<Response> for Dir {
+    fn from(response: Response) -> Dir {
+        match response {
+            Response::Yes => Low,
+            Response::No | Response::Equal | Response::Pass => High,
+        }
+    }
+}
+
+impl Question {
+    /// "Is it in any of `ranges`?", which may overlap and come in any order. When
+    /// they join up into one range this is the same as asking about that range.
+    pub fn subset(ranges: impl IntoIterator<Item = Range>) -> Question {
+        let set = PossibilitySet::new(ranges);
+        match *set.ranges() {
+            [range] => Question::Within(range),
+            _ => Question::Subset(set.ranges().to_vec()),
+        }
+    }
+    /// The true answer if the secret is `secret`.
+    pub fn truth(&self, secret: Number) -> Response {
+        let yes = match *self {
+            Question::Compare(guess) if secret == guess => return Response::Equal,
+            Question::Compare(guess) => secret < guess,
+            Question::Less(guess) => secret < guess,
+            Question::Equal(guess) => secret == guess,
+            Question::Within(interval) => interval.lower <= secret && secret < interval.higher,
+            Question::Subset(ref ranges) => ranges.iter().any(|range| range.lower <= secret && secret < range.higher),
+            Question::Bit(bit) => (secret >> min(bit, SIGN_BIT)) & 1 == 1,
+        };
+        if yes {
+            Response::Yes
+        } else {
+            Response::No
+        }
+    }
+    /// Whether `response` would be a lie if the secret were `secret`: an answer,
+    /// rather than a pass, that isn't the true one.
+    pub fn is_lie(&self, secret: Number, response: Response) -> bool {
+        response != Response::Pass && response != self.truth(secret)
+    }
+    /// Every answer the question may get, true or not, apart from a pass.
+    pub fn responses(&self) -> &'static [Response] {
+        match *self {
+            Question::Compare(_) => &[Response::Yes, Response::No, Response::Equal],
+            _ => &[Response::Yes, Response::No],
+        }
+    }
+    /// The answers that would be lies if `truth` were the true answer.
+    pub fn lies(&self, truth: Response) -> impl Iterator<Item = Response> {
+        self.responses().iter().cloned().filter(move |&response| response != truth)
+    }
+    // The ranges the answer is yes for, in order. Number::MAX is never a candidate,
+    // since limits are exclusive, so a range may stop short of it. Questions about
+    // bits are answered by bit_runs instead.
+    fn yes_ranges(&self) -> Vec<Range> {
+        match *self {
+            Question::Less(guess) | Question::Compare(guess) => vec![Range::new(Number::MIN, guess)],
+            Question::Equal(guess) => vec![Range::new(guess, guess.saturating_add(1))],
+            Question::Within(interval) => vec![interval],
+            Question::Subset(ref ranges) => ranges.clone(),
+            Question::Bit(_) => vec![],
+        }
+    }
+    /// The parts of `range` for which `response` is the true answer, leaving out
+    /// any that are empty. A question about a range, a subset or a bit can split
+    /// a range into several parts, and a pass fits the whole range.
+    pub fn restrict(&self, range: Range, response: Response) -> impl Iterator<Item = Range> {
+        let yes = match *self {
+            Question::Less(guess) | Question::Compare(guess) => Range::new(Number::MIN, guess),
+            Question::Equal(guess) => Range::new(guess, guess.saturating_add(1)),
+            Question::Within(interval) => interval,
+            // The sign bit, set exactly for the negative numbers
+            Question::Bit(bit) if bit >= SIGN_BIT => Range::new(Number::MIN, 0),
+            // Worked out below
+            Question::Subset(_) | Question::Bit(_) => Range::new(0, 0),
+        };
+        // Most questions are about a single range, which this answers without
+        // allocating, leaving the list of parts for subsets and bits empty
+        let mut parts = match (self, response) {
+            (_, Response::Pass) => [Range::new(Number::MIN, Number::MAX), Range::new(0, 0)],
+            (&Question::Compare(guess), Response::Equal) => [Range::new(guess, guess.saturating_add(1)), Range::new(0, 0)],
+            (&Question::Compare(guess), Response::No) => [Range::new(guess.saturating_add(1), Number::MAX), Range::new(0, 0)],
+            (_, Response::Yes) => [yes, Range::new(0, 0)],
+            (_, Response::No) => [Range::new(Number::MIN, yes.lower), Range::new(yes.higher, Number::MAX)],
+            // Only comparisons are answered Equal
+            (_, Response::Equal) => [Range::new(0, 0); 2],
+        };
+        let mut more_parts = vec![];
+        match *self {
+            _ if response == Response::Pass => {}
+            Question::Subset(ref ranges) => {
+                parts = [Range::new(0, 0); 2];
+                more_parts = match response {
+                    Response::Yes => ranges.clone(),
+                    Response::Equal | Response::Pass => vec![],
+                    Response::No => {
+                        let mut lower = Number::MIN;
+                        let mut gaps = vec![];
+                        for part in ranges {
+                            gaps.push(Range::new(lower, part.lower));
+                            lower = part.higher;
+                        }
+                        gaps.push(Range::new(lower, Number::MAX));
+                        gaps
+                    }
+                };
+            }
+            Question::Bit(bit) if bit < SIGN_BIT && response != Response::Equal => {
+                parts = [Range::new(0, 0); 2];
+                more_parts = bit_runs(range, bit, response);
+            }
+            _ => {}
+        }
+        IntoIterator::into_iter(parts).chain(more_parts)
+            .map(move |part| range.clamp_lower(part.lower).clamp_higher(part.higher))
+            .filter(|part| !part.is_empty())
+    }
+    // Where the true answer changes from one number to the next, among the numbers
+    // in `limits`.
+    pub(crate) fn bounds(&self, limits: Range) -> Vec<Number> {
+        match *self {
+            Question::Less(guess) => vec![guess],
+            Question::Equal(guess) | Question::Compare(guess) => vec![guess, guess.saturating_add(1)],
+            Question::Within(interval) => vec![interval.lower, interval.higher],
+            Question::Subset(ref ranges) => ranges.iter().flat_map(|range| [range.lower, range.higher]).collect(),
+            Question::Bit(_) => self.restrict(limits, Response::Yes).flat_map(|range| [range.lower, range.higher]).collect(),
+        }
+    }
+    /// How many ranges the answer could split `limits` into at most, which for
+    /// a question about a low bit can be very many.
+    pub fn parts(&self, limits: Range) -> Count {
+        match *self {
+            Question::Less(_) | Question::Equal(_) | Question::Within(_) | Question::Compare(_) => 2,
+            Question::Subset(ref ranges) => ranges.len() as Count + 1,
+            Question::Bit(bit) => (limits.len() >> min(bit, SIGN_BIT)).saturating_add(2),
+        }
+    }
+    /// For messages like "Is it less than 5?" or "Is it in 3..7?".
+    pub fn describe(&self) -> String {
+        match *self {
+            Question::Less(guess) => format!("less than {}", guess),
+            Question::Compare(guess) => format!("less than, equal to or greater than {}", guess),
+            Question::Equal(guess) => format!("exactly {}", guess),
+            Question::Within(_) | Question::Subset(_) => format!("in {}", self.describe_ranges()),
+            Question::Bit(0) => "odd".to_string(),
+            Question::Bit(bit) => format!("one with bit {} set", bit),
+        }
+    }
+    /// What `response` says about the secret, like "Less than 5" or "Not 5".
+    pub fn describe_answer(&self, response: Response) -> String {
+        match (self, response) {
+            (_, Response::Pass) => "No answer".to_string(),
+            (&Question::Less(guess), Response::Yes) => format!("Less than {}", guess),
+            (&Question::Less(guess), _) => format!("Greater than or equal to {}", guess),
+            (&Question::Compare(guess), Response::Yes) => format!("Less than {}", guess),
+            (&Question::Compare(guess), Response::Equal) => format!("Exactly {}", guess),
+            (&Question::Compare(guess), Response::No) => format!("Greater than {}", guess),
+            (&Question::Equal(guess), Response::Yes) => format!("Exactly {}", guess),
+            (&Question::Equal(guess), _) => format!("Not {}", guess),
+            (&Question::Bit(0), Response::Yes) => "Odd".to_string(),
+            (&Question::Bit(0), _) => "Even".to_string(),
+            (&Question::Bit(bit), Response::Yes) => format!("Bit {} set", bit),
+            (&Question::Bit(bit), _) => format!("Bit {} clear", bit),
+            (_, Response::Yes) => format!("In {}", self.describe_ranges()),
+            (_, _) => format!("Not in {}", self.describe_ranges()),
+        }
+    }
+    // "3..7" or "3..7, 10..12 or 15..20".
+    fn describe_ranges(&self) -> String {
+        let ranges: Vec<String> = self.yes_ranges().iter()
+            .map(|range| format!("{}..{}", range.lower, range.higher))
+            .collect();
+        match ranges.split_last() {
+            Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+            _ => ranges.join(""),
+        }
+    }
+}
+
+/// Questions as typed at the prompt: `5` for "less than 5", `=5`, `3..7`,
+/// `3..7,10..12`, `b3` for "is bit 3 set?" and `~5` to compare with 5.
+impl fmt::Display for Question {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Question::Less(guess) => write!(f, "{}", guess),
+            Question::Equal(guess) => write!(f, "={}", guess),
+            Question::Bit(bit) => write!(f, "b{}", bit),
+            Question::Compare(guess) => write!(f, "~{}", guess),
+            Question::Within(_) | Question::Subset(_) => {
+                let ranges: Vec<String> = self.yes_ranges().iter()
+                    .map(|range| format!("{}..{}", range.lower, range.higher))
+                    .collect();
+                write!(f, "{}", ranges.join(","))
+            }
+        }
+    }
+}
+
+/// Reads questions written as they are displayed, with single numbers allowed
+/// among the ranges of a subset, so `3..7,10,15..20`. Bits may also be asked
+/// about as `bit 3`, and `odd` asks about bit 0.
+impl core::str::FromStr for Question {
+    type Err = GameError;
+    fn from_str(text: &str) -> Result<Question, GameError> {
+        let number = |number: &str| {
+            number.trim().parse::<Number>().map_err(|_| GameError::ParseError(format!("Bad number in {}", text)))
+        };
+        if let Some(guess) = text.strip_prefix('=') {
+            return Ok(Question::Equal(number(guess)?));
+        }
+        if let Some(guess) = text.strip_prefix('~') {
+            return Ok(Question::Compare(number(guess)?));
+        }
+        if text == "odd" {
+            return Ok(Question::Bit(0));
+        }
+        if let Some(bit) = text.strip_prefix("bit").or_else(|| text.strip_prefix('b')) {
+            return bit.trim().parse().map(Question::Bit).map_err(|_| GameError::ParseError(format!("Bad bit in {}", text)));
+        }
+        if !text.contains("..") && !text.contains(',') {
+            return Ok(Question::Less(number(text)?));
+        }
+        let ranges = text.split(',')
+            .map(|part| match part.split_once("..") {
+                Some((lower, higher)) => Ok(Range::new(number(lower)?, number(higher)?)),
+                None => number(part).map(|number| Range::new(number, number.saturating_add(1))),
+            })
+            .collect::<Result<Vec<Range>, GameError>>()?;
+        Ok(Question::subset(ranges))
+    }
+}
+
+/// The only numbers the secret may be, for games that aren't over every number
+/// in a range, such as a list of valid IDs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateSet {
+    members: Vec<Number>,
+}
+
+impl CandidateSet {
+    /// Sorts the numbers and drops repeats.
+    pub fn new(mut members: Vec<Number>) -> CandidateSet {
+        members.sort_unstable();
+        members.dedup();
+        CandidateSet { members }
+    }
+    pub fn members(&self) -> &[Number] {
+        &self.members
+    }
+    // How many members are below `number`.
+    fn rank(&self, number: Number) -> usize {
+        self.members.partition_point(|&member| member < number)
+    }
+    /// How many members are in `range`.
+    pub fn count(&self, range: Range) -> Count {
+        if range.is_empty() {
+            return 0;
+        }
+        (self.rank(range.higher) - self.rank(range.lower)) as Count
+    }
+    /// The smallest range with the same members as `range`, which is empty if it
+    /// has none.
+    pub fn tighten(&self, range: Range) -> Range {
+        let (first, end) = (self.rank(range.lower), self.rank(range.higher));
+        if range.is_empty() || first == end {
+            Range::new(range.lower, range.lower)
+        } else {
+            Range::new(self.members[first], self.members[end - 1] + 1)
+        }
+    }
+    /// The member of `range` with `index` members of `range` below it.
+    pub fn nth(&self, range: Range, index: Count) -> Option<Number> {
+        let position = self.rank(range.lower) + index as usize;
+        self.members.get(position).cloned().filter(|&member| member < range.higher)
+    }
+}
+
+/// The union of the ranges the secret may be in, whichever way the opponent lied,
+/// kept as disjoint ranges in order with no two touching.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PossibilitySet {
+    ranges: Vec<Range>,
+    candidate_set: Option<Arc<CandidateSet>>,
+}
+
+impl PossibilitySet {
+    /// The union of `ranges`, which may overlap and come in any order.
+    pub fn new(ranges: impl IntoIterator<Item = Range>) -> PossibilitySet {
+        let mut set = PossibilitySet::default();
+        for range in ranges {
+            set.merge(range);
+        }
+        set
+    }
+    /// The same set, where only members of `candidate_set` count as possible.
+    pub fn with_candidates(mut self, candidate_set: Arc<CandidateSet>) -> PossibilitySet {
+        self.candidate_set = Some(candidate_set);
+        self
+    }
+    /// Adds `range`, joining it to any ranges it overlaps or touches.
+    pub fn merge(&mut self, range: Range) {
+        if range.is_empty() {
+            return;
+        }
+        let first = self.ranges.partition_point(|other| other.higher < range.lower);
+        let end = self.ranges.partition_point(|other| other.lower <= range.higher);
+        let mut merged = range;
+        if first < end {
+            merged.lower = min(merged.lower, self.ranges[first].lower);
+            merged.higher = max(merged.higher, self.ranges[end - 1].higher);
+        }
+        self.ranges.splice(first..end, Some(merged));
+    }
+    pub fn contains(&self, number: Number) -> bool {
+        let position = self.ranges.partition_point(|range| range.higher <= number);
+        let in_range = self.ranges.get(position).is_some_and(|range| range.lower <= number);
+        in_range && self.count_distinct_in(Range::new(number, number.saturating_add(1))) == 1
+    }
+    /// How many different numbers are possible, however many ways each could be.
+    pub fn count_distinct(&self) -> Count {
+        self.iter().map(|range| self.count_distinct_in(range)).fold(0, Count::saturating_add)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.count_distinct() == 0
+    }
+    /// The number that is possible, if only one is.
+    pub fn single(&self) -> Option<Number> {
+        if self.count_distinct() != 1 {
+            return None;
+        }
+        let range = *self.ranges.iter().find(|&&range| self.count_distinct_in(range) == 1)?;
+        match self.candidate_set {
+            Some(ref candidate_set) => candidate_set.nth(range, 0),
+            None => Some(range.lower),
+        }
+    }
+    // The possible numbers in one of the ranges.
+    fn count_distinct_in(&self, range: Range) -> Count {
+        match self.candidate_set {
+            Some(ref candidate_set) => candidate_set.count(range),
+            None => range.len(),
+        }
+    }
+    pub fn ranges(&self) -> &[Range] {
+        &self.ranges
+    }
+    /// The ranges in order.
+    pub fn iter(&self) -> impl Iterator<Item = Range> + '_ {
+        self.ranges.iter().cloned()
+    }
+}
+
+/// Lists the ranges like `3, 5..9`, giving ranges of one number as just the number.
+impl fmt::Display for PossibilitySet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ranges: Vec<String> = self.iter()
+            .map(|range| if range.len() == 1 {
+                range.lower.to_string()
+            } else {
+                format!("{}..{}", range.lower, range.higher)
+            })
+            .collect();
+        write!(f, "{}", ranges.join(", "))
+    }
+}
+
+/// How likely each number is to be the secret, for guessers that should do well
+/// on average rather than in the worst case.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prior {
+    /// Listed numbers with their weights, in order, and the running totals of the
+    /// weights. Other numbers weigh nothing. Made by [`Prior::weights`].
+    Weights { weights: Vec<(Number, f64)>, totals: Vec<f64> },
+    /// Each number is `ratio` times as likely as the one below it, for a ratio
+    /// in (0, 1].
+    Geometric(f64),
+}
+
+impl Prior {
+    /// Weights for single numbers. Repeats are added together, and every weight
+    /// must be finite and not negative, with some positive.
+    pub fn weights(mut weights: Vec<(Number, f64)>) -> Result<Prior, GameError> {
+        if let Some(&(number, weight)) = weights.iter().find(|&&(_, weight)| !(weight.is_finite() && weight >= 0.0)) {
+            return Err(GameError::InvalidRules(format!("Weight {} for {} must be a number, 0 or more", weight, number)));
+        }
+        weights.sort_by_key(|&(number, _)| number);
+        let mut merged: Vec<(Number, f64)> = vec![];
+        for (number, weight) in weights {
+            match merged.last_mut() {
+                Some(last) if last.0 == number => last.1 += weight,
+                _ => merged.push((number, weight)),
+            }
+        }
+        let totals: Vec<f64> = merged.iter()
+            .scan(0.0, |total, &(_, weight)| {
+                *total += weight;
+                Some(*total)
+            })
+            .collect();
+        if !totals.last().is_some_and(|&total| total > 0.0) {
+            return Err(GameError::InvalidRules("The weights must not all be 0".to_string()));
+        }
+        Ok(Prior::Weights { weights: merged, totals })
+    }
+    /// Reads a prior written by [`Prior::spec`]: `geometric:RATIO`, or `weights:`
+    /// followed by comma separated `NUMBER=WEIGHT` pairs.
+    pub fn parse(spec: &str) -> Result<Prior, GameError> {
+        if let Some(ratio) = spec.strip_prefix("geometric:") {
+            match ratio.parse::<f64>() {
+                Ok(ratio) if ratio > 0.0 && ratio <= 1.0 => Ok(Prior::Geometric(ratio)),
+                _ => Err(GameError::ParseError(format!("Geometric ratio {} is not in (0, 1]", ratio))),
+            }
+        } else if let Some(pairs) = spec.strip_prefix("weights:") {
+            let weights = pairs.split(',')
+                .map(|pair| {
+                    let (number, weight) = pair.split_once('=').ok_or_else(|| format!("Expected NUMBER=WEIGHT, not {}", pair))?;
+                    let number = number.trim().parse::<Number>().map_err(|_| format!("Bad number {}", number))?;
+                    let weight = weight.trim().parse::<f64>().map_err(|_| format!("Bad weight {}", weight))?;
+                    Ok((number, weight))
+                })
+                .collect::<Result<Vec<(Number, f64)>, String>>()
+                .map_err(GameError::ParseError)?;
+            Prior::weights(weights)
+        } else {
+            Err(GameError::ParseError(format!("Unknown prior {}, expected geometric:RATIO or weights:NUMBER=WEIGHT,...", spec)))
+        }
+    }
+    /// The prior in the form [`Prior::parse`] reads.
+    pub fn spec(&self) -> String {
+        match self {
+            Prior::Geometric(ratio) => format!("geometric:{}", ratio),
+            Prior::Weights { weights, .. } => {
+                let pairs: Vec<String> = weights.iter().map(|(number, weight)| format!("{}={}", number, weight)).collect();
+                format!("weights:{}", pairs.join(","))
+            }
+        }
+    }
+    /// The numbers given a weight, for priors that list them.
+    pub fn numbers(&self) -> Option<Vec<Number>> {
+        match self {
+            Prior::Weights { weights, .. } => Some(weights.iter().map(|&(number, _)| number).collect()),
+            Prior::Geometric(_) => None,
+        }
+    }
+    // The total weight below `number`, counting from the start of `within`, where
+    // `within` is every number the game is over.
+    fn below(&self, within: Range, number: Number) -> f64 {
+        match *self {
+            Prior::Weights { ref weights, ref totals } => {
+                let position = weights.partition_point(|&(member, _)| member < number);
+                position.checked_sub(1).map_or(0.0, |last| totals[last])
+            }
+            Prior::Geometric(ratio) => {
+                let steps = Range::new(within.lower, number).len() as f64;
+                if ratio == 1.0 {
+                    steps
+                } else {
+                    (1.0 - powf(ratio, steps)) / (1.0 - ratio)
+                }
+            }
+        }
+    }
+    /// The share of the weight in `within` that falls in `range`.
+    pub fn share(&self, within: Range, range: Range) -> f64 {
+        let total = self.below(within, within.higher) - self.below(within, within.lower);
+        if range.is_empty() || total <= 0.0 {
+            return 0.0;
+        }
+        let range = range.clamp_lower(within.lower).clamp_higher(within.higher);
+        ((self.below(within, range.higher) - self.below(within, range.lower)) / total).max(0.0)
+    }
+    /// The first number in `within` with at least `fraction` of the weight at or
+    /// below it, so a uniformly random fraction gives a number drawn from the prior.
+    pub fn quantile(&self, within: Range, fraction: f64) -> Number {
+        let start = self.below(within, within.lower);
+        let target = start + fraction * (self.below(within, within.higher) - start);
+        match *self {
+            Prior::Weights { ref weights, ref totals } => {
+                let first = weights.partition_point(|&(member, _)| member < within.lower);
+                let listed = weights[first..].iter().zip(&totals[first..])
+                    .take_while(|&(&(number, _), _)| number < within.higher);
+                listed.clone()
+                    .find(|&(_, &total)| total > target)
+                    .or_else(|| listed.last())
+                    .map_or(within.lower, |(&(number, _), _)| number)
+            }
+            Prior::Geometric(ratio) => {
+                let steps = if ratio == 1.0 {
+                    target
+                } else {
+                    ln(1.0 - target * (1.0 - ratio)) / ln(ratio)
+                };
+                let index = if steps.is_finite() && steps > 0.0 { steps as Count } else { 0 };
+                within.offset(min(index, within.len().saturating_sub(1)))
+            }
+        }
+    }
+    /// For messages like "Numbers are weighted geometrically, each 0.9 times the last".
+    pub fn describe(&self) -> String {
+        match self {
+            Prior::Geometric(ratio) => format!("geometrically, each {} times the last", ratio),
+            Prior::Weights { weights, .. } => format!("by the weights given for {} numbers", weights.len()),
+        }
+    }
+}
+
+/// Shares of the prior are scaled by this before being scored, so that weights
+/// fit in a Count.
+pub const PRIOR_SCALE: f64 = 18_446_744_073_709_551_616.0;
+
+/// Which answers the opponent may lie with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    /// Any answer may be a lie.
+    #[default]
+    Standard,
+    /// A lie may only say yes when the truth is no: falsely "less than" (`Low`),
+    /// but never falsely "greater than or equal" (`High`).
+    HalfLie,
+    /// Guesses are [`Question::Compare`], answered less, equal or greater, and a
+    /// lie may give either of the two wrong answers.
+    ThreeWay,
+    /// Any answer may be a lie, except the last one before the guesser names the
+    /// number, so the guesser may finish once that answer, taken as true, leaves
+    /// a single number.
+    TruthfulLast,
+    /// The lies are split between false yeses and false nos, with at most half of
+    /// them, rounded up, going each way, so an even budget gives the opponent as
+    /// many of each to tell independently.
+    Directional,
+}
+
+impl Variant {
+    /// Whether an answer may be a lie when the true answer is `truth`.
+    pub fn allows_lie(self, truth: Response) -> bool {
+        match self {
+            Variant::Standard | Variant::ThreeWay | Variant::TruthfulLast | Variant::Directional => true,
+            Variant::HalfLie => truth == Response::No,
+        }
+    }
+    /// Reads a variant by the name it is displayed with.
+    pub fn parse(name: &str) -> Result<Variant, GameError> {
+        match name {
+            "standard" => Ok(Variant::Standard),
+            "half-lie" => Ok(Variant::HalfLie),
+            "three-way" => Ok(Variant::ThreeWay),
+            "truthful-last" => Ok(Variant::TruthfulLast),
+            "directional" => Ok(Variant::Directional),
+            _ => Err(GameError::ParseError(format!(
+                "Unknown variant {}, expected standard, half-lie, three-way, truthful-last or directional",
+                name
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Variant::Standard => write!(f, "standard"),
+            Variant::HalfLie => write!(f, "half-lie"),
+            Variant::ThreeWay => write!(f, "three-way"),
+            Variant::TruthfulLast => write!(f, "truthful-last"),
+            Variant::Directional => write!(f, "directional"),
+        }
+    }
+}
+
+/// The most ranges a single answer may split the numbers into. Every hypothesis
+/// is a range, so a question about bit 0 of a number in 0..1000000 would leave a
+/// million of them.
+pub const MAX_QUESTION_PARTS: Count = 4096;
+
+/// The most lies a game may allow: far more than any game worth playing, but
+/// few enough that rules sent over the network can't ask for absurd ones.
+pub const MAX_LIES: usize = 1000;
+
+/// Checks that a game over `lower..upper` with up to `max_lies` lies can be
+/// played: there must be two numbers or more to guess among, and no more than
+/// [`MAX_LIES`] lies. Ranges too wide for their size to be a [`Number`] are refused.
+pub fn check_rules(lower: Number, upper: Number, max_lies: usize) -> Result<(), GameError> {
+    match upper.checked_sub(lower) {
+        None => return Err(GameError::InvalidRules(format!("{}..{} is too wide to count its numbers", lower, upper))),
+        Some(size) if size < 2 => {
+            return Err(GameError::InvalidRules("A game needs at least two numbers to guess among".to_string()));
+        }
+        Some(_) => {}
+    }
+    if max_lies > MAX_LIES {
+        return Err(GameError::InvalidRules(format!("A game can allow at most {} lies", MAX_LIES)));
+    }
+    Ok(())
+}
+
+/// What can go wrong setting up, playing or reading a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameError {
+    /// A question about a number outside the game's limits.
+    GuessOutOfRange { guess: Number, range: Range },
+    /// A question about a range with no numbers in it, or reaching outside the
+    /// game's limits.
+    IntervalOutOfRange { interval: Range, range: Range },
+    /// A question whose answer would split the numbers into more ranges than
+    /// [`MAX_QUESTION_PARTS`], such as whether a number with many digits is odd.
+    TooManyParts(Question),
+    /// A question about a bit beyond the highest bit of a Number.
+    NoSuchBit(u32),
+    /// A comparison asked outside a three-way game.
+    NotThreeWay(Question),
+    /// An answer the question can't get, such as Equal to a yes or no question.
+    InvalidResponse(Question, Response),
+    /// An answer given when no question is waiting for one.
+    NothingDue,
+    /// A question about one of several secrets that there isn't.
+    NoSuchTarget { target: usize, targets: usize },
+    /// A record of a game whose parts disagree, such as a Result tag that the
+    /// moves don't give.
+    InconsistentHistory(String),
+    /// Text that couldn't be read, with what was wrong with it.
+    ParseError(String),
+    /// Rules that no game can be played under, such as a range with no numbers.
+    InvalidRules(String),
+    /// A Mastermind guess or feedback that doesn't fit the game's pegs and colours.
+    InvalidCode(String),
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::GuessOutOfRange { guess, range } => write!(
+                f,
+                "Can't ask about {}: guesses must be at least {} and less than {}",
+                guess, range.lower, range.higher
+            ),
+            GameError::IntervalOutOfRange { interval, range } => write!(
+                f,
+                "Can't ask about {}..{}: ranges must hold at least one number and lie within {}..{}",
+                interval.lower, interval.higher, range.lower, range.higher
+            ),
+            GameError::TooManyParts(question) => write!(
+                f,
+                "Can't ask whether it's {}: the answer could split the numbers into more than {} ranges",
+                question.describe(),
+                MAX_QUESTION_PARTS
+            ),
+            GameError::NoSuchBit(bit) => write!(f, "Can't ask about bit {}: numbers only have bits 0 to {}", bit, SIGN_BIT),
+            GameError::NotThreeWay(question) => {
+                write!(f, "Can't ask whether it's {}: only three-way games answer that", question.describe())
+            }
+            GameError::InvalidResponse(question, response) => {
+                write!(f, "Can't answer {:?} to whether it's {}", response, question.describe())
+            }
+            GameError::NothingDue => write!(f, "No question is waiting for an answer"),
+            GameError::NoSuchTarget { target, targets } => {
+                write!(f, "Can't ask about number {}: there are only numbers 0 to {}", target, targets - 1)
+            }
+            GameError::InconsistentHistory(message)
+            | GameError::ParseError(message)
+            | GameError::InvalidRules(message)
+            | GameError::InvalidCode(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl core::error::Error for GameError {}
+
+/// So that code reporting errors as text can use `?` on a GameError.
+impl From<GameError> for String {
+    fn from(err: GameError) -> String {
+        err.to_string()
+    }
+}
+
+/// A game in progress: the rules, and every question asked so far with its answer.
+#[derive(Clone)]
+pub struct GameState {
+    pub(crate) history: Vec<(Question, Response)>,
+    pub(crate) lower_limit: Number,
+    pub(crate) upper_limit: Number,
+    pub(crate) max_lies: usize,
+    // In noisy mode every answer is independently a lie with this probability,
+    // and there is no bound on the number of lies.
+    pub(crate) lie_probability: Option<f64>,
+    // How sure a noisy game must be of a single number to finish.
+    pub(crate) confidence: f64,
+    // The game is lost if the number isn't pinned down within this many questions.
+    pub(crate) max_questions: Option<usize>,
+    // When set, only these numbers between the limits can be the secret. Shared,
+    // since games are cloned at every step of a search.
+    pub(crate) candidate_set: Option<Arc<CandidateSet>>,
+    // When set, value functions weigh numbers by how likely they are to be the
+    // secret rather than counting them.
+    pub(crate) prior: Option<Arc<Prior>>,
+    // Which answers may be lies.
+    pub(crate) variant: Variant,
+    // How many more turns pass after a question is asked before its answer comes.
+    pub(crate) delay: usize,
+    // The questions asked but not yet answered, oldest first, each with the turn
+    // it was asked on.
+    pub(crate) pending: VecDeque<(usize, Question)>,
+    // Turns spent waiting for an answer instead of asking a question.
+    pub(crate) waits: usize,
+    // When set, the secret may move up or down by one between questions, and is
+    // caught once it is cornered within this many numbers in a row.
+    pub(crate) moving: Option<Count>,
+    // When set, only the answers to this many questions from the start may be lies.
+    pub(crate) lie_window: Option<usize>,
+    // When set, the game is only won by announcing the number, asking whether it
+    // is exactly that number and hearing yes.
+    pub(crate) final_guess: bool,
+    // How many questions the opponent may refuse to answer.
+    pub(crate) max_passes: usize,
+    // The possibilities after the questions so far, extended by each answer as it
+    // is stored rather than replayed from the start whenever they are needed.
+    hypotheses: Vec<(Range, Vec<usize>)>,
+}
+
+impl GameState {
+    /// A game over `0..upper_limit` where the opponent may lie up to `max_lies` times.
+    pub fn new(upper_limit: Number, max_lies: usize) -> GameState {
+        GameState {
+            history: vec![],
+            lower_limit: 0,
+            upper_limit,
+            max_lies,
+            lie_probability: None,
+            confidence: CONFIDENCE,
+            max_questions: None,
+            candidate_set: None,
+            prior: None,
+            variant: Variant::Standard,
+            delay: 0,
+            pending: VecDeque::new(),
+            waits: 0,
+            moving: None,
+            lie_window: None,
+            final_guess: false,
+            max_passes: 0,
+            hypotheses: vec![(Range::new(0, upper_limit), vec![])],
+        }
+    }
+    /// A game over `0..upper_limit` where every answer is a lie with probability
+    /// `lie_probability`, however many lies that adds up to.
+    pub fn noisy(upper_limit: Number, lie_probability: f64) -> GameState {
+        GameState {
+            history: vec![],
+            lower_limit: 0,
+            upper_limit,
+            max_lies: 0,
+            lie_probability: Some(lie_probability),
+            confidence: CONFIDENCE,
+            max_questions: None,
+            candidate_set: None,
+            prior: None,
+            variant: Variant::Standard,
+            delay: 0,
+            pending: VecDeque::new(),
+            waits: 0,
+            moving: None,
+            lie_window: None,
+            final_guess: false,
+            max_passes: 0,
+            hypotheses: vec![(Range::new(0, upper_limit), vec![])],
+        }
+    }
+    /// The same game over `lower_limit..upper_limit` instead of starting from 0.
+    pub fn with_lower_limit(mut self, lower_limit: Number) -> GameState {
+        self.lower_limit = lower_limit;
+        self.hypotheses = self.replay();
+        self
+    }
+    /// The same game where the secret must be one of `candidate_set`, with the
+    /// limits moved to just fit it.
+    pub fn with_candidates(mut self, candidate_set: CandidateSet) -> GameState {
+        self.lower_limit = candidate_set.members.first().cloned().unwrap_or(0);
+        self.upper_limit = candidate_set.members.last().map_or(0, |&last| last + 1);
+        self.candidate_set = Some(Arc::new(candidate_set));
+        self.hypotheses = self.replay();
+        self
+    }
+    /// The same game where guessers aim to finish quickly on average, with the
+    /// secret drawn from `prior`.
+    pub fn with_prior(mut self, prior: Prior) -> GameState {
+        self.prior = Some(Arc::new(prior));
+        self
+    }
+    /// The same noisy game, finished once a single number is at least `confidence`
+    /// likely instead of [`CONFIDENCE`].
+    pub fn with_confidence(mut self, confidence: f64) -> GameState {
+        self.confidence = confidence;
+        self
+    }
+    /// The same game, lost if the number isn't pinned down within `max_questions` questions.
+    pub fn with_max_questions(mut self, max_questions: usize) -> GameState {
+        self.max_questions = Some(max_questions);
+        self
+    }
+    /// The same game, where the opponent may lie up to `max_lies` times instead.
+    pub fn with_max_lies(mut self, max_lies: usize) -> GameState {
+        self.max_lies = max_lies;
+        self.hypotheses = self.replay();
+        self
+    }
+    /// The same game, with the opponent only telling the lies `variant` allows.
+    pub fn with_variant(mut self, variant: Variant) -> GameState {
+        self.variant = variant;
+        self.hypotheses = self.replay();
+        self
+    }
+    /// The same game, where the answer to each question only comes once `delay`
+    /// more questions have been asked after it, or turns spent waiting.
+    pub fn with_delay(mut self, delay: usize) -> GameState {
+        self.delay = delay;
+        self
+    }
+    /// The same game, where the secret may move up or down by one, staying within
+    /// the limits, between one question and the next. Each possibility is then
+    /// where the secret may have been when the last question was answered, and the
+    /// game is won by cornering it within `width` numbers in a row, as a secret
+    /// that keeps moving can't always be pinned down to one.
+    pub fn with_moving_target(mut self, width: Count) -> GameState {
+        self.moving = Some(width);
+        self.hypotheses = self.replay();
+        self
+    }
+    /// The same game, where only the answers to the first `window` questions may
+    /// be lies, so once they are answered the rest are all true.
+    pub fn with_lie_window(mut self, window: usize) -> GameState {
+        self.lie_window = Some(window);
+        self.hypotheses = self.replay();
+        self
+    }
+    /// The same game, where pinning the secret down isn't enough: it must be
+    /// announced by asking whether it is exactly that number, and the opponent may
+    /// spend a lie denying it.
+    pub fn with_final_guess(mut self) -> GameState {
+        self.final_guess = true;
+        self
+    }
+    /// The same game, where the opponent may pass on up to `max_passes` questions,
+    /// which tells the guesser nothing but uses up a turn.
+    pub fn with_passes(mut self, max_passes: usize) -> GameState {
+        self.max_passes = max_passes;
+        self
+    }
+    /// The questions answered so far, as (guess, answer) pairs.
+    pub fn history(&self) -> &[(Question, Response)] {
+        &self.history
+    }
+    pub fn lower_limit(&self) -> Number {
+        self.lower_limit
+    }
+    pub fn upper_limit(&self) -> Number {
+        self.upper_limit
+    }
+    /// Every number the secret could be before any questions.
+    pub fn range(&self) -> Range {
+        Range::new(self.lower_limit, self.upper_limit)
+    }
+    pub fn max_lies(&self) -> usize {
+        self.max_lies
+    }
+    /// The chance of each answer being a lie, for noisy games.
+    pub fn lie_probability(&self) -> Option<f64> {
+        self.lie_probability
+    }
+    /// How sure a noisy game must be of a single number to finish.
+    pub fn confidence(&self) -> f64 {
+        self.confidence
+    }
+    pub fn max_questions(&self) -> Option<usize> {
+        self.max_questions
+    }
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+    /// How many numbers in a row a moving secret must be cornered within, or None
+    /// if it stays put.
+    pub fn moving_target(&self) -> Option<Count> {
+        self.moving
+    }
+    /// How many questions from the start may be lied about, if not all of them.
+    pub fn lie_window(&self) -> Option<usize> {
+        self.lie_window
+    }
+    /// How many questions the opponent may pass on in all.
+    pub fn max_passes(&self) -> usize {
+        self.max_passes
+    }
+    /// How many more questions the opponent may pass on.
+    pub fn passes_left(&self) -> usize {
+        self.max_passes.saturating_sub(self.passes())
+    }
+    /// How many questions the opponent has passed on so far.
+    pub fn passes(&self) -> usize {
+        self.history.iter().filter(|&&(_, response)| response == Response::Pass).count()
+    }
+    /// Every answer `question` may get now: its [`Question::responses`], and a
+    /// pass if there are any left.
+    pub fn responses(&self, question: &Question) -> Vec<Response> {
+        let mut responses = question.responses().to_vec();
+        if self.passes_left() > 0 {
+            responses.push(Response::Pass);
+        }
+        responses
+    }
+    /// Whether the number must be announced to win the game.
+    pub fn final_guess(&self) -> bool {
+        self.final_guess
+    }
+    /// The number every possibility agrees on, if there is just one, which in
+    /// final-guess games may not have been announced yet.
+    pub fn forced(&self) -> Option<Number> {
+        match result(self.hypotheses()) {
+            Finished(number) => Some(number),
+            _ => None,
+        }
+    }
+    /// Whether the answer to question `index` may be a lie.
+    pub fn in_lie_window(&self, index: usize) -> bool {
+        match self.lie_window {
+            Some(window) => index < window,
+            None => true,
+        }
+    }
+    /// Whether the opponent, having lied in the answers at `lies`, may yet answer
+    /// `lie` falsely: it has a lie left, and in directional games one left that way.
+    pub fn may_lie(&self, lies: &[usize], lie: Response) -> bool {
+        lies.len() < self.max_lies
+            && (self.variant != Variant::Directional
+                || lies.iter().filter(|&&index| self.history[index].1 == lie).count() < self.max_lies.div_ceil(2))
+    }
+    /// In directional games, how many more false yeses and how many more false nos
+    /// there may be after lying in the answers at `lies`.
+    pub fn lies_left_each_way(&self, lies: &[usize]) -> (usize, usize) {
+        let left = self.lies_left(lies.len());
+        let yeses = lies.iter().filter(|&&index| self.history[index].1 == Response::Yes).count();
+        let each_way = self.max_lies.div_ceil(2);
+        (
+            min(left, each_way.saturating_sub(yeses)),
+            min(left, each_way.saturating_sub(lies.len() - yeses)),
+        )
+    }
+    /// How many more lies there may be after `lies_told` so far: whatever is left
+    /// of the most there may be, but no more than the questions left in the lie
+    /// window.
+    pub fn lies_left(&self, lies_told: usize) -> usize {
+        let left = self.max_lies.saturating_sub(lies_told);
+        match self.lie_window {
+            Some(window) => min(left, window.saturating_sub(self.history.len())),
+            None => left,
+        }
+    }
+    /// How many turns each answer comes after its question, 0 for straight away.
+    pub fn delay(&self) -> usize {
+        self.delay
+    }
+    /// The questions asked but not yet answered, oldest first.
+    pub fn pending(&self) -> impl Iterator<Item = &Question> + '_ {
+        self.pending.iter().map(|(_, question)| question)
+    }
+    /// How many questions have been asked, answered or not.
+    pub fn asked(&self) -> usize {
+        self.history.len() + self.pending.len()
+    }
+    /// How many turns have gone by: every question asked, and every wait.
+    pub fn turns(&self) -> usize {
+        self.asked() + self.waits
+    }
+    /// The only numbers the secret may be, if not every number between the limits.
+    pub fn candidate_set(&self) -> Option<&CandidateSet> {
+        self.candidate_set.as_deref()
+    }
+    pub fn prior(&self) -> Option<&Prior> {
+        self.prior.as_deref()
+    }
+    /// How many numbers in `range` the secret may be.
+    pub fn count(&self, range: Range) -> Count {
+        match self.candidate_set {
+            None => range.len(),
+            Some(ref candidate_set) => candidate_set.count(range),
+        }
+    }
+    /// How much `range` counts for in value functions: the numbers in it, or with
+    /// a prior its share of the weight, scaled by PRIOR_SCALE. Any range holding a
+    /// number counts for at least 1, so unlikely numbers are never ignored.
+    pub fn mass(&self, range: Range) -> Count {
+        let count = self.count(range);
+        match self.prior {
+            Some(ref prior) if count > 0 => max(1, (prior.share(self.range(), range) * PRIOR_SCALE) as Count),
+            _ => count,
+        }
+    }
+    /// Whether the secret may be `number`, before any questions.
+    pub fn allows(&self, number: Number) -> bool {
+        self.count(self.range().clamp_lower(number).clamp_higher(number.saturating_add(1))) == 1
+    }
+    /// The number in `range` the secret may be with `index` such numbers below it.
+    pub fn nth(&self, range: Range, index: Count) -> Option<Number> {
+        match self.candidate_set {
+            None if index < range.len() => Some(range.offset(index)),
+            None => None,
+            Some(ref candidate_set) => candidate_set.nth(range, index),
+        }
+    }
+    // Shrinks a range to fit the numbers the secret may be, so that empty ranges
+    // and single numbers look the same as in a game over every number.
+    fn tighten(&self, range: Range) -> Range {
+        match self.candidate_set {
+            None => range,
+            Some(ref candidate_set) => candidate_set.tighten(range),
+        }
+    }
+    /// The question a guess of `guess` asks: "is it less than `guess`?", or in
+    /// three-way games how the secret compares with it. In final-guess games, once
+    /// the number is pinned down, it is announced instead, whatever the guess.
+    pub fn guess(&self, guess: Number) -> Question {
+        match (self.final_guess, self.forced(), self.variant) {
+            (true, Some(number), _) => Question::Equal(number),
+            (_, _, Variant::ThreeWay) => Question::Compare(guess),
+            _ => Question::Less(guess),
+        }
+    }
+    /// Records the answer to "is it less than `value`?".
+    pub fn store_guess(&mut self, value: Number, response: Dir) -> Result<(), GameError> {
+        self.ask(Question::Less(value), response.into())
+    }
+    /// Records the answer to any question.
+    pub fn ask(&mut self, question: Question, response: Response) -> Result<(), GameError> {
+        self.check_question(&question)?;
+        if !self.responses(&question).contains(&response) {
+            return Err(GameError::InvalidResponse(question, response));
+        }
+        let hypotheses = core::mem::take(&mut self.hypotheses);
+        self.hypotheses = self.extend(hypotheses, self.history.len(), &question, response);
+        self.history.push((question, response));
+        Ok(())
+    }
+    /// Asks `question` without hearing its answer, which is due once the delay
+    /// has passed. In a game with no delay it is due straight away.
+    pub fn pose(&mut self, question: Question) -> Result<(), GameError> {
+        self.check_question(&question)?;
+        self.pending.push_back((self.turns(), question));
+        Ok(())
+    }
+    /// Spends a turn without asking anything, to hear an answer sooner.
+    pub fn wait(&mut self) {
+        self.waits += 1;
+    }
+    /// The oldest unanswered question, if its answer is due: the delay has passed
+    /// since it was asked, or the questions have run out, so nothing more can be
+    /// asked and every answer comes.
+    pub fn due(&self) -> Option<&Question> {
+        let out_of_turns = match self.max_questions {
+            Some(max_questions) => self.turns() >= max_questions,
+            None => false,
+        };
+        match self.pending.front() {
+            Some((asked, question)) if out_of_turns || self.turns() > asked + self.delay => Some(question),
+            _ => None,
+        }
+    }
+    /// Records the answer to the question that is due.
+    pub fn answer(&mut self, response: Response) -> Result<(), GameError> {
+        if self.due().is_none() {
+            return Err(GameError::NothingDue);
+        }
+        let (asked, question) = self.pending.pop_front().expect("A question is due");
+        if let Err(err) = self.ask(question.clone(), response) {
+            self.pending.push_front((asked, question));
+            return Err(err);
+        }
+        Ok(())
+    }
+    /// Whether `guess` is within the limits, so it may be asked about.
+    pub fn check_guess(&self, guess: Number) -> Result<(), GameError> {
+        if guess < self.lower_limit || guess >= self.upper_limit {
+            Err(GameError::GuessOutOfRange { guess, range: self.range() })
+        } else {
+            Ok(())
+        }
+    }
+    /// Whether `question` only asks about numbers within the limits, and doesn't
+    /// split them into too many ranges to follow.
+    pub fn check_question(&self, question: &Question) -> Result<(), GameError> {
+        let check_interval = |interval: Range| {
+            if interval.is_empty() || interval.lower < self.lower_limit || interval.higher > self.upper_limit {
+                Err(GameError::IntervalOutOfRange { interval, range: self.range() })
+            } else {
+                Ok(())
+            }
+        };
+        match *question {
+            Question::Less(guess) | Question::Equal(guess) => self.check_guess(guess),
+            Question::Within(interval) => check_interval(interval),
+            // A subset with no numbers in it asks about an empty range
+            Question::Subset(ref ranges) if ranges.is_empty() => {
+                check_interval(Range::new(self.lower_limit, self.lower_limit))
+            }
+            Question::Subset(ref ranges) => ranges.iter().try_for_each(|&range| check_interval(range)),
+            Question::Bit(bit) if bit > SIGN_BIT => Err(GameError::NoSuchBit(bit)),
+            Question::Bit(_) => Ok(()),
+            Question::Compare(_) if self.variant != Variant::ThreeWay => Err(GameError::NotThreeWay(question.clone())),
+            Question::Compare(guess) => self.check_guess(guess),
+        }?;
+        if question.parts(self.range()) > MAX_QUESTION_PARTS {
+            return Err(GameError::TooManyParts(question.clone()));
+        }
+        Ok(())
+    }
+    /// Takes back the last question, returning it with its answer.
+    pub fn undo(&mut self) -> Option<(Question, Response)> {
+        let undone = self.history.pop();
+        self.hypotheses = self.replay();
+        undone
+    }
+    /// Takes back the answer to question `index`, renumbering the questions after
+    /// it, and returns the question with the answer it had.
+    pub fn retract(&mut self, index: usize) -> Option<(Question, Response)> {
+        if index >= self.history.len() {
+            return None;
+        }
+        let retracted = self.history.remove(index);
+        self.hypotheses = self.replay();
+        Some(retracted)
+    }
+    /// Pairs of questions whose answers can't both be true, such as "less than 5"
+    /// and "not less than 7", for explaining why a game is impossible.
+    pub fn conflicts(&self) -> Vec<(usize, usize)> {
+        let mut conflicts = vec![];
+        for (first, &(ref first_question, first_response)) in self.history.iter().enumerate() {
+            let fits_first: Vec<Range> = first_question.restrict(self.range(), first_response).collect();
+            for (second, &(ref second_question, second_response)) in self.history.iter().enumerate().skip(first + 1) {
+                let steps = if self.moving.is_some() { second - first } else { 0 };
+                let fits_both = fits_first.iter()
+                    .flat_map(|&range| second_question.restrict(self.spread(range, steps), second_response))
+                    .map(|range| self.count(range))
+                    .fold(0, Count::saturating_add);
+                if fits_both == 0 {
+                    conflicts.push((first, second));
+                }
+            }
+        }
+        conflicts
+    }
+    /// A set of answers, by question number, that can't all be true even with
+    /// every lie allowed, such as three answers each ruling out what the other two
+    /// allow, in a game with one lie. It is minimal: take any answer back and the
+    /// rest could all be true. None unless the game is impossible.
+    // Answers are dropped one at a time for as long as the rest still contradict
+    // each other, so the set found is minimal, though not always the smallest.
+    pub fn contradiction(&self) -> Option<Vec<usize>> {
+        if !self.hypotheses.is_empty() || self.lie_probability.is_some() {
+            return None;
+        }
+        let mut kept: Vec<usize> = (0..self.history.len()).collect();
+        let mut position = 0;
+        while position < kept.len() {
+            let mut without = self.clone();
+            without.history = kept.iter()
+                .filter(|&&index| index != kept[position])
+                .map(|&index| self.history[index].clone())
+                .collect();
+            without.hypotheses = without.replay();
+            if without.hypotheses.is_empty() {
+                kept.remove(position);
+            } else {
+                position += 1;
+            }
+        }
+        Some(kept)
+    }
+    /// Each possibility is a range together with the questions lied on to reach it.
+    /// With a candidate set the ranges are shrunk to fit it, but may still contain
+    /// numbers that aren't candidates, so count them with [`GameState::count`].
+    pub fn possibilities(&self) -> Vec<(Range, Vec<usize>)> {
+        self.hypotheses().map(|(range, lies)| (range, lies.to_vec())).collect()
+    }
+    /// The same possibilities one at a time, borrowed rather than copied.
+    pub fn hypotheses(&self) -> impl Iterator<Item = (Range, &[usize])> + '_ {
+        self.hypotheses.iter().map(|(range, lies)| (*range, lies.as_slice()))
+    }
+    // Hypotheses are extended one answer at a time and dropped as soon as their
+    // range is empty, rather than enumerating every subset of up to max_lies answers.
+    // A hypothesis whose numbers a question splits into several parts becomes a
+    // hypothesis for each part, all with the same lies.
+    fn extend(&self, hypotheses: Vec<(Range, Vec<usize>)>, index: usize, question: &Question, response: Response) -> Vec<(Range, Vec<usize>)> {
+        let hypotheses = if self.moving.is_some() && index > 0 { self.dilate(hypotheses) } else { hypotheses };
+        let mut extended = vec![];
+        for (range, lies) in hypotheses {
+            // Lying gave `response`, so the truth was one of the other answers. A
+            // pass is never a lie, and every number fits it
+            if response != Response::Pass && self.may_lie(&lies, response) && self.in_lie_window(index) {
+                for truth in question.lies(response).filter(|&truth| self.variant.allows_lie(truth)) {
+                    for lied in question.restrict(range, truth).map(|part| self.tighten(part)) {
+                        if !lied.is_empty() {
+                            let mut lies = lies.clone();
+                            lies.push(index);
+                            extended.push((lied, lies));
+                        }
+                    }
+                }
+            }
+            for truthful in question.restrict(range, response).map(|part| self.tighten(part)) {
+                if !truthful.is_empty() {
+                    extended.push((truthful, lies.clone()));
+                }
+            }
+        }
+        extended
+    }
+    // Where a moving secret may be one move after each possibility. The parts a
+    // question split a hypothesis into can grow into each other, and are merged.
+    fn dilate(&self, hypotheses: Vec<(Range, Vec<usize>)>) -> Vec<(Range, Vec<usize>)> {
+        let mut dilated: Vec<(Range, Vec<usize>)> = vec![];
+        for (range, lies) in hypotheses {
+            let range = self.spread(range, 1);
+            match dilated.last_mut() {
+                Some((last, last_lies)) if *last_lies == lies && last.higher >= range.lower => {
+                    last.higher = max(last.higher, range.higher);
+                }
+                _ => dilated.push((range, lies)),
+            }
+        }
+        dilated
+    }
+    // Everywhere a secret in `range` may get to in `steps` moves.
+    fn spread(&self, range: Range, steps: usize) -> Range {
+        if range.is_empty() || steps == 0 {
+            return range;
+        }
+        let steps = steps as Number;
+        Range::new(
+            max(range.lower.saturating_sub(steps), self.lower_limit),
+            min(range.higher.saturating_add(steps), self.upper_limit),
+        )
+    }
+    // The possibilities worked out from scratch, for when the rules or the history
+    // change other than by a new answer.
+    fn replay(&self) -> Vec<(Range, Vec<usize>)> {
+        let mut hypotheses = vec![(self.range(), vec![])];
+        for (index, &(ref question, response)) in self.history.iter().enumerate() {
+            hypotheses = self.extend(hypotheses, index, question, response);
+        }
+        hypotheses
+    }
+    /// Splits the candidates at every guess, pairing each piece with the number
+    /// of answers that would have to be lies if the number were in it. Like the
+    /// possibilities, pieces are shrunk to fit a candidate set. A piece that would
+    /// need a lie the variant, the lie window or the split of lies between
+    /// directions forbids counts as contradicting one more answer than there may
+    /// be lies, so it is never live. This assumes the secret stays put, so means
+    /// nothing in games with a moving target.
+    pub fn contradictions(&self) -> Vec<(Range, usize)> {
+        self.pieces().into_iter().map(|(range, lies)| (self.tighten(range), lies)).collect()
+    }
+    // The pieces between guesses, covering every number between the limits.
+    fn pieces(&self) -> Vec<(Range, usize)> {
+        let mut bounds: Vec<Number> = self.history.iter()
+            .flat_map(|(question, _)| question.bounds(self.range()))
+            .filter(|&bound| bound > self.lower_limit && bound < self.upper_limit)
+            .collect();
+        bounds.push(self.lower_limit);
+        bounds.push(self.upper_limit);
+        bounds.sort();
+        bounds.dedup();
+        bounds.windows(2)
+            .map(|pair| {
+                let range = Range::new(pair[0], pair[1]);
+                let mut lies = vec![];
+                for (index, &(ref question, response)) in self.history.iter().enumerate() {
+                    let truth = question.truth(range.lower);
+                    if truth != response && response != Response::Pass {
+                        let forbidden = !self.variant.allows_lie(truth)
+                            || !self.in_lie_window(index)
+                            || (self.variant == Variant::Directional && !self.may_lie(&lies, response));
+                        if forbidden {
+                            return (range, self.max_lies + 1);
+                        }
+                        lies.push(index);
+                    }
+                }
+                (range, lies.len())
+            })
+            .collect()
+    }
+    /// The live numbers in order, as runs of consecutive numbers that contradict
+    /// the same number of answers: `[(3, 0), (2, 1)]` is three numbers that fit
+    /// every answer followed by two that fit all but one. Only the order of the
+    /// live numbers matters to worst-case play, so games with the same canonical
+    /// form play out alike however they were reached. With a lie window, lies
+    /// that can no longer be told count as told.
+    pub fn canonical(&self) -> Vec<(Count, usize)> {
+        let mut runs: Vec<(Count, usize)> = vec![];
+        for (range, lies) in self.contradictions() {
+            let count = self.count(range);
+            if lies > self.max_lies || count == 0 {
+                continue;
+            }
+            let lies = self.max_lies - self.lies_left(lies);
+            match runs.last_mut() {
+                Some(last) if last.1 == lies => last.0 += count,
+                _ => runs.push((count, lies)),
+            }
+        }
+        runs
+    }
+    /// The probability of each single number within each piece, given that every
+    /// answer is a lie with probability `lie_probability`.
+    pub fn posterior(&self, lie_probability: f64) -> Vec<(Range, f64)> {
+        let pieces = self.contradictions();
+        let fewest_lies = pieces.iter().map(|&(_, lies)| lies).min().unwrap_or(0);
+        let odds = lie_probability / (1.0 - lie_probability);
+        let weighted: Vec<(Range, f64)> = pieces.into_iter()
+            .map(|(range, lies)| (range, powi(odds, (lies - fewest_lies) as i32)))
+            .collect();
+        let total: f64 = weighted.iter().map(|&(range, weight)| self.count(range) as f64 * weight).sum();
+        weighted.into_iter().map(|(range, weight)| (range, weight / total)).collect()
+    }
+    pub fn status(&self) -> GameResult {
+        let status = match self.lie_probability {
+            None => match self.moving {
+                Some(width) => cornered(self.hypotheses(), width),
+                None if self.final_guess => self.announced_result(),
+                None if self.variant == Variant::TruthfulLast => self.truthful_last_result(),
+                None => result(self.hypotheses()),
+            },
+            Some(lie_probability) => confident_result(self.posterior(lie_probability), self.confidence),
+        };
+        match self.max_questions {
+            Some(max_questions) if status == Ongoing && self.turns() >= max_questions && self.pending.is_empty() => Lost,
+            _ => status,
+        }
+    }
+    // In final-guess games a number that is pinned down still has to be announced,
+    // and the announcement confirmed.
+    fn announced_result(&self) -> GameResult {
+        match result(self.hypotheses()) {
+            Finished(number) if self.history.last() == Some(&(Question::Equal(number), Response::Yes)) => Finished(number),
+            Finished(_) => Ongoing,
+            status => status,
+        }
+    }
+    // In truthful-last games the guesser may stop once the last answer, if true,
+    // leaves one number, as it then must have been.
+    fn truthful_last_result(&self) -> GameResult {
+        let last = match self.history.len().checked_sub(1) {
+            Some(last) => last,
+            None => return result(self.hypotheses()),
+        };
+        match result(self.hypotheses().filter(|(_, lies)| !lies.contains(&last))) {
+            Finished(number) => Finished(number),
+            _ => result(self.hypotheses()),
+        }
+    }
+    /// Every number some lie hypothesis still allows, merged into one set.
+    pub fn possibility_set(&self) -> PossibilitySet {
+        self.within_candidates(PossibilitySet::new(self.hypotheses().map(|(range, _)| range)))
+    }
+    /// The numbers the opponent could still claim were the secret. In noisy games
+    /// that is every number.
+    pub fn candidates(&self) -> PossibilitySet {
+        if self.moving.is_some() {
+            return self.possibility_set();
+        }
+        let live = self.pieces().into_iter()
+            .filter(|&(_, lies)| self.lie_probability.is_some() || lies <= self.max_lies)
+            .map(|(range, _)| self.tighten(range));
+        self.within_candidates(PossibilitySet::new(live))
+    }
+    fn within_candidates(&self, set: PossibilitySet) -> PossibilitySet {
+        match self.candidate_set {
+            Some(ref candidate_set) => set.with_candidates(Arc::clone(candidate_set)),
+            None => set,
+        }
+    }
+    /// The lying rules, for messages like "Guess the number, with up to one lie".
+    pub fn describe(&self) -> String {
+        let rules = self.describe_lie_rules();
+        match self.lie_window {
+            Some(window) if self.lie_probability.is_none() => format!("{}, only in the first {} answers", rules, window),
+            _ => rules,
+        }
+    }
+    // The same, leaving out the lie window.
+    fn describe_lie_rules(&self) -> String {
+        match self.lie_probability {
+            None if self.variant == Variant::HalfLie && self.max_lies == 1 => {
+                format!("{}, which can only be a false yes", describe_lies(self.max_lies))
+            }
+            None if self.variant == Variant::HalfLie => {
+                format!("{}, which can only be false yeses", describe_lies(self.max_lies))
+            }
+            None if self.variant == Variant::ThreeWay => {
+                format!("{} in answers of less, equal or greater", describe_lies(self.max_lies))
+            }
+            None if self.variant == Variant::TruthfulLast => {
+                format!("{}, but never in the last answer", describe_lies(self.max_lies))
+            }
+            None if self.variant == Variant::Directional => describe_directional_lies(self.max_lies),
+            None => describe_lies(self.max_lies),
+            Some(lie_probability) => format!("each answer a lie with probability {}", lie_probability),
+        }
+    }
+    /// Which numbers the secret is among, for messages like "Guess the number, with
+    /// up to one lie, out of 100".
+    pub fn describe_range(&self) -> String {
+        if let Some(ref candidate_set) = self.candidate_set {
+            format!(
+                "among {} numbers from {} to {}",
+                candidate_set.members.len(),
+                self.lower_limit,
+                self.upper_limit.saturating_sub(1)
+            )
+        } else if self.lower_limit == 0 {
+            format!("out of {}", self.upper_limit)
+        } else {
+            format!("from {} to {}", self.lower_limit, self.upper_limit.saturating_sub(1))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    Ongoing,
+    Finished(Number),
+    Impossible,
+    /// The question budget ran out before the number was pinned down.
+    Lost,
+}
+
+use self::GameResult::*;
+
+/// Finished once every possibility agrees on a single number.
+pub fn result<L>(poss: impl IntoIterator<Item = (Range, L)>) -> GameResult {
+    let possible = PossibilitySet::new(poss.into_iter().map(|(range, _)| range));
+    match possible.ranges() {
+        [] => Impossible,
+        [only] if only.len() == 1 => Finished(only.lower),
+        _ => Ongoing,
+    }
+}
+
+/// Finished once every possibility is within `width` numbers in a row, giving the
+/// middle one, or the lower of the middle two.
+pub fn cornered<L>(poss: impl IntoIterator<Item = (Range, L)>, width: Count) -> GameResult {
+    let possible = PossibilitySet::new(poss.into_iter().map(|(range, _)| range));
+    match (possible.ranges().first(), possible.ranges().last()) {
+        (Some(first), Some(last)) => match Range::new(first.lower, last.higher).len() {
+            span if span <= width => Finished(first.lower + ((span - 1) / 2) as Number),
+            _ => Ongoing,
+        },
+        _ => Impossible,
+    }
+}
+
+/// How sure a noisy game must be of a single number to finish, unless it says
+/// otherwise.
+pub const CONFIDENCE: f64 = 0.99;
+
+/// Finished once a single number is at least `confidence` likely.
+pub fn confident_result(posterior: Vec<(Range, f64)>, confidence: f64) -> GameResult {
+    let best = posterior.iter()
+        .filter(|&&(range, _)| !range.is_empty())
+        .max_by(|a, b| a.1.partial_cmp(&b.1).expect("Probabilities are never NaN"));
+    match best {
+        None => Impossible,
+        Some(&(range, probability)) => if range.len() == 1 && probability >= confidence {
+            Finished(range.lower)
+        } else {
+            Ongoing
+        },
+    }
+}
+
+// The lies in a directional game, where at most half of them, rounded up, can go
+// each way.
+fn describe_directional_lies(max_lies: usize) -> String {
+    let each_way = max_lies.div_ceil(2);
+    match max_lies {
+        0 | 1 => describe_lies(max_lies),
+        2 => "up to one false yes and one false no".to_string(),
+        _ if max_lies.is_multiple_of(2) => format!("up to {} false yeses and {} false nos", each_way, each_way),
+        _ => format!("{}, no more than {} of them either way", describe_lies(max_lies), each_way),
+    }
+}
+
+fn describe_lies(max_lies: usize) -> String {
+    match max_lies {
+        0 => "no lies".to_string(),
+        1 => "up to one lie".to_string(),
+        n => format!("up to {} lies", n),
+    }
+}
@@ -0,0 +1,203 @@
+//! Guessing a point on a grid, asking whether it lies left of a column or above
+//! a row.
+//!
+//! Points are numbered row by row, so that the point at column x of row y is
+//! y·width + x. "Is y less than b?" is then "is it less than b·width?", and "is x
+//! less than a?" asks whether it is in the first a numbers of any row, so the game
+//! is an ordinary [`GameState`] that every opponent plays as it would any other.
+//! What each lie hypothesis allows is read back as [`Rect`]s, since the runs of
+//! numbers it allows are strips of rows that stack up into rectangles.
+
+use std::fmt;
+
+use crate::game::{Count, GameError, GameState, Number, Question, Range, Response, MAX_QUESTION_PARTS};
+use crate::value::ValueFunction;
+
+/// The columns `left..right` of the rows `top..bottom`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub left: Number,
+    pub right: Number,
+    pub top: Number,
+    pub bottom: Number,
+}
+
+impl Rect {
+    /// How many points it holds.
+    pub fn area(&self) -> Count {
+        ((self.right - self.left) * (self.bottom - self.top)) as Count
+    }
+}
+
+impl fmt::Display for Rect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.right - self.left, self.bottom - self.top) {
+            (1, 1) => write!(f, "({}, {})", self.left, self.top),
+            _ => write!(f, "x {}..{}, y {}..{}", self.left, self.right, self.top, self.bottom),
+        }
+    }
+}
+
+/// Which coordinate a question is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+/// A grid of `width` columns and `height` rows, both counted from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grid {
+    width: Number,
+    height: Number,
+}
+
+impl Grid {
+    /// A grid of at least two points, with few enough rows that a question about
+    /// columns, which has a run of numbers in each row, stays within
+    /// [`MAX_QUESTION_PARTS`].
+    pub fn new(width: Number, height: Number) -> Result<Grid, GameError> {
+        if width < 1 || height < 1 || width * height < 2 {
+            return Err(GameError::InvalidRules("A grid needs at least two points".to_string()));
+        }
+        if height >= MAX_QUESTION_PARTS as Number {
+            return Err(GameError::InvalidRules(format!("A grid can have at most {} rows", MAX_QUESTION_PARTS - 1)));
+        }
+        Ok(Grid { width, height })
+    }
+    pub fn width(&self) -> Number {
+        self.width
+    }
+    pub fn height(&self) -> Number {
+        self.height
+    }
+    /// A game over every point, with up to `max_lies` lies.
+    pub fn game(&self, max_lies: usize) -> GameState {
+        GameState::new(self.width * self.height, max_lies)
+    }
+    /// The number of the point at column `x` of row `y`.
+    pub fn number(&self, x: Number, y: Number) -> Number {
+        y * self.width + x
+    }
+    /// The column and row of the point numbered `number`.
+    pub fn point(&self, number: Number) -> (Number, Number) {
+        (number % self.width, number / self.width)
+    }
+    /// Reads a point written x,y, as in 3,4.
+    pub fn parse_point(&self, text: &str) -> Result<Number, GameError> {
+        let bad_point = || GameError::ParseError(format!(
+            "{} isn't a point: points are written x,y with x below {} and y below {}",
+            text, self.width, self.height
+        ));
+        let (x, y) = text.split_once(',').ok_or_else(bad_point)?;
+        match (x.trim().parse::<Number>(), y.trim().parse::<Number>()) {
+            (Ok(x), Ok(y)) if (0..self.width).contains(&x) && (0..self.height).contains(&y) => Ok(self.number(x, y)),
+            _ => Err(bad_point()),
+        }
+    }
+    /// Whether the point's coordinate on `axis` is less than `threshold`.
+    pub fn question(&self, axis: Axis, threshold: Number) -> Question {
+        match axis {
+            Axis::Y => Question::Less(threshold * self.width),
+            Axis::X => Question::subset((0..self.height).map(|row| {
+                Range::new(self.number(0, row), self.number(threshold, row))
+            })),
+        }
+    }
+    /// Whether asking about `threshold` on `axis` could split the points at all.
+    pub fn splits(&self, axis: Axis, threshold: Number) -> bool {
+        0 < threshold && threshold < self.side(axis)
+    }
+    /// The axis and threshold `question` asks about, if it is a question about one.
+    pub fn threshold(&self, question: &Question) -> Option<(Axis, Number)> {
+        [Axis::X, Axis::Y].iter()
+            .flat_map(|&axis| (1..self.side(axis)).map(move |threshold| (axis, threshold)))
+            .find(|&(axis, threshold)| self.question(axis, threshold) == *question)
+    }
+    fn side(&self, axis: Axis) -> Number {
+        match axis {
+            Axis::X => self.width,
+            Axis::Y => self.height,
+        }
+    }
+    /// `question` as in "x < 3", for questions about a threshold.
+    pub fn describe(&self, question: &Question) -> String {
+        match self.threshold(question) {
+            Some((Axis::X, threshold)) => format!("x < {}", threshold),
+            Some((Axis::Y, threshold)) => format!("y < {}", threshold),
+            None => question.describe(),
+        }
+    }
+    /// The points in `ranges` of point numbers, as rectangles: each range split
+    /// into strips of rows, and strips over the same columns in rows next to each
+    /// other stacked together.
+    pub fn rectangles(&self, ranges: impl IntoIterator<Item = Range>) -> Vec<Rect> {
+        let mut strips = vec![];
+        for range in ranges {
+            let mut start = range.lower;
+            while start < range.higher {
+                let (x, y) = self.point(start);
+                let end = range.higher.min(self.number(self.width, y));
+                strips.push(Rect { left: x, right: x + end - start, top: y, bottom: y + 1 });
+                start = end;
+            }
+        }
+        // A strip joins a rectangle over the same columns that ends at its row
+        let mut rectangles: Vec<Rect> = vec![];
+        for strip in strips {
+            let below = rectangles.iter_mut().find(|rectangle| {
+                rectangle.left == strip.left && rectangle.right == strip.right && rectangle.bottom == strip.top
+            });
+            match below {
+                Some(rectangle) => rectangle.bottom = strip.bottom,
+                None => rectangles.push(strip),
+            }
+        }
+        rectangles
+    }
+    /// The points still possible with each number of lies, as rectangles, for
+    /// each count of lies from none up.
+    pub fn possibilities(&self, game: &GameState) -> Vec<Vec<Rect>> {
+        (0..=game.max_lies())
+            .map(|lies| {
+                self.rectangles(game.contradictions().into_iter()
+                    .filter(|&(range, contradicted)| contradicted == lies && !range.is_empty())
+                    .map(|(range, _)| range))
+            })
+            .collect()
+    }
+    /// The threshold question whose worst answer leaves the smallest `value`,
+    /// trying every threshold on each axis, preferring the first if several tie.
+    pub fn best_question(&self, game: &GameState, value: &ValueFunction) -> (Axis, Number) {
+        let mut best: Option<(Count, Axis, Number)> = None;
+        for &axis in &[Axis::X, Axis::Y] {
+            for threshold in 1..self.side(axis) {
+                let question = self.question(axis, threshold);
+                let worst = game.responses(&question).into_iter()
+                    .filter(|&response| response != Response::Pass)
+                    .map(|response| {
+                        let mut next = game.clone();
+                        next.ask(question.clone(), response).expect("Threshold questions are legal");
+                        value.value(&next)
+                    })
+                    .max()
+                    .unwrap_or(0);
+                let better = match best {
+                    Some((best, _, _)) => worst < best,
+                    None => true,
+                };
+                if better {
+                    best = Some((worst, axis, threshold));
+                }
+            }
+        }
+        let (_, axis, threshold) = best.expect("Grids have at least two points, so a threshold to ask about");
+        (axis, threshold)
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}×{}", self.width, self.height)
+    }
+}
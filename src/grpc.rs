@@ -0,0 +1,438 @@
+//! The gRPC service in proto/one_lie.proto, served over http2 for typed clients
+//! of a game backend. Its methods are those of engine --rpc, with each game kept
+//! under the id NewGame hands out, as api keeps them. Messages are read and
+//! written by hand, field by field, as the proto lays them out.
+//!
+//! Each connection is read on its own thread, but requests are handled one at a
+//! time on the thread that called serve_grpc, as the opponents share one search.
+
+use std::collections::BTreeMap;
+use std::net::TcpListener;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::engine::{parse_response, Rules};
+use crate::http2::{self, Request, Response};
+use crate::setup::{make_guesser, make_opponent, OpponentSetup};
+use crate::transcript::{format_move, parse_move};
+use crate::{GameResult, GameState, Guesser, Opponent, Question, Variant};
+
+const SERVICE: &str = "/one_lie.OneLie/";
+
+// Status codes, from the gRPC spec
+const OK: u32 = 0;
+const INVALID_ARGUMENT: u32 = 3;
+const NOT_FOUND: u32 = 5;
+const FAILED_PRECONDITION: u32 = 9;
+const UNIMPLEMENTED: u32 = 12;
+
+type Failure = (u32, String);
+
+fn invalid(err: impl ToString) -> Failure {
+    (INVALID_ARGUMENT, err.to_string())
+}
+
+fn refused(err: impl ToString) -> Failure {
+    (FAILED_PRECONDITION, err.to_string())
+}
+
+// A protobuf message being written. Every field is written, even those left at
+// their defaults, which readers take the same way.
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn uint(mut self, field: u32, value: u64) -> Message {
+        self.varint(u64::from(field) << 3);
+        self.varint(value);
+        self
+    }
+
+    // int64, whose negative values take all ten bytes
+    fn int(self, field: u32, value: i64) -> Message {
+        self.uint(field, value as u64)
+    }
+
+    fn bytes(mut self, field: u32, bytes: &[u8]) -> Message {
+        self.varint(u64::from(field) << 3 | 2);
+        self.varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+        self
+    }
+
+    fn string(self, field: u32, text: &str) -> Message {
+        self.bytes(field, text.as_bytes())
+    }
+
+    fn message(self, field: u32, message: Message) -> Message {
+        self.bytes(field, &message.0)
+    }
+
+    // A repeated integer field, packed as proto3 packs them.
+    fn packed(self, field: u32, values: impl Iterator<Item = u64>) -> Message {
+        let mut packed = Message::default();
+        values.for_each(|value| packed.varint(value));
+        self.bytes(field, &packed.0)
+    }
+}
+
+// A field's value as read, before the proto says what it means.
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+// A protobuf message's fields, in the order they came.
+struct Fields<'a>(Vec<(u32, Value<'a>)>);
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> Result<u64, String> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*position).ok_or("The message ended early")?;
+        *position += 1;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("The message has a varint too long".to_string())
+}
+
+impl<'a> Fields<'a> {
+    fn parse(bytes: &'a [u8]) -> Result<Fields<'a>, String> {
+        let mut fields = vec![];
+        let mut position = 0;
+        while position < bytes.len() {
+            let key = read_varint(bytes, &mut position)?;
+            let field = (key >> 3) as u32;
+            let value = match key & 7 {
+                0 => Value::Varint(read_varint(bytes, &mut position)?),
+                // fixed64 and fixed32, which no message here has, so are skipped
+                wire @ (1 | 5) => {
+                    position += if wire == 1 { 8 } else { 4 };
+                    if position > bytes.len() {
+                        return Err("The message ended early".to_string());
+                    }
+                    Value::Fixed
+                }
+                2 => {
+                    let length = read_varint(bytes, &mut position)? as usize;
+                    let value = bytes.get(position..position.saturating_add(length)).ok_or("The message ended early")?;
+                    position += length;
+                    Value::Bytes(value)
+                }
+                wire => return Err(format!("The message has field {} of wire type {}, which isn't supported", field, wire)),
+            };
+            fields.push((field, value));
+        }
+        Ok(Fields(fields))
+    }
+
+    // The last value of an integer field, as protobuf takes the last.
+    fn uint(&self, field: u32) -> Result<Option<u64>, String> {
+        match self.0.iter().rev().find(|(number, _)| *number == field) {
+            None => Ok(None),
+            Some((_, Value::Varint(value))) => Ok(Some(*value)),
+            Some(_) => Err(format!("Field {} should be an integer", field)),
+        }
+    }
+
+    fn int(&self, field: u32) -> Result<i64, String> {
+        Ok(self.uint(field)?.unwrap_or(0) as i64)
+    }
+
+    fn strings(&self, field: u32) -> Result<Vec<&'a str>, String> {
+        self.0.iter()
+            .filter(|(number, _)| *number == field)
+            .map(|(_, value)| match *value {
+                Value::Bytes(bytes) => std::str::from_utf8(bytes).map_err(|_| format!("Field {} isn't UTF-8", field)),
+                _ => Err(format!("Field {} should be a string", field)),
+            })
+            .collect()
+    }
+
+    fn string(&self, field: u32) -> Result<&'a str, String> {
+        Ok(self.strings(field)?.pop().unwrap_or_default())
+    }
+}
+
+fn state(game: &GameState) -> Message {
+    let mut state = Message::default();
+    for (range, lies) in game.hypotheses().filter(|(range, _)| !range.is_empty()) {
+        let range = Message::default().int(1, range.lower as i64).int(2, range.higher as i64);
+        let hypothesis = Message::default().message(1, range).packed(2, lies.iter().map(|&lie| lie as u64));
+        state = state.message(1, hypothesis);
+    }
+    let status = match game.status() {
+        GameResult::Ongoing => Message::default().uint(1, 1),
+        GameResult::Finished(number) => Message::default().int(2, number as i64),
+        GameResult::Impossible => Message::default().uint(3, 1),
+        GameResult::Lost => Message::default().uint(4, 1),
+    };
+    state.message(2, status)
+}
+
+// How many games are kept at once, and how long one may go unplayed before it is
+// forgotten, as api keeps them.
+const MAX_SESSIONS: usize = 1000;
+const SESSION_IDLE: Duration = Duration::from_secs(60 * 60);
+
+// The game, with a guesser and opponent made for it, and the rules it started under.
+struct Session {
+    rules: Rules,
+    game: GameState,
+    guesser: Box<dyn Guesser>,
+    opponent: Box<dyn Opponent>,
+    // When a request last used the game
+    used: Instant,
+}
+
+struct Service {
+    setup: OpponentSetup,
+    guesser_name: String,
+    sessions: BTreeMap<u64, Session>,
+    next_id: u64,
+}
+
+// The question in a request, with a number on its own asking whatever a guess
+// asks in this game.
+fn question(game: &GameState, text: &str) -> Result<Question, Failure> {
+    let question = match text.parse::<Question>().map_err(invalid)? {
+        Question::Less(guess) => game.guess(guess),
+        question => question,
+    };
+    if game.status() != GameResult::Ongoing {
+        return Err(refused("The game is over"));
+    }
+    game.check_question(&question).map_err(refused)?;
+    Ok(question)
+}
+
+impl Service {
+    fn session(&mut self, fields: &Fields) -> Result<(u64, &mut Session), Failure> {
+        let id = fields.uint(1).map_err(invalid)?.unwrap_or(0);
+        match self.sessions.get_mut(&id) {
+            Some(session) => {
+                session.used = Instant::now();
+                Ok((id, session))
+            }
+            None => Err((NOT_FOUND, format!("There's no game {}", id))),
+        }
+    }
+
+    // Forgets idle games, and the least recently used if there are still too many.
+    fn make_room(&mut self) {
+        self.sessions.retain(|_, session| session.used.elapsed() < SESSION_IDLE);
+        if self.sessions.len() >= MAX_SESSIONS {
+            let oldest = self.sessions.iter().min_by_key(|(_, session)| session.used).map(|(&id, _)| id);
+            self.sessions.retain(|&id, _| Some(id) != oldest);
+        }
+    }
+
+    fn game(id: u64, session: &Session) -> Message {
+        let rules = &session.rules;
+        let mut game = Message::default()
+            .uint(1, id)
+            .int(2, rules.limit as i64)
+            .int(3, rules.lower as i64)
+            .uint(4, rules.lies as u64)
+            .string(5, &rules.variant.to_string());
+        for (question, response) in session.game.history() {
+            game = game.string(6, &format_move(question, *response));
+        }
+        game.message(7, state(&session.game))
+    }
+
+    fn new_game(&mut self, fields: &Fields) -> Result<Message, Failure> {
+        let variant = match fields.string(4).map_err(invalid)? {
+            "" => Variant::Standard,
+            name => Variant::parse(name).map_err(invalid)?,
+        };
+        let rules = Rules {
+            lower: fields.int(2).map_err(invalid)?.into(),
+            limit: fields.int(1).map_err(invalid)?.into(),
+            lies: fields.uint(3).map_err(invalid)?.unwrap_or(1) as usize,
+            variant,
+        };
+        rules.validate().map_err(invalid)?;
+        rules.check_players(Some(&self.guesser_name), Some(self.setup.kind)).map_err(refused)?;
+        let mut game = rules.game();
+        for text in fields.strings(5).map_err(invalid)? {
+            let (question, response) = parse_move(text).map_err(invalid)?;
+            game.ask(question, response).map_err(refused)?;
+        }
+        let mut setup = self.setup.clone();
+        setup.resolve(&game).map_err(refused)?;
+        let session = Session {
+            rules,
+            guesser: make_guesser(&game, &setup, &self.guesser_name),
+            opponent: make_opponent(&game, &setup),
+            game,
+            used: Instant::now(),
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        let reply = Service::game(id, &session);
+        self.make_room();
+        self.sessions.insert(id, session);
+        Ok(reply)
+    }
+
+    fn call(&mut self, method: &str, fields: &Fields) -> Result<Message, Failure> {
+        match method {
+            "NewGame" => self.new_game(fields),
+            "GetGame" => {
+                let (id, session) = self.session(fields)?;
+                Ok(Service::game(id, session))
+            }
+            "Guess" => {
+                let (_, session) = self.session(fields)?;
+                let question = question(&session.game, fields.string(2).map_err(invalid)?)?;
+                let response = session.opponent.respond(&session.game, &question);
+                let asked = session.game.history().len();
+                session.game.ask(question.clone(), response).map_err(refused)?;
+                Ok(Message::default()
+                    .uint(1, asked as u64)
+                    .string(2, &format_move(&question, response))
+                    .message(3, state(&session.game)))
+            }
+            "Respond" => {
+                let (_, session) = self.session(fields)?;
+                let question = question(&session.game, fields.string(2).map_err(invalid)?)?;
+                let answer = fields.string(3).map_err(invalid)?;
+                let response = parse_response(&question, answer)
+                    .ok()
+                    .filter(|response| session.game.responses(&question).contains(response))
+                    .ok_or_else(|| invalid(format!("{} isn't an answer to {}", answer, question)))?;
+                session.game.ask(question, response).map_err(refused)?;
+                Ok(state(&session.game))
+            }
+            "BestQuestion" => {
+                let (_, session) = self.session(fields)?;
+                if session.game.status() != GameResult::Ongoing {
+                    return Ok(Message::default());
+                }
+                let guess = session.guesser.choose(&session.game);
+                Ok(Message::default().string(1, &session.game.guess(guess).to_string()))
+            }
+            "Possibilities" => Ok(state(&self.session(fields)?.1.game)),
+            "DeleteGame" => {
+                let (id, _) = self.session(fields)?;
+                self.sessions.remove(&id);
+                Ok(Message::default().uint(1, id))
+            }
+            _ => Err((UNIMPLEMENTED, format!("There's no method {}", method))),
+        }
+    }
+
+    fn handle(&mut self, request: &Request) -> Response {
+        if !request.header("content-type").is_some_and(|content_type| content_type.starts_with("application/grpc")) {
+            return Response { status: 415, headers: vec![], body: vec![], trailers: vec![] };
+        }
+        let path = request.header(":path").unwrap_or_default();
+        let reply = match (path.strip_prefix(SERVICE), unframe(&request.body)) {
+            (None, _) => Err((UNIMPLEMENTED, format!("There's no service at {}", path))),
+            (_, Err(err)) => Err(err),
+            (Some(method), Ok(message)) => Fields::parse(message).map_err(invalid).and_then(|fields| self.call(method, &fields)),
+        };
+        let content_type = ("content-type".to_string(), "application/grpc".to_string());
+        match reply {
+            Ok(message) => {
+                let mut body = vec![0];
+                body.extend_from_slice(&(message.0.len() as u32).to_be_bytes());
+                body.extend_from_slice(&message.0);
+                Response {
+                    status: 200,
+                    headers: vec![content_type],
+                    body,
+                    trailers: vec![("grpc-status".to_string(), OK.to_string())],
+                }
+            }
+            // Failures go in the headers alone, with no message or trailers
+            Err((code, message)) => Response {
+                status: 200,
+                headers: vec![
+                    content_type,
+                    ("grpc-status".to_string(), code.to_string()),
+                    ("grpc-message".to_string(), percent_encode(&message)),
+                ],
+                body: vec![],
+                trailers: vec![],
+            },
+        }
+    }
+}
+
+// The one message in a unary request's body, after its flag and length.
+fn unframe(body: &[u8]) -> Result<&[u8], Failure> {
+    match body {
+        [0, a, b, c, d, message @ ..] if u32::from_be_bytes([*a, *b, *c, *d]) as usize == message.len() => Ok(message),
+        [1, ..] => Err((UNIMPLEMENTED, "Compressed messages aren't supported".to_string())),
+        _ => Err(invalid("The request should hold exactly one message")),
+    }
+}
+
+// grpc-message as the spec has it sent, with anything outside printable ASCII,
+// and the percent sign, written as %XX.
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b' '..=b'~' if byte != b'%' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+// A request read from a connection, with where to send its response.
+struct Call {
+    request: Request,
+    reply: Sender<Response>,
+}
+
+/// Listens on `port`, starting games against `setup`'s opponent, with best
+/// questions from the guesser named. Runs until the server is stopped. Only this
+/// machine can connect, as there is no TLS or authentication; backends elsewhere
+/// reach it through a proxy of their own.
+pub fn serve_grpc(setup: OpponentSetup, guesser_name: String, port: u16) -> Result<(), String> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|err| format!("Couldn't listen on port {}: {}", port, err))?;
+    println!("Listening on localhost port {} for gRPC, as proto/one_lie.proto describes", port);
+    let (calls, requests) = channel::<Call>();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    println!("Couldn't accept a connection: {}", err);
+                    continue;
+                }
+            };
+            let calls = calls.clone();
+            thread::spawn(move || {
+                let served = http2::serve_connection(&stream, |request| {
+                    let (reply, response) = channel();
+                    // Only fails once the server is stopping
+                    let _ = calls.send(Call { request, reply });
+                    response.recv().unwrap_or(Response { status: 503, headers: vec![], body: vec![], trailers: vec![] })
+                });
+                if let Err(err) = served {
+                    println!("Dropped a connection: {}", err);
+                }
+            });
+        }
+    });
+    let mut service = Service { setup, guesser_name, sessions: BTreeMap::new(), next_id: 1 };
+    for Call { request, reply } in requests {
+        let _ = reply.send(service.handle(&request));
+    }
+    Ok(())
+}
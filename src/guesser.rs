@@ -0,0 +1,153 @@
+use std::cmp::{max, min};
+use std::collections::HashMap;
+
+use crate::game::GameResult::{Impossible, Ongoing};
+use crate::game::{GameState, Count, Number, Question, Range, Response};
+use crate::value::ValueFunction;
+
+/// Asks the questions.
+pub trait Guesser {
+    /// The next guess to ask "is it less than?" about, or in three-way games to
+    /// compare with, as [`GameState::guess`] asks.
+    fn choose(&mut self, game: &GameState) -> Number;
+}
+
+/// The most questions `guesser` can be made to ask from `game`, found by following
+/// it down every sequence of answers that keeps a number possible, or None if
+/// that is more than `cap` in all.
+///
+/// Positions are remembered by the numbers each count of lies allows and the
+/// number of questions asked and passed on, so the guesser's choices must depend on nothing
+/// else, as is true of every guesser here. Only for games with a fixed number of
+/// lies, as noisy games can go on forever.
+pub fn worst_case(guesser: &mut dyn Guesser, game: &GameState, cap: usize) -> Option<usize> {
+    worst_case_from(guesser, game, cap, &mut HashMap::new())
+}
+
+type Position = (Vec<(Number, Number, usize)>, usize, usize);
+
+fn worst_case_from(
+    guesser: &mut dyn Guesser,
+    game: &GameState,
+    cap: usize,
+    seen: &mut HashMap<Position, Option<usize>>,
+) -> Option<usize> {
+    if game.status() != Ongoing {
+        return Some(game.history.len());
+    }
+    if game.history.len() >= cap {
+        return None;
+    }
+    let mut hypotheses: Vec<(Number, Number, usize)> = game.hypotheses()
+        .filter(|&(range, _)| !range.is_empty())
+        .map(|(range, lies)| (range.lower, range.higher, lies.len()))
+        .collect();
+    hypotheses.sort_unstable();
+    let position = (hypotheses, game.history.len(), game.passes());
+    if let Some(&questions) = seen.get(&position) {
+        return questions;
+    }
+    let question = game.guess(guesser.choose(game));
+    let mut questions = Some(0);
+    for answer in game.responses(&question) {
+        let mut next = game.clone();
+        next.ask(question.clone(), answer).expect("Guessers only ask legal questions");
+        if next.status() != Impossible {
+            match worst_case_from(guesser, &next, cap, seen) {
+                Some(answer_questions) => questions = questions.map(|questions| max(questions, answer_questions)),
+                None => questions = None,
+            }
+        }
+        if questions.is_none() {
+            break;
+        }
+    }
+    seen.insert(position, questions);
+    questions
+}
+
+/// Asks the question whose worst answer leaves the smallest value.
+// The value after a High answer can only fall as the guess rises, and after a
+// Low answer only rise, so the best question is found by binary searching for
+// where they cross. Only guesses that split the live numbers are considered, as
+// any other question leaves nothing learned and could be asked forever. In
+// three-way games the Equal answer also counts towards the worst case, but
+// changes little from one guess to the next, so it is left out of the search.
+pub struct Greedy {
+    pub value: ValueFunction,
+}
+
+impl Greedy {
+    fn worst_case(&self, game: &GameState, guess: Number) -> (Count, Count) {
+        let value = |response| {
+            let mut next = game.clone();
+            next.ask(game.guess(guess), response).unwrap();
+            self.value.value(&next)
+        };
+        (value(Response::No), value(Response::Yes))
+    }
+
+    // The value the worst answer to the question about `guess` leaves.
+    fn worst_value(&self, game: &GameState, guess: Number) -> Count {
+        let (high_remaining, low_remaining) = self.worst_case(game, guess);
+        let worst = max(high_remaining, low_remaining);
+        match game.guess(guess) {
+            question @ Question::Compare(_) => {
+                let mut next = game.clone();
+                next.ask(question, Response::Equal).unwrap();
+                max(worst, self.value.value(&next))
+            }
+            _ => worst,
+        }
+    }
+}
+
+impl Guesser for Greedy {
+    fn choose(&mut self, game: &GameState) -> Number {
+        let live: Vec<Range> = game.contradictions().into_iter()
+            .filter(|&(range, lies)| lies <= game.max_lies && !range.is_empty())
+            .map(|(range, _)| range)
+            .collect();
+        let first = live.first().map_or(game.lower_limit, |range| range.lower);
+        let last = live.last().map_or(game.upper_limit, |range| range.higher - 1);
+        // Splitting needs a live number below the guess and one at or above it
+        let (lowest, highest) = (first + 1, max(last, first + 1));
+        let (mut low, mut high) = (lowest, highest);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let (high_remaining, low_remaining) = self.worst_case(game, mid);
+            if low_remaining >= high_remaining {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        let crossing = min(low, game.upper_limit.saturating_sub(1));
+        let worse = |guess| self.worst_value(game, guess);
+        if crossing > lowest && worse(crossing - 1) < worse(crossing) {
+            crossing - 1
+        } else {
+            crossing
+        }
+    }
+}
+
+/// Splits the posterior probability as evenly as possible, for noisy games.
+pub struct Median;
+
+impl Guesser for Median {
+    fn choose(&mut self, game: &GameState) -> Number {
+        let lie_probability = game.lie_probability.expect("Median guesser is for noisy games");
+        let mut below = 0.0;
+        for (range, probability) in game.posterior(lie_probability) {
+            let mass = game.count(range) as f64 * probability;
+            if below + mass >= 0.5 {
+                let needed = ((0.5 - below) / probability).round() as Count;
+                let guess = game.nth(range, needed).unwrap_or(range.higher);
+                return max(guess, game.lower_limit + 1);
+            }
+            below += mass;
+        }
+        game.range().offset(game.range().len() / 2)
+    }
+}
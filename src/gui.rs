@@ -0,0 +1,259 @@
+//! A page to play in, for play --gui, served to the browser from this machine. It
+//! drives the same Play state machine as the terminal: the slider and buttons on
+//! gui.html send lines as if typed, and every reply carries what Play has said,
+//! with the numbers still possible for a bar across the range and how many
+//! questions perfect play needs from each position reached, for a graph. The
+//! server stops once the game is over, after sending the page its end.
+
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+
+use crate::api::{body_json, read_request, write_response, Request};
+use crate::commands::PlayCommand;
+use crate::frontend::{describe_answer, Frontend};
+use crate::game::GameResult::{self, *};
+use crate::json::Json;
+use crate::modes::wrap_up;
+use crate::play::{Effect, Play};
+use crate::setup::{make_opponent, make_solver, save_game, OpponentSetup};
+use crate::style::Style;
+use crate::{GameState, Opponent, Solver, Variant};
+
+// How long the graph's search may take after each answer, so the page stays
+// responsive in games too large to search fully.
+const EVALUATION_TIME: Duration = Duration::from_millis(300);
+
+const PAGE: &str = include_str!("gui.html");
+
+// Everything said, kept for the log.
+struct Log(Vec<String>);
+
+impl Frontend for Log {
+    fn say(&mut self, text: &str) {
+        self.0.push(text.to_string());
+    }
+    fn answered(&mut self, game: &GameState) {
+        self.0.push(describe_answer(game, Style::plain()));
+    }
+    fn show_state(&mut self, _game: &GameState) {}
+    fn prompt(&mut self, _game: &GameState) -> Option<String> {
+        None
+    }
+}
+
+struct App {
+    play: Play,
+    opponent: Box<dyn Opponent>,
+    setup: OpponentSetup,
+    record: Option<String>,
+    log: Log,
+    // How many questions perfect play needs, from the start and after each answer,
+    // with whether the search finished or only found a lower bound
+    evaluation: Vec<(usize, usize, bool)>,
+    evaluator: Option<Solver>,
+    over: bool,
+}
+
+// Whether the solver can evaluate positions of `game`, which it can for the
+// games it gives hints in.
+fn evaluable(game: &GameState) -> bool {
+    game.lie_probability().is_none()
+        && game.variant() != Variant::Directional
+        && game.delay() == 0
+        && game.moving_target().is_none()
+        && !game.final_guess()
+}
+
+fn describe_status(status: GameResult) -> String {
+    match status {
+        Ongoing => "still going".to_string(),
+        Finished(number) => format!("found it: {}", number),
+        Impossible => "the answers contradict each other".to_string(),
+        Lost => "out of questions".to_string(),
+    }
+}
+
+impl App {
+    fn new(game: GameState, setup: OpponentSetup, record: Option<String>) -> App {
+        let opponent = make_opponent(&game, &setup);
+        let evaluator = evaluable(&game).then(|| {
+            Solver::new(game.max_lies()).with_variant(game.variant()).with_time_limit(EVALUATION_TIME)
+        });
+        let solver = make_solver(&game, &setup);
+        let mut app = App {
+            play: Play::new(game, solver, Style::plain(), false),
+            opponent,
+            setup,
+            record,
+            log: Log(vec![]),
+            evaluation: vec![],
+            evaluator,
+            over: false,
+        };
+        app.evaluate();
+        let effects = app.play.start(&app.setup);
+        app.apply(effects);
+        app
+    }
+
+    // Carries out what Play asks for, having the opponent answer its questions.
+    fn apply(&mut self, mut effects: Vec<Effect>) {
+        loop {
+            let mut asked = None;
+            for effect in effects {
+                match effect {
+                    Effect::Say(text) => self.log.say(&text),
+                    Effect::Answered => {
+                        self.log.answered(self.play.game());
+                        self.evaluate();
+                    }
+                    Effect::ShowState => {}
+                    Effect::Ask(question) => asked = Some(question),
+                    Effect::Save(path) => match save_game(&path, self.play.game(), &self.setup) {
+                        Ok(()) => self.log.say(&format!("Saved to {}", path)),
+                        Err(err) => self.log.say(&format!("Couldn't save: {}", err)),
+                    },
+                    Effect::Over => {
+                        self.over = true;
+                        wrap_up(self.play.game(), &*self.opponent, self.record.as_deref(), &mut self.log);
+                    }
+                }
+            }
+            effects = match asked {
+                Some(question) => {
+                    let response = self.opponent.respond(self.play.game(), &question);
+                    self.play.respond(response)
+                }
+                None => return,
+            };
+        }
+    }
+
+    // A line from the page, as if typed at the terminal.
+    fn command(&mut self, line: &str) {
+        if self.over {
+            return;
+        }
+        match PlayCommand::parse(line) {
+            Ok(command) => {
+                let effects = self.play.command(command);
+                self.apply(effects);
+                // Undoing takes a position off the graph as well
+                self.evaluation.truncate(self.play.game().history().len() + 1);
+            }
+            Err(err) => self.log.say(&err),
+        }
+    }
+
+    fn evaluate(&mut self) {
+        let game = self.play.game();
+        if let Some(solver) = self.evaluator.as_mut().filter(|_| game.status() == Ongoing) {
+            let (needed, finished) = solver.questions_bound(game);
+            self.evaluation.push((game.history().len(), needed, finished));
+        }
+    }
+
+    // Everything the page shows, as it stands.
+    fn state(&self) -> Json {
+        let game = self.play.game();
+        let range = game.range();
+        let possibilities = game.hypotheses()
+            .filter(|(hypothesis, _)| !hypothesis.is_empty())
+            .map(|(hypothesis, lies)| Json::object(vec![
+                ("lower", Json::Int(hypothesis.lower)),
+                ("higher", Json::Int(hypothesis.higher)),
+                ("lies", Json::Int(lies.len() as i128)),
+            ]))
+            .collect();
+        let evaluation = self.evaluation.iter()
+            .map(|&(turn, needed, finished)| Json::object(vec![
+                ("turn", Json::Int(turn as i128)),
+                ("needed", Json::Int(needed as i128)),
+                ("finished", Json::Bool(finished)),
+            ]))
+            .collect();
+        Json::object(vec![
+            ("rules", Json::Str(format!("Guess the number, with {}, {}", game.describe(), game.describe_range()))),
+            ("status", Json::Str(format!("{} question(s) asked, {}", game.turns(), describe_status(game.status())))),
+            ("lower", Json::Int(range.lower)),
+            ("higher", Json::Int(range.higher)),
+            ("compare", Json::Bool(game.variant() == Variant::ThreeWay)),
+            ("max_lies", Json::Int(game.max_lies() as i128)),
+            ("candidates", Json::Str(game.candidates().to_string())),
+            ("possibilities", Json::Array(possibilities)),
+            ("evaluation", Json::Array(evaluation)),
+            ("log", Json::Array(self.log.0.iter().map(|line| Json::Str(line.clone())).collect())),
+            ("over", Json::Bool(self.over)),
+        ])
+    }
+
+    // Replies with the state, after carrying out the line in a POST to /command,
+    // as in {"command": "50"}.
+    fn handle(&mut self, request: &Request) -> Result<Json, (u16, String)> {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/state") => {}
+            ("POST", "/command") => {
+                let body = body_json(request)?;
+                let line = body.field("command").and_then(Json::as_str).map_err(|err| (400, err))?;
+                self.command(line);
+            }
+            _ => return Err((404, format!("There's nothing at {}", request.path))),
+        }
+        Ok(self.state())
+    }
+}
+
+// Asks the desktop to open `url` in the browser, if it has a way to.
+fn open_browser(url: &str) {
+    let (program, arguments): (&str, &[&str]) = match std::env::consts::OS {
+        "macos" => ("open", &[]),
+        "windows" => ("cmd", &["/C", "start", ""]),
+        _ => ("xdg-open", &[]),
+    };
+    let _ = Command::new(program).args(arguments).arg(url).stdout(Stdio::null()).stderr(Stdio::null()).spawn();
+}
+
+/// Plays `game` in the browser against the setup's opponent, which must not be
+/// human, as no one is at the terminal to answer.
+pub fn play_gui(game: GameState, mut setup: OpponentSetup, record: Option<String>) -> Result<(), String> {
+    setup.resolve(&game)?;
+    let mut app = App::new(game, setup, record);
+    // Only this machine may play, on whatever port is free
+    let listener = TcpListener::bind(("127.0.0.1", 0)).map_err(|err| format!("Couldn't listen for the page: {}", err))?;
+    let url = format!("http://{}/", listener.local_addr().map_err(|err| err.to_string())?);
+    println!("Play at {}, or press Ctrl-C to stop", url);
+    open_browser(&url);
+    // Each connection's request is read on its own thread, as browsers open some
+    // they never send on, and answered here
+    let (requests, received) = channel::<(TcpStream, Request)>();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let requests = requests.clone();
+            thread::spawn(move || {
+                let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+                if let Ok(request) = read_request(&stream) {
+                    let _ = requests.send((stream, request));
+                }
+            });
+        }
+    });
+    for (stream, request) in received {
+        if request.method == "GET" && request.path == "/" {
+            write_response(&stream, 200, "text/html; charset=utf-8", PAGE);
+            continue;
+        }
+        let (status, json) = match app.handle(&request) {
+            Ok(json) => (200, json),
+            Err((status, err)) => (status, Json::object(vec![("error", Json::Str(err))])),
+        };
+        write_response(&stream, status, "application/json", &format!("{}\n", json));
+        if app.over {
+            println!("The game is over");
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,653 @@
+//! Just enough HTTP/2 (RFC 9113) for grpc to serve gRPC clients: plain TCP with
+//! prior knowledge, as gRPC's insecure channels speak it, with no TLS, upgrade,
+//! server push or priorities. Requests on a connection are handed over one at a
+//! time as each finishes arriving, and answered in turn. Header blocks are read
+//! with HPACK (RFC 7541), Huffman coding and all, but written without compression,
+//! which every client reads. A connection holds at most MAX_STREAMS requests open
+//! at once, refusing the streams past that, and header blocks up to MAX_HEADERS.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const SETTINGS: u8 = 0x4;
+const PUSH_PROMISE: u8 = 0x5;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const WINDOW_UPDATE: u8 = 0x8;
+const CONTINUATION: u8 = 0x9;
+
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY: u8 = 0x20;
+
+const MAX_CONCURRENT_STREAMS: u16 = 0x3;
+const INITIAL_WINDOW_SIZE: u16 = 0x4;
+const MAX_FRAME_SIZE: u16 = 0x5;
+const MAX_HEADER_LIST_SIZE: u16 = 0x6;
+
+// The sizes both sides start with, which this side never changes
+const WINDOW: i64 = 65535;
+const FRAME: usize = 16384;
+const TABLE: usize = 4096;
+// The largest a window or a frame may be made
+const MAX_WINDOW: i64 = 0x7FFF_FFFF;
+const MAX_FRAME: usize = 0xFF_FFFF;
+
+// Nothing grpc is sent needs more than this
+const MAX_BODY: usize = 1 << 20;
+const MAX_HEADERS: usize = 1 << 16;
+const MAX_STREAMS: usize = 100;
+
+const PROTOCOL_ERROR: u32 = 0x1;
+const FLOW_CONTROL_ERROR: u32 = 0x3;
+const FRAME_SIZE_ERROR: u32 = 0x6;
+const REFUSED_STREAM: u32 = 0x7;
+const ENHANCE_YOUR_CALM: u32 = 0xB;
+
+// The HPACK static table, which indexes 1 to 61 refer to.
+const STATIC: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+// The HPACK Huffman code for each byte and end of string, with its length in bits.
+const HUFFMAN: [(u32, u8); 257] = [
+    (0x1ff8, 13), (0x7fffd8, 23), (0xfffffe2, 28), (0xfffffe3, 28), (0xfffffe4, 28), (0xfffffe5, 28),
+    (0xfffffe6, 28), (0xfffffe7, 28), (0xfffffe8, 28), (0xffffea, 24), (0x3ffffffc, 30), (0xfffffe9, 28),
+    (0xfffffea, 28), (0x3ffffffd, 30), (0xfffffeb, 28), (0xfffffec, 28), (0xfffffed, 28), (0xfffffee, 28),
+    (0xfffffef, 28), (0xffffff0, 28), (0xffffff1, 28), (0xffffff2, 28), (0x3ffffffe, 30), (0xffffff3, 28),
+    (0xffffff4, 28), (0xffffff5, 28), (0xffffff6, 28), (0xffffff7, 28), (0xffffff8, 28), (0xffffff9, 28),
+    (0xffffffa, 28), (0xffffffb, 28), (0x14, 6), (0x3f8, 10), (0x3f9, 10), (0xffa, 12),
+    (0x1ff9, 13), (0x15, 6), (0xf8, 8), (0x7fa, 11), (0x3fa, 10), (0x3fb, 10),
+    (0xf9, 8), (0x7fb, 11), (0xfa, 8), (0x16, 6), (0x17, 6), (0x18, 6),
+    (0x0, 5), (0x1, 5), (0x2, 5), (0x19, 6), (0x1a, 6), (0x1b, 6),
+    (0x1c, 6), (0x1d, 6), (0x1e, 6), (0x1f, 6), (0x5c, 7), (0xfb, 8),
+    (0x7ffc, 15), (0x20, 6), (0xffb, 12), (0x3fc, 10), (0x1ffa, 13), (0x21, 6),
+    (0x5d, 7), (0x5e, 7), (0x5f, 7), (0x60, 7), (0x61, 7), (0x62, 7),
+    (0x63, 7), (0x64, 7), (0x65, 7), (0x66, 7), (0x67, 7), (0x68, 7),
+    (0x69, 7), (0x6a, 7), (0x6b, 7), (0x6c, 7), (0x6d, 7), (0x6e, 7),
+    (0x6f, 7), (0x70, 7), (0x71, 7), (0x72, 7), (0xfc, 8), (0x73, 7),
+    (0xfd, 8), (0x1ffb, 13), (0x7fff0, 19), (0x1ffc, 13), (0x3ffc, 14), (0x22, 6),
+    (0x7ffd, 15), (0x3, 5), (0x23, 6), (0x4, 5), (0x24, 6), (0x5, 5),
+    (0x25, 6), (0x26, 6), (0x27, 6), (0x6, 5), (0x74, 7), (0x75, 7),
+    (0x28, 6), (0x29, 6), (0x2a, 6), (0x7, 5), (0x2b, 6), (0x76, 7),
+    (0x2c, 6), (0x8, 5), (0x9, 5), (0x2d, 6), (0x77, 7), (0x78, 7),
+    (0x79, 7), (0x7a, 7), (0x7b, 7), (0x7ffe, 15), (0x7fc, 11), (0x3ffd, 14),
+    (0x1ffd, 13), (0xffffffc, 28), (0xfffe6, 20), (0x3fffd2, 22), (0xfffe7, 20), (0xfffe8, 20),
+    (0x3fffd3, 22), (0x3fffd4, 22), (0x3fffd5, 22), (0x7fffd9, 23), (0x3fffd6, 22), (0x7fffda, 23),
+    (0x7fffdb, 23), (0x7fffdc, 23), (0x7fffdd, 23), (0x7fffde, 23), (0xffffeb, 24), (0x7fffdf, 23),
+    (0xffffec, 24), (0xffffed, 24), (0x3fffd7, 22), (0x7fffe0, 23), (0xffffee, 24), (0x7fffe1, 23),
+    (0x7fffe2, 23), (0x7fffe3, 23), (0x7fffe4, 23), (0x1fffdc, 21), (0x3fffd8, 22), (0x7fffe5, 23),
+    (0x3fffd9, 22), (0x7fffe6, 23), (0x7fffe7, 23), (0xffffef, 24), (0x3fffda, 22), (0x1fffdd, 21),
+    (0xfffe9, 20), (0x3fffdb, 22), (0x3fffdc, 22), (0x7fffe8, 23), (0x7fffe9, 23), (0x1fffde, 21),
+    (0x7fffea, 23), (0x3fffdd, 22), (0x3fffde, 22), (0xfffff0, 24), (0x1fffdf, 21), (0x3fffdf, 22),
+    (0x7fffeb, 23), (0x7fffec, 23), (0x1fffe0, 21), (0x1fffe1, 21), (0x3fffe0, 22), (0x1fffe2, 21),
+    (0x7fffed, 23), (0x3fffe1, 22), (0x7fffee, 23), (0x7fffef, 23), (0xfffea, 20), (0x3fffe2, 22),
+    (0x3fffe3, 22), (0x3fffe4, 22), (0x7ffff0, 23), (0x3fffe5, 22), (0x3fffe6, 22), (0x7ffff1, 23),
+    (0x3ffffe0, 26), (0x3ffffe1, 26), (0xfffeb, 20), (0x7fff1, 19), (0x3fffe7, 22), (0x7ffff2, 23),
+    (0x3fffe8, 22), (0x1ffffec, 25), (0x3ffffe2, 26), (0x3ffffe3, 26), (0x3ffffe4, 26), (0x7ffffde, 27),
+    (0x7ffffdf, 27), (0x3ffffe5, 26), (0xfffff1, 24), (0x1ffffed, 25), (0x7fff2, 19), (0x1fffe3, 21),
+    (0x3ffffe6, 26), (0x7ffffe0, 27), (0x7ffffe1, 27), (0x3ffffe7, 26), (0x7ffffe2, 27), (0xfffff2, 24),
+    (0x1fffe4, 21), (0x1fffe5, 21), (0x3ffffe8, 26), (0x3ffffe9, 26), (0xffffffd, 28), (0x7ffffe3, 27),
+    (0x7ffffe4, 27), (0x7ffffe5, 27), (0xfffec, 20), (0xfffff3, 24), (0xfffed, 20), (0x1fffe6, 21),
+    (0x3fffe9, 22), (0x1fffe7, 21), (0x1fffe8, 21), (0x7ffff3, 23), (0x3fffea, 22), (0x3fffeb, 22),
+    (0x1ffffee, 25), (0x1ffffef, 25), (0xfffff4, 24), (0xfffff5, 24), (0x3ffffea, 26), (0x7ffff4, 23),
+    (0x3ffffeb, 26), (0x7ffffe6, 27), (0x3ffffec, 26), (0x3ffffed, 26), (0x7ffffe7, 27), (0x7ffffe8, 27),
+    (0x7ffffe9, 27), (0x7ffffea, 27), (0x7ffffeb, 27), (0xffffffe, 28), (0x7ffffec, 27), (0x7ffffed, 27),
+    (0x7ffffee, 27), (0x7ffffef, 27), (0x7fffff0, 27), (0x3ffffee, 26), (0x3fffffff, 30),
+];
+
+pub struct Request {
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(header, _)| header == name).map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Headers sent after the body, as gRPC sends its status
+    pub trailers: Vec<(String, String)>,
+}
+
+// Reads HPACK header blocks, keeping the dynamic table they build between them.
+struct Decoder {
+    table: VecDeque<(String, String)>,
+    size: usize,
+    max_size: usize,
+}
+
+fn entry_size(name: &str, value: &str) -> usize {
+    name.len() + value.len() + 32
+}
+
+impl Decoder {
+    fn get(&self, index: usize) -> Result<(String, String), String> {
+        let (name, value) = match index {
+            0 => return Err("Header index 0".to_string()),
+            1..=61 => STATIC[index - 1],
+            _ => match self.table.get(index - 62) {
+                Some((name, value)) => (name.as_str(), value.as_str()),
+                None => return Err(format!("Header index {} is past the table", index)),
+            },
+        };
+        Ok((name.to_string(), value.to_string()))
+    }
+
+    fn insert(&mut self, name: &str, value: &str) {
+        self.table.push_front((name.to_string(), value.to_string()));
+        self.size += entry_size(name, value);
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.max_size {
+            match self.table.pop_back() {
+                Some((name, value)) => self.size -= entry_size(&name, &value),
+                None => break,
+            }
+        }
+    }
+
+    fn decode(&mut self, block: &[u8]) -> Result<Vec<(String, String)>, String> {
+        let mut headers = vec![];
+        // Counted as SETTINGS_MAX_HEADER_LIST_SIZE counts, since indexes into the
+        // table can make a small block into a great many headers
+        let mut size = 0;
+        let mut reader = Reader { bytes: block, position: 0 };
+        while let Some(&first) = reader.bytes.get(reader.position) {
+            let (name, value) = if first & 0x80 != 0 {
+                let index = reader.integer(7)?;
+                self.get(index)?
+            } else if first & 0xE0 == 0x20 {
+                let size = reader.integer(5)?;
+                if size > TABLE {
+                    return Err(format!("The header table can't grow to {}", size));
+                }
+                self.max_size = size;
+                self.evict();
+                continue;
+            } else {
+                // Literals, indexed if the 0x40 bit is set, which gives a 6 bit
+                // prefix to the index of their name, else 4 bits
+                let indexed = first & 0x40 != 0;
+                let index = reader.integer(if indexed { 6 } else { 4 })?;
+                let name = if index == 0 { reader.string()? } else { self.get(index)?.0 };
+                let value = reader.string()?;
+                if indexed {
+                    self.insert(&name, &value);
+                }
+                (name, value)
+            };
+            size += entry_size(&name, &value);
+            if size > MAX_HEADERS {
+                return Err("The headers are larger than allowed".to_string());
+            }
+            headers.push((name, value));
+        }
+        Ok(headers)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self.bytes.get(self.position).ok_or("A header block ended early")?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    // An integer with an `bits` bit prefix, in the first byte's low bits.
+    fn integer(&mut self, bits: u32) -> Result<usize, String> {
+        let mask = (1 << bits) - 1;
+        let mut value = (self.byte()? & mask) as usize;
+        if value < mask as usize {
+            return Ok(value);
+        }
+        for shift in (0..28).step_by(7) {
+            let byte = self.byte()?;
+            value += ((byte & 0x7F) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("A header block has an integer too large".to_string())
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let huffman = self.bytes.get(self.position).is_some_and(|&byte| byte & 0x80 != 0);
+        let length = self.integer(7)?;
+        let bytes = self.bytes.get(self.position..self.position + length).ok_or("A header block ended early")?;
+        self.position += length;
+        let bytes = if huffman { unhuffman(bytes)? } else { bytes.to_vec() };
+        String::from_utf8(bytes).map_err(|_| "A header isn't UTF-8".to_string())
+    }
+}
+
+fn unhuffman(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decoded = vec![];
+    let (mut code, mut length) = (0u32, 0u8);
+    for byte in bytes {
+        for bit in (0..8).rev() {
+            code = code << 1 | u32::from(byte >> bit & 1);
+            length += 1;
+            match HUFFMAN.iter().position(|&entry| entry == (code, length)) {
+                Some(256) => return Err("A header holds the end of string code".to_string()),
+                Some(symbol) => {
+                    decoded.push(symbol as u8);
+                    (code, length) = (0, 0);
+                }
+                None if length >= 30 => return Err("A header has a bad Huffman code".to_string()),
+                None => {}
+            }
+        }
+    }
+    // The last byte is padded out with the start of the end of string code, all ones
+    if length > 7 || code != (1 << length) - 1 {
+        return Err("A header has bad Huffman padding".to_string());
+    }
+    Ok(decoded)
+}
+
+// An integer with a `bits` bit prefix, after `flags` in the first byte.
+fn encode_integer(block: &mut Vec<u8>, bits: u32, flags: u8, value: usize) {
+    let mask = (1 << bits) - 1;
+    if value < mask {
+        block.push(flags | value as u8);
+        return;
+    }
+    block.push(flags | mask as u8);
+    let mut rest = value - mask;
+    while rest >= 0x80 {
+        block.push((rest & 0x7F) as u8 | 0x80);
+        rest >>= 7;
+    }
+    block.push(rest as u8);
+}
+
+// Headers as literals that aren't indexed, with their names written out, so
+// nothing depends on the client's table.
+fn encode(headers: &[(String, String)]) -> Vec<u8> {
+    let mut block = vec![];
+    for (name, value) in headers {
+        block.push(0);
+        for text in [name, value] {
+            encode_integer(&mut block, 7, 0, text.len());
+            block.extend_from_slice(text.as_bytes());
+        }
+    }
+    block
+}
+
+struct Frame {
+    kind: u8,
+    flags: u8,
+    stream: u32,
+    payload: Vec<u8>,
+}
+
+// The payload of a DATA or HEADERS frame without its padding.
+fn unpadded(frame: &Frame) -> Result<&[u8], String> {
+    if frame.flags & PADDED == 0 {
+        return Ok(&frame.payload);
+    }
+    let padding = *frame.payload.first().ok_or("A padded frame is empty")? as usize;
+    frame.payload.get(1..frame.payload.len().saturating_sub(padding)).ok_or_else(|| "A frame has more padding than payload".to_string())
+}
+
+// A request whose headers have arrived, waiting for the rest of its body.
+struct Incoming {
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+struct Connection<'a> {
+    stream: &'a TcpStream,
+    decoder: Decoder,
+    incoming: HashMap<u32, Incoming>,
+    // A header block continued in CONTINUATION frames: its stream, what has come
+    // of it, and whether the stream ends with it
+    continuing: Option<(u32, Vec<u8>, bool)>,
+    ready: VecDeque<(u32, Request)>,
+    // The latest stream the client opened, as streams only open in order
+    last_stream: u32,
+    // How much the client will take, in all and on each open stream
+    window: i64,
+    windows: HashMap<u32, i64>,
+    initial_window: i64,
+    max_frame: usize,
+}
+
+impl Connection<'_> {
+    fn write_frame(&mut self, kind: u8, flags: u8, stream: u32, payload: &[u8]) -> Result<(), String> {
+        let mut frame = Vec::with_capacity(9 + payload.len());
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+        frame.extend_from_slice(&[kind, flags]);
+        frame.extend_from_slice(&stream.to_be_bytes());
+        frame.extend_from_slice(payload);
+        self.stream.write_all(&frame).map_err(|err| err.to_string())
+    }
+
+    // The next frame, or None if the client has gone.
+    fn read_frame(&mut self) -> Result<Option<Frame>, String> {
+        let mut header = [0; 9];
+        if let Err(err) = self.stream.read_exact(&mut header) {
+            return match err.kind() {
+                std::io::ErrorKind::UnexpectedEof => Ok(None),
+                _ => Err(err.to_string()),
+            };
+        }
+        let length = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        if length > FRAME {
+            return Err(self.fail(FRAME_SIZE_ERROR, "A frame is larger than allowed"));
+        }
+        let mut payload = vec![0; length];
+        self.stream.read_exact(&mut payload).map_err(|err| err.to_string())?;
+        Ok(Some(Frame {
+            kind: header[3],
+            flags: header[4],
+            stream: u32::from_be_bytes([header[5], header[6], header[7], header[8]]) & 0x7FFF_FFFF,
+            payload,
+        }))
+    }
+
+    // Tells the client the connection is over, for the reason given.
+    fn fail(&mut self, code: u32, reason: &str) -> String {
+        let mut payload = vec![0; 4];
+        payload.extend_from_slice(&code.to_be_bytes());
+        let _ = self.write_frame(GOAWAY, 0, 0, &payload);
+        reason.to_string()
+    }
+
+    // Acts on a frame, returning false if the client is closing the connection.
+    fn receive(&mut self, frame: &Frame) -> Result<bool, String> {
+        if self.continuing.is_some() && frame.kind != CONTINUATION {
+            return Err(self.fail(PROTOCOL_ERROR, "A header block was interrupted"));
+        }
+        match frame.kind {
+            HEADERS => {
+                let mut block = unpadded(frame)?;
+                if frame.flags & PRIORITY != 0 {
+                    block = block.get(5..).ok_or("A HEADERS frame is too short for its priority")?;
+                }
+                let (block, end_stream) = (block.to_vec(), frame.flags & END_STREAM != 0);
+                if block.len() > MAX_HEADERS {
+                    return Err(self.fail(ENHANCE_YOUR_CALM, "A header block is larger than allowed"));
+                }
+                if frame.flags & END_HEADERS != 0 {
+                    self.headers(frame.stream, &block, end_stream)?;
+                } else {
+                    self.continuing = Some((frame.stream, block, end_stream));
+                }
+            }
+            CONTINUATION => match self.continuing.take() {
+                Some((stream, mut block, end_stream)) if stream == frame.stream => {
+                    block.extend_from_slice(&frame.payload);
+                    if block.len() > MAX_HEADERS {
+                        return Err(self.fail(ENHANCE_YOUR_CALM, "A header block is larger than allowed"));
+                    }
+                    if frame.flags & END_HEADERS != 0 {
+                        self.headers(stream, &block, end_stream)?;
+                    } else {
+                        self.continuing = Some((stream, block, end_stream));
+                    }
+                }
+                _ => return Err(self.fail(PROTOCOL_ERROR, "A CONTINUATION frame continues nothing")),
+            },
+            DATA => {
+                let data = unpadded(frame)?.to_vec();
+                // Let the client send as much again, on the connection and the stream
+                if !frame.payload.is_empty() {
+                    let increment = (frame.payload.len() as u32).to_be_bytes();
+                    self.write_frame(WINDOW_UPDATE, 0, 0, &increment)?;
+                    if frame.flags & END_STREAM == 0 {
+                        self.write_frame(WINDOW_UPDATE, 0, frame.stream, &increment)?;
+                    }
+                }
+                if let Some(incoming) = self.incoming.get_mut(&frame.stream) {
+                    incoming.body.extend_from_slice(&data);
+                    if incoming.body.len() > MAX_BODY {
+                        return Err(self.fail(PROTOCOL_ERROR, "A request is too long"));
+                    }
+                }
+                if frame.flags & END_STREAM != 0 {
+                    self.finish(frame.stream);
+                }
+            }
+            SETTINGS if frame.flags & ACK == 0 => {
+                for setting in frame.payload.chunks_exact(6) {
+                    let value = u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]]);
+                    match u16::from_be_bytes([setting[0], setting[1]]) {
+                        INITIAL_WINDOW_SIZE if i64::from(value) > MAX_WINDOW => {
+                            return Err(self.fail(FLOW_CONTROL_ERROR, "A window is larger than allowed"));
+                        }
+                        INITIAL_WINDOW_SIZE => {
+                            let change = i64::from(value) - self.initial_window;
+                            self.initial_window = i64::from(value);
+                            self.windows.values_mut().for_each(|window| *window += change);
+                        }
+                        // Were it 0, nothing could ever be sent
+                        MAX_FRAME_SIZE if !(FRAME..=MAX_FRAME).contains(&(value as usize)) => {
+                            return Err(self.fail(PROTOCOL_ERROR, "A frame size is out of range"));
+                        }
+                        MAX_FRAME_SIZE => self.max_frame = value as usize,
+                        _ => {}
+                    }
+                }
+                self.write_frame(SETTINGS, ACK, 0, &[])?;
+            }
+            PING if frame.flags & ACK == 0 => self.write_frame(PING, ACK, 0, &frame.payload)?,
+            WINDOW_UPDATE => {
+                let increment = match frame.payload[..] {
+                    [a, b, c, d] => i64::from(u32::from_be_bytes([a, b, c, d]) & 0x7FFF_FFFF),
+                    _ => return Err(self.fail(FRAME_SIZE_ERROR, "A WINDOW_UPDATE frame isn't 4 bytes")),
+                };
+                if increment == 0 {
+                    return Err(self.fail(PROTOCOL_ERROR, "A WINDOW_UPDATE adds nothing"));
+                }
+                let window = match frame.stream {
+                    0 => &mut self.window,
+                    stream => match self.windows.get_mut(&stream) {
+                        Some(window) => window,
+                        None => return Ok(true),
+                    },
+                };
+                *window += increment;
+                if *window > MAX_WINDOW {
+                    return Err(self.fail(FLOW_CONTROL_ERROR, "A window is larger than allowed"));
+                }
+            }
+            RST_STREAM => {
+                self.incoming.remove(&frame.stream);
+                self.windows.remove(&frame.stream);
+                self.ready.retain(|&(stream, _)| stream != frame.stream);
+            }
+            GOAWAY => return Ok(false),
+            PUSH_PROMISE => return Err(self.fail(PROTOCOL_ERROR, "Clients can't push")),
+            // Priorities, acknowledgements, and frame types this side doesn't know
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn headers(&mut self, stream: u32, block: &[u8], end_stream: bool) -> Result<(), String> {
+        // Every block is decoded, even those ignored, to keep the table in step
+        let headers = self.decoder.decode(block).map_err(|err| self.fail(PROTOCOL_ERROR, &err))?;
+        // Trailers on a request add nothing the server needs
+        if let Entry::Vacant(entry) = self.incoming.entry(stream) {
+            if stream.is_multiple_of(2) || stream <= self.last_stream {
+                return Err(self.fail(PROTOCOL_ERROR, "A stream was opened out of order"));
+            }
+            self.last_stream = stream;
+            // Every stream open is in windows until it is answered
+            if self.windows.len() >= MAX_STREAMS {
+                return self.write_frame(RST_STREAM, 0, stream, &REFUSED_STREAM.to_be_bytes());
+            }
+            entry.insert(Incoming { headers, body: vec![] });
+            self.windows.insert(stream, self.initial_window);
+        }
+        if end_stream {
+            self.finish(stream);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, stream: u32) {
+        if let Some(Incoming { headers, body }) = self.incoming.remove(&stream) {
+            self.ready.push_back((stream, Request { headers, body }));
+        }
+    }
+
+    fn respond(&mut self, stream: u32, response: Response) -> Result<(), String> {
+        let mut headers = vec![(":status".to_string(), response.status.to_string())];
+        headers.extend(response.headers);
+        let ended = response.body.is_empty() && response.trailers.is_empty();
+        self.write_frame(HEADERS, END_HEADERS | if ended { END_STREAM } else { 0 }, stream, &encode(&headers))?;
+        let mut rest = &response.body[..];
+        while !rest.is_empty() {
+            let window = match self.windows.get(&stream) {
+                Some(&window) => window,
+                // The client reset the stream while waiting
+                None => return Ok(()),
+            };
+            let open = self.window.min(window).min(self.max_frame as i64);
+            if open <= 0 {
+                // Hear from the client until it will take more
+                match self.read_frame()? {
+                    Some(frame) if self.receive(&frame)? => continue,
+                    _ => return Err("The connection closed during a response".to_string()),
+                }
+            }
+            let (chunk, after) = rest.split_at(rest.len().min(open as usize));
+            let last = after.is_empty() && response.trailers.is_empty();
+            self.write_frame(DATA, if last { END_STREAM } else { 0 }, stream, chunk)?;
+            self.window -= chunk.len() as i64;
+            self.windows.insert(stream, window - chunk.len() as i64);
+            rest = after;
+        }
+        if !response.trailers.is_empty() {
+            self.write_frame(HEADERS, END_HEADERS | END_STREAM, stream, &encode(&response.trailers))?;
+        }
+        self.windows.remove(&stream);
+        Ok(())
+    }
+}
+
+/// Serves one connection until the client closes it, answering each request with
+/// `handle`.
+pub fn serve_connection(stream: &TcpStream, mut handle: impl FnMut(Request) -> Response) -> Result<(), String> {
+    let mut connection = Connection {
+        stream,
+        decoder: Decoder { table: VecDeque::new(), size: 0, max_size: TABLE },
+        incoming: HashMap::new(),
+        continuing: None,
+        ready: VecDeque::new(),
+        last_stream: 0,
+        window: WINDOW,
+        windows: HashMap::new(),
+        initial_window: WINDOW,
+        max_frame: FRAME,
+    };
+    // Frames are small and each written as soon as it is ready, so waiting to
+    // gather them only holds up replies
+    let _ = stream.set_nodelay(true);
+    let mut preface = [0; PREFACE.len()];
+    connection.stream.read_exact(&mut preface).map_err(|err| err.to_string())?;
+    if preface != PREFACE {
+        return Err("Not an HTTP/2 connection".to_string());
+    }
+    let mut settings = vec![];
+    for (setting, value) in [(MAX_CONCURRENT_STREAMS, MAX_STREAMS), (MAX_HEADER_LIST_SIZE, MAX_HEADERS)] {
+        settings.extend_from_slice(&setting.to_be_bytes());
+        settings.extend_from_slice(&(value as u32).to_be_bytes());
+    }
+    connection.write_frame(SETTINGS, 0, 0, &settings)?;
+    loop {
+        while let Some((stream, request)) = connection.ready.pop_front() {
+            let response = handle(request);
+            connection.respond(stream, response)?;
+        }
+        match connection.read_frame()? {
+            Some(frame) if connection.receive(&frame)? => {}
+            _ => return Ok(()),
+        }
+    }
+}
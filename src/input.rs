@@ -0,0 +1,248 @@
+//! Where the player's typed lines come from. Games read standard input, but take
+//! their input as a trait object so anything else can feed them lines instead.
+
+use std::collections::VecDeque;
+use std::io::{stdin, stdout, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+pub trait Input {
+    /// The next line, trimmed, or None once the input has run out, as when the
+    /// player presses Ctrl-D or a piped script ends.
+    fn read_line(&mut self) -> Option<String>;
+}
+
+/// Standard input. Every Terminal reads through the same buffer, so the frontend
+/// and a human opponent can each have their own. At a terminal, lines are edited
+/// in place, with the arrow keys moving along the line and through the lines
+/// typed before, and Ctrl-C clearing the line or, on an empty one, quitting.
+pub struct Terminal;
+
+// The lines typed so far, shared as stdin is.
+static HISTORY: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+impl Input for Terminal {
+    fn read_line(&mut self) -> Option<String> {
+        if stdin().is_terminal() {
+            if let Some(raw) = RawMode::enter() {
+                let line = edit_line();
+                drop(raw);
+                return line;
+            }
+        }
+        let mut line = String::new();
+        match stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim().to_string()),
+        }
+    }
+}
+
+// The terminal with line buffering, echo and signal keys turned off, so each key
+// comes straight here, put back as it was when dropped. stty does the work, as
+// the terminal's settings are only reachable through ioctls otherwise.
+struct RawMode {
+    saved: String,
+}
+
+fn stty(arguments: &[&str]) -> Option<String> {
+    let output = Command::new("stty").args(arguments).stdin(Stdio::inherit()).stderr(Stdio::null()).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+impl RawMode {
+    fn enter() -> Option<RawMode> {
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "-isig", "min", "1", "time", "0"])?;
+        Some(RawMode { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&[&self.saved]);
+    }
+}
+
+fn read_byte() -> Option<u8> {
+    let mut byte = [0];
+    match stdin().lock().read(&mut byte) {
+        Ok(1) => Some(byte[0]),
+        _ => None,
+    }
+}
+
+// The keys the editor acts on, once escape sequences and UTF-8 are decoded.
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    KillBefore,
+    KillAfter,
+    Interrupt,
+    EndOfInput,
+    Ignored,
+}
+
+fn read_key() -> Option<Key> {
+    let key = match read_byte()? {
+        b'\r' | b'\n' => Key::Enter,
+        0x7F | 0x08 => Key::Backspace,
+        0x01 => Key::Home,
+        0x02 => Key::Left,
+        0x03 => Key::Interrupt,
+        0x04 => Key::EndOfInput,
+        0x05 => Key::End,
+        0x06 => Key::Right,
+        0x0B => Key::KillAfter,
+        0x0E => Key::Down,
+        0x10 => Key::Up,
+        0x15 => Key::KillBefore,
+        0x1B => read_escape()?,
+        byte @ 0x20..=0x7E => Key::Char(byte as char),
+        byte @ 0xC0..=0xF7 => {
+            let length = byte.leading_ones() as usize;
+            let mut bytes = vec![byte];
+            for _ in 1..length {
+                bytes.push(read_byte()?);
+            }
+            match std::str::from_utf8(&bytes).ok().and_then(|text| text.chars().next()) {
+                Some(letter) => Key::Char(letter),
+                None => Key::Ignored,
+            }
+        }
+        _ => Key::Ignored,
+    };
+    Some(key)
+}
+
+// The rest of a sequence after ESC, as the arrow, Home, End and Delete keys send:
+// ESC [ then any digits then a letter or ~, or ESC O then a letter.
+fn read_escape() -> Option<Key> {
+    let mut parameter = 0;
+    let last = match read_byte()? {
+        b'[' => loop {
+            match read_byte()? {
+                digit @ b'0'..=b'9' => parameter = parameter * 10 + u32::from(digit - b'0'),
+                last @ 0x40..=0x7E => break last,
+                _ => {}
+            }
+        },
+        b'O' => read_byte()?,
+        _ => return Some(Key::Ignored),
+    };
+    Some(match (last, parameter) {
+        (b'A', _) => Key::Up,
+        (b'B', _) => Key::Down,
+        (b'C', _) => Key::Right,
+        (b'D', _) => Key::Left,
+        (b'H', _) | (b'~', 1 | 7) => Key::Home,
+        (b'F', _) | (b'~', 4 | 8) => Key::End,
+        (b'~', 3) => Key::Delete,
+        _ => Key::Ignored,
+    })
+}
+
+// Writes `line` over whatever the current row holds, with the cursor at `cursor`.
+fn redraw(line: &[char], cursor: usize) {
+    let mut text = format!("\r\x1b[K{}", line.iter().collect::<String>());
+    if cursor < line.len() {
+        text += &format!("\x1b[{}D", line.len() - cursor);
+    }
+    print!("{}", text);
+    let _ = stdout().flush();
+}
+
+// Reads a line key by key, in raw mode, redrawing it after every change.
+fn edit_line() -> Option<String> {
+    let history = HISTORY.lock().map(|history| history.clone()).unwrap_or_default();
+    // Where in the history the line shown is from, with the end being the line
+    // being typed, which is kept in draft while older lines are shown
+    let mut place = history.len();
+    let mut draft = vec![];
+    let (mut line, mut cursor): (Vec<char>, usize) = (vec![], 0);
+    loop {
+        let key = match read_key() {
+            Some(key) => key,
+            None if line.is_empty() => return None,
+            None => Key::Enter,
+        };
+        match key {
+            Key::Char(letter) => {
+                line.insert(cursor, letter);
+                cursor += 1;
+            }
+            Key::Enter => {
+                println!();
+                let line: String = line.into_iter().collect();
+                let line = line.trim().to_string();
+                if let Ok(mut history) = HISTORY.lock() {
+                    if !line.is_empty() && history.last() != Some(&line) {
+                        history.push(line.clone());
+                    }
+                }
+                return Some(line);
+            }
+            Key::Backspace if cursor > 0 => {
+                cursor -= 1;
+                line.remove(cursor);
+            }
+            Key::Delete if cursor < line.len() => {
+                line.remove(cursor);
+            }
+            Key::Left if cursor > 0 => cursor -= 1,
+            Key::Right if cursor < line.len() => cursor += 1,
+            Key::Home => cursor = 0,
+            Key::End => cursor = line.len(),
+            Key::Up if place > 0 => {
+                if place == history.len() {
+                    draft = line;
+                }
+                place -= 1;
+                line = history[place].chars().collect();
+                cursor = line.len();
+            }
+            Key::Down if place < history.len() => {
+                place += 1;
+                line = match history.get(place) {
+                    Some(older) => older.chars().collect(),
+                    None => std::mem::take(&mut draft),
+                };
+                cursor = line.len();
+            }
+            Key::KillBefore => {
+                line.drain(..cursor);
+                cursor = 0;
+            }
+            Key::KillAfter => line.truncate(cursor),
+            // Ctrl-C gives up on the line, and on an empty one leaves, as Ctrl-D does
+            Key::Interrupt | Key::EndOfInput if line.is_empty() => {
+                println!();
+                return None;
+            }
+            Key::Interrupt => {
+                line.clear();
+                cursor = 0;
+            }
+            Key::EndOfInput if cursor < line.len() => {
+                line.remove(cursor);
+            }
+            _ => continue,
+        }
+        redraw(&line, cursor);
+    }
+}
+
+// Lines given up front, one at a time.
+impl Input for VecDeque<String> {
+    fn read_line(&mut self) -> Option<String> {
+        self.pop_front().map(|line| line.trim().to_string())
+    }
+}
@@ -0,0 +1,690 @@
+//! A small JSON reader and writer, so games can be saved, sent over the network and
+//! inspected by other tools without pulling in any dependencies.
+//!
+//! The encodings follow serde's conventions: structs are objects keyed by field
+//! name, unit variants are strings, and other variants are single key objects.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::game::{CandidateSet, Count, Dir, GameResult, GameState, Number, Prior, Question, Range, Response, Variant};
+use crate::solver::{Move, Strategy};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i128),
+    /// Integers too large for `Int`, which only counts of the numbers in enormous
+    /// games need.
+    UInt(u128),
+    Float(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            position: 0,
+            depth: 0,
+        };
+        let json = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position < parser.chars.len() {
+            return Err(format!("Unexpected trailing text at character {}", parser.position));
+        }
+        Ok(json)
+    }
+    /// Shorthand for building objects.
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref fields) => fields.iter().find(|field| field.0 == key).map(|field| &field.1),
+            _ => None,
+        }
+    }
+    /// Like `get`, but missing fields are an error.
+    pub fn field(&self, key: &str) -> Result<&Json, String> {
+        self.get(key).ok_or_else(|| format!("Missing field {}", key))
+    }
+    /// A count of numbers, as an `Int` if it fits.
+    pub fn count(count: Count) -> Json {
+        i128::try_from(count).map_or(Json::UInt(count), Json::Int)
+    }
+    pub fn as_number(&self) -> Result<Number, String> {
+        match *self {
+            Json::Int(number) => Ok(number),
+            _ => Err(format!("Expected an integer, found {}", self)),
+        }
+    }
+    pub fn as_u64(&self) -> Result<u64, String> {
+        match *self {
+            Json::Int(number) if number >= 0 && number <= i128::from(u64::MAX) => Ok(number as u64),
+            _ => Err(format!("Expected a non-negative integer, found {}", self)),
+        }
+    }
+    /// A count of numbers, as [`Json::count`] writes it.
+    pub fn as_count(&self) -> Result<Count, String> {
+        match *self {
+            Json::Int(number) if number >= 0 => Ok(number as Count),
+            Json::UInt(number) => Ok(number),
+            _ => Err(format!("Expected a non-negative integer, found {}", self)),
+        }
+    }
+    pub fn as_f64(&self) -> Result<f64, String> {
+        match *self {
+            Json::Int(number) => Ok(number as f64),
+            Json::UInt(number) => Ok(number as f64),
+            Json::Float(number) => Ok(number),
+            _ => Err(format!("Expected a number, found {}", self)),
+        }
+    }
+    pub fn as_str(&self) -> Result<&str, String> {
+        match *self {
+            Json::Str(ref text) => Ok(text),
+            _ => Err(format!("Expected a string, found {}", self)),
+        }
+    }
+    pub fn as_array(&self) -> Result<&[Json], String> {
+        match *self {
+            Json::Array(ref items) => Ok(items),
+            _ => Err(format!("Expected an array, found {}", self)),
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Compact JSON, with no whitespace.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Int(number) => write!(f, "{}", number),
+            Json::UInt(number) => write!(f, "{}", number),
+            // JSON has no infinities or NaN
+            Json::Float(number) if !number.is_finite() => write!(f, "null"),
+            Json::Float(number) => write!(f, "{:?}", number),
+            Json::Str(ref text) => write_string(f, text),
+            Json::Array(ref items) => {
+                write!(f, "[")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(ref fields) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+// How deeply arrays and objects may nest. Each level is parsed by a recursive
+// call, so without a limit a long run of [ from the network would overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    // How many arrays and objects the current value is inside
+    depth: usize,
+}
+
+impl Parser {
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.position).is_some_and(|c| c.is_whitespace()) {
+            self.position += 1;
+        }
+    }
+    fn next(&mut self) -> Result<char, String> {
+        let c = *self.chars.get(self.position).ok_or("Unexpected end of JSON")?;
+        self.position += 1;
+        Ok(c)
+    }
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        let position = self.position;
+        if self.next()? == expected {
+            Ok(())
+        } else {
+            Err(format!("Expected {} at character {}", expected, position))
+        }
+    }
+    fn keyword(&mut self, word: &str, json: Json) -> Result<Json, String> {
+        for expected in word.chars() {
+            if self.next()? != expected {
+                return Err(format!("Expected {} at character {}", word, self.position - 1));
+            }
+        }
+        Ok(json)
+    }
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.get(self.position) {
+            None => Err("Unexpected end of JSON".to_string()),
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::Str),
+            Some('[') => self.nested(Parser::array),
+            Some('{') => self.nested(Parser::object),
+            Some(_) => self.number(),
+        }
+    }
+    fn nested(&mut self, parse: fn(&mut Parser) -> Result<Json, String>) -> Result<Json, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!("JSON nested too deeply at character {}", self.position));
+        }
+        self.depth += 1;
+        let json = parse(self);
+        self.depth -= 1;
+        json
+    }
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Ok(text),
+                '\\' => match self.next()? {
+                    '"' => text.push('"'),
+                    '\\' => text.push('\\'),
+                    '/' => text.push('/'),
+                    'b' => text.push('\u{8}'),
+                    'f' => text.push('\u{c}'),
+                    'n' => text.push('\n'),
+                    'r' => text.push('\r'),
+                    't' => text.push('\t'),
+                    'u' => {
+                        let mut code = self.hex()?;
+                        // Characters outside the basic plane come as a surrogate pair
+                        if (0xD800..0xDC00).contains(&code) {
+                            if self.next()? != '\\' || self.next()? != 'u' {
+                                return Err("Unpaired surrogate in unicode escape".to_string());
+                            }
+                            let low = self.hex()?.checked_sub(0xDC00).ok_or("Invalid unicode escape")?;
+                            code = 0x10000 + ((code - 0xD800) << 10) + low;
+                        }
+                        text.push(std::char::from_u32(code).ok_or("Invalid unicode escape")?);
+                    }
+                    c => return Err(format!("Invalid escape \\{}", c)),
+                },
+                c => text.push(c),
+            }
+        }
+    }
+    fn hex(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.next()?.to_digit(16).ok_or("Invalid unicode escape")?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.position;
+        while self.chars.get(self.position).is_some_and(|&c| c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' || c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        if let Ok(number) = text.parse::<i128>() {
+            Ok(Json::Int(number))
+        } else if let Ok(number) = text.parse::<u128>() {
+            Ok(Json::UInt(number))
+        } else {
+            text.parse::<f64>()
+                .map(Json::Float)
+                .map_err(|_| format!("Unexpected {:?} at character {}", text, start))
+        }
+    }
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.chars.get(self.position) == Some(&']') {
+            self.position += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                ']' => return Ok(Json::Array(items)),
+                c => return Err(format!("Expected , or ] but found {}", c)),
+            }
+        }
+    }
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = vec![];
+        self.skip_whitespace();
+        if self.chars.get(self.position) == Some(&'}') {
+            self.position += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                '}' => return Ok(Json::Object(fields)),
+                c => return Err(format!("Expected , or }} but found {}", c)),
+            }
+        }
+    }
+}
+
+pub trait ToJson {
+    fn to_json(&self) -> Json;
+}
+
+pub trait FromJson: Sized {
+    fn from_json(json: &Json) -> Result<Self, String>;
+}
+
+impl ToJson for Range {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("lower", Json::Int(self.lower)),
+            ("higher", Json::Int(self.higher)),
+        ])
+    }
+}
+
+impl FromJson for Range {
+    fn from_json(json: &Json) -> Result<Range, String> {
+        Ok(Range::new(json.field("lower")?.as_number()?, json.field("higher")?.as_number()?))
+    }
+}
+
+impl ToJson for Dir {
+    fn to_json(&self) -> Json {
+        Json::Str(format!("{:?}", self))
+    }
+}
+
+impl FromJson for Dir {
+    fn from_json(json: &Json) -> Result<Dir, String> {
+        match json.as_str()? {
+            "High" => Ok(Dir::High),
+            "Low" => Ok(Dir::Low),
+            other => Err(format!("Expected High or Low, found {}", other)),
+        }
+    }
+}
+
+impl ToJson for Response {
+    fn to_json(&self) -> Json {
+        Json::Str(format!("{:?}", self))
+    }
+}
+
+/// Also reads High and Low, the answers to "is it less than the guess?".
+impl FromJson for Response {
+    fn from_json(json: &Json) -> Result<Response, String> {
+        match json.as_str()? {
+            "Yes" | "Low" => Ok(Response::Yes),
+            "No" | "High" => Ok(Response::No),
+            "Equal" => Ok(Response::Equal),
+            "Pass" => Ok(Response::Pass),
+            other => Err(format!("Expected Yes, No, Equal or Pass, found {}", other)),
+        }
+    }
+}
+
+/// "Is it less than 5?" is just the number 5, "is it exactly 5?" is
+/// `{"equal": 5}` and comparing with 5 is `{"compare": 5}`.
+impl ToJson for Question {
+    fn to_json(&self) -> Json {
+        match *self {
+            Question::Less(guess) => Json::Int(guess),
+            Question::Equal(guess) => Json::object(vec![("equal", Json::Int(guess))]),
+            Question::Within(interval) => Json::object(vec![("within", interval.to_json())]),
+            Question::Bit(bit) => Json::object(vec![("bit", Json::Int(bit as i128))]),
+            Question::Compare(guess) => Json::object(vec![("compare", Json::Int(guess))]),
+            Question::Subset(ref ranges) => {
+                Json::object(vec![("subset", Json::Array(ranges.iter().map(Range::to_json).collect()))])
+            }
+        }
+    }
+}
+
+impl FromJson for Question {
+    fn from_json(json: &Json) -> Result<Question, String> {
+        if let Some(guess) = json.get("equal") {
+            return Ok(Question::Equal(guess.as_number()?));
+        }
+        if let Some(guess) = json.get("compare") {
+            return Ok(Question::Compare(guess.as_number()?));
+        }
+        if let Some(bit) = json.get("bit") {
+            let bit = bit.as_number()?;
+            return u32::try_from(bit).map(Question::Bit).map_err(|_| format!("Bad bit {}", bit));
+        }
+        if let Some(ranges) = json.get("subset") {
+            let ranges = ranges.as_array()?.iter().map(Range::from_json).collect::<Result<Vec<Range>, String>>()?;
+            return Ok(Question::subset(ranges));
+        }
+        match json.get("within") {
+            Some(interval) => Ok(Question::Within(Range::from_json(interval)?)),
+            None => Ok(Question::Less(json.as_number()?)),
+        }
+    }
+}
+
+/// The answer to `question`. Answers to "is it less than" questions are written
+/// High or Low, as they always have been, comparisons High, Low or Equal, the rest
+/// Yes or No, and passes Pass.
+pub fn response_to_json(question: &Question, response: Response) -> Json {
+    match (question, response) {
+        (_, Response::Equal | Response::Pass) => response.to_json(),
+        (&Question::Less(_) | &Question::Compare(_), _) => Dir::from(response).to_json(),
+        _ => response.to_json(),
+    }
+}
+
+/// A question with its answer, as a `[question, response]` pair.
+pub fn answered_to_json(question: &Question, response: Response) -> Json {
+    Json::Array(vec![question.to_json(), response_to_json(question, response)])
+}
+
+/// The surviving hypotheses with the questions they lie on, or for noisy games,
+/// each piece with the probability of each number in it.
+pub fn possibilities_to_json(game: &GameState) -> Json {
+    let pieces = match game.lie_probability() {
+        Some(lie_probability) => game.posterior(lie_probability).into_iter()
+            .filter(|&(range, _)| !range.is_empty())
+            .map(|(range, probability)| Json::object(vec![
+                ("range", range.to_json()),
+                ("probability", Json::Float(probability)),
+            ]))
+            .collect(),
+        None => game.hypotheses()
+            .filter(|&(range, _)| !range.is_empty())
+            .map(|(range, lies)| Json::object(vec![
+                ("range", range.to_json()),
+                ("lies", Json::Array(lies.iter().map(|&lie| Json::Int(lie as i128)).collect())),
+            ]))
+            .collect(),
+    };
+    Json::Array(pieces)
+}
+
+impl ToJson for GameResult {
+    fn to_json(&self) -> Json {
+        match *self {
+            GameResult::Ongoing => Json::Str("Ongoing".to_string()),
+            GameResult::Finished(answer) => Json::object(vec![("Finished", Json::Int(answer))]),
+            GameResult::Impossible => Json::Str("Impossible".to_string()),
+            GameResult::Lost => Json::Str("Lost".to_string()),
+        }
+    }
+}
+
+impl FromJson for GameResult {
+    fn from_json(json: &Json) -> Result<GameResult, String> {
+        if let Some(answer) = json.get("Finished") {
+            return Ok(GameResult::Finished(answer.as_number()?));
+        }
+        match json.as_str()? {
+            "Ongoing" => Ok(GameResult::Ongoing),
+            "Impossible" => Ok(GameResult::Impossible),
+            "Lost" => Ok(GameResult::Lost),
+            other => Err(format!("Unknown game result {}", other)),
+        }
+    }
+}
+
+impl ToJson for GameState {
+    fn to_json(&self) -> Json {
+        let history = self.history.iter()
+            .map(|(question, response)| answered_to_json(question, *response))
+            .collect();
+        Json::object(vec![
+            ("history", Json::Array(history)),
+            ("lower_limit", Json::Int(self.lower_limit)),
+            ("upper_limit", Json::Int(self.upper_limit)),
+            ("max_lies", Json::Int(self.max_lies as i128)),
+            ("lie_probability", self.lie_probability.map_or(Json::Null, Json::Float)),
+            ("confidence", self.lie_probability.map_or(Json::Null, |_| Json::Float(self.confidence))),
+            ("max_questions", self.max_questions.map_or(Json::Null, |max_questions| Json::Int(max_questions as i128))),
+            ("candidates", self.candidate_set().map_or(Json::Null, |candidate_set| {
+                Json::Array(candidate_set.members().iter().map(|&member| Json::Int(member)).collect())
+            })),
+            ("prior", self.prior().map_or(Json::Null, |prior| Json::Str(prior.spec()))),
+            ("variant", Json::Str(self.variant.to_string())),
+            ("delay", Json::Int(self.delay as i128)),
+            ("pending", Json::Array(self.pending.iter()
+                .map(|(asked, question)| Json::Array(vec![Json::Int(*asked as i128), question.to_json()]))
+                .collect())),
+            ("waits", Json::Int(self.waits as i128)),
+            ("moving_target", self.moving.map_or(Json::Null, Json::count)),
+            ("lie_window", self.lie_window.map_or(Json::Null, |window| Json::Int(window as i128))),
+            ("final_guess", Json::Bool(self.final_guess)),
+            ("max_passes", Json::Int(self.max_passes as i128)),
+        ])
+    }
+}
+
+/// Replays the stored history through `ask`, so a loaded game obeys the
+/// same rules as one played from the start.
+impl FromJson for GameState {
+    fn from_json(json: &Json) -> Result<GameState, String> {
+        let upper_limit = json.field("upper_limit")?.as_number()?;
+        let max_lies = json.field("max_lies")?.as_u64()? as usize;
+        let mut game = match json.get("lie_probability") {
+            None | Some(&Json::Null) => GameState::new(upper_limit, max_lies),
+            Some(lie_probability) => {
+                let lie_probability = lie_probability.as_f64()?;
+                if !(0.0..0.5).contains(&lie_probability) {
+                    return Err(format!("Lie probability {} is not in [0, 0.5)", lie_probability));
+                }
+                GameState::noisy(upper_limit, lie_probability)
+            }
+        };
+        match json.get("confidence") {
+            None | Some(&Json::Null) => {}
+            Some(confidence) => {
+                let confidence = confidence.as_f64()?;
+                if !(0.5..1.0).contains(&confidence) {
+                    return Err(format!("Confidence {} is not in [0.5, 1)", confidence));
+                }
+                game = game.with_confidence(confidence);
+            }
+        }
+        match json.get("lower_limit") {
+            None | Some(&Json::Null) => {}
+            Some(lower_limit) => game = game.with_lower_limit(lower_limit.as_number()?),
+        }
+        match json.get("candidates") {
+            None | Some(&Json::Null) => {}
+            Some(candidates) => {
+                let members = candidates.as_array()?.iter().map(Json::as_number).collect::<Result<Vec<Number>, String>>()?;
+                game = game.with_candidates(CandidateSet::new(members));
+            }
+        }
+        if game.lower_limit >= game.upper_limit {
+            return Err(format!("The range {}..{} has no numbers", game.lower_limit, game.upper_limit));
+        }
+        match json.get("max_questions") {
+            None | Some(&Json::Null) => {}
+            Some(max_questions) => game = game.with_max_questions(max_questions.as_u64()? as usize),
+        }
+        match json.get("prior") {
+            None | Some(&Json::Null) => {}
+            Some(prior) => game = game.with_prior(Prior::parse(prior.as_str()?)?),
+        }
+        match json.get("variant") {
+            None | Some(&Json::Null) => {}
+            Some(variant) => game = game.with_variant(Variant::parse(variant.as_str()?)?),
+        }
+        match json.get("max_passes") {
+            None | Some(&Json::Null) => {}
+            Some(passes) => game = game.with_passes(passes.as_u64()? as usize),
+        }
+        for entry in json.field("history")?.as_array()? {
+            match *entry.as_array()? {
+                [ref question, ref response] => game.ask(Question::from_json(question)?, Response::from_json(response)?)?,
+                _ => return Err(format!("Expected a [question, response] pair, found {}", entry)),
+            }
+        }
+        match json.get("lie_window") {
+            None | Some(&Json::Null) => {}
+            Some(window) => game = game.with_lie_window(window.as_u64()? as usize),
+        }
+        match json.get("moving_target") {
+            None | Some(&Json::Null) => {}
+            Some(width) => game = game.with_moving_target(width.as_u64()? as Count),
+        }
+        match json.get("final_guess") {
+            None | Some(&Json::Null) | Some(&Json::Bool(false)) => {}
+            Some(&Json::Bool(true)) => game = game.with_final_guess(),
+            Some(other) => return Err(format!("Expected true or false, found {}", other)),
+        }
+        match json.get("delay") {
+            None | Some(&Json::Null) => {}
+            Some(delay) => game = game.with_delay(delay.as_u64()? as usize),
+        }
+        match json.get("waits") {
+            None | Some(&Json::Null) => {}
+            Some(waits) => game.waits = waits.as_u64()? as usize,
+        }
+        if let Some(pending) = json.get("pending") {
+            for entry in pending.as_array()? {
+                match *entry.as_array()? {
+                    [ref asked, ref question] => {
+                        let question = Question::from_json(question)?;
+                        game.check_question(&question)?;
+                        game.pending.push_back((asked.as_u64()? as usize, question));
+                    }
+                    _ => return Err(format!("Expected an [asked, question] pair, found {}", entry)),
+                }
+            }
+        }
+        Ok(game)
+    }
+}
+
+impl ToJson for Strategy {
+    fn to_json(&self) -> Json {
+        let moves = self.moves.iter()
+            .map(|step| {
+                let state = step.state.iter()
+                    .map(|&(len, lies)| Json::Array(vec![Json::count(len), Json::Int(lies as i128)]))
+                    .collect();
+                Json::object(vec![
+                    ("state", Json::Array(state)),
+                    ("below", Json::count(step.below)),
+                    ("needed", Json::Int(step.needed as i128)),
+                ])
+            })
+            .collect();
+        Json::object(vec![
+            ("upper_limit", Json::Int(self.upper_limit)),
+            ("max_lies", Json::Int(self.max_lies as i128)),
+            ("moves", Json::Array(moves)),
+        ])
+    }
+}
+
+impl FromJson for Strategy {
+    fn from_json(json: &Json) -> Result<Strategy, String> {
+        let moves = json.field("moves")?.as_array()?.iter()
+            .map(|step| {
+                let state = step.field("state")?.as_array()?.iter()
+                    .map(|run| match *run.as_array()? {
+                        [ref len, ref lies] => Ok((len.as_count()?, lies.as_u64()? as usize)),
+                        _ => Err(format!("Expected a [length, lies] pair, found {}", run)),
+                    })
+                    .collect::<Result<_, String>>()?;
+                Ok(Move {
+                    state,
+                    below: step.field("below")?.as_count()?,
+                    needed: step.field("needed")?.as_u64()? as usize,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Strategy {
+            upper_limit: json.field("upper_limit")?.as_number()?,
+            max_lies: json.field("max_lies")?.as_u64()? as usize,
+            moves,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn printing_round_trips() {
+        let json = Json::object(vec![
+            ("null", Json::Null),
+            ("bools", Json::Array(vec![Json::Bool(true), Json::Bool(false)])),
+            ("ints", Json::Array(vec![Json::Int(0), Json::Int(-42), Json::Int(i128::MIN), Json::Int(i128::MAX)])),
+            ("huge", Json::UInt(u128::MAX)),
+            ("floats", Json::Array(vec![Json::Float(0.5), Json::Float(-1.25e-7), Json::Float(3e100)])),
+            ("text", Json::Str("quote \" backslash \\ newline \n tab \t bell \u{7} é".to_string())),
+            ("empty", Json::Object(vec![])),
+            ("nested", Json::object(vec![("array", Json::Array(vec![Json::Array(vec![])]))])),
+        ]);
+        assert_eq!(Json::parse(&json.to_string()), Ok(json));
+    }
+
+    #[test]
+    fn games_round_trip() {
+        let mut game = GameState::new(1000, 2).with_lower_limit(-10).with_max_questions(30);
+        game.ask(Question::Less(500), Response::No).unwrap();
+        game.ask("=700".parse().unwrap(), Response::No).unwrap();
+        game.ask("600..650,800".parse().unwrap(), Response::Yes).unwrap();
+        let json = Json::parse(&game.to_json().to_string()).unwrap();
+        let loaded = GameState::from_json(&json).unwrap();
+        assert_eq!(loaded.to_json(), game.to_json());
+        assert_eq!(loaded.history(), game.history());
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(Json::parse(&nested(MAX_DEPTH + 1)).unwrap_err().contains("nested too deeply"));
+        // Deep enough to overflow the stack, if the limit didn't stop it first
+        assert!(Json::parse(&"[{\"a\":".repeat(100_000)).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! An opponent that has learned how to answer by playing, rather than from any
+//! rule written for it. A [`QTable`] rates each answer to each question in each
+//! position by how many questions the game went on for after it, and learns by
+//! tabular Q-learning as a guesser and an opponent sharing the table play each
+//! other. The guesser asks whichever question the table says ends the game
+//! soonest, and the opponent gives whichever answer it says drags the game out
+//! longest, each now and then trying something at random to learn where it leads.
+//! Positions are kept in their [`GameState::canonical`] form and questions as how
+//! many live numbers are below the guess, so what is learned at one limit applies
+//! wherever the same position comes up. The [`Learned`] opponent answers from a
+//! table, and falls back on the adversary wherever the table has nothing to say.
+
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use crate::game::{Count, GameResult, GameState, Number, Question, Response, Variant};
+use crate::opponent::{Adversary, Opponent};
+use crate::rng::Rng;
+use crate::solver::{live_below, number_at};
+
+// A canonical position, and how many of its live numbers are below the guess
+type Key = (Vec<(Count, usize)>, Count);
+
+const FORMAT: &[u8; 8] = b"onelieQ1";
+// How often either side tries something at random while training
+const EXPLORATION: f64 = 0.2;
+// The least weight a new sample gets, so that early guesses, learned while the
+// rest of the table was still wrong, are forgotten in time
+const LEARNING_RATE: f64 = 0.1;
+
+// What the answers yes and no, in that order, have led to.
+#[derive(Debug, Clone, Copy, Default)]
+struct Entry {
+    questions: [f64; 2],
+    visits: [u32; 2],
+}
+
+/// What has been learned: for each position and question, how many questions each
+/// answer led to, counting the question itself.
+#[derive(Debug, Clone, Default)]
+pub struct QTable {
+    max_lies: usize,
+    entries: HashMap<Key, Entry>,
+}
+
+fn answer_index(response: Response) -> usize {
+    match response {
+        Response::Yes => 0,
+        _ => 1,
+    }
+}
+
+fn live(game: &GameState) -> Count {
+    game.canonical().iter().map(|&(count, _)| count).sum()
+}
+
+// Whether the game is one the table's positions describe: the usual rules, with
+// only the limits and lies changed.
+fn plain(game: &GameState) -> bool {
+    game.lie_probability().is_none()
+        && game.variant() == Variant::Standard
+        && game.lie_window().is_none()
+        && game.max_passes() == 0
+        && !game.final_guess()
+        && game.delay() == 0
+        && game.moving_target().is_none()
+}
+
+impl QTable {
+    pub fn new(max_lies: usize) -> QTable {
+        QTable { max_lies, entries: HashMap::new() }
+    }
+
+    pub fn max_lies(&self) -> usize {
+        self.max_lies
+    }
+
+    /// How many questions in how many positions have been answered.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // The games after each answer to asking about the `below`th live number that
+    // leaves some number possible, with how many questions the table expects each
+    // to take, counting this one. Answers never tried are guessed at as a halving
+    // search would take.
+    fn rate(&self, game: &GameState, below: Count) -> Vec<(Response, GameState, f64)> {
+        let question = Question::Less(number_at(game, below));
+        let entry = self.entries.get(&(game.canonical(), below));
+        [Response::Yes, Response::No].into_iter()
+            .filter_map(|response| {
+                let mut next = game.clone();
+                next.ask(question.clone(), response).ok()?;
+                if next.status() == GameResult::Impossible {
+                    return None;
+                }
+                let index = answer_index(response);
+                let questions = match entry {
+                    Some(entry) if entry.visits[index] > 0 => entry.questions[index],
+                    _ if next.status() != GameResult::Ongoing => 1.0,
+                    _ => 1.0 + (live(&next) as f64).log2() * (1 + next.max_lies()) as f64,
+                };
+                Some((response, next, questions))
+            })
+            .collect()
+    }
+
+    // The question the guesser thinks best, as how many live numbers are below it,
+    // with how many questions the table expects the game to take from here.
+    fn best_question(&self, game: &GameState) -> (Count, f64) {
+        (1..live(game))
+            .map(|below| {
+                let worst = self.rate(game, below).iter().map(|&(_, _, questions)| questions).fold(0.0, f64::max);
+                (below, worst)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or((0, 0.0))
+    }
+
+    /// Plays `episodes` games against itself, each out of one of `limits` chosen at
+    /// random, learning from every answer.
+    pub fn train(&mut self, limits: &[Number], episodes: u64, rng: &mut Rng) {
+        for _ in 0..episodes {
+            let upper_limit = limits[rng.below(limits.len() as u64) as usize];
+            let mut game = GameState::new(upper_limit, self.max_lies);
+            while game.status() == GameResult::Ongoing {
+                let count = live(&game);
+                let below = if rng.chance(EXPLORATION) { 1 + rng.below((count - 1) as u64) as Count } else { self.best_question(&game).0 };
+                let mut answers = self.rate(&game, below);
+                let choice = if rng.chance(EXPLORATION) {
+                    rng.below(answers.len() as u64) as usize
+                } else {
+                    (0..answers.len()).max_by(|&a, &b| answers[a].2.total_cmp(&answers[b].2)).unwrap_or(0)
+                };
+                let (response, next, _) = answers.swap_remove(choice);
+                let target = match next.status() {
+                    GameResult::Ongoing => 1.0 + self.best_question(&next).1,
+                    _ => 1.0,
+                };
+                let entry = self.entries.entry((game.canonical(), below)).or_default();
+                let index = answer_index(response);
+                entry.visits[index] = entry.visits[index].saturating_add(1);
+                let rate = (1.0 / f64::from(entry.visits[index])).max(LEARNING_RATE);
+                entry.questions[index] += rate * (target - entry.questions[index]);
+                game = next;
+            }
+        }
+    }
+
+    /// The table as bytes, for saving.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = FORMAT.to_vec();
+        bytes.extend((self.max_lies as u32).to_le_bytes());
+        bytes.extend((self.entries.len() as u64).to_le_bytes());
+        for ((state, below), entry) in &self.entries {
+            bytes.extend((state.len() as u32).to_le_bytes());
+            for &(count, lies) in state {
+                bytes.extend(count.to_le_bytes());
+                bytes.extend((lies as u32).to_le_bytes());
+            }
+            bytes.extend(below.to_le_bytes());
+            for index in 0..2 {
+                bytes.extend(entry.questions[index].to_le_bytes());
+                bytes.extend(entry.visits[index].to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Reads a table written by [`QTable::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<QTable, String> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take(FORMAT.len())? != FORMAT {
+            return Err("It isn't a model learned by one-lie".to_string());
+        }
+        let max_lies = reader.u32()? as usize;
+        let mut entries = HashMap::new();
+        for _ in 0..reader.u64()? {
+            let runs = reader.u32()?;
+            let mut state = vec![];
+            for _ in 0..runs {
+                state.push((reader.count()?, reader.u32()? as usize));
+            }
+            let below = reader.count()?;
+            let mut entry = Entry::default();
+            for index in 0..2 {
+                entry.questions[index] = f64::from_le_bytes(reader.array()?);
+                entry.visits[index] = reader.u32()?;
+            }
+            entries.insert((state, below), entry);
+        }
+        if reader.position < bytes.len() {
+            return Err("It has more in it than a model should".to_string());
+        }
+        Ok(QTable { max_lies, entries })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let end = self.position.checked_add(length).filter(|&end| end <= self.bytes.len()).ok_or("It ends too soon")?;
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().expect("Exactly N bytes were taken"))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn count(&mut self) -> Result<Count, String> {
+        Ok(Count::from_le_bytes(self.array::<{ size_of::<Count>() }>()?))
+    }
+}
+
+/// Answers as its table says drags the game out longest, or as the adversary
+/// would where the table has never seen the question, or the game's rules aren't
+/// those it learned.
+pub struct Learned {
+    pub table: QTable,
+    pub fallback: Adversary,
+}
+
+impl Opponent for Learned {
+    fn respond(&mut self, game: &GameState, question: &Question) -> Response {
+        if let Question::Less(guess) = *question {
+            let below = live_below(game, guess);
+            if plain(game) && game.max_lies() == self.table.max_lies && self.table.entries.contains_key(&(game.canonical(), below)) {
+                let answers = self.table.rate(game, below);
+                if let Some(&(response, _, _)) = answers.iter().max_by(|a, b| a.2.total_cmp(&b.2)) {
+                    return response;
+                }
+            }
+        }
+        self.fallback.respond(game, question)
+    }
+}
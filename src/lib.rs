@@ -0,0 +1,145 @@
+//! The engine behind one-lie, a guessing game where the opponent may lie.
+//!
+//! A [`GameState`] holds the rules and the questions asked so far, and works out
+//! which numbers are still possible under each way the opponent could have lied.
+//! [`Opponent`]s answer questions, [`Guesser`]s ask them, and the [`Solver`]
+//! finds the best question by searching the whole game tree, which the [`table`]
+//! module uses to tabulate how many numbers each number of questions can handle.
+//! The [`json`] module reads and writes games as JSON, and the [`transcript`]
+//! module as compact text. The [`display`] module draws them for the terminal.
+//! The [`batch`] module checks questions asked all at once, before any answers,
+//! and the [`multi`] module plays for several secrets at once. The [`cost`] module
+//! searches for the cheapest strategy when questions cost different amounts.
+//! The [`mastermind`] module is a second game on the same idea, guessing a code of
+//! coloured pegs from feedback that may lie, and the [`twenty`] module plays
+//! twenty questions over a list of items with yes or no attributes. The [`words`]
+//! module guesses a word from a wordlist, asking about dictionary order, and the
+//! [`domain`] module numbers other ordered values, such as dates and versions.
+//! The [`grid`] module guesses a point on a grid, one coordinate at a time, and
+//! the [`continuous`] module a real number to within a given precision. The
+//! [`web`] module plays a game through strings of JSON, for WebAssembly builds,
+//! and the [`ffi`] module offers games to C. The [`expression`] module reads
+//! value functions written as arithmetic, and the [`learned`] module has an
+//! opponent learn how to answer by playing against itself. The [`mcts`] module
+//! guesses by Monte Carlo tree search, for limits too large to search fully, and
+//! the [`proof`] module settles whether a game can be won in a given number of
+//! questions by proof-number search. The [`verify`] module checks an exported
+//! strategy by playing it against every secret and placement of lies.
+//!
+//! The rest drives games for the command line. The [`setup`] module builds the
+//! opponent and guesser it asks for and keeps them in save files, the [`modes`]
+//! and [`puzzles`] modules play the games at the terminal through a
+//! [`frontend`] and an [`input`], and the [`simulate`] and [`review`] modules
+//! play games unwatched and look back over recorded ones. The [`engine`],
+//! [`rpc`], [`api`], [`grpc`], [`serve`] and [`gui`] modules offer games to
+//! other programs, over standard input and output or the network.
+//!
+//! Everything but the [`game`], [`value`] and [`expression`] modules needs the
+//! standard library. Those three build with only `alloc` for targets without an
+//! operating system, such as embedded devices and `wasm32v1-none`, where the
+//! rest is left out.
+
+#![cfg_attr(target_os = "none", no_std)]
+
+extern crate alloc;
+
+#[cfg(not(target_os = "none"))]
+pub mod api;
+#[cfg(not(target_os = "none"))]
+pub mod batch;
+#[cfg(not(target_os = "none"))]
+pub mod commands;
+#[cfg(not(target_os = "none"))]
+pub mod continuous;
+#[cfg(not(target_os = "none"))]
+pub mod cost;
+#[cfg(not(target_os = "none"))]
+pub mod display;
+#[cfg(not(target_os = "none"))]
+pub mod domain;
+#[cfg(not(target_os = "none"))]
+pub mod engine;
+#[cfg(not(target_os = "none"))]
+pub mod evolve;
+pub mod expression;
+#[cfg(not(target_os = "none"))]
+pub mod ffi;
+mod float;
+#[cfg(not(target_os = "none"))]
+pub mod frontend;
+pub mod game;
+#[cfg(not(target_os = "none"))]
+pub mod grid;
+#[cfg(not(target_os = "none"))]
+pub mod grpc;
+#[cfg(not(target_os = "none"))]
+pub mod guesser;
+#[cfg(not(target_os = "none"))]
+pub mod gui;
+#[cfg(not(target_os = "none"))]
+pub mod http2;
+#[cfg(not(target_os = "none"))]
+pub mod input;
+#[cfg(not(target_os = "none"))]
+pub mod json;
+#[cfg(not(target_os = "none"))]
+pub mod learned;
+#[cfg(not(target_os = "none"))]
+pub mod mastermind;
+#[cfg(not(target_os = "none"))]
+pub mod mcts;
+#[cfg(not(target_os = "none"))]
+pub mod modes;
+#[cfg(not(target_os = "none"))]
+pub mod multi;
+#[cfg(not(target_os = "none"))]
+pub mod opponent;
+#[cfg(not(target_os = "none"))]
+pub mod play;
+#[cfg(not(target_os = "none"))]
+pub mod plugin;
+#[cfg(not(target_os = "none"))]
+pub mod proof;
+#[cfg(not(target_os = "none"))]
+pub mod puzzles;
+#[cfg(not(target_os = "none"))]
+pub mod review;
+#[cfg(not(target_os = "none"))]
+pub mod rng;
+#[cfg(not(target_os = "none"))]
+pub mod rpc;
+#[cfg(not(target_os = "none"))]
+pub mod serve;
+#[cfg(not(target_os = "none"))]
+pub mod setup;
+#[cfg(not(target_os = "none"))]
+pub mod simulate;
+#[cfg(not(target_os = "none"))]
+pub mod solver;
+#[cfg(not(target_os = "none"))]
+pub mod style;
+#[cfg(not(target_os = "none"))]
+pub mod table;
+#[cfg(not(target_os = "none"))]
+pub mod tournament;
+#[cfg(not(target_os = "none"))]
+pub mod transcript;
+#[cfg(not(target_os = "none"))]
+pub mod twenty;
+pub mod value;
+#[cfg(not(target_os = "none"))]
+pub mod verify;
+#[cfg(not(target_os = "none"))]
+pub mod web;
+#[cfg(not(target_os = "none"))]
+pub mod websocket;
+#[cfg(not(target_os = "none"))]
+pub mod words;
+
+pub use crate::game::{CandidateSet, Count, Dir, GameError, GameResult, GameState, Number, PossibilitySet, Prior, Question, Range, Response, Variant};
+#[cfg(not(target_os = "none"))]
+pub use crate::guesser::Guesser;
+#[cfg(not(target_os = "none"))]
+pub use crate::opponent::Opponent;
+#[cfg(not(target_os = "none"))]
+pub use crate::solver::Solver;
@@ -1,200 +1,177 @@
-use std::cmp::{min, max};
-use std::io::stdin;
-use std::env::args;
+extern crate one_lie;
 
-#[derive(Clone)]
-struct GameState {
-    history: Vec<(u64, Dir)>,
-    upper_limit: u64,
-}
+mod cli;
 
-// Half open
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Range {
-    lower: u64,
-    higher: u64,
-}
+use std::env::args;
+use std::fs;
+use std::process::exit;
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum Dir {
-    High,
-    Low,
-}
+use one_lie::cost::CostSolver;
+use one_lie::display::question_tree;
+use one_lie::engine::run_engine;
+use one_lie::frontend::Output;
+use one_lie::json::ToJson;
+use one_lie::modes::{batch_game, grid_game, multi_game, play_game, real_game, solve_game};
+use one_lie::proof::ProofSearch;
+use one_lie::puzzles::{bisect, mastermind_game, twenty_game, words_game};
+use one_lie::review::{analyze_game, replay_game, verify_strategy, volume_report};
+use one_lie::setup::load_game;
+use one_lie::simulate::{benchmark_values, simulate_games, train_model};
+use one_lie::style::Style;
+use one_lie::table::largest_limits;
+use one_lie::value::volume_limit;
+use one_lie::{api, evolve, grpc, rpc, serve, tournament, GameState, Solver, Variant};
 
-use Dir::*;
+use crate::cli::Command;
 
-impl Dir {
-    fn opposite(self) -> Self {
-        match self {
-            High => Low,
-            Low => High,
+fn main() {
+    let (command, mut no_color) = match cli::parse(args().skip(1).collect()) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, cli::USAGE);
+            exit(2);
         }
-    }
-}
-
-impl Range {
-    fn new(lower: u64, higher: u64) -> Range {
-        Range {
-            lower: lower,
-            higher: higher,
+    };
+    // Colour codes would only get in the way of programs reading JSON
+    no_color |= command.output() == Some(Output::Json);
+    let style = Style::detect(no_color);
+    let outcome = match command {
+        Command::Play { game, setup, record, batch: true, .. } => batch_game(game, setup, record.as_deref(), style),
+        Command::Play { game, setup, show_state, targets, .. } if targets > 1 => {
+            multi_game(game, setup, targets, style, show_state)
         }
-    }
-    fn clamp_lower(&self, clamp: u64) -> Range {
-        Range {
-            lower: max(self.lower, clamp),
-            higher: self.higher,
+        Command::Play { game, setup, record, show_state, output, .. } => {
+            play_game(game, setup, record.as_deref(), output, style, show_state)
         }
-    }
-    fn clamp_higher(&self, clamp: u64) -> Range {
-        Range {
-            lower: self.lower,
-            higher: min(self.higher, clamp),
+        Command::Resume { path, record, show_state, output } => load_game(&path)
+            .map_err(|err| format!("Couldn't resume {}: {}", path, err))
+            .and_then(|(game, setup)| play_game(game, setup, record.as_deref(), output, style, show_state)),
+        Command::Solve { game, setup, guesser, games: 1, csv: None, record, show_state, output } => {
+            solve_game(game, setup, &guesser, record.as_deref(), output, style, show_state)
         }
-    }
-    fn len(&self) -> u64 {
-        self.higher.saturating_sub(self.lower)
-    }
-}
-
-impl GameState {
-    fn new(upper_limit: u64) -> GameState {
-        GameState {
-            history: vec![],
-            upper_limit: upper_limit,
+        Command::Solve { game, setup, guesser, games, csv, .. } => {
+            simulate_games(game, setup, &guesser, games, csv.as_deref())
         }
-    }
-    fn store_guess(&mut self, value: u64, response: Dir) -> Result<(), &str> {
-        if value >= self.upper_limit {
-            Err("Value too large")
-        } else {
-            self.history.push((value, response));
+        Command::Analyze { path } => analyze_game(&path).map_err(|err| format!("Couldn't analyze {}: {}", path, err)),
+        Command::Replay { path } => replay_game(&path, style).map_err(|err| format!("Couldn't replay {}: {}", path, err)),
+        Command::Bound { upper_limit, max_lies, variant, cost: Some(cost), .. } => {
+            let game = GameState::new(upper_limit, max_lies).with_variant(variant);
+            println!(
+                "A total cost of {:.2} guarantees finding a number below {} with {}, each question costing {}",
+                CostSolver::new(max_lies, cost.clone()).with_variant(variant).worst_cost(&game),
+                upper_limit,
+                game.describe(),
+                cost.describe()
+            );
             Ok(())
         }
-    }
-    fn possibilities(&self) -> Vec<(Range, Option<usize>)> {
-        let mut lies: Vec<Option<usize>> = (0..self.history.len()).map(|num| Some(num)).collect();
-        lies.push(None);
-        let lies = lies;
-        lies.iter()
-            .map(|&lie| {
-                let mut range = Range::new(0, self.upper_limit);
-                for (index, &(guess, response)) in self.history.iter().enumerate() {
-                    let truth = if lie == Some(index) {
-                        response.opposite()
-                    } else {
-                        response
-                    };
-                    match truth {
-                        High => range = range.clamp_lower(guess),
-                        Low => range = range.clamp_higher(guess),
-                    }
+        Command::Bound { upper_limit, max_lies, variant, cost: None, questions: Some(questions), .. } => {
+            let game = GameState::new(upper_limit, max_lies).with_variant(variant);
+            let enough = ProofSearch::new(max_lies).with_variant(variant).can_finish(&game, questions);
+            println!(
+                "{} questions {} to guarantee finding a number below {} with {}",
+                questions,
+                if enough { "are enough" } else { "aren't enough" },
+                upper_limit,
+                game.describe()
+            );
+            Ok(())
+        }
+        Command::Bound { upper_limit, max_lies, variant, threads, cost: None, questions: None } => {
+            let game = GameState::new(upper_limit, max_lies).with_variant(variant);
+            let needed = Solver::new(max_lies).with_variant(variant).with_threads(threads).questions_needed(&game);
+            println!(
+                "{} questions guarantee finding a number below {} with {}",
+                needed,
+                upper_limit,
+                game.describe()
+            );
+            // Half lies leave some placements of the lies impossible, so the volume
+            // bound doesn't hold for them
+            if variant == Variant::Standard {
+                println!("{}", volume_report(&game, needed));
+            }
+            Ok(())
+        }
+        Command::Benchmark { limits, max_lies, games, setup } => benchmark_values(&limits, max_lies, games, &setup),
+        Command::Tournament(tournament) => tournament::run_tournament(&tournament),
+        Command::Evolve(evolution) => evolve::evolve(&evolution),
+        Command::Train { out, limits, max_lies, episodes, seed } => train_model(&out, &limits, max_lies, episodes, seed),
+        Command::Export { upper_limit, max_lies, out } => {
+            let strategy = Solver::new(max_lies).strategy(&GameState::new(upper_limit, max_lies));
+            let json = format!("{}\n", strategy.to_json());
+            match out {
+                None => {
+                    print!("{}", json);
+                    Ok(())
                 }
-                (range, lie)
-            })
-            .collect()
-    }
-}
-
-fn simple_value(game: &GameState) -> u64 {
-    game.possibilities().iter().map(|&(range, _)| range.len()).sum()
-}
-
-fn better_value(game: &GameState) -> u64 {
-    let multiplier = (simple_value(game) as f64).log2() - 1 as f64;
-    game.possibilities().iter().map(|&(range, lie)| if lie.is_none() {
-        range.len() as f64 * multiplier
-    } else {
-        range.len() as f64
-    }).sum::<f64>() as u64
-}        
-
-fn adversarial_response(value: &Fn(&GameState) -> u64, game: &GameState, guess: u64) -> Dir {
-    let mut game_high = game.clone();
-    game_high.store_guess(guess, High).unwrap();
-    let mut game_low = game.clone();
-    game_low.store_guess(guess, Low).unwrap();
-    let high_remaining: u64 = value(&game_high);
-    let low_remaining: u64 = value(&game_low);
-    if high_remaining > low_remaining {
-        High
-    } else {
-        Low
-    }
-}
-
-#[derive(PartialEq, Eq)]
-enum GameResult {
-    Ongoing,
-    Finished(u64),
-    Impossible,
-}
-
-use GameResult::*;
-
-fn result(poss: Vec<(Range, Option<usize>)>) -> GameResult {
-    let ranges: Vec<Range> = poss.iter().map(|&(range, _)| range)
-        .filter(|range| range.len() > 0)
-        .collect();
-    if ranges.iter().any(|range| range.len() > 1) {
-        Ongoing
-    } else {
-        if let Some(first) = ranges.first() {
-            if ranges.iter().all(|range| range == first) {
-                Finished(first.lower)
-            } else {
-                Ongoing
+                Some(path) => fs::write(&path, json).map_err(|err| format!("Couldn't write {}: {}", path, err)),
             }
-        } else {
-            Impossible
         }
-    }
-}
-
-fn play_game(upper_limit: u64, opponent: &Fn(&GameState, u64) -> Dir) {
-    let mut game = GameState::new(upper_limit);
-    println!(
-        "Guess the number, with up to one lie, out of {}",
-        upper_limit
-    );
-    while result(game.possibilities()) == Ongoing {
-        println!(
-            "{}: What number do you want to know if it's less than?",
-            game.history.len()
-        );
-        let mut input = String::new();
-        stdin().read_line(&mut input).expect("Failed to read stdin");
-        match input.trim().parse::<u64>() {
-            Err(_) => println!("Input could not be parsed as a number in range"),
-            Ok(guess) => {
-                if guess >= upper_limit {
-                    println!("Guesses must be less than {}", upper_limit);
-                } else {
-                    let response = opponent(&game, guess);
-                    if response == High {
-                        println!("Greater than or equal to {}", guess);
-                    } else {
-                        println!("Less than {}\n", guess);
-                    }
-                    game.store_guess(guess, response).expect("Already checked guess was legal");
+        Command::Verify { path } => verify_strategy(&path).map_err(|err| format!("Couldn't verify {}: {}", path, err)),
+        Command::Tree { upper_limit, max_lies, depth } => {
+            let game = GameState::new(upper_limit, max_lies);
+            print!("{}", question_tree(&game, &mut Solver::new(max_lies), depth));
+            Ok(())
+        }
+        Command::Mastermind { game, random, seed, secret, show_state } => {
+            mastermind_game(game, random, seed, secret, style, show_state);
+            Ok(())
+        }
+        Command::Twenty { database, max_lies, random, seed, secret, value, show_state } => {
+            twenty_game(database, max_lies, random, seed, secret, &value, style, show_state);
+            Ok(())
+        }
+        Command::Words { wordlist, max_lies, random, seed, secret, value, show_state } => {
+            words_game(wordlist, max_lies, random, seed, secret, &value, style, show_state);
+            Ok(())
+        }
+        Command::Bisect { domain, game, secret, seed, value } => {
+            bisect(&*domain, game, secret, seed, &value, style);
+            Ok(())
+        }
+        Command::Serve { game, port, websocket } => serve::serve(game, port, websocket),
+        Command::Api { rules, setup, port, page } => api::serve_api(rules, setup, port, page),
+        Command::Grpc { setup, guesser, port } => grpc::serve_grpc(setup, guesser, port),
+        Command::Engine { setup, guesser, rpc: false } => {
+            run_engine(setup, guesser);
+            Ok(())
+        }
+        Command::Engine { setup, guesser, rpc: true } => {
+            rpc::run_rpc(setup, guesser);
+            Ok(())
+        }
+        Command::Continuous { continuum, game, setup, show_state } => real_game(continuum, game, setup, style, show_state),
+        Command::Grid { grid, game, setup, show_state } => grid_game(grid, game, setup, style, show_state),
+        Command::Table { max_lies, max_questions } => {
+            println!("The most numbers that each number of questions can always find the secret among");
+            let mut header = format!("{:>9}", "questions");
+            for lies in 0..=max_lies {
+                header += &format!(" {:>12} ", if lies == 1 { "1 lie".to_string() } else { format!("{} lies", lies) });
+            }
+            println!("{}", header.trim_end());
+            let mut short = false;
+            for (questions, row) in largest_limits(max_lies, max_questions).iter().enumerate() {
+                let mut line = format!("{:>9}", questions);
+                for (lies, &largest) in row.iter().enumerate() {
+                    let below = largest < volume_limit(questions, lies);
+                    short |= below;
+                    line += &format!(" {:>12}{}", largest, if below { "*" } else { " " });
                 }
+                println!("{}", line.trim_end());
+            }
+            if short {
+                println!("* Fewer than the volume bound leaves room for, so some answer sequences go unused");
             }
+            Ok(())
         }
+        Command::Help => {
+            print!("{}", cli::USAGE);
+            Ok(())
+        }
+    };
+    if let Err(err) = outcome {
+        eprintln!("error: {}", err);
+        exit(1);
     }
-    if let Finished(answer) = result(game.possibilities()) {
-        println!("You got it in {} guesses", game.history.len());
-        println!("It was {}", answer);
-        let poss_lies: Vec<Option<usize>> = game.possibilities().iter()
-            .filter(|&&(range, _)| range.len() > 0)
-            .map(|&(_, lie)| lie)
-            .collect();
-        println!(
-            "The opponent could have lied on question(s) {:?}",
-            poss_lies
-        );
-    }
-}
-
-fn main() {
-    let upper_limit = args().nth(1).map_or(10, |arg| arg.parse().unwrap());
-    play_game(upper_limit, &|game, guess| adversarial_response(&better_value, game, guess));
 }
@@ -0,0 +1,348 @@
+//! Mastermind with lies, a second game on the same idea as [`GameState`]: guess a
+//! hidden code of coloured pegs from the feedback each guess gets, where up to
+//! `max_lies` of the feedbacks may be lies.
+//!
+//! As with numbers, every code is paired with how many feedbacks would have to be
+//! lies if it were the secret, and stays possible while that is within the budget.
+//! Codes don't fall into ranges the way numbers do, so each is tracked on its own,
+//! and games are limited to [`MAX_CODES`] codes.
+//!
+//! [`GameState`]: crate::game::GameState
+
+use std::cmp::{max, min};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::game::{Count, GameError};
+use crate::rng::Rng;
+use crate::value::lie_patterns;
+
+/// The most codes a game may have, as every one is tracked.
+pub const MAX_CODES: usize = 1 << 16;
+
+/// The most colours, so that each can be written as a digit from 1 to 9.
+pub const MAX_COLOURS: u8 = 9;
+
+// How many feedbacks the guesser works out before choosing a guess. Past this it
+// only tries codes that are still possible, and then only some of them.
+const SEARCH_BUDGET: usize = 1 << 22;
+
+/// Pegs, each a colour from 0 to one less than the number of colours, though
+/// written from 1 up.
+pub type Code = Vec<u8>;
+
+/// What a guess is told: how many pegs are the right colour in the right place,
+/// and how many more are a colour the code has, but in the wrong place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Feedback {
+    pub black: usize,
+    pub white: usize,
+}
+
+impl Feedback {
+    /// The true feedback to `guess` if the code is `secret`.
+    pub fn score(secret: &[u8], guess: &[u8]) -> Feedback {
+        let black = secret.iter().zip(guess).filter(|(secret, guess)| secret == guess).count();
+        let mut secret_colours = [0; MAX_COLOURS as usize];
+        let mut guess_colours = [0; MAX_COLOURS as usize];
+        for (&secret, &guess) in secret.iter().zip(guess) {
+            secret_colours[secret as usize] += 1;
+            guess_colours[guess as usize] += 1;
+        }
+        let common: usize = secret_colours.iter().zip(&guess_colours).map(|(&secret, &guess)| min(secret, guess)).sum();
+        Feedback { black, white: common - black }
+    }
+    /// Every feedback a guess of `pegs` pegs can get. Every peg but one right,
+    /// with the last the right colour, can't happen, as it would be right too.
+    pub fn all(pegs: usize) -> Vec<Feedback> {
+        let mut all = vec![];
+        for black in 0..=pegs {
+            for white in 0..=pegs - black {
+                if black + 1 != pegs || white != 1 {
+                    all.push(Feedback { black, white });
+                }
+            }
+        }
+        all
+    }
+}
+
+impl fmt::Display for Feedback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} black, {} white", self.black, self.white)
+    }
+}
+
+/// Writes a code with its colours numbered from 1, as in `1123`.
+pub fn format_code(code: &[u8]) -> String {
+    code.iter().map(|&colour| char::from(b'1' + colour)).collect()
+}
+
+/// A game of Mastermind in progress: the rules, every guess so far with its
+/// feedback, and how many of the feedbacks each code contradicts.
+#[derive(Clone)]
+pub struct Mastermind {
+    pegs: usize,
+    colours: u8,
+    max_lies: usize,
+    history: Vec<(Code, Feedback)>,
+    // Every code in order, shared, since games are cloned to try each feedback
+    codes: Arc<Vec<Code>>,
+    // How many feedbacks each code contradicts, in the same order
+    contradictions: Vec<usize>,
+}
+
+impl Mastermind {
+    /// A game with codes of `pegs` pegs in `colours` colours, where the opponent
+    /// may lie in up to `max_lies` feedbacks.
+    pub fn new(pegs: usize, colours: u8, max_lies: usize) -> Result<Mastermind, GameError> {
+        if pegs == 0 || !(2..=MAX_COLOURS).contains(&colours) {
+            return Err(GameError::InvalidRules(format!("Codes need at least one peg, and 2 to {} colours", MAX_COLOURS)));
+        }
+        let count = (0..pegs)
+            .try_fold(1usize, |count, _| count.checked_mul(colours as usize))
+            .filter(|&count| count <= MAX_CODES)
+            .ok_or_else(|| GameError::InvalidRules(format!("{} pegs in {} colours make more than {} codes", pegs, colours, MAX_CODES)))?;
+        // Counting in base `colours`, with the first peg the most significant
+        let codes: Vec<Code> = (0..count)
+            .map(|index| {
+                let mut code = vec![0; pegs];
+                let mut rest = index;
+                for peg in code.iter_mut().rev() {
+                    *peg = (rest % colours as usize) as u8;
+                    rest /= colours as usize;
+                }
+                code
+            })
+            .collect();
+        Ok(Mastermind {
+            pegs,
+            colours,
+            max_lies,
+            history: vec![],
+            codes: Arc::new(codes),
+            contradictions: vec![0; count],
+        })
+    }
+    pub fn pegs(&self) -> usize {
+        self.pegs
+    }
+    pub fn colours(&self) -> u8 {
+        self.colours
+    }
+    pub fn max_lies(&self) -> usize {
+        self.max_lies
+    }
+    /// The guesses so far with their feedback.
+    pub fn history(&self) -> &[(Code, Feedback)] {
+        &self.history
+    }
+    /// Every code, possible or not, in order.
+    pub fn codes(&self) -> &[Code] {
+        &self.codes
+    }
+    /// Reads a code written as digits from 1 to the number of colours, one for
+    /// each peg, ignoring spaces.
+    pub fn parse_code(&self, text: &str) -> Result<Code, GameError> {
+        let code = text.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| match c.to_digit(10) {
+                Some(digit) if digit >= 1 && digit <= u32::from(self.colours) => Ok(digit as u8 - 1),
+                _ => Err(GameError::ParseError(format!("{} isn't a colour: colours are 1 to {}", c, self.colours))),
+            })
+            .collect::<Result<Code, GameError>>()?;
+        self.check_code(&code)?;
+        Ok(code)
+    }
+    /// Whether `code` has a peg for every place, each a colour in the game.
+    pub fn check_code(&self, code: &[u8]) -> Result<(), GameError> {
+        if code.len() != self.pegs || code.iter().any(|&colour| colour >= self.colours) {
+            return Err(GameError::InvalidCode(format!(
+                "Codes are {} pegs, each a colour from 1 to {}",
+                self.pegs, self.colours
+            )));
+        }
+        Ok(())
+    }
+    /// Records the feedback to a guess.
+    pub fn ask(&mut self, guess: Code, feedback: Feedback) -> Result<(), GameError> {
+        self.check_code(&guess)?;
+        if !Feedback::all(self.pegs).contains(&feedback) {
+            return Err(GameError::InvalidCode(format!("A guess of {} pegs can't get {}", self.pegs, feedback)));
+        }
+        for (code, contradicted) in self.codes.iter().zip(self.contradictions.iter_mut()) {
+            if Feedback::score(code, &guess) != feedback {
+                *contradicted += 1;
+            }
+        }
+        self.history.push((guess, feedback));
+        Ok(())
+    }
+    /// Takes back the last guess, returning it with its feedback.
+    pub fn undo(&mut self) -> Option<(Code, Feedback)> {
+        let (guess, feedback) = self.history.pop()?;
+        for (code, contradicted) in self.codes.iter().zip(self.contradictions.iter_mut()) {
+            if Feedback::score(code, &guess) != feedback {
+                *contradicted -= 1;
+            }
+        }
+        Some((guess, feedback))
+    }
+    /// Every code still possible, with how many feedbacks would be lies if it
+    /// were the secret.
+    pub fn possibilities(&self) -> Vec<(&[u8], usize)> {
+        self.codes.iter()
+            .zip(&self.contradictions)
+            .filter(|&(_, &lies)| lies <= self.max_lies)
+            .map(|(code, &lies)| (code.as_slice(), lies))
+            .collect()
+    }
+    /// The code, once it is the only one possible.
+    pub fn solved(&self) -> Option<Code> {
+        match *self.possibilities() {
+            [(code, _)] => Some(code.to_vec()),
+            _ => None,
+        }
+    }
+    /// Whether no code fits the feedback, however the lies fell.
+    pub fn is_impossible(&self) -> bool {
+        self.contradictions.iter().all(|&lies| lies > self.max_lies)
+    }
+    /// How much work the position leaves: each possible code counts once for each
+    /// way its lies left could fall among the guesses still needed, each lie any
+    /// of the wrong feedbacks, as [`lie_patterns`] counts them. The guesses needed
+    /// are estimated from how many codes are left.
+    pub fn value(&self) -> Count {
+        let mut levels = vec![0; self.max_lies + 1];
+        for &lies in self.contradictions.iter().filter(|&&lies| lies <= self.max_lies) {
+            levels[lies] += 1;
+        }
+        self.weigh(&levels)
+    }
+    // The value of a position with `levels[lies]` codes needing each number of lies.
+    fn weigh(&self, levels: &[Count]) -> Count {
+        let live: Count = levels.iter().sum();
+        let feedbacks = Feedback::all(self.pegs).len();
+        // Each guess splits the codes at best as many ways as there are feedbacks
+        let questions = max(((live as f64).ln() / (feedbacks as f64).ln()).ceil() as usize, 1);
+        levels.iter()
+            .enumerate()
+            .map(|(lies, &count)| count.saturating_mul(lie_patterns(questions, self.max_lies - lies, feedbacks as u128 - 1)))
+            .fold(0, Count::saturating_add)
+    }
+    /// Each feedback `guess` could get, with the value it would leave, in
+    /// [`Feedback::all`] order.
+    pub fn outcomes(&self, guess: &[u8]) -> Vec<(Feedback, Count)> {
+        let all = Feedback::all(self.pegs);
+        // The possible codes each feedback is true for, by the lies they need
+        let mut matching = vec![vec![0; self.max_lies + 1]; all.len()];
+        let mut totals: Vec<Count> = vec![0; self.max_lies + 1];
+        for (code, &lies) in self.codes.iter().zip(&self.contradictions) {
+            if lies > self.max_lies {
+                continue;
+            }
+            let truth = Feedback::score(code, guess);
+            let position = all.iter().position(|&feedback| feedback == truth).expect("Every score is a feedback");
+            matching[position][lies] += 1;
+            totals[lies] += 1;
+        }
+        all.into_iter()
+            .zip(matching)
+            .map(|(feedback, matching)| {
+                // The codes it is false for need one more lie
+                let levels: Vec<Count> = (0..=self.max_lies)
+                    .map(|lies| matching[lies] + if lies > 0 { totals[lies - 1] - matching[lies - 1] } else { 0 })
+                    .collect();
+                (feedback, self.weigh(&levels))
+            })
+            .collect()
+    }
+    /// The guess whose worst feedback leaves the least value, preferring a code
+    /// that is still possible, and then the first. Every code is tried in small
+    /// games, but in large ones only the possible codes, evenly spaced if there
+    /// are too many of those to try them all.
+    pub fn best_guess(&self) -> Code {
+        let live: Vec<usize> = (0..self.codes.len()).filter(|&index| self.contradictions[index] <= self.max_lies).collect();
+        let mut tried: Vec<usize> = if self.codes.len() * live.len() <= SEARCH_BUDGET {
+            (0..self.codes.len()).collect()
+        } else {
+            live.clone()
+        };
+        let step = (tried.len() * live.len()).div_ceil(SEARCH_BUDGET);
+        if step > 1 {
+            tried = tried.into_iter().step_by(step).collect();
+        }
+        let mut best: Option<(Count, bool, usize)> = None;
+        for index in tried {
+            let worst = self.outcomes(&self.codes[index]).into_iter().map(|(_, value)| value).max().unwrap_or(0);
+            let candidate = (worst, self.contradictions[index] > self.max_lies, index);
+            let better = match best {
+                Some(best) => candidate < best,
+                None => true,
+            };
+            if better {
+                best = Some(candidate);
+            }
+        }
+        best.map_or_else(|| self.codes[0].clone(), |(_, _, index)| self.codes[index].clone())
+    }
+}
+
+/// Gives feedback on guesses at the code.
+pub trait MastermindOpponent {
+    /// The feedback to `guess`, given the game so far.
+    fn respond(&mut self, game: &Mastermind, guess: &[u8]) -> Feedback;
+    /// Whatever the opponent committed to in advance, shown once the game is over.
+    fn reveal(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Never settles on a code, and gives whichever feedback leaves the largest
+/// value, the first of them in [`Feedback::all`] order if several tie.
+pub struct MastermindAdversary;
+
+impl MastermindOpponent for MastermindAdversary {
+    fn respond(&mut self, game: &Mastermind, guess: &[u8]) -> Feedback {
+        let mut best: Option<(Feedback, Count)> = None;
+        for (feedback, value) in game.outcomes(guess) {
+            let better = match best {
+                Some((_, best)) => value > best,
+                None => true,
+            };
+            if better {
+                best = Some((feedback, value));
+            }
+        }
+        best.expect("Every guess has feedback").0
+    }
+}
+
+/// Picks its code at random, and spreads its lies uniformly at random over the
+/// first `horizon` guesses, deciding as each is made. Each lie is any of the
+/// wrong feedbacks, at random.
+pub struct MastermindLiar {
+    pub secret: Code,
+    pub horizon: usize,
+    pub rng: Rng,
+}
+
+impl MastermindOpponent for MastermindLiar {
+    fn respond(&mut self, game: &Mastermind, guess: &[u8]) -> Feedback {
+        let truth = Feedback::score(&self.secret, guess);
+        let lies_told = game.history.iter()
+            .filter(|(past_guess, feedback)| Feedback::score(&self.secret, past_guess) != *feedback)
+            .count();
+        let lies_left = game.max_lies.saturating_sub(lies_told);
+        let guesses_left = max(self.horizon.saturating_sub(game.history.len()), 1);
+        if lies_left > 0 && self.rng.below(guesses_left as u64) < lies_left as u64 {
+            let lies: Vec<Feedback> = Feedback::all(game.pegs).into_iter().filter(|&feedback| feedback != truth).collect();
+            lies[self.rng.below(lies.len() as u64) as usize]
+        } else {
+            truth
+        }
+    }
+    fn reveal(&self) -> Option<String> {
+        Some(format!("The code was {}", format_code(&self.secret)))
+    }
+}
@@ -0,0 +1,181 @@
+//! A guesser that plays games out at random rather than searching them
+//! exhaustively, for limits too large for the [`Solver`](crate::solver::Solver).
+//! Monte Carlo tree search grows a tree of questions and answers from the
+//! position, a playout at a time. Each playout follows the tree down, with the
+//! guesser taking the question that has ended games soonest and the opponent the
+//! answer that has dragged them out longest, as weighed against how little each
+//! has been tried. It then adds a node and plays the rest of the game as the greedy
+//! guesser against answers mostly the adversary's, now and then random ones. The
+//! question tried most often is asked.
+//!
+//! The questions tried in each position are the greedy guesser's, and those with
+//! a few, a few more, and so on, more or fewer live numbers below them, so that
+//! the tree stays narrow however large the limit.
+
+use crate::game::{Count, GameResult, GameState, Number, Question};
+use crate::guesser::{Greedy, Guesser};
+use crate::rng::Rng;
+use crate::solver::{live_below, number_at};
+
+// How often playouts answer as the adversary would, rather than at random
+const ADVERSARIAL: f64 = 0.75;
+
+// Either the guesser's turn, or the opponent's to answer the question about `guess`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Turn {
+    Guess,
+    Answer(Number),
+}
+
+struct Node {
+    game: GameState,
+    turn: Turn,
+    // Indices into the tree, filled in the first time the node is passed through
+    children: Vec<usize>,
+    expanded: bool,
+    visits: u32,
+    // The questions the games through here took, summed
+    questions: f64,
+}
+
+/// Monte Carlo tree search, with `playouts` playouts a question. Nodes are
+/// weighed by their mean questions, give or take `exploration` times the usual
+/// UCT bonus for being little tried, which is in questions, so 1 is a lot.
+pub struct Mcts {
+    pub playouts: u32,
+    pub exploration: f64,
+    /// The guesser playouts are played with, and which suggests the first question to try.
+    pub greedy: Greedy,
+    pub rng: Rng,
+}
+
+impl Mcts {
+    // The questions to try, as guesses: the greedy one, and those with 1, 2, 4 and
+    // so on more or fewer live numbers below them.
+    fn guesses(&mut self, game: &GameState) -> Vec<Number> {
+        let live: Count = game.canonical().iter().map(|&(count, _)| count).sum();
+        let greedy = self.greedy.choose(game);
+        let below = live_below(game, greedy);
+        let mut guesses = vec![greedy];
+        let mut step: Count = 1;
+        while step < live {
+            for position in [below.checked_sub(step), below.checked_add(step)].into_iter().flatten() {
+                if position > 0 && position < live {
+                    guesses.push(number_at(game, position));
+                }
+            }
+            step *= 2;
+        }
+        guesses.sort_unstable();
+        guesses.dedup();
+        guesses
+    }
+
+    // The children of `node`, each a game the tree doesn't hold yet.
+    fn expand(&mut self, node: &Node) -> Vec<Node> {
+        let child = |game: GameState, turn| Node { game, turn, children: vec![], expanded: false, visits: 0, questions: 0.0 };
+        match node.turn {
+            Turn::Guess => self.guesses(&node.game).into_iter().map(|guess| child(node.game.clone(), Turn::Answer(guess))).collect(),
+            Turn::Answer(guess) => answers(&node.game, guess).into_iter().map(|next| child(next, Turn::Guess)).collect(),
+        }
+    }
+
+    // The child to go down, the guesser's taking the fewest questions and the
+    // opponent's the most, with a bonus for being little tried.
+    fn select(&self, tree: &[Node], node: &Node) -> usize {
+        if let Some(&untried) = node.children.iter().find(|&&child| tree[child].visits == 0) {
+            return untried;
+        }
+        let log_visits = f64::from(node.visits).ln();
+        let score = |child: usize| {
+            let child = &tree[child];
+            let mean = child.questions / f64::from(child.visits);
+            let bonus = self.exploration * (log_visits / f64::from(child.visits)).sqrt();
+            match node.turn {
+                Turn::Guess => -mean + bonus,
+                Turn::Answer(_) => mean + bonus,
+            }
+        };
+        *node.children.iter().max_by(|&&a, &&b| score(a).total_cmp(&score(b))).expect("Expanded nodes have children")
+    }
+
+    // Plays the rest of the game as the greedy guesser against the playout answers,
+    // returning how many questions it took in all.
+    fn playout(&mut self, node: &Node) -> usize {
+        let mut game = node.game.clone();
+        if let Turn::Answer(guess) = node.turn {
+            game = self.playout_answer(&game, guess);
+        }
+        while game.status() == GameResult::Ongoing {
+            let guess = self.greedy.choose(&game);
+            game = self.playout_answer(&game, guess);
+        }
+        game.history().len()
+    }
+
+    // Mostly the answer leaving the greedy guesser's value function the most to do,
+    // as the adversary gives, and otherwise any answer at all.
+    fn playout_answer(&mut self, game: &GameState, guess: Number) -> GameState {
+        let mut answers = answers(game, guess);
+        let choice = if self.rng.chance(ADVERSARIAL) {
+            (0..answers.len()).max_by_key(|&index| self.greedy.value.value(&answers[index])).unwrap_or(0)
+        } else {
+            self.rng.below(answers.len() as u64) as usize
+        };
+        answers.swap_remove(choice)
+    }
+}
+
+// The games after each answer to the question about `guess` that leaves some
+// number possible, or after the first answer if none does.
+fn answers(game: &GameState, guess: Number) -> Vec<GameState> {
+    let question: Question = game.guess(guess);
+    let mut answers: Vec<GameState> = game.responses(&question).into_iter()
+        .map(|response| {
+            let mut next = game.clone();
+            next.ask(question.clone(), response).expect("Guesses that split the live numbers are legal");
+            next
+        })
+        .collect();
+    if answers.iter().any(|next| next.status() != GameResult::Impossible) {
+        answers.retain(|next| next.status() != GameResult::Impossible);
+    } else {
+        answers.truncate(1);
+    }
+    answers
+}
+
+impl Guesser for Mcts {
+    fn choose(&mut self, game: &GameState) -> Number {
+        let mut tree = vec![Node { game: game.clone(), turn: Turn::Guess, children: vec![], expanded: false, visits: 0, questions: 0.0 }];
+        for _ in 0..self.playouts {
+            let mut path = vec![0];
+            let mut current = 0;
+            while tree[current].expanded && tree[current].game.status() == GameResult::Ongoing {
+                current = self.select(&tree, &tree[current]);
+                path.push(current);
+            }
+            let questions = if tree[current].game.status() != GameResult::Ongoing {
+                tree[current].game.history().len()
+            } else {
+                let children = self.expand(&tree[current]);
+                tree[current].children = (tree.len()..tree.len() + children.len()).collect();
+                tree[current].expanded = true;
+                tree.extend(children);
+                current = self.select(&tree, &tree[current]);
+                path.push(current);
+                self.playout(&tree[current])
+            };
+            for &index in &path {
+                tree[index].visits += 1;
+                tree[index].questions += questions as f64;
+            }
+        }
+        let best = tree[0].children.iter().max_by_key(|&&child| tree[child].visits);
+        match best.map(|&child| tree[child].turn) {
+            Some(Turn::Answer(guess)) => guess,
+            // No playouts at all, so take the greedy guesser's word for it
+            _ => self.greedy.choose(game),
+        }
+    }
+}
@@ -0,0 +1,813 @@
+//! The number game played at the terminal: the player guessing, the computer
+//! guessing against the player or an automated opponent, and the variations with
+//! several secrets, real numbers, points on a grid and questions asked all at once.
+//! Each game loop speaks through a Frontend and reads through an Input, so anything
+//! that implements them can drive it.
+
+use std::fs;
+
+use crate::batch::{confusion, Confusion};
+use crate::commands::{BatchCommand, GridCommand, MultiCommand, PlayCommand, RealCommand, BATCH_HELP, GRID_HELP, MULTI_HELP, REAL_HELP};
+use crate::continuous::Continuum;
+use crate::frontend::{Frontend, JsonLines, Lines, Output, Tui};
+use crate::game::GameResult::*;
+use crate::grid::Grid;
+use crate::guesser::Greedy;
+use crate::input::{Input, Terminal};
+use crate::multi::{MultiAdversary, MultiGame, MultiHonest, MultiOpponent};
+use crate::play::{Effect, Play};
+use crate::rng::Rng;
+use crate::setup::{draw_secret, make_guesser, make_opponent, make_solver, save_game, OpponentKind, OpponentSetup};
+use crate::style::Style;
+use crate::value::{value_function, ValueFunction};
+use crate::{gui, GameState, Guesser, Opponent, Question, Range, Response, Solver};
+
+/// The answer to a question in a word: less, equal or greater for comparisons, and
+/// yes or no for the rest.
+pub fn answer_word(question: &Question, response: Response) -> &'static str {
+    match (question, response) {
+        (&Question::Compare(_), Response::Yes) => "Less",
+        (&Question::Compare(_), Response::No) => "Greater",
+        (_, Response::Equal) => "Equal",
+        (_, Response::Pass) => "Pass",
+        (_, Response::Yes) => "Yes",
+        (_, Response::No) => "No",
+    }
+}
+
+/// A question and its answer, like "question 2, less than 5".
+pub fn describe_question(game: &GameState, index: usize) -> String {
+    let (ref question, response) = game.history()[index];
+    format!("question {}, {}", index, question.describe_answer(response).to_lowercase())
+}
+
+/// Which answers can't both be true, now that no number fits them all.
+pub fn impossible_explanation(game: &GameState) -> Vec<String> {
+    let mut lines = vec![format!("Those answers contradict each other, even allowing {}", game.describe())];
+    for (first, second) in game.conflicts() {
+        lines.push(format!(
+            "The answers to {} and {} can't both be true",
+            describe_question(game, first),
+            describe_question(game, second)
+        ));
+    }
+    lines
+}
+
+fn explain_impossible(game: &GameState, frontend: &mut dyn Frontend) {
+    for line in impossible_explanation(game) {
+        frontend.say(&line);
+    }
+}
+
+// Says which answers need more lies than the game allows, when you answered them
+// yourself and so may have slipped.
+fn audit(game: &GameState, frontend: &mut dyn Frontend) {
+    frontend.say(&format!("Your answers need more lies than the game allows, which is {}", game.describe()));
+    if let Some(contradiction) = game.contradiction() {
+        frontend.say(&format!("These {} answers can't all be true together:", contradiction.len()));
+        for index in contradiction {
+            frontend.say(&format!("    {}", describe_question(game, index)));
+        }
+    }
+}
+
+// Offers to take back one of the answers that left no number possible, reading the
+// choice from `input`, returning whether one was taken back. If `insist`, it keeps
+// asking until one is, or the input runs out.
+fn offer_retraction(game: &mut GameState, frontend: &mut dyn Frontend, input: &mut dyn Input, insist: bool) -> bool {
+    let retractable: Vec<usize> = (0..game.history().len()).rev()
+        .filter(|&index| {
+            let mut without = game.clone();
+            without.retract(index);
+            without.status() != Impossible
+        })
+        .collect();
+    if retractable.is_empty() {
+        return false;
+    }
+    let choices: Vec<String> = retractable.iter().map(|index| index.to_string()).collect();
+    loop {
+        frontend.say(&format!(
+            "Type the number of a question to take back its answer ({}){}",
+            choices.join(", "),
+            if insist { ", as I can't go on until one is" } else { ", or anything else to stop" }
+        ));
+        let line = match input.read_line() {
+            Some(line) => line,
+            None => return false,
+        };
+        match line.trim().parse::<usize>() {
+            Ok(index) if retractable.contains(&index) => {
+                frontend.say(&format!("Took back the answer to {}", describe_question(game, index)));
+                game.retract(index);
+                return true;
+            }
+            _ if insist => {}
+            _ => return false,
+        }
+    }
+}
+
+/// How many questions there are to find the number in, if they are limited, which
+/// seed the opponent's random choices came from, if it makes any, and any other
+/// rules beyond the limits and lies.
+pub fn rules_announcements(game: &GameState, setup: &OpponentSetup) -> Vec<String> {
+    let mut lines = vec![];
+    if let (true, Some(seed)) = (setup.randomised(game), setup.seed) {
+        lines.push(seed_announcement(seed));
+    }
+    if let Some(prior) = game.prior() {
+        lines.push(format!("Numbers are weighted {}", prior.describe()));
+    }
+    if let Some(max_questions) = game.max_questions() {
+        lines.push(format!("The number must be found within {} questions", max_questions));
+    }
+    if let Some(width) = game.moving_target() {
+        lines.push(format!(
+            "The number may move up or down by one between questions, so corner it by narrowing it down to {} numbers in a row",
+            width
+        ));
+    }
+    if game.delay() > 0 {
+        lines.push(format!(
+            "Each answer only comes after {} more question(s), or type wait to hear the next one sooner, which uses up a question",
+            game.delay()
+        ));
+    }
+    if game.max_passes() > 0 {
+        lines.push(format!(
+            "The opponent may refuse to answer up to {} question(s), which tells you nothing but uses up the question",
+            game.max_passes()
+        ));
+    }
+    if game.final_guess() {
+        lines.push("Pinning the number down isn't enough: announce it to win, which uses up a question, and the opponent may lie to deny it".to_string());
+    }
+    lines
+}
+
+/// Tells the player how to replay the opponent's random choices.
+pub fn seed_announcement(seed: u64) -> String {
+    format!("The opponent's choices come from seed {}, so --seed {} replays this game", seed, seed)
+}
+
+fn announce_rules(game: &GameState, setup: &OpponentSetup, frontend: &mut dyn Frontend) {
+    for line in rules_announcements(game, setup) {
+        frontend.say(&line);
+    }
+}
+
+// Plays `game` out, driving a Play with the player's input from `frontend` and
+// the answers from `opponent`.
+fn run_game(
+    game: GameState,
+    opponent: &mut dyn Opponent,
+    setup: &OpponentSetup,
+    frontend: &mut dyn Frontend,
+    style: Style,
+    show_state: bool,
+) -> GameState {
+    let solver = make_solver(&game, setup);
+    let mut play = Play::new(game, solver, style, show_state);
+    frontend.start(play.game());
+    let mut effects = play.start(setup);
+    loop {
+        let mut asked = None;
+        for effect in effects {
+            match effect {
+                Effect::Say(text) => frontend.say(&text),
+                Effect::Answered => frontend.answered(play.game()),
+                Effect::ShowState => frontend.show_state(play.game()),
+                Effect::Ask(question) => asked = Some(question),
+                Effect::Save(path) => match save_game(&path, play.game(), setup) {
+                    Ok(()) => frontend.say(&format!("Saved to {}", path)),
+                    Err(err) => frontend.say(&format!("Couldn't save: {}", err)),
+                },
+                Effect::Over => return play.into_game(),
+            }
+        }
+        effects = match asked {
+            Some(question) => {
+                let response = opponent.respond(play.game(), &question);
+                play.respond(response)
+            }
+            None => match frontend.prompt(play.game()) {
+                Some(input) => play.input(&input),
+                None => play.command(PlayCommand::Quit),
+            },
+        };
+    }
+}
+
+/// Has `guesser` find the number, with `input` choosing which answer to take back
+/// when they contradict each other.
+#[allow(clippy::too_many_arguments)]
+pub fn run_solver(
+    mut game: GameState,
+    guesser: &mut dyn Guesser,
+    opponent: &mut dyn Opponent,
+    setup: &OpponentSetup,
+    frontend: &mut dyn Frontend,
+    input: &mut dyn Input,
+    style: Style,
+    show_state: bool,
+) -> GameState {
+    frontend.start(&game);
+    frontend.say(&format!("I'll find your number, with {}, {}", game.describe(), game.describe_range()));
+    announce_rules(&game, setup, frontend);
+    loop {
+        match game.status() {
+            Ongoing => {}
+            // A person answering may just have slipped, so is held to fixing it
+            Impossible if setup.kind == OpponentKind::Human => {
+                audit(&game, frontend);
+                if offer_retraction(&mut game, frontend, input, true) {
+                    continue;
+                }
+                break;
+            }
+            Impossible => {
+                explain_impossible(&game, frontend);
+                if offer_retraction(&mut game, frontend, input, false) {
+                    continue;
+                }
+                break;
+            }
+            _ => break,
+        }
+        let guess = guesser.choose(&game);
+        let question = game.guess(guess);
+        frontend.say(&format!("{}: Is it {}?", game.history().len(), question.describe()));
+        let response = opponent.respond(&game, &question);
+        game.ask(question, response).expect("Guessers only ask legal questions");
+        frontend.answered(&game);
+        if show_state {
+            frontend.show_state(&game);
+        }
+    }
+    match game.status() {
+        Finished(answer) => frontend.say(&format!(
+            "{}, found in {} questions",
+            style.answer(&format!("It's {}", answer)),
+            game.history().len()
+        )),
+        Impossible => frontend.say("With no number left that fits, I give up"),
+        Lost => frontend.say(&format!(
+            "Out of questions after {}, with {} still possible",
+            game.history().len(),
+            game.candidates()
+        )),
+        Ongoing => unreachable!(),
+    }
+    game
+}
+
+/// Everything said once the game is over: the opponent's secrets and where the
+/// transcript went.
+pub fn wrap_up(game: &GameState, opponent: &dyn Opponent, record: Option<&str>, frontend: &mut dyn Frontend) {
+    if let Some(reveal) = opponent.reveal() {
+        frontend.say(&reveal);
+    }
+    if let Some(path) = record {
+        match fs::write(path, game.to_transcript()) {
+            Ok(()) => frontend.say(&format!("Recorded the game in {}, replay it with replay {}", path, path)),
+            Err(err) => frontend.say(&format!("Couldn't record the game: {}", err)),
+        }
+    }
+    frontend.finish(game);
+}
+
+fn make_frontend(game: &GameState, output: Output, style: Style) -> Box<dyn Frontend> {
+    match output {
+        Output::Text => Box::new(Lines { style, input: Box::new(Terminal) }),
+        Output::Tui => Box::new(Tui::new(Solver::new(game.max_lies()).with_variant(game.variant()), style, Box::new(Terminal))),
+        Output::Json => Box::new(JsonLines::new(Solver::new(game.max_lies()).with_variant(game.variant()), Box::new(Terminal))),
+        Output::Gui => unreachable!("Games in the browser are played by gui::play_gui"),
+    }
+}
+
+/// The player asks the questions and the setup's opponent answers, shown as
+/// `output` says, with the transcript written to `record` at the end.
+pub fn play_game(
+    game: GameState,
+    mut setup: OpponentSetup,
+    record: Option<&str>,
+    output: Output,
+    style: Style,
+    show_state: bool,
+) -> Result<(), String> {
+    if output == Output::Gui {
+        return gui::play_gui(game, setup, record.map(str::to_string));
+    }
+    setup.resolve(&game)?;
+    let mut opponent = make_opponent(&game, &setup);
+    let mut frontend = make_frontend(&game, output, style);
+    let game = run_game(game, &mut *opponent, &setup, &mut *frontend, style, show_state);
+    wrap_up(&game, &*opponent, record, &mut *frontend);
+    Ok(())
+}
+
+/// The computer asks the questions, and the chosen opponent (by default, you) answers.
+pub fn solve_game(
+    game: GameState,
+    mut setup: OpponentSetup,
+    guesser_name: &str,
+    record: Option<&str>,
+    output: Output,
+    style: Style,
+    show_state: bool,
+) -> Result<(), String> {
+    setup.resolve(&game)?;
+    let mut opponent = make_opponent(&game, &setup);
+    let mut guesser = make_guesser(&game, &setup, guesser_name);
+    let mut frontend = make_frontend(&game, output, style);
+    let game = run_solver(game, &mut *guesser, &mut *opponent, &setup, &mut *frontend, &mut Terminal, style, show_state);
+    if let Some(ref cost) = setup.cost {
+        frontend.say(&format!("The questions cost {:.2} in all", cost.total(game.history())));
+    }
+    wrap_up(&game, &*opponent, record, &mut *frontend);
+    Ok(())
+}
+
+// Which numbers each secret may still be.
+fn show_multi_state(game: &MultiGame, frontend: &mut dyn Frontend) {
+    for target in 0..game.targets() {
+        match game.view(target) {
+            Some(view) => frontend.say(&format!("    number {} could be {}", target, view.candidates())),
+            None => frontend.say(&format!("    number {} could be nothing", target)),
+        }
+    }
+}
+
+// Guessing several secrets at once, each question about one of them.
+fn run_multi(
+    mut game: MultiGame,
+    opponent: &mut dyn MultiOpponent,
+    setup: &OpponentSetup,
+    frontend: &mut dyn Frontend,
+    input: &mut dyn Input,
+    style: Style,
+    show_state: bool,
+) -> MultiGame {
+    let rules = game.target(0).clone();
+    frontend.say(&format!(
+        "Guess {} numbers, with {} between them, each {}",
+        game.targets(),
+        rules.describe(),
+        rules.describe_range()
+    ));
+    announce_rules(&rules, setup, frontend);
+    frontend.say("Type 0:50 to ask whether number 0 is less than 50, or help for the other commands");
+    let mut resigned = false;
+    while game.found().is_none() && !game.is_impossible() {
+        frontend.say(&format!("{}: Which number do you want to ask about, and what? (help lists the commands)", game.history().len()));
+        let command = match input.read_line().map(|line| MultiCommand::parse(&line)) {
+            Some(Ok(command)) => command,
+            Some(Err(err)) => {
+                frontend.say(&err);
+                continue;
+            }
+            None => MultiCommand::Quit,
+        };
+        match command {
+            MultiCommand::Ask(target, question) => {
+                let question = match question {
+                    Question::Less(guess) if target < game.targets() => game.target(target).guess(guess),
+                    question => question,
+                };
+                if let Err(err) = game.check_question(target, &question) {
+                    frontend.say(&err.to_string());
+                    continue;
+                }
+                let response = opponent.respond(&game, target, &question);
+                game.ask(target, question.clone(), response).expect("Already checked question was legal");
+                frontend.say(&style.response(response, &format!("Number {}: {}", target, question.describe_answer(response))));
+                if show_state {
+                    show_multi_state(&game, frontend);
+                }
+            }
+            MultiCommand::Status => show_multi_state(&game, frontend),
+            MultiCommand::History if game.history().is_empty() => frontend.say("No questions yet"),
+            MultiCommand::History => {
+                for (index, (target, question, response)) in game.history().into_iter().enumerate() {
+                    frontend.say(&format!(
+                        "    question {}, number {} {}",
+                        index,
+                        target,
+                        question.describe_answer(response).to_lowercase()
+                    ));
+                }
+            }
+            MultiCommand::Undo => match game.undo() {
+                Some((target, question, _)) => frontend.say(&format!(
+                    "Took back question {}, whether number {} is {}",
+                    game.history().len(),
+                    target,
+                    question.describe()
+                )),
+                None => frontend.say("There's nothing to undo"),
+            },
+            MultiCommand::Resign => {
+                resigned = true;
+                break;
+            }
+            MultiCommand::Quit => {
+                frontend.say(&format!("Left the game unfinished after {} questions", game.history().len()));
+                break;
+            }
+            MultiCommand::Help => frontend.say(MULTI_HELP),
+        }
+    }
+    if resigned {
+        frontend.say(&format!("You resigned after {} questions", game.history().len()));
+        show_multi_state(&game, frontend);
+    }
+    if let Some(answers) = game.found() {
+        let answers: Vec<String> = answers.iter().map(|answer| answer.to_string()).collect();
+        frontend.say(&format!("You got them all in {} guesses", game.history().len()));
+        frontend.say(&style.answer(&format!("They were {}", answers.join(", "))));
+    } else if game.is_impossible() {
+        frontend.say(&format!("Those answers contradict each other, even allowing {} between them", rules.describe()));
+    }
+    game
+}
+
+/// The player guesses `targets` secrets at once.
+pub fn multi_game(game: GameState, mut setup: OpponentSetup, targets: usize, style: Style, show_state: bool) -> Result<(), String> {
+    setup.resolve(&game)?;
+    let mut opponent: Box<dyn MultiOpponent> = match setup.kind {
+        OpponentKind::Honest => {
+            // The same draws as the single secret the setup was resolved with, so
+            // number 0's secret is that one
+            let mut rng = Rng::new(setup.seed.expect("Opponent setup is resolved"));
+            Box::new(MultiHonest {
+                secrets: (0..targets).map(|_| draw_secret(&game, &mut rng)).collect(),
+                lie_positions: setup.lie_positions.clone().expect("Opponent setup is resolved"),
+            })
+        }
+        _ => Box::new(MultiAdversary {
+            value: value_function(&setup.value).expect("Value function names are checked when parsed"),
+        }),
+    };
+    let mut frontend = Lines { style, input: Box::new(Terminal) };
+    run_multi(MultiGame::new(game, targets), &mut *opponent, &setup, &mut frontend, &mut Terminal, style, show_state);
+    if let Some(reveal) = opponent.reveal() {
+        frontend.say(&reveal);
+    }
+    Ok(())
+}
+
+// Where a real secret could still be, as intervals.
+fn show_real_state(continuum: &Continuum, game: &GameState, frontend: &mut dyn Frontend) {
+    let candidates = game.candidates();
+    let intervals: Vec<String> = candidates.ranges()
+        .iter()
+        .map(|&range| {
+            let (lower, higher) = continuum.interval(range);
+            format!("[{}, {})", continuum.format(lower), continuum.format(higher))
+        })
+        .collect();
+    frontend.say(&format!("    it could be in {}, {} wide in all", intervals.join(" or "), continuum.format(continuum.width(game))));
+}
+
+// A question about a real, as in "less than 0.35".
+fn real_question(continuum: &Continuum, question: &Question) -> String {
+    match *question {
+        Question::Less(boundary) => format!("less than {}", continuum.format(continuum.at(boundary))),
+        ref question => question.describe(),
+    }
+}
+
+// The same with its answer, as in "less than 0.35: yes".
+fn real_answer(continuum: &Continuum, question: &Question, response: Response) -> String {
+    format!("{}: {}", real_question(continuum, question), answer_word(question, response).to_lowercase())
+}
+
+// Guessing a real number to within epsilon.
+#[allow(clippy::too_many_arguments)]
+fn run_real(
+    continuum: &Continuum,
+    mut game: GameState,
+    opponent: &mut dyn Opponent,
+    value: &ValueFunction,
+    frontend: &mut dyn Frontend,
+    input: &mut dyn Input,
+    style: Style,
+    show_state: bool,
+) -> GameState {
+    frontend.say(&format!(
+        "Guess the number, at least 0 and less than {}, to within {}, with {}",
+        continuum.limit(),
+        continuum.epsilon(),
+        game.describe()
+    ));
+    frontend.say("Type a number such as 0.35 to ask whether the secret is less than it, or help for the other commands");
+    let mut resigned = false;
+    while game.status() == Ongoing {
+        frontend.say(&format!("{}: What do you want to ask? (help lists the commands)", game.history().len()));
+        let command = match input.read_line().map(|line| RealCommand::parse(&line)) {
+            Some(Ok(command)) => command,
+            Some(Err(err)) => {
+                frontend.say(&err);
+                continue;
+            }
+            None => RealCommand::Quit,
+        };
+        match command {
+            RealCommand::Ask(threshold) => {
+                let question = continuum.question(threshold);
+                if game.check_question(&question).is_err() || question == Question::Less(0) {
+                    frontend.say("Every number is on one side of that, so asking tells you nothing");
+                    continue;
+                }
+                let response = opponent.respond(&game, &question);
+                game.ask(question.clone(), response).expect("Already checked question was legal");
+                frontend.say(&style.response(response, &real_answer(continuum, &question, response)));
+                if show_state {
+                    show_real_state(continuum, &game, frontend);
+                }
+            }
+            RealCommand::Hint => {
+                let question = game.guess(Greedy { value: value.clone() }.choose(&game));
+                frontend.say(&format!("Ask whether it's {}", real_question(continuum, &question)));
+            }
+            RealCommand::Status => show_real_state(continuum, &game, frontend),
+            RealCommand::History if game.history().is_empty() => frontend.say("No questions yet"),
+            RealCommand::History => {
+                for (index, (question, response)) in game.history().iter().enumerate() {
+                    frontend.say(&format!("    question {}, {}", index, real_answer(continuum, question, *response)));
+                }
+            }
+            RealCommand::Undo => match game.undo() {
+                Some((question, response)) => frontend.say(&format!(
+                    "Took back question {}, {}",
+                    game.history().len(),
+                    real_answer(continuum, &question, response)
+                )),
+                None => frontend.say("There's nothing to undo"),
+            },
+            RealCommand::Resign => {
+                resigned = true;
+                break;
+            }
+            RealCommand::Quit => {
+                frontend.say(&format!("Left the game unfinished after {} questions", game.history().len()));
+                break;
+            }
+            RealCommand::Help => frontend.say(REAL_HELP),
+        }
+    }
+    if resigned {
+        frontend.say(&format!("You resigned after {} questions", game.history().len()));
+        show_real_state(continuum, &game, frontend);
+    }
+    match game.status() {
+        Finished(cell) => {
+            let (lower, higher) = continuum.interval(Range::new(cell, cell + 1));
+            frontend.say(&format!("You found it in {} questions", game.history().len()));
+            frontend.say(&style.answer(&format!("It is in [{}, {})", continuum.format(lower), continuum.format(higher))));
+        }
+        Impossible => frontend.say(&format!("Those answers contradict each other, even allowing {}", game.describe())),
+        _ => {}
+    }
+    game
+}
+
+/// The player guesses a real number in `continuum`.
+pub fn real_game(continuum: Continuum, game: GameState, mut setup: OpponentSetup, style: Style, show_state: bool) -> Result<(), String> {
+    setup.resolve(&game)?;
+    let mut opponent = make_opponent(&game, &setup);
+    let value = value_function(&setup.value).expect("Value function names are checked when parsed");
+    let mut frontend = Lines { style, input: Box::new(Terminal) };
+    run_real(&continuum, game, &mut *opponent, &value, &mut frontend, &mut Terminal, style, show_state);
+    // Opponents reveal the cell their secret is in, so it is given as an interval here
+    if setup.kind != OpponentKind::Adversarial {
+        let cell = setup.secret.expect("Opponent setup is resolved");
+        let (lower, higher) = continuum.interval(Range::new(cell, cell + 1));
+        frontend.say(&format!("The secret was in [{}, {})", continuum.format(lower), continuum.format(higher)));
+    }
+    Ok(())
+}
+
+// The points still possible, as rectangles for each number of lies.
+fn show_grid_state(grid: &Grid, game: &GameState, frontend: &mut dyn Frontend) {
+    for (lies, rectangles) in grid.possibilities(game).iter().enumerate() {
+        if rectangles.is_empty() {
+            continue;
+        }
+        let shown: Vec<String> = rectangles.iter().take(8).map(|rectangle| rectangle.to_string()).collect();
+        let more = match rectangles.len() {
+            count if count > 8 => format!(" and {} more", count - 8),
+            _ => String::new(),
+        };
+        frontend.say(&format!("    with {} lie(s): {}{}", lies, shown.join("; "), more));
+    }
+}
+
+// A question about the grid with its answer, as in "x < 3: yes".
+fn grid_answer(grid: &Grid, question: &Question, response: Response) -> String {
+    format!("{}: {}", grid.describe(question), answer_word(question, response).to_lowercase())
+}
+
+// Guessing a point on a grid, one coordinate at a time.
+#[allow(clippy::too_many_arguments)]
+fn run_grid(
+    grid: &Grid,
+    mut game: GameState,
+    opponent: &mut dyn Opponent,
+    value: &ValueFunction,
+    frontend: &mut dyn Frontend,
+    input: &mut dyn Input,
+    style: Style,
+    show_state: bool,
+) -> GameState {
+    frontend.say(&format!(
+        "Guess the point on a {} grid, x from 0 to {} and y from 0 to {}, with {}",
+        grid,
+        grid.width() - 1,
+        grid.height() - 1,
+        game.describe()
+    ));
+    frontend.say("Type x<3 to ask whether x is less than 3, y<3 to ask about y, or help for the other commands");
+    let mut resigned = false;
+    while game.status() == Ongoing {
+        frontend.say(&format!("{}: What do you want to ask? (help lists the commands)", game.history().len()));
+        let command = match input.read_line().map(|line| GridCommand::parse(&line)) {
+            Some(Ok(command)) => command,
+            Some(Err(err)) => {
+                frontend.say(&err);
+                continue;
+            }
+            None => GridCommand::Quit,
+        };
+        match command {
+            GridCommand::Ask(axis, threshold) if !grid.splits(axis, threshold) => {
+                frontend.say("Every point is on one side of that, so asking tells you nothing");
+            }
+            GridCommand::Ask(axis, threshold) => {
+                let question = grid.question(axis, threshold);
+                let response = opponent.respond(&game, &question);
+                game.ask(question.clone(), response).expect("Threshold questions are legal");
+                frontend.say(&style.response(response, &grid_answer(grid, &question, response)));
+                if show_state {
+                    show_grid_state(grid, &game, frontend);
+                }
+            }
+            GridCommand::Hint => {
+                let (axis, threshold) = grid.best_question(&game, value);
+                let question = grid.question(axis, threshold);
+                frontend.say(&format!("Ask whether {}", grid.describe(&question)));
+            }
+            GridCommand::Status => show_grid_state(grid, &game, frontend),
+            GridCommand::History if game.history().is_empty() => frontend.say("No questions yet"),
+            GridCommand::History => {
+                for (index, (question, response)) in game.history().iter().enumerate() {
+                    frontend.say(&format!("    question {}, {}", index, grid_answer(grid, question, *response)));
+                }
+            }
+            GridCommand::Undo => match game.undo() {
+                Some((question, response)) => frontend.say(&format!(
+                    "Took back question {}, {}",
+                    game.history().len(),
+                    grid_answer(grid, &question, response)
+                )),
+                None => frontend.say("There's nothing to undo"),
+            },
+            GridCommand::Resign => {
+                resigned = true;
+                break;
+            }
+            GridCommand::Quit => {
+                frontend.say(&format!("Left the game unfinished after {} questions", game.history().len()));
+                break;
+            }
+            GridCommand::Help => frontend.say(GRID_HELP),
+        }
+    }
+    if resigned {
+        frontend.say(&format!("You resigned after {} questions", game.history().len()));
+        show_grid_state(grid, &game, frontend);
+    }
+    match game.status() {
+        Finished(found) => {
+            let (x, y) = grid.point(found);
+            frontend.say(&format!("You found it in {} questions", game.history().len()));
+            frontend.say(&style.answer(&format!("It is ({}, {})", x, y)));
+        }
+        Impossible => frontend.say(&format!("Those answers contradict each other, even allowing {}", game.describe())),
+        _ => {}
+    }
+    game
+}
+
+/// The player guesses a point on `grid`.
+pub fn grid_game(grid: Grid, game: GameState, mut setup: OpponentSetup, style: Style, show_state: bool) -> Result<(), String> {
+    setup.resolve(&game)?;
+    let mut opponent = make_opponent(&game, &setup);
+    let value = value_function(&setup.value).expect("Value function names are checked when parsed");
+    let mut frontend = Lines { style, input: Box::new(Terminal) };
+    run_grid(&grid, game, &mut *opponent, &value, &mut frontend, &mut Terminal, style, show_state);
+    // Opponents reveal their secrets as numbers, so they are given as points here
+    if setup.kind != OpponentKind::Adversarial {
+        let (x, y) = grid.point(setup.secret.expect("Opponent setup is resolved"));
+        frontend.say(&format!("The point was ({}, {})", x, y));
+    }
+    Ok(())
+}
+
+// Says whether a batch of questions always finds the number.
+fn check_batch(game: &GameState, questions: &[Question]) -> String {
+    match confusion(game, questions) {
+        None => format!("These {} questions always find the number", questions.len()),
+        Some(Confusion { first, second, distance: 0 }) => {
+            format!("These questions can't tell {} from {}, which they answer alike", first, second)
+        }
+        Some(Confusion { first, second, distance }) => format!(
+            "These questions can't always tell {} from {}, which they answer differently only {} time(s)",
+            first, second, distance
+        ),
+    }
+}
+
+// The player writes every question down before the opponent answers any of them,
+// then hears what the answers decode to.
+fn run_batch(
+    mut game: GameState,
+    opponent: &mut dyn Opponent,
+    setup: &OpponentSetup,
+    frontend: &mut dyn Frontend,
+    input: &mut dyn Input,
+    style: Style,
+) -> GameState {
+    frontend.say(&format!("Guess the number, with {}, {}", game.describe(), game.describe_range()));
+    announce_rules(&game, setup, frontend);
+    frontend.say("Type your questions one per line, and done to hear all the answers at once, or help for the other commands");
+    let mut questions: Vec<Question> = vec![];
+    loop {
+        frontend.say(&format!("{}: What do you want to ask? (done once the batch is ready)", questions.len()));
+        let command = match input.read_line().map(|line| BatchCommand::parse(&line)) {
+            Some(Ok(command)) => command,
+            Some(Err(err)) => {
+                frontend.say(&err);
+                continue;
+            }
+            None => BatchCommand::Quit,
+        };
+        match command {
+            BatchCommand::Ask(question) => {
+                let question = match question {
+                    Question::Less(guess) => game.guess(guess),
+                    question => question,
+                };
+                match game.check_question(&question) {
+                    Ok(()) => questions.push(question),
+                    Err(err) => frontend.say(&err.to_string()),
+                }
+            }
+            BatchCommand::Check => frontend.say(&check_batch(&game, &questions)),
+            BatchCommand::List if questions.is_empty() => frontend.say("No questions yet"),
+            BatchCommand::List => {
+                for (index, question) in questions.iter().enumerate() {
+                    frontend.say(&format!("    question {}, whether it's {}", index, question.describe()));
+                }
+            }
+            BatchCommand::Undo => match questions.pop() {
+                Some(question) => frontend.say(&format!("Took back question {}, whether it's {}", questions.len(), question.describe())),
+                None => frontend.say("There's nothing to undo"),
+            },
+            BatchCommand::Done => break,
+            BatchCommand::Quit => {
+                frontend.say(&format!("Left with {} questions unanswered", questions.len()));
+                return game;
+            }
+            BatchCommand::Help => frontend.say(BATCH_HELP),
+        }
+    }
+    frontend.say(&check_batch(&game, &questions));
+    for question in questions {
+        frontend.say(&format!("{}: Is it {}?", game.history().len(), question.describe()));
+        let response = opponent.respond(&game, &question);
+        game.ask(question, response).expect("Already checked question was legal");
+        frontend.answered(&game);
+    }
+    match game.status() {
+        Finished(answer) => {
+            frontend.say(&format!("The answers decode to {}", answer));
+            frontend.say(&style.answer(&format!("It was {}", answer)));
+        }
+        Impossible => explain_impossible(&game, frontend),
+        _ => frontend.say(&format!("The answers leave {} possible", game.candidates())),
+    }
+    game
+}
+
+/// The player writes every question down before any is answered.
+pub fn batch_game(game: GameState, mut setup: OpponentSetup, record: Option<&str>, style: Style) -> Result<(), String> {
+    setup.resolve(&game)?;
+    let mut opponent = make_opponent(&game, &setup);
+    let mut frontend = Lines { style, input: Box::new(Terminal) };
+    let game = run_batch(game, &mut *opponent, &setup, &mut frontend, &mut Terminal, style);
+    wrap_up(&game, &*opponent, record, &mut frontend);
+    Ok(())
+}
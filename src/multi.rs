@@ -0,0 +1,182 @@
+//! Guessing several secret numbers at once, with one budget of lies shared
+//! between them.
+//!
+//! Each question is about one of the targets, and each target keeps its own
+//! [`GameState`], allowing the whole budget of lies. What joins them is that the
+//! lies must add up to no more than the budget, so a target is left only what
+//! the others can't do without: its view is its game with the budget less the
+//! fewest lies each other target's answers need.
+
+use crate::game::{Count, GameError, GameResult, GameState, Number, Question, Response};
+use crate::value::ValueFunction;
+
+/// A game with `targets` secrets under the same rules, where the opponent may lie
+/// up to `max_lies` times in all.
+#[derive(Clone)]
+pub struct MultiGame {
+    targets: Vec<GameState>,
+    // Which target each question so far was about, in the order they were asked.
+    order: Vec<usize>,
+    max_lies: usize,
+}
+
+impl MultiGame {
+    /// `targets` secrets, each following the rules of `game`, which should have
+    /// no questions asked yet, a fixed number of lies, and no limit on the
+    /// number of questions.
+    pub fn new(game: GameState, targets: usize) -> MultiGame {
+        MultiGame {
+            max_lies: game.max_lies(),
+            targets: vec![game; targets],
+            order: vec![],
+        }
+    }
+    /// How many secrets there are.
+    pub fn targets(&self) -> usize {
+        self.targets.len()
+    }
+    pub fn max_lies(&self) -> usize {
+        self.max_lies
+    }
+    /// Target `target`'s questions and answers, allowing every lie in the budget.
+    pub fn target(&self, target: usize) -> &GameState {
+        &self.targets[target]
+    }
+    /// Every question so far in the order asked, with the target it was about.
+    pub fn history(&self) -> Vec<(usize, &Question, Response)> {
+        let mut asked = vec![0; self.targets.len()];
+        self.order.iter()
+            .map(|&target| {
+                let (ref question, response) = self.targets[target].history()[asked[target]];
+                asked[target] += 1;
+                (target, question, response)
+            })
+            .collect()
+    }
+    /// Whether `question` may be asked about `target`.
+    pub fn check_question(&self, target: usize, question: &Question) -> Result<(), GameError> {
+        match self.targets.get(target) {
+            Some(game) => game.check_question(question),
+            None => Err(GameError::NoSuchTarget { target, targets: self.targets.len() }),
+        }
+    }
+    /// Records the answer to a question about `target`.
+    pub fn ask(&mut self, target: usize, question: Question, response: Response) -> Result<(), GameError> {
+        self.check_question(target, &question)?;
+        self.targets[target].ask(question, response)?;
+        self.order.push(target);
+        Ok(())
+    }
+    /// Takes back the last question, returning it with its target and answer.
+    pub fn undo(&mut self) -> Option<(usize, Question, Response)> {
+        let target = self.order.pop()?;
+        let (question, response) = self.targets[target].undo()?;
+        Some((target, question, response))
+    }
+    /// The question number, counting every target's questions, of question `index`
+    /// about `target`, for reporting which questions a hypothesis lies on.
+    pub fn overall_index(&self, target: usize, index: usize) -> usize {
+        self.order.iter()
+            .enumerate()
+            .filter(|&(_, &asked)| asked == target)
+            .nth(index)
+            .map_or(self.order.len(), |(overall, _)| overall)
+    }
+    // The fewest lies that `target`'s answers need, or one more than the budget if
+    // no number fits them.
+    fn fewest_lies(&self, target: usize) -> usize {
+        self.targets[target].hypotheses()
+            .filter(|&(range, _)| self.targets[target].count(range) > 0)
+            .map(|(_, lies)| lies.len())
+            .min()
+            .unwrap_or(self.max_lies + 1)
+    }
+    /// Target `target`'s game with only the lies the other targets leave it, or
+    /// None if their answers already need more lies than there may be.
+    pub fn view(&self, target: usize) -> Option<GameState> {
+        let others: usize = (0..self.targets.len())
+            .filter(|&other| other != target)
+            .map(|other| self.fewest_lies(other))
+            .sum();
+        let left = self.max_lies.checked_sub(others)?;
+        Some(self.targets[target].clone().with_max_lies(left))
+    }
+    /// How each target stands: finished once it is pinned down allowing for the
+    /// lies the others may need.
+    pub fn status(&self) -> Vec<GameResult> {
+        (0..self.targets.len())
+            .map(|target| self.view(target).map_or(GameResult::Impossible, |view| view.status()))
+            .collect()
+    }
+    /// Every secret, once all of them are pinned down.
+    pub fn found(&self) -> Option<Vec<Number>> {
+        self.status().into_iter()
+            .map(|status| match status {
+                GameResult::Finished(answer) => Some(answer),
+                _ => None,
+            })
+            .collect()
+    }
+    /// Whether no numbers fit every answer, however the lies are shared out.
+    pub fn is_impossible(&self) -> bool {
+        self.status().contains(&GameResult::Impossible)
+    }
+}
+
+/// Answers questions about any of the targets.
+pub trait MultiOpponent {
+    /// The answer to `question` about `target`, given the game so far.
+    fn respond(&mut self, game: &MultiGame, target: usize, question: &Question) -> Response;
+    /// Whatever the opponent committed to in advance, shown once the game is over.
+    fn reveal(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Gives whichever answer leaves the largest value, added up over every target's
+/// view, so an answer that spends a lie on one target counts against the others.
+pub struct MultiAdversary {
+    pub value: ValueFunction,
+}
+
+impl MultiOpponent for MultiAdversary {
+    fn respond(&mut self, game: &MultiGame, target: usize, question: &Question) -> Response {
+        let mut best = (Response::Yes, 0);
+        for (index, &response) in question.responses().iter().enumerate() {
+            let mut next = game.clone();
+            next.ask(target, question.clone(), response).unwrap();
+            let remaining = (0..next.targets())
+                .map(|target| next.view(target).map_or(0, |view| self.value.value(&view)))
+                .fold(0, Count::saturating_add);
+            if index == 0 || remaining > best.1 {
+                best = (response, remaining);
+            }
+        }
+        best.0
+    }
+}
+
+/// Commits to every secret and to which questions it will lie on, counting every
+/// target's questions, before the game starts.
+pub struct MultiHonest {
+    pub secrets: Vec<Number>,
+    pub lie_positions: Vec<usize>,
+}
+
+impl MultiOpponent for MultiHonest {
+    fn respond(&mut self, game: &MultiGame, target: usize, question: &Question) -> Response {
+        let truth = question.truth(self.secrets[target]);
+        match question.lies(truth).next() {
+            Some(lie) if self.lie_positions.contains(&game.order.len()) && game.target(target).variant().allows_lie(truth) => lie,
+            _ => truth,
+        }
+    }
+    fn reveal(&self) -> Option<String> {
+        let secrets: Vec<String> = self.secrets.iter().map(|secret| secret.to_string()).collect();
+        Some(format!(
+            "The secrets were {}\nI planned to lie on question(s) {:?}",
+            secrets.join(", "),
+            self.lie_positions
+        ))
+    }
+}
@@ -0,0 +1,197 @@
+use std::cmp::{max, min};
+
+use crate::expression::Script;
+use crate::game::Response::*;
+use crate::game::{Count, GameResult, GameState, Number, Question, Response, Variant};
+use crate::rng::Rng;
+use crate::value::ValueFunction;
+
+/// Answers the guesser's questions.
+pub trait Opponent {
+    /// The answer to `question`, given the game so far. In a game with a delay,
+    /// `question` is the oldest of [`GameState::pending`], and the rest of them
+    /// have been asked after it.
+    fn respond(&mut self, game: &GameState, question: &Question) -> Response;
+    /// Whatever the opponent committed to in advance, shown once the game is over.
+    fn reveal(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Never settles on a number, and gives whichever answer leaves the largest value,
+/// the first of them in [`GameState::responses`] order if several tie. A pass
+/// leaves the value as it was, so it passes while it can. In a game
+/// with a delay, the questions asked since `question` were chosen without its
+/// answer, and will be answered by the adversary too, so each answer is judged by
+/// the largest value left after answering them as well.
+pub struct Adversary {
+    pub value: ValueFunction,
+}
+
+// The largest value the adversary can leave after answering `later` in turn.
+fn committed_value(game: &GameState, later: &[Question], value: &ValueFunction) -> Count {
+    match later.split_first() {
+        None => value.value(game),
+        Some((question, rest)) => game.responses(question).into_iter()
+            .map(|response| {
+                let mut next = game.clone();
+                next.ask(question.clone(), response).unwrap();
+                committed_value(&next, rest, value)
+            })
+            .max()
+            .unwrap_or(0),
+    }
+}
+
+impl Opponent for Adversary {
+    fn respond(&mut self, game: &GameState, question: &Question) -> Response {
+        let later: Vec<Question> = game.pending().skip(1).cloned().collect();
+        let mut best = (Yes, 0);
+        for (index, response) in game.responses(question).into_iter().enumerate() {
+            let mut next = game.clone();
+            next.ask(question.clone(), response).unwrap();
+            // Values count the numbers a lie in the last answer would leave, but
+            // in truthful-last games that answer may end the game, and in
+            // final-guess games confirming an announcement does
+            let remaining: Count = match next.status() {
+                GameResult::Finished(_) if game.variant == Variant::TruthfulLast || game.final_guess => 0,
+                _ => committed_value(&next, &later, &self.value),
+            };
+            if index == 0 || remaining > best.1 {
+                best = (response, remaining);
+            }
+        }
+        best.0
+    }
+}
+
+/// Answers guesses as its [`Script`] says, and anything else as `fallback` does.
+/// An answer no number fits would end the game in a contradiction, so it gives the
+/// other instead.
+pub struct Scripted {
+    pub script: Script,
+    pub fallback: Adversary,
+}
+
+impl Opponent for Scripted {
+    fn respond(&mut self, game: &GameState, question: &Question) -> Response {
+        if let Question::Less(guess) = *question {
+            let (said, other) = if self.script.says_yes(game, guess) { (Yes, No) } else { (No, Yes) };
+            let mut next = game.clone();
+            return match next.ask(question.clone(), said) {
+                Ok(()) if next.status() != GameResult::Impossible => said,
+                _ => other,
+            };
+        }
+        self.fallback.respond(game, question)
+    }
+}
+
+// Whether `lie` may be told about `secret` in answer to `question`. There must be
+// a lie left, in directional games one left that way, and in truthful-last games
+// the answer that ends the game must be true, so a lie can't end it.
+fn may_tell(game: &GameState, question: &Question, secret: Number, lie: Response) -> bool {
+    let told: Vec<usize> = game.history.iter()
+        .enumerate()
+        .filter(|&(_, &(ref past_question, response))| past_question.is_lie(secret, response))
+        .map(|(index, _)| index)
+        .collect();
+    if !game.may_lie(&told, lie) {
+        return false;
+    }
+    if game.variant != Variant::TruthfulLast {
+        return true;
+    }
+    let mut next = game.clone();
+    next.ask(question.clone(), lie).is_ok() && !matches!(next.status(), GameResult::Finished(_))
+}
+
+/// Commits to the secret and to which questions it will lie on before the game starts.
+pub struct Honest {
+    pub secret: Number,
+    pub lie_positions: Vec<usize>,
+}
+
+impl Opponent for Honest {
+    fn respond(&mut self, game: &GameState, question: &Question) -> Response {
+        let truth = question.truth(self.secret);
+        // A planned lie the rules don't allow is told as the truth
+        let index = game.history.len();
+        match question.lies(truth).find(|&lie| may_tell(game, question, self.secret, lie)) {
+            Some(lie) if self.lie_positions.contains(&index) && game.variant.allows_lie(truth) && game.in_lie_window(index) => lie,
+            _ => truth,
+        }
+    }
+    fn reveal(&self) -> Option<String> {
+        Some(format!(
+            "The secret was {}\nI planned to lie on question(s) {:?}",
+            self.secret,
+            self.lie_positions
+        ))
+    }
+}
+
+/// Tells the truth about its secret, but spreads its lies uniformly at random over
+/// the first `horizon` questions, or the lie window if that is shorter, deciding
+/// as each question is asked. Its passes are spread over the first `horizon`
+/// questions in the same way.
+pub struct RandomLiar {
+    pub secret: Number,
+    pub horizon: usize,
+    pub rng: Rng,
+}
+
+impl Opponent for RandomLiar {
+    fn respond(&mut self, game: &GameState, question: &Question) -> Response {
+        let secret = self.secret;
+        let passes_left = game.passes_left();
+        let unpassed = max(self.horizon.saturating_sub(game.history.len()), 1);
+        if passes_left > 0 && self.rng.below(unpassed as u64) < passes_left as u64 {
+            return Pass;
+        }
+        let truth = question.truth(secret);
+        let lies_told = game.history.iter()
+            .filter(|&&(ref past_question, response)| past_question.is_lie(secret, response))
+            .count();
+        let lies_left = game.lies_left(lies_told);
+        let horizon = match game.lie_window {
+            Some(window) => min(window, self.horizon),
+            None => self.horizon,
+        };
+        let questions_left = max(horizon.saturating_sub(game.history.len()), 1);
+        if lies_left > 0 && game.variant.allows_lie(truth) && self.rng.below(questions_left as u64) < lies_left as u64 {
+            // Any wrong answer will do, and comparisons have two
+            let lies: Vec<Response> = question.lies(truth).filter(|&lie| may_tell(game, question, secret, lie)).collect();
+            match lies.len() {
+                0 => truth,
+                1 => lies[0],
+                count => lies[self.rng.below(count as u64) as usize],
+            }
+        } else {
+            truth
+        }
+    }
+    fn reveal(&self) -> Option<String> {
+        Some(format!("The secret was {}", self.secret))
+    }
+}
+
+/// Lies on each answer independently, for noisy games.
+pub struct NoisyLiar {
+    pub secret: Number,
+    pub lie_probability: f64,
+    pub rng: Rng,
+}
+
+impl Opponent for NoisyLiar {
+    fn respond(&mut self, _game: &GameState, question: &Question) -> Response {
+        let truth = question.truth(self.secret);
+        match question.lies(truth).next() {
+            Some(lie) if self.rng.chance(self.lie_probability) => lie,
+            _ => truth,
+        }
+    }
+    fn reveal(&self) -> Option<String> {
+        Some(format!("The secret was {}", self.secret))
+    }
+}
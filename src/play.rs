@@ -0,0 +1,204 @@
+//! The game of play as a state machine, with no input or output of its own: it is
+//! handed each line the player types and each answer the opponent gives, and
+//! replies with what should happen as a result. run_game drives it from the
+//! terminal, but anything that can show text and fetch answers can drive it too.
+
+use crate::commands::{PlayCommand, HELP};
+use crate::frontend::hint;
+use crate::game::GameResult::*;
+use crate::modes::{describe_question, impossible_explanation, rules_announcements};
+use crate::setup::OpponentSetup;
+use crate::style::Style;
+use crate::{GameState, Question, Response, Solver, Variant};
+
+pub enum Effect {
+    /// Something to tell the player
+    Say(String),
+    /// An answer was heard, and is the last in the game's history
+    Answered,
+    /// Show which numbers are still possible
+    ShowState,
+    /// The opponent must answer this question, which is passed back with respond
+    Ask(Question),
+    /// Save the game to this path, saying whether it worked
+    Save(String),
+    /// The game is over, and everything about it has been said
+    Over,
+}
+
+pub struct Play {
+    game: GameState,
+    solver: Solver,
+    style: Style,
+    show_state: bool,
+}
+
+impl Play {
+    pub fn new(game: GameState, solver: Solver, style: Style, show_state: bool) -> Play {
+        Play { game, solver, style, show_state }
+    }
+
+    pub fn game(&self) -> &GameState {
+        &self.game
+    }
+
+    pub fn into_game(self) -> GameState {
+        self.game
+    }
+
+    /// The introduction, before the first question.
+    pub fn start(&mut self, setup: &OpponentSetup) -> Vec<Effect> {
+        let game = &self.game;
+        let mut effects = vec![Effect::Say(format!("Guess the number, with {}, {}", game.describe(), game.describe_range()))];
+        effects.extend(rules_announcements(game, setup).into_iter().map(Effect::Say));
+        effects.push(Effect::Say(if game.variant() == Variant::ThreeWay {
+            "Type a number to ask whether the secret is less than, equal to or greater than it, or help for the other commands".to_string()
+        } else {
+            "Type a number to ask whether the secret is less than it, or help for the other commands".to_string()
+        }));
+        if game.status() != Ongoing {
+            effects.extend(self.finish(false));
+        }
+        effects
+    }
+
+    /// A line the player typed.
+    pub fn input(&mut self, input: &str) -> Vec<Effect> {
+        match PlayCommand::parse(input) {
+            Ok(command) => self.command(command),
+            Err(err) => vec![Effect::Say(err)],
+        }
+    }
+
+    /// The opponent's answer to the question last asked of it.
+    pub fn respond(&mut self, response: Response) -> Vec<Effect> {
+        self.game.answer(response).expect("Already checked guess was legal");
+        let mut effects = vec![Effect::Answered];
+        if self.show_state {
+            effects.push(Effect::ShowState);
+        }
+        self.hear_answers(effects)
+    }
+
+    // Asks for the next answer that is due, in the order the questions were asked,
+    // or once none are, says what follows from them.
+    fn hear_answers(&mut self, mut effects: Vec<Effect>) -> Vec<Effect> {
+        let game = &self.game;
+        if let Some(question) = game.due() {
+            effects.push(Effect::Ask(question.clone()));
+            return effects;
+        }
+        if let (true, Ongoing, Some(number)) = (game.final_guess(), game.status(), game.forced()) {
+            effects.push(Effect::Say(format!("It can only be {}, so type announce {} to win", number, number)));
+        }
+        if game.status() != Ongoing {
+            effects.extend(self.finish(false));
+        }
+        effects
+    }
+
+    pub fn command(&mut self, command: PlayCommand) -> Vec<Effect> {
+        let say = |text: String| vec![Effect::Say(text)];
+        let game = &mut self.game;
+        match command {
+            PlayCommand::Ask(question) => {
+                // A number on its own asks whatever a guess asks in this game
+                let question = match question {
+                    Question::Less(guess) => game.guess(guess),
+                    question => question,
+                };
+                match game.pose(question) {
+                    Ok(()) => self.hear_answers(vec![]),
+                    Err(err) => say(err.to_string()),
+                }
+            }
+            PlayCommand::Announce(number) if !game.final_guess() => {
+                say(format!("There's no need to announce the number in this game, but ={} asks whether it's {}", number, number))
+            }
+            PlayCommand::Announce(number) => match game.pose(Question::Equal(number)) {
+                Ok(()) => self.hear_answers(vec![]),
+                Err(err) => say(err.to_string()),
+            },
+            PlayCommand::Wait if game.pending().next().is_none() => say("No question is waiting for an answer".to_string()),
+            PlayCommand::Wait => {
+                game.wait();
+                self.hear_answers(vec![])
+            }
+            PlayCommand::Hint => hint(game, &mut self.solver).map_or(vec![], say),
+            PlayCommand::Status => vec![Effect::ShowState],
+            PlayCommand::History if game.asked() == 0 => say("No questions yet".to_string()),
+            PlayCommand::History => {
+                let mut effects: Vec<Effect> = (0..game.history().len())
+                    .map(|index| Effect::Say(format!("    {}", describe_question(game, index))))
+                    .collect();
+                for (index, question) in game.pending().enumerate() {
+                    effects.push(Effect::Say(format!(
+                        "    question {}, whether it's {}, not answered yet",
+                        game.history().len() + index,
+                        question.describe()
+                    )));
+                }
+                effects
+            }
+            PlayCommand::Undo if game.delay() > 0 => {
+                say("Can't undo with delayed answers, as later questions were asked without knowing them".to_string())
+            }
+            PlayCommand::Undo => match game.undo() {
+                Some((question, _)) => say(format!("Took back question {}, whether it's {}", game.history().len(), question.describe())),
+                None => say("There's nothing to undo".to_string()),
+            },
+            PlayCommand::Save(path) => vec![Effect::Save(path)],
+            PlayCommand::Resign => self.finish(true),
+            PlayCommand::Quit => {
+                let mut effects = say(format!("Left the game unfinished after {} questions", game.turns()));
+                effects.extend(self.finish(false));
+                effects
+            }
+            PlayCommand::Help => say(HELP.to_string()),
+        }
+    }
+
+    // Everything said once the game ends, whether by the number being found, the
+    // questions running out, or the player resigning or leaving.
+    fn finish(&mut self, resigned: bool) -> Vec<Effect> {
+        let game = &self.game;
+        let style = self.style;
+        let mut lines = vec![];
+        if resigned {
+            lines.push(format!("You resigned after {} questions", game.turns()));
+            lines.push(format!("It could still have been {}", game.candidates()));
+        }
+        if let (Finished(answer), Some(lie_probability)) = (game.status(), game.lie_probability()) {
+            let confidence = game.posterior(lie_probability).iter()
+                .find(|&&(range, _)| range.lower == answer)
+                .map_or(0.0, |&(_, probability)| probability);
+            lines.push(format!("After {} guesses you are {:.2}% sure", game.turns(), confidence * 100.0));
+            lines.push(style.answer(&format!("It was {}", answer)));
+            let lies: Vec<usize> = game.history().iter().enumerate()
+                .filter(|&(_, &(ref question, response))| question.is_lie(answer, response))
+                .map(|(index, _)| index)
+                .collect();
+            lines.push(format!("If so, the opponent lied on question(s) {:?}", lies));
+        } else if let (Finished(answer), Some(_)) = (game.status(), game.moving_target()) {
+            lines.push(format!("You cornered it in {} guesses", game.turns()));
+            lines.push(style.answer(&format!("It was {}, or near it", answer)));
+            lines.push(format!("It could have been {}", game.candidates()));
+        } else if let Finished(answer) = game.status() {
+            lines.push(format!("You got it in {} guesses", game.turns()));
+            lines.push(style.answer(&format!("It was {}", answer)));
+            let poss_lies: Vec<&[usize]> = game.hypotheses()
+                .filter(|&(range, _)| !range.is_empty())
+                .map(|(_, lies)| lies)
+                .collect();
+            lines.push(format!("The opponent could have lied on question(s) {:?}", poss_lies));
+        } else if game.status() == Lost {
+            lines.push(format!("Out of questions after {}, so you lose", game.turns()));
+            lines.push(format!("It could still have been {}", game.candidates()));
+        } else if game.status() == Impossible {
+            lines.extend(impossible_explanation(game));
+        }
+        let mut effects: Vec<Effect> = lines.into_iter().map(Effect::Say).collect();
+        effects.push(Effect::Over);
+        effects
+    }
+}
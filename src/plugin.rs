@@ -0,0 +1,166 @@
+//! Strategies loaded from shared libraries at run time, so others can ship
+//! opponents and guessers without changing one-lie. --strategy-path names a
+//! directory of them, and each is chosen by its file's name without any lib prefix
+//! or extension, as in --opponent sly for libsly.so. A library exports either or
+//! both of:
+//!
+//! ```text
+//!   const char *one_lie_respond(const char *position, const char *question);
+//!   const char *one_lie_choose(const char *position);
+//! ```
+//!
+//! each sent the position as the engine protocol's setposition line. respond
+//! replies to the question as a cmd: program would, as in L or response 37H, and
+//! choose with the next number to guess. A reply must stay valid until the next
+//! call. Libraries are opened with dlopen, so plugins only work on Unix.
+
+use std::ffi::{c_char, CStr, CString};
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+
+use crate::engine::{read_reply, set_position};
+use crate::{GameState, Guesser, Number, Opponent, Question, Response};
+
+type RespondFn = unsafe extern "C" fn(*const c_char, *const c_char) -> *const c_char;
+type ChooseFn = unsafe extern "C" fn(*const c_char) -> *const c_char;
+
+#[cfg(unix)]
+mod dl {
+    use std::ffi::{c_char, c_int, c_void, CStr, CString};
+
+    const RTLD_NOW: c_int = 2;
+
+    extern "C" {
+        fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+        fn dlerror() -> *const c_char;
+    }
+
+    fn error() -> String {
+        // SAFETY: dlerror returns null or a nul terminated message
+        unsafe {
+            let message = dlerror();
+            if message.is_null() {
+                "unknown error".to_string()
+            } else {
+                CStr::from_ptr(message).to_string_lossy().into_owned()
+            }
+        }
+    }
+
+    /// The library at `path`, which is never closed, as its strategies are used
+    /// until the program ends.
+    pub fn open(path: &str) -> Result<*mut c_void, String> {
+        let path = CString::new(path).map_err(|_| "The path holds a nul".to_string())?;
+        // SAFETY: the path is nul terminated, and loading runs the library's own
+        // initialisers, which is what asking for a plugin asks for
+        let handle = unsafe { dlopen(path.as_ptr(), RTLD_NOW) };
+        if handle.is_null() {
+            return Err(error());
+        }
+        Ok(handle)
+    }
+
+    pub fn symbol(handle: *mut c_void, name: &str) -> Option<*mut c_void> {
+        let name = CString::new(name).expect("Symbol names hold no nul");
+        // SAFETY: the handle came from dlopen, and the name is nul terminated
+        let symbol = unsafe { dlsym(handle, name.as_ptr()) };
+        (!symbol.is_null()).then_some(symbol)
+    }
+}
+
+/// The library in `dir` whose name, without any lib prefix or extension, is `name`.
+pub fn find(dir: &str, name: &str) -> Result<String, String> {
+    let entries = fs::read_dir(dir).map_err(|err| format!("Couldn't read --strategy-path {}: {}", dir, err))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+        if let Some("so" | "dylib") = extension {
+            if stem.strip_prefix("lib").unwrap_or(stem) == name {
+                return Ok(path.to_string_lossy().into_owned());
+            }
+        }
+    }
+    Err(format!("There's no strategy {} in {}", name, dir))
+}
+
+pub struct Plugin {
+    path: String,
+    respond: Option<RespondFn>,
+    choose: Option<ChooseFn>,
+}
+
+impl Plugin {
+    #[cfg(unix)]
+    pub fn load(path: &str) -> Result<Plugin, String> {
+        if !Path::new(path).is_file() {
+            return Err(format!("There's no plugin {}", path));
+        }
+        let handle = dl::open(path).map_err(|err| format!("Couldn't load the plugin {}: {}", path, err))?;
+        // SAFETY: the plugin interface gives these symbols these signatures
+        let respond = dl::symbol(handle, "one_lie_respond").map(|symbol| unsafe { std::mem::transmute::<_, RespondFn>(symbol) });
+        let choose = dl::symbol(handle, "one_lie_choose").map(|symbol| unsafe { std::mem::transmute::<_, ChooseFn>(symbol) });
+        if respond.is_none() && choose.is_none() {
+            return Err(format!("The plugin {} exports neither one_lie_respond nor one_lie_choose", path));
+        }
+        Ok(Plugin { path: path.to_string(), respond, choose })
+    }
+
+    #[cfg(not(unix))]
+    pub fn load(path: &str) -> Result<Plugin, String> {
+        Err(format!("Couldn't load the plugin {}, as plugins need dlopen, which only Unix has", path))
+    }
+
+    pub fn responds(&self) -> bool {
+        self.respond.is_some()
+    }
+
+    pub fn chooses(&self) -> bool {
+        self.choose.is_some()
+    }
+
+    fn fail(&self, why: &str) -> ! {
+        eprintln!("error: The plugin {} {}", self.path, why);
+        exit(1);
+    }
+
+    // The reply to a call, copied out before the plugin can reuse its memory.
+    fn reply(&self, reply: *const c_char) -> String {
+        if reply.is_null() {
+            self.fail("replied with null");
+        }
+        // SAFETY: the plugin interface has replies be nul terminated
+        unsafe { CStr::from_ptr(reply) }.to_string_lossy().trim().to_string()
+    }
+}
+
+fn c_string(text: String) -> CString {
+    CString::new(text).expect("Positions and questions hold no nul")
+}
+
+impl Opponent for Plugin {
+    fn respond(&mut self, game: &GameState, question: &Question) -> Response {
+        let respond = self.respond.expect("Plugin opponents are checked to respond when parsed");
+        let position = c_string(set_position(game));
+        let asked = c_string(question.to_string());
+        // SAFETY: both arguments are nul terminated, and the signature is the interface's
+        let reply = self.reply(unsafe { respond(position.as_ptr(), asked.as_ptr()) });
+        read_reply(game, question, &reply)
+            .unwrap_or_else(|| self.fail(&format!("replied {} to {}, which isn't an answer the game allows", reply, question)))
+    }
+}
+
+impl Guesser for Plugin {
+    fn choose(&mut self, game: &GameState) -> Number {
+        let choose = self.choose.expect("Plugin guessers are checked to choose when parsed");
+        let position = c_string(set_position(game));
+        // SAFETY: the argument is nul terminated, and the signature is the interface's
+        let reply = self.reply(unsafe { choose(position.as_ptr()) });
+        match reply.parse::<Number>() {
+            Ok(guess) if game.check_question(&game.guess(guess)).is_ok() => guess,
+            _ => self.fail(&format!("chose {}, which isn't a question the game allows", reply)),
+        }
+    }
+}
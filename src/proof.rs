@@ -0,0 +1,296 @@
+//! Proof-number search, for asking whether a game can be won within a given
+//! number of questions rather than how few it takes. The game tree is an AND/OR
+//! tree: the guesser needs one question every answer to which can be finished,
+//! and the opponent one answer that can't be. Each position carries a proof number,
+//! how many positions at least must be settled to prove it can be finished, and a
+//! disproof number, how many to prove it can't. The search always works on the
+//! position that proves or disproves most cheaply, so a question that is easy
+//! either way is settled long before a full search would get to it.
+//!
+//! It searches depth first, as df-pn does, keeping the numbers in a table keyed
+//! by the [`GameState::canonical`] position and questions left. Questions are
+//! tried from the one splitting the lie placements most evenly outwards, a few at
+//! a time, and those that can't help are cut off as the [`Solver`] cuts them.
+
+use std::collections::HashMap;
+
+use crate::game::{Count, GameState, Response, Variant};
+use crate::solver::{count, ended, Solver, State};
+
+// Proof and disproof numbers at least this large mean proven impossible
+const INFINITE: u64 = u64::MAX / 2;
+
+// What the search has learned about a position with some questions left.
+#[derive(Clone, Copy)]
+struct Node {
+    proof: u64,
+    disproof: u64,
+    // The positions still worth asking about, and how far either side of the centre
+    // of them are being tried
+    first: Count,
+    last: Count,
+    centre: Count,
+    radius: Count,
+}
+
+/// A proof-number search of games with `max_lies` lies, under the rules of a
+/// [`Solver`] with the same settings. Keep one for as long as the number of lies
+/// stays the same, as what it proves about one position is reused for others.
+pub struct ProofSearch {
+    rules: Solver,
+    // For each position, what is known of it with each number of questions left
+    table: HashMap<State, Vec<(usize, Node)>>,
+    // For each position, the most questions proven not to suffice and the fewest
+    // proven to
+    known: HashMap<State, (usize, usize)>,
+}
+
+fn add(a: u64, b: u64) -> u64 {
+    a.saturating_add(b).min(INFINITE)
+}
+
+// How far the search of one child may go before the next best is tried instead.
+// Going a quarter past the next best, rather than only just past it, saves
+// switching back and forth between children that are nearly as good as each other.
+fn past(second: u64) -> u64 {
+    add(second, 1).max(add(second, second / 4))
+}
+
+impl ProofSearch {
+    pub fn new(max_lies: usize) -> ProofSearch {
+        ProofSearch { rules: Solver::new(max_lies), table: HashMap::new(), known: HashMap::new() }
+    }
+
+    /// The same search, for games where the opponent only tells the lies `variant`
+    /// allows. As with the [`Solver`], directional games can't be searched.
+    pub fn with_variant(mut self, variant: Variant) -> ProofSearch {
+        self.rules = self.rules.with_variant(variant);
+        self
+    }
+
+    /// How many positions the search has expanded.
+    pub fn positions(&self) -> usize {
+        self.table.values().map(Vec::len).sum()
+    }
+
+    /// Whether the guesser can always finish `game` within `questions` questions.
+    pub fn can_finish(&mut self, game: &GameState, questions: usize) -> bool {
+        ended(game) || self.prove(&game.canonical(), questions)
+    }
+
+    /// The fewest questions that guarantee finishing `game`, found by asking
+    /// whether each number of questions suffices, from the volume bound up.
+    pub fn questions_needed(&mut self, game: &GameState) -> usize {
+        if ended(game) {
+            return 0;
+        }
+        let state = game.canonical();
+        let mut questions = 0;
+        while self.rules.volume(&state, questions) > self.rules.capacity(questions) {
+            questions += 1;
+        }
+        while !self.prove(&state, questions) {
+            questions += 1;
+        }
+        questions
+    }
+
+    fn prove(&mut self, state: &[(Count, usize)], questions: usize) -> bool {
+        if let Some(settled) = self.settled(state, questions) {
+            return settled.0 == 0;
+        }
+        self.search(state, questions, INFINITE, INFINITE);
+        self.node(state, questions).is_some_and(|node| node.proof == 0)
+    }
+
+    // The proof and disproof numbers of a position whose answer is already known.
+    fn settled(&self, state: &[(Count, usize)], questions: usize) -> Option<(u64, u64)> {
+        const PROVEN: (u64, u64) = (0, INFINITE);
+        const DISPROVEN: (u64, u64) = (INFINITE, 0);
+        if count(state) <= 1 {
+            return Some(PROVEN);
+        }
+        if questions == 0 || self.rules.volume(state, questions) > self.rules.capacity(questions) {
+            return Some(DISPROVEN);
+        }
+        match self.known.get(state) {
+            Some(&(_, sufficient)) if questions >= sufficient => Some(PROVEN),
+            Some(&(insufficient, _)) if questions <= insufficient => Some(DISPROVEN),
+            _ => None,
+        }
+    }
+
+    fn node(&self, state: &[(Count, usize)], questions: usize) -> Option<Node> {
+        let nodes = self.table.get(state)?;
+        nodes.iter().find(|&&(left, _)| left == questions).map(|&(_, node)| node)
+    }
+
+    fn store(&mut self, state: &[(Count, usize)], questions: usize, node: Node) {
+        let nodes = match self.table.get_mut(state) {
+            Some(nodes) => nodes,
+            None => self.table.entry(state.to_vec()).or_default(),
+        };
+        match nodes.iter_mut().find(|(left, _)| *left == questions) {
+            Some((_, stored)) => *stored = node,
+            None => nodes.push((questions, node)),
+        }
+    }
+
+    // The proof and disproof numbers of a position, as far as they are known. One
+    // not yet searched is guessed to need a proof as large as it has live numbers,
+    // one for each number it ends on, and a disproof as long as the number of
+    // questions its answer sequences could double before they ran out.
+    fn numbers(&self, state: &[(Count, usize)], questions: usize) -> (u64, u64) {
+        self.settled(state, questions)
+            .or_else(|| self.node(state, questions).map(|node| (node.proof, node.disproof)))
+            .unwrap_or_else(|| {
+                let spare = self.rules.capacity(questions) / self.rules.volume(state, questions).max(1);
+                (self.estimate(state, questions), u64::from(128 - spare.leading_zeros()))
+            })
+    }
+
+    fn estimate(&self, state: &[(Count, usize)], questions: usize) -> u64 {
+        u64::try_from(self.rules.volume(state, questions)).unwrap_or(INFINITE).min(INFINITE)
+    }
+
+    // The answers to the question at `position` that don't end the game, each with
+    // the position after it and that position's proof and disproof numbers.
+    fn answers(&self, state: &[(Count, usize)], position: Count, questions: usize) -> Vec<(Response, State, u64, u64)> {
+        self.rules.responses().iter()
+            .filter(|&&response| !self.rules.finishes(state, position, response))
+            .map(|&response| {
+                let next = self.rules.split(state, position, response);
+                let (proof, disproof) = self.numbers(&next, questions - 1);
+                (response, next, proof, disproof)
+            })
+            .collect()
+    }
+
+    // The question at `position` needs every answer proven, and is disproven by any
+    // one answer disproven.
+    fn question_numbers(answers: &[(Response, State, u64, u64)]) -> (u64, u64) {
+        let proof = answers.iter().fold(0, |total, answer| add(total, answer.2));
+        let disproof = answers.iter().map(|answer| answer.3).min().unwrap_or(INFINITE);
+        (proof, disproof)
+    }
+
+    // Searches the guesser's position until its proof number reaches `proof_limit`
+    // or its disproof number `disproof_limit`, leaving the numbers in the table.
+    fn search(&mut self, state: &[(Count, usize)], questions: usize, proof_limit: u64, disproof_limit: u64) {
+        let mut node = match self.node(state, questions) {
+            Some(node) => node,
+            None => match self.rules.window(state, questions) {
+                Some((first, last)) => {
+                    let centre = self.rules.centre(state, questions, (first, last));
+                    Node { proof: 1, disproof: 1, first, last, centre, radius: 0 }
+                }
+                None => {
+                    self.store(state, questions, Node { proof: INFINITE, disproof: 0, first: 1, last: 0, centre: 0, radius: 0 });
+                    self.known.entry(state.to_vec()).or_insert((0, usize::MAX)).0 = questions;
+                    return;
+                }
+            },
+        };
+        loop {
+            let rated = self.rate_questions(state, questions, &mut node);
+            let unrated = node.last.checked_sub(node.first).map_or(0, |width| width + 1) - rated.len() as Count;
+            let unrated = u64::try_from(unrated).unwrap_or(INFINITE).min(INFINITE);
+            node.proof = rated.iter().map(|&(_, proof, _)| proof).min().unwrap_or(INFINITE);
+            node.disproof = rated.iter().fold(unrated, |total, &(_, _, disproof)| add(total, disproof));
+            // The next questions out from the centre are tried too, once they look
+            // easier to prove than those tried so far
+            if unrated > 0 && self.next_out(state, questions, &node).saturating_mul(4) < node.proof {
+                node.radius += 1;
+                continue;
+            }
+            if node.proof >= proof_limit || node.disproof >= disproof_limit {
+                break;
+            }
+            let best = (0..rated.len()).min_by_key(|&index| rated[index].1).expect("Unsettled positions have questions");
+            let second = rated.iter().enumerate().filter(|&(index, _)| index != best).map(|(_, rated)| rated.1).min().unwrap_or(INFINITE);
+            let (position, _, disproof) = rated[best];
+            self.store(state, questions, node);
+            self.search_question(
+                state,
+                position,
+                questions,
+                proof_limit.min(past(second)),
+                disproof_limit - node.disproof + disproof,
+            );
+        }
+        if node.proof == 0 {
+            self.known.entry(state.to_vec()).or_insert((0, usize::MAX)).1 = questions;
+        } else if node.disproof == 0 {
+            let known = self.known.entry(state.to_vec()).or_insert((0, usize::MAX));
+            known.0 = known.0.max(questions);
+        }
+        self.store(state, questions, node);
+    }
+
+    // The least proof number of the two questions just beyond those being tried.
+    fn next_out(&self, state: &[(Count, usize)], questions: usize, node: &Node) -> u64 {
+        let below = node.centre.checked_sub(node.radius + 1).filter(|&position| position >= node.first);
+        let above = node.centre.checked_add(node.radius + 1).filter(|&position| position <= node.last);
+        [below, above].into_iter().flatten()
+            .map(|position| Self::question_numbers(&self.answers(state, position, questions)).0)
+            .min()
+            .unwrap_or(INFINITE)
+    }
+
+    // The questions being tried, as positions with their proof and disproof
+    // numbers, nearest the centre first. Any question an answer to which is
+    // disproven rules out every question on the far side of it from that answer,
+    // so the window is narrowed first.
+    fn rate_questions(&self, state: &[(Count, usize)], questions: usize, node: &mut Node) -> Vec<(Count, u64, u64)> {
+        'narrowed: loop {
+            if node.first > node.last {
+                return vec![];
+            }
+            node.centre = node.centre.clamp(node.first, node.last);
+            let lower = node.first.max(node.centre.saturating_sub(node.radius));
+            let upper = node.last.min(node.centre.saturating_add(node.radius));
+            let mut positions: Vec<Count> = (lower..=upper).collect();
+            positions.sort_by_key(|&position| position.abs_diff(node.centre));
+            let mut rated = vec![];
+            for position in positions {
+                let answers = self.answers(state, position, questions);
+                let (proof, disproof) = Self::question_numbers(&answers);
+                if disproof == 0 {
+                    match answers.iter().find(|answer| answer.3 == 0).map(|answer| answer.0) {
+                        Some(Response::No) => {
+                            node.first = position + 1;
+                            continue 'narrowed;
+                        }
+                        Some(Response::Yes) if position == 0 => {
+                            node.first = node.last + 1;
+                            continue 'narrowed;
+                        }
+                        Some(Response::Yes) => {
+                            node.last = position - 1;
+                            continue 'narrowed;
+                        }
+                        _ => {}
+                    }
+                }
+                rated.push((position, proof, disproof));
+            }
+            return rated;
+        }
+    }
+
+    // Searches the opponent's turn, after the question at `position`, until its
+    // proof number reaches `proof_limit` or its disproof number `disproof_limit`.
+    fn search_question(&mut self, state: &[(Count, usize)], position: Count, questions: usize, proof_limit: u64, disproof_limit: u64) {
+        loop {
+            let answers = self.answers(state, position, questions);
+            let (proof, disproof) = Self::question_numbers(&answers);
+            if proof >= proof_limit || disproof >= disproof_limit {
+                return;
+            }
+            let best = (0..answers.len()).min_by_key(|&index| answers[index].3).expect("Unsettled questions have answers");
+            let second = answers.iter().enumerate().filter(|&(index, _)| index != best).map(|(_, answer)| answer.3).min().unwrap_or(INFINITE);
+            let (_, next, answer_proof, _) = &answers[best];
+            self.search(next, questions - 1, proof_limit - proof + answer_proof, disproof_limit.min(past(second)));
+        }
+    }
+}
@@ -0,0 +1,539 @@
+//! The games on the same idea as the number game, played at the terminal:
+//! mastermind, twenty questions, guessing a word, and bisecting a domain's values.
+
+use crate::commands::{MastermindCommand, TwentyCommand, WordsCommand, MASTERMIND_HELP, TWENTY_HELP, WORDS_HELP};
+use crate::domain::Domain;
+use crate::frontend::{Frontend, Lines};
+use crate::game::GameResult::*;
+use crate::guesser::Greedy;
+use crate::input::{Input, Terminal};
+use crate::mastermind::{format_code, Feedback, Mastermind, MastermindAdversary, MastermindLiar, MastermindOpponent};
+use crate::modes::{answer_word, seed_announcement};
+use crate::opponent::{Adversary, RandomLiar};
+use crate::rng::Rng;
+use crate::style::Style;
+use crate::twenty::ItemDatabase;
+use crate::value::{value_function, volume_bound, ValueFunction};
+use crate::words::Wordlist;
+use crate::{Count, GameState, Guesser, Number, Opponent, Question, Range, Response};
+
+// The codes still possible, listing them all only when there are few.
+fn show_mastermind_state(game: &Mastermind, frontend: &mut dyn Frontend) {
+    let possibilities = game.possibilities();
+    if possibilities.len() > 20 {
+        frontend.say(&format!("    {} codes are still possible", possibilities.len()));
+        return;
+    }
+    for (code, lies) in possibilities {
+        frontend.say(&format!("    {} with {} lie(s)", format_code(code), lies));
+    }
+}
+
+// Guessing a code of coloured pegs from feedback that may lie, announcing the
+// `seed` the opponent's random choices come from, if it makes any.
+fn run_mastermind(
+    mut game: Mastermind,
+    opponent: &mut dyn MastermindOpponent,
+    frontend: &mut dyn Frontend,
+    input: &mut dyn Input,
+    seed: Option<u64>,
+    style: Style,
+    show_state: bool,
+) -> Mastermind {
+    let lies = match game.max_lies() {
+        1 => "1 lie".to_string(),
+        lies => format!("{} lies", lies),
+    };
+    frontend.say(&format!(
+        "Guess the code of {} pegs, each one of {} colours, with up to {} in the feedback",
+        game.pegs(),
+        game.colours(),
+        lies
+    ));
+    frontend.say("Black counts pegs the right colour in the right place, and white more pegs the right colour in the wrong place");
+    if let Some(seed) = seed {
+        frontend.say(&seed_announcement(seed));
+    }
+    frontend.say(&format!("Type a code such as {} to guess it, or help for the other commands", format_code(&game.codes()[1])));
+    let mut resigned = false;
+    while game.solved().is_none() && !game.is_impossible() {
+        frontend.say(&format!("{}: What is your guess? (help lists the commands)", game.history().len()));
+        let command = match input.read_line() {
+            Some(line) => MastermindCommand::parse(&line),
+            None => MastermindCommand::Quit,
+        };
+        match command {
+            MastermindCommand::Guess(text) => {
+                let guess = match game.parse_code(&text) {
+                    Ok(guess) => guess,
+                    Err(err) => {
+                        frontend.say(&format!("{}, type help to see the commands", err));
+                        continue;
+                    }
+                };
+                let feedback = opponent.respond(&game, &guess);
+                frontend.say(&format!("{}: {}", format_code(&guess), feedback));
+                game.ask(guess, feedback).expect("Already checked guess was legal");
+                if show_state {
+                    show_mastermind_state(&game, frontend);
+                }
+            }
+            MastermindCommand::Hint => frontend.say(&format!("Try {}", format_code(&game.best_guess()))),
+            MastermindCommand::Status => show_mastermind_state(&game, frontend),
+            MastermindCommand::History if game.history().is_empty() => frontend.say("No guesses yet"),
+            MastermindCommand::History => {
+                for (index, (guess, feedback)) in game.history().iter().enumerate() {
+                    frontend.say(&format!("    guess {}, {}: {}", index, format_code(guess), feedback));
+                }
+            }
+            MastermindCommand::Undo => match game.undo() {
+                Some((guess, _)) => frontend.say(&format!("Took back guess {}, {}", game.history().len(), format_code(&guess))),
+                None => frontend.say("There's nothing to undo"),
+            },
+            MastermindCommand::Resign => {
+                resigned = true;
+                break;
+            }
+            MastermindCommand::Quit => {
+                frontend.say(&format!("Left the game unfinished after {} guesses", game.history().len()));
+                break;
+            }
+            MastermindCommand::Help => frontend.say(MASTERMIND_HELP),
+        }
+    }
+    if resigned {
+        frontend.say(&format!("You resigned after {} guesses", game.history().len()));
+        show_mastermind_state(&game, frontend);
+    }
+    if let Some(code) = game.solved() {
+        frontend.say(&format!("You found it in {} guesses", game.history().len()));
+        frontend.say(&style.answer(&format!("The code is {}", format_code(&code))));
+    } else if game.is_impossible() {
+        frontend.say(&format!("That feedback contradicts itself, even allowing {}", lies));
+    }
+    game
+}
+
+/// The player guesses a code, against the random opponent if `random` and the
+/// adversary otherwise.
+pub fn mastermind_game(game: Mastermind, random: bool, seed: Option<u64>, secret: Option<Vec<u8>>, style: Style, show_state: bool) {
+    // Only the random opponent makes random choices, so only its seed is announced
+    let seed = random.then(|| seed.unwrap_or_else(|| Rng::from_time().next_u64()));
+    let mut opponent: Box<dyn MastermindOpponent> = if let Some(seed) = seed {
+        let mut rng = Rng::new(seed);
+        let secret = secret.unwrap_or_else(|| game.codes()[rng.below(game.codes().len() as u64) as usize].clone());
+        // A game without lies takes about as many guesses as it takes feedbacks to
+        // tell the codes apart, and each lie a couple more
+        let feedbacks = Feedback::all(game.pegs()).len() as f64;
+        let needed = ((game.codes().len() as f64).ln() / feedbacks.ln()).ceil() as usize;
+        Box::new(MastermindLiar {
+            secret,
+            horizon: needed + 2 * game.max_lies() + 2,
+            rng,
+        })
+    } else {
+        Box::new(MastermindAdversary)
+    };
+    let mut frontend = Lines { style, input: Box::new(Terminal) };
+    run_mastermind(game, &mut *opponent, &mut frontend, &mut Terminal, seed, style, show_state);
+    if let Some(reveal) = opponent.reveal() {
+        frontend.say(&reveal);
+    }
+}
+
+// The items still possible, listing them all only when there are few.
+fn show_twenty_state(database: &ItemDatabase, game: &GameState, frontend: &mut dyn Frontend) {
+    let candidates = database.candidates(game);
+    if candidates.count() > 20 {
+        frontend.say(&format!("    {} items are still possible", candidates.count()));
+        return;
+    }
+    let items: Vec<&str> = candidates.iter().map(|item| database.items()[item].as_str()).collect();
+    frontend.say(&format!("    it could be {}", items.join(", ")));
+}
+
+// The attribute a twenty questions question asks about, for the history.
+fn twenty_answer(database: &ItemDatabase, question: &Question, response: Response) -> String {
+    let attribute = database.attribute(question).map_or("that", |attribute| database.attributes()[attribute].as_str());
+    format!("{}: {}", attribute, answer_word(question, response).to_lowercase())
+}
+
+// Guessing an item from a database, asking about its attributes, announcing the
+// `seed` the opponent's random choices come from, if it makes any.
+#[allow(clippy::too_many_arguments)]
+fn run_twenty(
+    database: &ItemDatabase,
+    mut game: GameState,
+    opponent: &mut dyn Opponent,
+    value: &ValueFunction,
+    frontend: &mut dyn Frontend,
+    input: &mut dyn Input,
+    seed: Option<u64>,
+    style: Style,
+    show_state: bool,
+) -> GameState {
+    frontend.say(&format!("Guess the item, one of {}, with {}", database, game.describe()));
+    if let Some(seed) = seed {
+        frontend.say(&seed_announcement(seed));
+    }
+    frontend.say(&format!(
+        "Type an attribute such as {} to ask whether the item has it, or help for the other commands",
+        database.attributes()[0]
+    ));
+    let mut resigned = false;
+    while game.status() == Ongoing {
+        frontend.say(&format!("{}: Which attribute do you want to ask about? (help lists the commands)", game.history().len()));
+        let command = match input.read_line() {
+            Some(line) => TwentyCommand::parse(&line),
+            None => TwentyCommand::Quit,
+        };
+        match command {
+            TwentyCommand::Ask(name) => {
+                let attribute = match database.find(&name) {
+                    Some(attribute) => attribute,
+                    None => {
+                        frontend.say(&format!("{} isn't an attribute or a command, type help to see the commands", name));
+                        continue;
+                    }
+                };
+                let question = database.question(attribute);
+                if let Err(err) = game.check_question(&question) {
+                    frontend.say(&format!("No item has {}: {}", database.attributes()[attribute], err));
+                    continue;
+                }
+                let response = opponent.respond(&game, &question);
+                game.ask(question.clone(), response).expect("Already checked question was legal");
+                frontend.say(&style.response(response, &twenty_answer(database, &question, response)));
+                if show_state {
+                    show_twenty_state(database, &game, frontend);
+                }
+            }
+            TwentyCommand::Attributes => frontend.say(&format!("    {}", database.attributes().join(", "))),
+            TwentyCommand::Hint => match database.best_attribute(&game, value) {
+                Some(attribute) => frontend.say(&format!("Ask about {}", database.attributes()[attribute])),
+                None => frontend.say("No attribute tells the items still possible apart"),
+            },
+            TwentyCommand::Status => show_twenty_state(database, &game, frontend),
+            TwentyCommand::History if game.history().is_empty() => frontend.say("No questions yet"),
+            TwentyCommand::History => {
+                for (index, (question, response)) in game.history().iter().enumerate() {
+                    frontend.say(&format!("    question {}, {}", index, twenty_answer(database, question, *response)));
+                }
+            }
+            TwentyCommand::Undo => match game.undo() {
+                Some((question, response)) => frontend.say(&format!(
+                    "Took back question {}, {}",
+                    game.history().len(),
+                    twenty_answer(database, &question, response)
+                )),
+                None => frontend.say("There's nothing to undo"),
+            },
+            TwentyCommand::Resign => {
+                resigned = true;
+                break;
+            }
+            TwentyCommand::Quit => {
+                frontend.say(&format!("Left the game unfinished after {} questions", game.history().len()));
+                break;
+            }
+            TwentyCommand::Help => frontend.say(TWENTY_HELP),
+        }
+    }
+    if resigned {
+        frontend.say(&format!("You resigned after {} questions", game.history().len()));
+        show_twenty_state(database, &game, frontend);
+    }
+    if let Some(item) = database.found(&game) {
+        frontend.say(&format!("You found it in {} questions", game.history().len()));
+        frontend.say(&style.answer(&format!("It is {}", item)));
+    } else if game.status() == Impossible {
+        frontend.say(&format!("Those answers contradict each other, even allowing {}", game.describe()));
+    }
+    game
+}
+
+/// The player guesses an item from `database`, against a random liar holding
+/// `secret` if `random` and the adversary otherwise.
+#[allow(clippy::too_many_arguments)]
+pub fn twenty_game(
+    database: ItemDatabase,
+    max_lies: usize,
+    random: bool,
+    seed: Option<u64>,
+    secret: Option<usize>,
+    value: &str,
+    style: Style,
+    show_state: bool,
+) {
+    let game = database.game(max_lies);
+    let value = value_function(value).expect("Value function names are checked when parsed");
+    let seed = seed.unwrap_or_else(|| Rng::from_time().next_u64());
+    let mut rng = Rng::new(seed);
+    let secret = secret.unwrap_or_else(|| rng.below(database.items().len() as u64) as usize);
+    let mut opponent: Box<dyn Opponent> = if random {
+        Box::new(RandomLiar {
+            secret: secret as Number,
+            horizon: volume_bound(database.items().len() as Count, max_lies),
+            rng,
+        })
+    } else {
+        Box::new(Adversary { value: value.clone() })
+    };
+    let mut frontend = Lines { style, input: Box::new(Terminal) };
+    run_twenty(&database, game, &mut *opponent, &value, &mut frontend, &mut Terminal, random.then_some(seed), style, show_state);
+    // The random opponent reveals its secret by number, so it is named here instead
+    if random {
+        frontend.say(&format!("It was {}", database.items()[secret]));
+    }
+}
+
+// The words still possible, as runs from the first to the last.
+fn show_words_state(wordlist: &Wordlist, game: &GameState, frontend: &mut dyn Frontend) {
+    let runs: Vec<String> = game.candidates().ranges().iter().map(|&range| wordlist.describe_range(range)).collect();
+    match runs.len() {
+        0 => frontend.say("    it could be nothing"),
+        count if count > 10 => frontend.say(&format!("    it could be in any of {} runs of words", count)),
+        _ => frontend.say(&format!("    it could be {}", runs.join(", "))),
+    }
+}
+
+// A question about a word with its answer, as in "before melon: yes".
+fn words_answer(wordlist: &Wordlist, question: &Question, response: Response) -> String {
+    format!("{}: {}", wordlist.describe(question), answer_word(question, response).to_lowercase())
+}
+
+// Guessing a word from a wordlist, asking which words it comes before, announcing
+// the `seed` the opponent's random choices come from, if it makes any.
+#[allow(clippy::too_many_arguments)]
+fn run_words(
+    wordlist: &Wordlist,
+    mut game: GameState,
+    opponent: &mut dyn Opponent,
+    value: &ValueFunction,
+    frontend: &mut dyn Frontend,
+    input: &mut dyn Input,
+    seed: Option<u64>,
+    style: Style,
+    show_state: bool,
+) -> GameState {
+    frontend.say(&format!("Guess the word, one of {}, with {}", wordlist, game.describe()));
+    if let Some(seed) = seed {
+        frontend.say(&seed_announcement(seed));
+    }
+    frontend.say("Type a word to ask whether the secret comes before it, or help for the other commands");
+    let mut resigned = false;
+    while game.status() == Ongoing {
+        frontend.say(&format!("{}: Which word do you want to ask about? (help lists the commands)", game.history().len()));
+        let command = match input.read_line() {
+            Some(line) => WordsCommand::parse(&line),
+            None => WordsCommand::Quit,
+        };
+        let question = match command {
+            WordsCommand::Before(word) => wordlist.before(&word),
+            WordsCommand::Exactly(word) => match wordlist.find(&word) {
+                Some(place) => Question::Equal(place),
+                None => {
+                    frontend.say(&format!("{} isn't in the list", word));
+                    continue;
+                }
+            },
+            WordsCommand::Hint => {
+                let question = game.guess(Greedy { value: value.clone() }.choose(&game));
+                frontend.say(&format!("Ask whether it's {}", wordlist.describe(&question)));
+                continue;
+            }
+            WordsCommand::Status => {
+                show_words_state(wordlist, &game, frontend);
+                continue;
+            }
+            WordsCommand::History => {
+                if game.history().is_empty() {
+                    frontend.say("No questions yet");
+                }
+                for (index, (question, response)) in game.history().iter().enumerate() {
+                    frontend.say(&format!("    question {}, {}", index, words_answer(wordlist, question, *response)));
+                }
+                continue;
+            }
+            WordsCommand::Undo => {
+                match game.undo() {
+                    Some((question, response)) => frontend.say(&format!(
+                        "Took back question {}, {}",
+                        game.history().len(),
+                        words_answer(wordlist, &question, response)
+                    )),
+                    None => frontend.say("There's nothing to undo"),
+                }
+                continue;
+            }
+            WordsCommand::Resign => {
+                resigned = true;
+                break;
+            }
+            WordsCommand::Quit => {
+                frontend.say(&format!("Left the game unfinished after {} questions", game.history().len()));
+                break;
+            }
+            WordsCommand::Help => {
+                frontend.say(WORDS_HELP);
+                continue;
+            }
+        };
+        // Asking before the first word or after the last tells the player nothing
+        match question {
+            Question::Less(0) => {
+                frontend.say("Every word comes after that, so asking tells you nothing");
+                continue;
+            }
+            Question::Less(place) if place == game.upper_limit() => {
+                frontend.say("Every word comes before that, so asking tells you nothing");
+                continue;
+            }
+            _ => {}
+        }
+        let response = opponent.respond(&game, &question);
+        game.ask(question.clone(), response).expect("Words in the list make legal questions");
+        frontend.say(&style.response(response, &words_answer(wordlist, &question, response)));
+        if show_state {
+            show_words_state(wordlist, &game, frontend);
+        }
+    }
+    if resigned {
+        frontend.say(&format!("You resigned after {} questions", game.history().len()));
+        show_words_state(wordlist, &game, frontend);
+    }
+    if let Some(word) = wordlist.found(&game) {
+        frontend.say(&format!("You found it in {} questions", game.history().len()));
+        frontend.say(&style.answer(&format!("It is {}", word)));
+    } else if game.status() == Impossible {
+        frontend.say(&format!("Those answers contradict each other, even allowing {}", game.describe()));
+    }
+    game
+}
+
+/// The player guesses a word from `wordlist`, against a random liar holding
+/// `secret` if `random` and the adversary otherwise.
+#[allow(clippy::too_many_arguments)]
+pub fn words_game(
+    wordlist: Wordlist,
+    max_lies: usize,
+    random: bool,
+    seed: Option<u64>,
+    secret: Option<Number>,
+    value: &str,
+    style: Style,
+    show_state: bool,
+) {
+    let game = wordlist.game(max_lies);
+    let value = value_function(value).expect("Value function names are checked when parsed");
+    let seed = seed.unwrap_or_else(|| Rng::from_time().next_u64());
+    let mut rng = Rng::new(seed);
+    let secret = secret.unwrap_or_else(|| rng.below(wordlist.words().len() as u64) as Number);
+    let mut opponent: Box<dyn Opponent> = if random {
+        Box::new(RandomLiar {
+            secret,
+            horizon: volume_bound(wordlist.words().len() as Count, max_lies),
+            rng,
+        })
+    } else {
+        Box::new(Adversary { value: value.clone() })
+    };
+    let mut frontend = Lines { style, input: Box::new(Terminal) };
+    run_words(&wordlist, game, &mut *opponent, &value, &mut frontend, &mut Terminal, random.then_some(seed), style, show_state);
+    // The random opponent reveals its secret by place, so it is named here instead
+    if random {
+        frontend.say(&format!("It was {}", wordlist.word(secret).expect("The secret is a place in the list")));
+    }
+}
+
+// The computer searches a domain's values, asking which values the secret comes
+// before, and either the player answers through `input` or else `opponent`,
+// whose random choices come from `seed`.
+#[allow(clippy::too_many_arguments)]
+fn run_bisect(
+    domain: &dyn Domain,
+    mut game: GameState,
+    guesser: &mut dyn Guesser,
+    mut opponent: Option<&mut dyn Opponent>,
+    frontend: &mut dyn Frontend,
+    input: &mut dyn Input,
+    seed: Option<u64>,
+    style: Style,
+) -> GameState {
+    let (first, last) = (game.lower_limit(), game.upper_limit() - 1);
+    frontend.say(&format!(
+        "Searching {} {}s from {} to {}, with {}",
+        game.count(game.range()),
+        domain.name(),
+        domain.format(first),
+        domain.format(last),
+        game.describe()
+    ));
+    if let Some(seed) = seed {
+        frontend.say(&seed_announcement(seed));
+    }
+    while game.status() == Ongoing {
+        let question = game.guess(guesser.choose(&game));
+        let described = match question {
+            // Before a value that isn't listed is before the next one that is
+            Question::Less(guess) => {
+                let next = game.candidate_set().map_or(guess, |set| set.tighten(Range::new(guess, game.upper_limit())).lower);
+                format!("before {}", domain.format(next))
+            }
+            ref question => question.describe(),
+        };
+        let response = match opponent {
+            Some(ref mut opponent) => opponent.respond(&game, &question),
+            None => {
+                frontend.say(&format!("{}: Is it {}? (y/n, or undo)", game.history().len(), described));
+                match input.read_line().map(|line| line.to_lowercase()).as_deref() {
+                    Some("y" | "yes") => Response::Yes,
+                    Some("n" | "no") => Response::No,
+                    Some("undo") => {
+                        if game.undo().is_none() {
+                            frontend.say("There's nothing to undo");
+                        }
+                        continue;
+                    }
+                    Some(_) => {
+                        frontend.say("Please answer y or n, or type undo to take back the last answer");
+                        continue;
+                    }
+                    None => {
+                        frontend.say(&format!("Left the search unfinished after {} questions", game.history().len()));
+                        return game;
+                    }
+                }
+            }
+        };
+        game.ask(question.clone(), response).expect("Guessers only ask legal questions");
+        frontend.say(&style.response(response, &format!("{}: {}", described, answer_word(&question, response).to_lowercase())));
+    }
+    match game.status() {
+        Finished(found) => {
+            frontend.say(&format!("Found it in {} questions", game.history().len()));
+            frontend.say(&style.answer(&format!("It's {}", domain.format(found))));
+        }
+        _ => frontend.say(&format!("Those answers contradict each other, even allowing {}", game.describe())),
+    }
+    game
+}
+
+/// The computer searches `domain`'s values, asking the player, or a random liar
+/// holding `secret` if given.
+pub fn bisect(domain: &dyn Domain, game: GameState, secret: Option<Number>, seed: Option<u64>, value: &str, style: Style) {
+    let value = value_function(value).expect("Value function names are checked when parsed");
+    let mut guesser = Greedy { value };
+    let seed = secret.map(|_| seed.unwrap_or_else(|| Rng::from_time().next_u64()));
+    let mut opponent = secret.zip(seed).map(|(secret, seed)| RandomLiar {
+        secret,
+        horizon: volume_bound(game.count(game.range()), game.max_lies()),
+        rng: Rng::new(seed),
+    });
+    let mut frontend = Lines { style, input: Box::new(Terminal) };
+    let opponent = opponent.as_mut().map(|opponent| opponent as &mut dyn Opponent);
+    run_bisect(domain, game, &mut guesser, opponent, &mut frontend, &mut Terminal, seed, style);
+    if let Some(secret) = secret {
+        frontend.say(&format!("The secret was {}", domain.format(secret)));
+    }
+}
@@ -0,0 +1,169 @@
+//! Looking back over recorded games and exported strategies.
+
+use std::fs;
+use std::io::{stdin, IsTerminal};
+
+use crate::frontend::survivors;
+use crate::game::GameResult::*;
+use crate::input::{Input, Terminal};
+use crate::json::{FromJson, Json};
+use crate::modes::answer_word;
+use crate::solver::Strategy;
+use crate::style::Style;
+use crate::transcript::format_move;
+use crate::value::volume_bound;
+use crate::verify::verify;
+use crate::{GameState, Solver, Variant};
+
+/// Checks an exported strategy against every secret and placement of lies, listing
+/// the first games it fails.
+pub fn verify_strategy(path: &str) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let strategy = Strategy::from_json(&Json::parse(&text)?)?;
+    let report = verify(&strategy)?;
+    if report.failures == 0 {
+        println!(
+            "The strategy finds every number below {} with {} within {} questions, over all {} games",
+            strategy.upper_limit,
+            GameState::new(strategy.upper_limit, strategy.max_lies).describe(),
+            report.claimed,
+            report.games
+        );
+        return Ok(());
+    }
+    println!("Counterexamples, with each lie marked *:");
+    for counterexample in &report.counterexamples {
+        println!("  {}", counterexample);
+    }
+    if report.failures > report.counterexamples.len() as u64 {
+        println!("  and {} more", report.failures - report.counterexamples.len() as u64);
+    }
+    Err(format!("it fails {} of {} games within the {} questions it claims", report.failures, report.games, report.claimed))
+}
+
+/// Steps through a recorded game, showing what was still possible after each answer.
+pub fn replay_game(path: &str, style: Style) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let recorded = GameState::from_transcript(&text)?;
+    // The same rules, with every question taken back
+    let mut game = recorded.clone();
+    while game.undo().is_some() {}
+    println!("Replaying a game with {}, {}", game.describe(), game.describe_range());
+    print!("{}", survivors(&game, 60, style));
+    // Only wait between questions when someone is there to press enter
+    let pause = stdin().is_terminal();
+    for &(ref question, response) in recorded.history() {
+        if pause {
+            Terminal.read_line();
+        }
+        println!(
+            "{}: Is it {}? {}",
+            game.history().len(),
+            question.describe(),
+            style.response(response, answer_word(question, response))
+        );
+        game.ask(question.clone(), response).expect("Transcripts are checked when read");
+        print!("{}", survivors(&game, 60, style));
+    }
+    match game.status() {
+        Finished(answer) => println!(
+            "{}, found in {} questions",
+            style.answer(&format!("It was {}", answer)),
+            game.history().len()
+        ),
+        Impossible => println!("Those answers contradict each other, even allowing {}", game.describe()),
+        Lost => println!(
+            "Out of questions after {}, with {} still possible",
+            game.history().len(),
+            game.candidates()
+        ),
+        Ongoing => println!("The game was left unfinished after {} questions", game.history().len()),
+    }
+    Ok(())
+}
+
+/// How the `needed` questions of perfect play compare with the volume bound for
+/// `game`. Meeting it proves no strategy at all could do better, and falling short
+/// means some answer sequences are bound to go unused.
+pub fn volume_report(game: &GameState, needed: usize) -> String {
+    let bound = volume_bound(game.count(game.range()), game.max_lies());
+    let verdict = match needed.saturating_sub(bound) {
+        0 => "which perfect play meets".to_string(),
+        1 => "which perfect play falls one question short of".to_string(),
+        short => format!("which perfect play falls {} questions short of", short),
+    };
+    format!(
+        "The volume bound, giving each number and placement of lies its own answers, is {}, {}",
+        bound, verdict
+    )
+}
+
+/// Goes through a recorded game, comparing each question with a perfect guesser's.
+pub fn analyze_game(path: &str) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let recorded = GameState::from_transcript(&text)?;
+    if recorded.lie_probability().is_some() {
+        return Err("Only games with a fixed number of lies can be analyzed".to_string());
+    }
+    if recorded.variant() == Variant::Directional {
+        return Err("Games with directional lies can't be analyzed, as the engine doesn't track which way they went".to_string());
+    }
+    if recorded.final_guess() {
+        return Err("Final-guess games can't be analyzed, as the engine doesn't count the announcements".to_string());
+    }
+    // The same rules, with every question taken back
+    let mut game = recorded.clone();
+    while game.undo().is_some() {}
+    let mut solver = Solver::new(game.max_lies()).with_variant(game.variant());
+    let needed = solver.questions_needed(&game);
+    println!(
+        "A game with {}, {}, needs {} questions with perfect play",
+        game.describe(),
+        game.describe_range(),
+        needed
+    );
+    if game.variant() == Variant::Standard {
+        println!("{}", volume_report(&game, needed));
+    }
+    for &(ref question, response) in recorded.history() {
+        if game.status() != Ongoing {
+            break;
+        }
+        let (best, needed) = solver.best_question(&game).expect("The game is still going");
+        let worst_case = question.responses().iter()
+            .map(|&answer| {
+                let mut next = game.clone();
+                next.ask(question.clone(), answer).expect("Transcripts are checked when read");
+                solver.questions_needed(&next)
+            })
+            .max()
+            .unwrap();
+        let answer = format_move(question, response);
+        if worst_case + 1 == needed {
+            println!("{}. {}  best, {} question(s) to go", game.history().len(), answer, needed);
+        } else {
+            println!(
+                "{}. {}  costs {} question(s), {} was best",
+                game.history().len(),
+                answer,
+                worst_case + 1 - needed,
+                best
+            );
+        }
+        game.ask(question.clone(), response).expect("Transcripts are checked when read");
+    }
+    match game.status() {
+        Finished(answer) => println!("It was {}, found in {} questions", answer, game.history().len()),
+        Impossible => println!("Those answers contradict each other, even allowing {}", game.describe()),
+        Lost => println!(
+            "Out of questions after {}, with {} still possible",
+            game.history().len(),
+            game.candidates()
+        ),
+        Ongoing => println!(
+            "The game was left unfinished, {} question(s) from the end",
+            solver.questions_needed(&game)
+        ),
+    }
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use std::convert::TryFrom;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Tiny xorshift64* generator, so random opponents don't need any dependencies.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // Zero is a fixed point of xorshift, so scramble the seed first.
+        Rng {
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+    /// A generator for another use of `seed`, numbered `stream`, whose draws are
+    /// independent of those of `Rng::new(seed)` and of the seed's other streams.
+    pub fn stream(seed: u64, stream: u64) -> Rng {
+        Rng::new(Rng::new(seed ^ stream.wrapping_mul(0xD1B5_4A32_D192_ED03)).next_u64())
+    }
+    pub fn from_time() -> Rng {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Clock before 1970");
+        Rng::new(now.as_secs() ^ u64::from(now.subsec_nanos()))
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+    /// Uniform in `0..bound`
+    pub fn below(&mut self, bound: u64) -> u64 {
+        ((u128::from(self.next_u64()) * u128::from(bound)) >> 64) as u64
+    }
+    /// Uniform in `0..bound`, or very nearly so for bounds beyond 2^64, drawing
+    /// the same numbers as `below` for smaller bounds.
+    pub fn below_u128(&mut self, bound: u128) -> u128 {
+        match u64::try_from(bound) {
+            Ok(bound) => u128::from(self.below(bound)),
+            Err(_) => ((u128::from(self.next_u64()) << 64) | u128::from(self.next_u64())) % bound,
+        }
+    }
+    /// Uniform in `[0, 1)`
+    pub fn fraction(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.fraction() < probability
+    }
+}
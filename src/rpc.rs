@@ -0,0 +1,200 @@
+//! JSON-RPC 2.0 on standard input and output, for editors, notebooks and programs
+//! in other languages to embed the engine. Each line in is one request, and each
+//! request with an id gets one line back. The methods are:
+//!
+//! ```text
+//!   new_game        {"limit": 100, "lower": 0, "lies": 1, "variant": "standard",
+//!                   "moves": ["50L", "25H"]}, all but limit optional, starts a
+//!                   game with any moves already made, written as in transcripts
+//!   guess           {"question": "37"} asks the opponent, replying with its answer
+//!   respond         {"question": "37", "response": "low"} gives the answer to a
+//!                   question, as when the caller is the one thinking of a number
+//!   best_question   The question the guesser would ask next, or null once the
+//!                   game is over
+//!   possibilities   What the number could still be
+//! ```
+//!
+//! Every method that changes the game replies with the possibilities and status
+//! after it, as possibilities does.
+
+use crate::engine::{parse_response, Rules};
+use crate::input::{Input, Terminal};
+use crate::json::{possibilities_to_json, response_to_json, Json, ToJson};
+use crate::setup::{make_guesser, make_opponent, OpponentSetup};
+use crate::transcript::parse_move;
+use crate::{GameResult, GameState, Guesser, Opponent, Question, Variant};
+
+const PARSE_ERROR: i128 = -32700;
+const INVALID_REQUEST: i128 = -32600;
+const METHOD_NOT_FOUND: i128 = -32601;
+const INVALID_PARAMS: i128 = -32602;
+// Requests that are well formed, but that the game refuses
+const REFUSED: i128 = -32000;
+
+type Failure = (i128, String);
+
+fn invalid(err: impl ToString) -> Failure {
+    (INVALID_PARAMS, err.to_string())
+}
+
+fn refused(err: impl ToString) -> Failure {
+    (REFUSED, err.to_string())
+}
+
+// The game, with a guesser and opponent made for it.
+struct Session {
+    game: GameState,
+    guesser: Box<dyn Guesser>,
+    opponent: Box<dyn Opponent>,
+}
+
+struct Rpc {
+    setup: OpponentSetup,
+    guesser_name: String,
+    session: Option<Session>,
+}
+
+// The question in `params`, with a number on its own asking whatever a guess asks
+// in this game.
+fn question_param(game: &GameState, params: &Json) -> Result<Question, Failure> {
+    let question = match params.field("question").map_err(invalid)? {
+        Json::Int(guess) => game.guess(*guess),
+        json => match json.as_str().map_err(invalid)?.parse::<Question>().map_err(invalid)? {
+            Question::Less(guess) => game.guess(guess),
+            question => question,
+        },
+    };
+    if game.status() != GameResult::Ongoing {
+        return Err(refused("The game is over"));
+    }
+    game.check_question(&question).map_err(refused)?;
+    Ok(question)
+}
+
+fn state(game: &GameState) -> Vec<(&'static str, Json)> {
+    vec![("possibilities", possibilities_to_json(game)), ("status", game.status().to_json())]
+}
+
+impl Rpc {
+    fn session(&mut self) -> Result<&mut Session, Failure> {
+        self.session.as_mut().ok_or_else(|| refused("There's no game yet, so call new_game first"))
+    }
+
+    fn new_game(&mut self, params: &Json) -> Result<Json, Failure> {
+        let mut rules = Rules {
+            lower: 0,
+            limit: params.field("limit").and_then(Json::as_number).map_err(invalid)?,
+            lies: 1,
+            variant: Variant::Standard,
+        };
+        if let Some(lower) = params.get("lower") {
+            rules.lower = lower.as_number().map_err(invalid)?;
+        }
+        if let Some(lies) = params.get("lies") {
+            rules.lies = lies.as_u64().map_err(invalid)? as usize;
+        }
+        if let Some(variant) = params.get("variant") {
+            rules.variant = Variant::parse(variant.as_str().map_err(invalid)?).map_err(invalid)?;
+        }
+        rules.validate().map_err(invalid)?;
+        rules.check_players(Some(&self.guesser_name), Some(self.setup.kind)).map_err(refused)?;
+        let mut game = rules.game();
+        if let Some(moves) = params.get("moves") {
+            let moves = match *moves {
+                Json::Array(ref moves) => moves,
+                _ => return Err(invalid("moves must be an array, such as [\"50L\", \"25H\"]")),
+            };
+            for text in moves {
+                let (question, response) = parse_move(text.as_str().map_err(invalid)?).map_err(invalid)?;
+                game.ask(question, response).map_err(refused)?;
+            }
+        }
+        let mut setup = self.setup.clone();
+        setup.resolve(&game).map_err(refused)?;
+        let mut fields = vec![("game", game.to_json())];
+        fields.extend(state(&game));
+        self.session = Some(Session {
+            guesser: make_guesser(&game, &setup, &self.guesser_name),
+            opponent: make_opponent(&game, &setup),
+            game,
+        });
+        Ok(Json::object(fields))
+    }
+
+    fn call(&mut self, method: &str, params: &Json) -> Result<Json, Failure> {
+        match method {
+            "new_game" => self.new_game(params),
+            "guess" => {
+                let session = self.session()?;
+                let question = question_param(&session.game, params)?;
+                let response = session.opponent.respond(&session.game, &question);
+                session.game.ask(question.clone(), response).map_err(refused)?;
+                let mut fields = vec![("response", response_to_json(&question, response))];
+                fields.extend(state(&session.game));
+                Ok(Json::object(fields))
+            }
+            "respond" => {
+                let session = self.session()?;
+                let question = question_param(&session.game, params)?;
+                let answer = params.field("response").and_then(Json::as_str).map_err(invalid)?;
+                let response = parse_response(&question, answer)
+                    .ok()
+                    .filter(|response| session.game.responses(&question).contains(response))
+                    .ok_or_else(|| invalid(format!("{} isn't an answer to {}", answer, question)))?;
+                session.game.ask(question, response).map_err(refused)?;
+                Ok(Json::object(state(&session.game)))
+            }
+            "best_question" => {
+                let session = self.session()?;
+                if session.game.status() != GameResult::Ongoing {
+                    return Ok(Json::object(vec![("question", Json::Null)]));
+                }
+                let guess = session.guesser.choose(&session.game);
+                Ok(Json::object(vec![("question", Json::Str(session.game.guess(guess).to_string()))]))
+            }
+            "possibilities" => Ok(Json::object(state(&self.session()?.game))),
+            _ => Err((METHOD_NOT_FOUND, format!("There's no method {}", method))),
+        }
+    }
+
+    // The reply to one line of input, or None for a notification.
+    fn handle(&mut self, line: &str) -> Option<Json> {
+        let request = match Json::parse(line) {
+            Ok(request) => request,
+            Err(err) => return Some(reply(Json::Null, Err((PARSE_ERROR, err)))),
+        };
+        let id = request.get("id").cloned();
+        let method = match request.get("method").map(Json::as_str) {
+            Some(Ok(method)) => method,
+            _ => return Some(reply(id.unwrap_or(Json::Null), Err((INVALID_REQUEST, "Missing method".to_string())))),
+        };
+        let empty = Json::Object(vec![]);
+        let result = match request.get("params") {
+            None => self.call(method, &empty),
+            Some(params @ Json::Object(_)) => self.call(method, params),
+            Some(_) => Err(invalid("params must be an object")),
+        };
+        id.map(|id| reply(id, result))
+    }
+}
+
+fn reply(id: Json, result: Result<Json, Failure>) -> Json {
+    let outcome = match result {
+        Ok(result) => ("result", result),
+        Err((code, message)) => ("error", Json::object(vec![("code", Json::Int(code)), ("message", Json::Str(message))])),
+    };
+    Json::object(vec![("jsonrpc", Json::Str("2.0".to_string())), ("id", id), outcome])
+}
+
+/// Answers requests on standard input until it runs out.
+pub fn run_rpc(setup: OpponentSetup, guesser_name: String) {
+    let mut rpc = Rpc { setup, guesser_name, session: None };
+    while let Some(line) = Terminal.read_line() {
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(reply) = rpc.handle(&line) {
+            println!("{}", reply);
+        }
+    }
+}
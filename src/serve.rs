@@ -0,0 +1,359 @@
+//! Hosting games over TCP, for two people on different machines to play each
+//! other. The first to connect thinks of a number and answers the questions,
+//! perhaps lying, and the second asks them. Both type plain lines, so nc or telnet
+//! will do to connect, or with --websocket, browsers connect over WebSockets and are
+//! sent a JSON object for each thing that happens. The server keeps the rules,
+//! refusing any answer that would need more lies than the game allows, and plays a
+//! game for each pair of players to connect, each on its own thread.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use crate::game::GameResult::*;
+use crate::json::{possibilities_to_json, response_to_json, Json, ToJson};
+use crate::websocket::WebSocket;
+use crate::{GameState, Question, Response};
+
+enum Connection {
+    Lines { reader: BufReader<TcpStream>, writer: TcpStream },
+    WebSocket(WebSocket),
+}
+
+// One end of a game, named by where it connected from.
+struct Player {
+    name: String,
+    connection: Connection,
+}
+
+impl Player {
+    fn new(stream: TcpStream, websocket: bool) -> Result<Player, String> {
+        let name = stream.peer_addr().map_or_else(|_| "someone".to_string(), |address| address.to_string());
+        let connection = if websocket {
+            // So a client that never finishes the handshake can't hold up everyone after it
+            stream.set_read_timeout(Some(Duration::from_secs(10))).map_err(|err| err.to_string())?;
+            let socket = WebSocket::accept(stream.try_clone().map_err(|err| err.to_string())?)?;
+            stream.set_read_timeout(None).map_err(|err| err.to_string())?;
+            Connection::WebSocket(socket)
+        } else {
+            let writer = stream.try_clone().map_err(|err| err.to_string())?;
+            Connection::Lines { reader: BufReader::new(stream), writer }
+        };
+        Ok(Player { name, connection })
+    }
+
+    // Sends `text` as a line, or over a WebSocket as a JSON object with `event`
+    // saying what happened, `text` and `fields`. A player who has left simply
+    // misses it.
+    fn emit(&mut self, event: &str, text: &str, mut fields: Vec<(&str, Json)>) {
+        match self.connection {
+            Connection::Lines { ref mut writer, .. } => {
+                let _ = writeln!(writer, "{}", text);
+            }
+            Connection::WebSocket(ref mut socket) => {
+                fields.insert(0, ("event", Json::Str(event.to_string())));
+                fields.insert(1, ("text", Json::Str(text.to_string())));
+                let _ = socket.send(&Json::object(fields).to_string());
+            }
+        }
+    }
+
+    fn send(&mut self, text: &str) {
+        self.emit("message", text, vec![]);
+    }
+
+    // The next line, trimmed, or None once the player has left. WebSocket messages
+    // may be JSON objects with the line as their question, response or command.
+    fn read_line(&mut self) -> Option<String> {
+        match self.connection {
+            Connection::Lines { ref mut reader, .. } => {
+                let mut line = String::new();
+                match reader.read_line(&mut line) {
+                    Ok(0) | Err(_) => None,
+                    Ok(_) => Some(line.trim().to_string()),
+                }
+            }
+            Connection::WebSocket(ref mut socket) => {
+                let message = socket.receive()?;
+                let line = match Json::parse(&message) {
+                    Ok(json) => ["question", "response", "command"].iter()
+                        .find_map(|&key| json.get(key).and_then(|value| value.as_str().ok()))
+                        .unwrap_or("")
+                        .to_string(),
+                    Err(_) => message,
+                };
+                Some(line.trim().to_string())
+            }
+        }
+    }
+}
+
+// An answer typed as y or n, or l, e or g for comparisons.
+fn parse_answer(question: &Question, input: &str) -> Option<Response> {
+    let three_way = question.responses().contains(&Response::Equal);
+    match (three_way, input.to_lowercase().as_str()) {
+        (false, "y" | "yes") | (true, "l" | "less") => Some(Response::Yes),
+        (false, "n" | "no") | (true, "g" | "greater") => Some(Response::No),
+        (true, "e" | "equal") => Some(Response::Equal),
+        _ => None,
+    }
+}
+
+// The fields of the end event, sent to both players however the game ends.
+fn ending(game: &GameState) -> Vec<(&'static str, Json)> {
+    vec![("status", game.status().to_json()), ("questions", Json::Int(game.history().len() as i128))]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Answerer,
+    Guesser,
+}
+
+// Something to tell one of the players, as Player::emit sends it.
+struct Message {
+    to: Role,
+    event: &'static str,
+    text: String,
+    fields: Vec<(&'static str, Json)>,
+}
+
+impl Message {
+    fn new(to: Role, event: &'static str, text: String, fields: Vec<(&'static str, Json)>) -> Message {
+        Message { to, event, text, fields }
+    }
+
+    fn text(to: Role, text: &str) -> Message {
+        Message::new(to, "message", text.to_string(), vec![])
+    }
+}
+
+// Keeps the rules of one game between two players, apart from how they are
+// connected: it is handed each line as it arrives, and says what to tell whom.
+// Only one player is ever waited on, so a driver can block reading from them, as
+// play does, or read from both as lines come and pass each one on.
+struct Referee {
+    game: GameState,
+    // The question the answerer is considering, if any
+    asked: Option<Question>,
+    // How the game ended, once it has
+    result: Option<String>,
+}
+
+impl Referee {
+    fn new(game: GameState, answerer: &str, guesser: &str) -> (Referee, Vec<Message>) {
+        let rules = format!("{}, {}", game.describe(), game.describe_range());
+        let mut messages = vec![
+            Message::new(
+                Role::Answerer,
+                "start",
+                format!("{} will guess your number, with {}. Answer y or n to each question.", guesser, rules),
+                vec![("role", Json::Str("answerer".to_string())), ("opponent", Json::Str(guesser.to_string())), ("game", game.to_json())],
+            ),
+            Message::new(
+                Role::Guesser,
+                "start",
+                format!(
+                    "Guess {}'s number, with {}. Type a number to ask whether it is less than that, =42 to ask whether it is 42, \
+                     3..7 whether it is at least 3 and less than 7, status to see what is still possible, or resign to give up.",
+                    answerer, rules
+                ),
+                vec![("role", Json::Str("guesser".to_string())), ("opponent", Json::Str(answerer.to_string())), ("game", game.to_json())],
+            ),
+        ];
+        let mut referee = Referee { game, asked: None, result: None };
+        messages.extend(referee.next_turn());
+        (referee, messages)
+    }
+
+    // Who the game is waiting on.
+    fn waiting_for(&self) -> Role {
+        if self.asked.is_some() { Role::Answerer } else { Role::Guesser }
+    }
+
+    fn end(&mut self, result: &str) -> Vec<Message> {
+        self.result = Some(result.to_string());
+        vec![
+            Message::new(Role::Answerer, "end", result.to_string(), ending(&self.game)),
+            Message::new(Role::Guesser, "end", result.to_string(), ending(&self.game)),
+        ]
+    }
+
+    // Ends the game if it is over, or else asks the guesser for a question.
+    fn next_turn(&mut self) -> Vec<Message> {
+        match self.game.status() {
+            Ongoing => vec![Message::new(
+                Role::Guesser,
+                "prompt",
+                format!("{}: Ask a question", self.game.history().len()),
+                vec![("question", Json::Int(self.game.history().len() as i128))],
+            )],
+            Finished(answer) => self.end(&format!("Found {} in {} questions", answer, self.game.history().len())),
+            Lost => self.end(&format!("Out of questions, with {} still possible", self.game.candidates())),
+            Impossible => unreachable!("Answers needing too many lies are refused"),
+        }
+    }
+
+    // A line from `from`, who may not be the one the game is waiting on.
+    fn receive(&mut self, from: Role, input: &str) -> Vec<Message> {
+        if self.result.is_some() || input.is_empty() {
+            return vec![];
+        }
+        match (from, self.waiting_for()) {
+            (Role::Guesser, Role::Guesser) => self.question(input),
+            (Role::Answerer, Role::Answerer) => self.answer(input),
+            (Role::Guesser, Role::Answerer) => vec![Message::text(from, "Wait for the answer")],
+            (Role::Answerer, Role::Guesser) => vec![Message::text(from, "Wait for the next question")],
+        }
+    }
+
+    fn question(&mut self, input: &str) -> Vec<Message> {
+        let game = &self.game;
+        let question = match input {
+            "status" => {
+                return vec![Message::new(
+                    Role::Guesser,
+                    "state",
+                    format!("Still possible: {}", game.candidates()),
+                    vec![("possibilities", possibilities_to_json(game)), ("status", game.status().to_json())],
+                )];
+            }
+            "resign" => {
+                let resigned = format!("Resigned, with {} still possible", game.candidates());
+                self.result = Some("The guesser resigned".to_string());
+                return vec![
+                    Message::new(Role::Answerer, "end", "The guesser resigned".to_string(), ending(game)),
+                    Message::new(Role::Guesser, "end", resigned, ending(game)),
+                ];
+            }
+            _ => match input.parse::<Question>() {
+                // A number on its own asks whatever a guess asks in this game
+                Ok(Question::Less(guess)) => game.guess(guess),
+                Ok(question) => question,
+                Err(err) => return vec![Message::text(Role::Guesser, &err.to_string())],
+            },
+        };
+        if let Err(err) = game.check_question(&question) {
+            return vec![Message::text(Role::Guesser, &err.to_string())];
+        }
+        let three_way = question.responses().contains(&Response::Equal);
+        let choices = if three_way { "(l/e/g)" } else { "(y/n)" };
+        let answers: &[&str] = if three_way { &["l", "e", "g"] } else { &["y", "n"] };
+        let messages = vec![
+            Message::text(Role::Guesser, "Waiting for the answer"),
+            Message::new(
+                Role::Answerer,
+                "question",
+                format!("{}: Is your number {}? {}", game.history().len(), question.describe(), choices),
+                vec![
+                    ("question", Json::Int(game.history().len() as i128)),
+                    ("guess", question.to_json()),
+                    ("answers", Json::Array(answers.iter().map(|answer| Json::Str(answer.to_string())).collect())),
+                ],
+            ),
+        ];
+        self.asked = Some(question);
+        messages
+    }
+
+    fn answer(&mut self, input: &str) -> Vec<Message> {
+        let question = self.asked.clone().expect("The answerer is only waited on with a question asked");
+        let response = match parse_answer(&question, input) {
+            Some(response) => response,
+            None => {
+                let choices = if question.responses().contains(&Response::Equal) { "(l/e/g)" } else { "(y/n)" };
+                return vec![Message::text(Role::Answerer, &format!("Please answer {}", choices))];
+            }
+        };
+        let mut next = self.game.clone();
+        next.ask(question.clone(), response).expect("Checked questions take their own answers");
+        if next.status() == Impossible {
+            return vec![Message::text(
+                Role::Answerer,
+                &format!("That would need more lies than {} allows, so give another answer", self.game.describe()),
+            )];
+        }
+        self.game = next;
+        self.asked = None;
+        let answered = question.describe_answer(response);
+        let turn = || vec![
+            ("question", Json::Int(self.game.history().len() as i128 - 1)),
+            ("guess", question.to_json()),
+            ("response", response_to_json(&question, response)),
+            ("possibilities", possibilities_to_json(&self.game)),
+            ("status", self.game.status().to_json()),
+        ];
+        let mut messages = vec![
+            Message::new(Role::Answerer, "turn", format!("You answered {}", answered), turn()),
+            Message::new(Role::Guesser, "turn", answered, turn()),
+        ];
+        messages.extend(self.next_turn());
+        messages
+    }
+
+    // The player `who` has gone, which ends the game.
+    fn left(&mut self, who: Role) -> Vec<Message> {
+        let (left, other) = match who {
+            Role::Answerer => ("The answerer left", Role::Guesser),
+            Role::Guesser => ("The guesser left", Role::Answerer),
+        };
+        self.result = Some(left.to_string());
+        vec![Message::new(other, "end", left.to_string(), ending(&self.game))]
+    }
+}
+
+// Plays one game between the two, reading from whichever the game waits on, and
+// returns how it ended for the server's log.
+fn play(game: GameState, mut answerer: Player, mut guesser: Player) -> String {
+    let (mut referee, mut messages) = Referee::new(game, &answerer.name, &guesser.name);
+    loop {
+        for message in messages {
+            let player = if message.to == Role::Answerer { &mut answerer } else { &mut guesser };
+            player.emit(message.event, &message.text, message.fields);
+        }
+        if let Some(ref result) = referee.result {
+            return result.clone();
+        }
+        let from = referee.waiting_for();
+        let player = if from == Role::Answerer { &mut answerer } else { &mut guesser };
+        messages = match player.read_line() {
+            Some(input) => referee.receive(from, &input),
+            None => referee.left(from),
+        };
+    }
+}
+
+/// Listens on `port` for pairs of players, starting a game of `game`'s rules for
+/// each, over WebSockets if `websocket`. Runs until the server is stopped.
+pub fn serve(game: GameState, port: u16, websocket: bool) -> Result<(), String> {
+    let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|err| format!("Couldn't listen on port {}: {}", port, err))?;
+    println!("Listening on port {} for games with {}, {}", port, game.describe(), game.describe_range());
+    let mut waiting: Option<Player> = None;
+    let mut games = 0;
+    for stream in listener.incoming() {
+        let player = match stream.map_err(|err| err.to_string()).and_then(|stream| Player::new(stream, websocket)) {
+            Ok(player) => player,
+            Err(err) => {
+                println!("Couldn't accept a player: {}", err);
+                continue;
+            }
+        };
+        match waiting.take() {
+            None => {
+                let mut answerer = player;
+                println!("{} is waiting to answer", answerer.name);
+                answerer.send("Think of a number, and wait for someone to guess it");
+                waiting = Some(answerer);
+            }
+            Some(answerer) => {
+                games += 1;
+                println!("Game {}: {} answers and {} guesses", games, answerer.name, player.name);
+                let game = game.clone();
+                let number = games;
+                thread::spawn(move || println!("Game {}: {}", number, play(game, answerer, player)));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,416 @@
+//! The opponent and guesser a game is played with, as chosen on the command line,
+//! and the save files that keep the opponent with a game left unfinished.
+
+use std::fs;
+use std::process::exit;
+use std::time::Duration;
+
+use crate::cost::{CheapestGuesser, CostModel, CostSolver};
+use crate::engine::ProgramOpponent;
+use crate::expression::Script;
+use crate::frontend::{Frontend, Lines};
+use crate::game::GameResult::*;
+use crate::guesser::{Greedy, Median};
+use crate::input::{Input, Terminal};
+use crate::json::{FromJson, Json, ToJson};
+use crate::learned::{Learned, QTable};
+use crate::mcts::Mcts;
+use crate::opponent::{Adversary, Honest, NoisyLiar, RandomLiar, Scripted};
+use crate::plugin::{self, Plugin};
+use crate::rng::Rng;
+use crate::solver::{ExpectedOpponent, MinimaxGuesser, MinimaxOpponent};
+use crate::style::Style;
+use crate::value::{value_function, volume_bound};
+use crate::{GameState, Guesser, Number, Opponent, Question, Response, Solver};
+
+// Whoever is at the keyboard, answering the computer's questions in solve mode,
+// asked through `frontend`.
+struct Human {
+    frontend: Box<dyn Frontend>,
+    input: Box<dyn Input>,
+}
+
+impl Opponent for Human {
+    fn respond(&mut self, game: &GameState, question: &Question) -> Response {
+        let three_way = question.responses().contains(&Response::Equal);
+        let may_pass = game.passes_left() > 0;
+        loop {
+            let choices = match (three_way, may_pass) {
+                (false, false) => "(y/n)",
+                (false, true) => "(y/n, or p to pass)",
+                (true, false) => "(l/e/g)",
+                (true, true) => "(l/e/g, or p to pass)",
+            };
+            self.frontend.say(&format!("Is your number {}? {}", question.describe(), choices));
+            let input = match self.input.read_line() {
+                Some(input) => input,
+                // There's no way to give up from inside an answer, so stop here
+                None => {
+                    self.frontend.say("The input ended before the question was answered");
+                    exit(1);
+                }
+            };
+            match (three_way, input.to_lowercase().as_str()) {
+                (false, "y" | "yes") | (true, "l" | "less") => return Response::Yes,
+                (false, "n" | "no") | (true, "g" | "greater") => return Response::No,
+                (true, "e" | "equal") => return Response::Equal,
+                (_, "p" | "pass") if may_pass => return Response::Pass,
+                (false, _) => self.frontend.say("Please answer y or n"),
+                (true, _) => self.frontend.say("Please answer l, e or g"),
+            }
+        }
+    }
+}
+
+/// Who answers the questions, as --opponent names them.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OpponentKind {
+    Adversarial,
+    Honest,
+    Random,
+    Human,
+    Minimax,
+    Expected,
+    /// Another program, run as the setup's program says
+    Program,
+    /// A shared library, loaded from the setup's program
+    Plugin,
+    /// Answers as learned by train, from the model at the setup's program
+    Learned,
+    /// Answers as the script at the setup's program says
+    Script,
+}
+
+impl OpponentKind {
+    pub fn from_name(name: &str) -> Option<OpponentKind> {
+        match name {
+            "adversarial" => Some(OpponentKind::Adversarial),
+            "honest" => Some(OpponentKind::Honest),
+            "random" => Some(OpponentKind::Random),
+            "human" => Some(OpponentKind::Human),
+            "minimax" => Some(OpponentKind::Minimax),
+            "expected" => Some(OpponentKind::Expected),
+            _ => None,
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            OpponentKind::Adversarial => "adversarial",
+            OpponentKind::Honest => "honest",
+            OpponentKind::Random => "random",
+            OpponentKind::Human => "human",
+            OpponentKind::Minimax => "minimax",
+            OpponentKind::Expected => "expected",
+            OpponentKind::Program => "program",
+            OpponentKind::Plugin => "plugin",
+            OpponentKind::Learned => "learned",
+            OpponentKind::Script => "script",
+        }
+    }
+}
+
+/// The mcts guesser's defaults.
+pub const PLAYOUTS: u32 = 200;
+pub const EXPLORATION: f64 = 1.0;
+
+/// Everything about the opponent that is decided before the game starts.
+#[derive(Clone)]
+pub struct OpponentSetup {
+    pub kind: OpponentKind,
+    pub secret: Option<Number>,
+    pub lie_positions: Option<Vec<usize>>,
+    pub seed: Option<u64>,
+    /// Scores positions for the adversarial opponent and the greedy guesser
+    pub value: String,
+    /// How long the minimax opponent, the minimax guesser and hints may search
+    pub time_limit: Option<Duration>,
+    /// How many threads they search on. Not saved, as it is about the machine
+    /// rather than the game.
+    pub threads: usize,
+    /// What each question costs, for the minimax guesser to keep the total lowest
+    /// and for reporting. Not saved, as only the computer's guessing uses it.
+    pub cost: Option<CostModel>,
+    /// The command line of the program opponent, as in ./my_bot --fast, or the
+    /// path to the plugin opponent's library, the learned opponent's model or the
+    /// script opponent's script
+    pub program: Option<String>,
+    /// Where plugin guessers are found. Not saved, as the guesser isn't.
+    pub strategy_path: Option<String>,
+    /// How many playouts the mcts guesser plays a question, and how much it
+    /// explores. Not saved, as the guesser isn't.
+    pub playouts: u32,
+    pub exploration: f64,
+}
+
+impl OpponentSetup {
+    /// Whether the opponent makes random choices, which only the seed can repeat.
+    /// The others answer from the position alone.
+    pub fn randomised(&self, game: &GameState) -> bool {
+        game.lie_probability().is_some() || self.kind == OpponentKind::Honest || self.kind == OpponentKind::Random
+    }
+    /// Settles every random choice the opponent makes before the game, so that the
+    /// setup describes the opponent completely and can be saved.
+    pub fn resolve(&mut self, game: &GameState) -> Result<(), String> {
+        let seed = *self.seed.get_or_insert_with(|| Rng::from_time().next_u64());
+        let mut rng = Rng::new(seed);
+        let range = game.range();
+        let max_lies = game.max_lies();
+        if self.randomised(game) && self.secret.is_none() {
+            self.secret = Some(draw_secret(game, &mut rng));
+        }
+        if game.lie_probability().is_none() && self.kind == OpponentKind::Honest && self.lie_positions.is_none() {
+            // Lie somewhere within the length of a well played game, so the lies usually
+            // get used, and within the lie window if there is one
+            let horizon = volume_bound(game.count(range), max_lies) as u64;
+            let horizon = game.lie_window().map_or(horizon, |window| (window as u64).min(horizon));
+            self.lie_positions = Some((0..max_lies).map(|_| rng.below(horizon) as usize).collect());
+        }
+        if self.secret.is_some_and(|secret| !game.allows(secret)) {
+            return Err("--secret must be one of the numbers being guessed".to_string());
+        }
+        if self.lie_positions.as_ref().is_some_and(|positions| positions.len() > max_lies) {
+            return Err("--lie-at lists more lies than --lies allows".to_string());
+        }
+        Ok(())
+    }
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("kind", Json::Str(self.kind.name().to_string())),
+            ("secret", self.secret.map_or(Json::Null, Json::Int)),
+            ("lie_positions", self.lie_positions.as_ref().map_or(Json::Null, |positions| {
+                Json::Array(positions.iter().map(|&position| Json::Int(position as i128)).collect())
+            })),
+            ("seed", self.seed.map_or(Json::Null, |seed| Json::Int(i128::from(seed)))),
+            ("value", Json::Str(self.value.clone())),
+            ("time_limit", self.time_limit.map_or(Json::Null, |time_limit| Json::Float(time_limit.as_secs_f64()))),
+            ("program", self.program.clone().map_or(Json::Null, Json::Str)),
+        ])
+    }
+    pub fn from_json(json: &Json) -> Result<OpponentSetup, String> {
+        let kind = json.field("kind")?.as_str()?;
+        let secret = match json.get("secret") {
+            None | Some(&Json::Null) => None,
+            Some(secret) => Some(secret.as_number()?),
+        };
+        let seed = match json.get("seed") {
+            None | Some(&Json::Null) => None,
+            Some(seed) => Some(seed.as_u64()?),
+        };
+        let lie_positions = match json.get("lie_positions") {
+            None | Some(&Json::Null) => None,
+            Some(positions) => Some(positions.as_array()?.iter()
+                .map(|position| position.as_u64().map(|position| position as usize))
+                .collect::<Result<Vec<usize>, String>>()?),
+        };
+        let value = json.field("value")?.as_str()?.to_string();
+        let time_limit = match json.get("time_limit") {
+            None | Some(&Json::Null) => None,
+            Some(time_limit) => Some(Duration::try_from_secs_f64(time_limit.as_f64()?).map_err(|err| err.to_string())?),
+        };
+        if let Err(err) = value_function(&value) {
+            return Err(format!("The value function {}", err));
+        }
+        let program = match json.get("program") {
+            None | Some(&Json::Null) => None,
+            Some(program) => Some(program.as_str()?.to_string()),
+        };
+        let kind = match (kind, &program) {
+            ("program", Some(_)) => OpponentKind::Program,
+            ("program", None) => return Err("A program opponent needs its program".to_string()),
+            ("plugin", Some(_)) => OpponentKind::Plugin,
+            ("plugin", None) => return Err("A plugin opponent needs its library".to_string()),
+            ("learned", Some(_)) => OpponentKind::Learned,
+            ("learned", None) => return Err("A learned opponent needs its model".to_string()),
+            ("script", Some(_)) => OpponentKind::Script,
+            ("script", None) => return Err("A script opponent needs its script".to_string()),
+            _ => OpponentKind::from_name(kind).ok_or_else(|| format!("Unknown opponent {}", kind))?,
+        };
+        Ok(OpponentSetup {
+            kind,
+            secret,
+            lie_positions,
+            seed,
+            value,
+            time_limit,
+            threads: 1,
+            cost: None,
+            program,
+            strategy_path: None,
+            playouts: PLAYOUTS,
+            exploration: EXPLORATION,
+        })
+    }
+}
+
+const SAVE_FORMAT: &str = "one-lie save";
+const SAVE_VERSION: u64 = 1;
+
+/// Writes the game so far to `path` with the opponent that is answering it, for
+/// resume to carry on from.
+pub fn save_game(path: &str, game: &GameState, setup: &OpponentSetup) -> Result<(), String> {
+    let save = Json::object(vec![
+        ("format", Json::Str(SAVE_FORMAT.to_string())),
+        ("version", Json::Int(i128::from(SAVE_VERSION))),
+        ("game", game.to_json()),
+        ("opponent", setup.to_json()),
+    ]);
+    fs::write(path, format!("{}\n", save)).map_err(|err| err.to_string())
+}
+
+/// Reads a save file, checking that the opponent it describes could really have
+/// given the answers it records.
+pub fn load_game(path: &str) -> Result<(GameState, OpponentSetup), String> {
+    let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let save = Json::parse(&text)?;
+    if save.get("format") != Some(&Json::Str(SAVE_FORMAT.to_string())) {
+        return Err(format!("{} is not a one-lie save file", path));
+    }
+    let version = save.field("version")?.as_u64()?;
+    if version != SAVE_VERSION {
+        return Err(format!("Save file version {} is not supported", version));
+    }
+    let game = GameState::from_json(save.field("game")?)?;
+    let setup = OpponentSetup::from_json(save.field("opponent")?)?;
+    if let Some(secret) = setup.secret {
+        if !game.allows(secret) {
+            return Err(format!("Secret {} is out of range", secret));
+        }
+        let lies = game.history().iter()
+            .filter(|&&(ref question, response)| question.is_lie(secret, response))
+            .count();
+        if game.lie_probability().is_none() && lies > game.max_lies() {
+            return Err(format!("The answers would need {} lies about the secret", lies));
+        }
+    }
+    if setup.lie_positions.as_ref().is_some_and(|positions| positions.len() > game.max_lies()) {
+        return Err("More lie positions than lies allowed".to_string());
+    }
+    if game.status() != Ongoing {
+        return Err("That game is already over".to_string());
+    }
+    Ok((game, setup))
+}
+
+/// A secret for a randomised opponent to pick, weighted by the prior if there is one.
+pub fn draw_secret(game: &GameState, rng: &mut Rng) -> Number {
+    let range = game.range();
+    match game.prior() {
+        Some(prior) => prior.quantile(range, rng.fraction()),
+        None => {
+            let index = rng.below_u128(game.count(range));
+            game.nth(range, index).expect("The index is below the count")
+        }
+    }
+}
+
+/// A solver for the game, on the setup's threads and stopping its anytime searches
+/// at its time limit.
+pub fn make_solver(game: &GameState, setup: &OpponentSetup) -> Solver {
+    let solver = Solver::new(game.max_lies()).with_variant(game.variant()).with_threads(setup.threads);
+    match setup.time_limit {
+        None => solver,
+        Some(time_limit) => solver.with_time_limit(time_limit),
+    }
+}
+
+// The setup's seed first draws the secret, so the opponent's and guesser's random
+// choices each get a stream of their own, rather than replaying the one that
+// picked the secret and so depending on it.
+const OPPONENT_STREAM: u64 = 1;
+const GUESSER_STREAM: u64 = 2;
+
+/// The opponent the setup describes, for `game`.
+pub fn make_opponent(game: &GameState, setup: &OpponentSetup) -> Box<dyn Opponent> {
+    let max_lies = game.max_lies();
+    let rng = Rng::stream(setup.seed.expect("Opponent setup is resolved"), OPPONENT_STREAM);
+    let secret = setup.secret.unwrap_or(0);
+    let value = value_function(&setup.value).expect("Value function names are checked when parsed");
+    match (game.lie_probability(), setup.kind) {
+        (_, OpponentKind::Human) => Box::new(Human {
+            frontend: Box::new(Lines { style: Style::plain(), input: Box::new(Terminal) }),
+            input: Box::new(Terminal),
+        }),
+        (Some(lie_probability), _) => Box::new(NoisyLiar {
+            secret,
+            lie_probability,
+            rng,
+        }),
+        (None, OpponentKind::Adversarial) => Box::new(Adversary { value }),
+        (None, OpponentKind::Minimax) => Box::new(MinimaxOpponent { solver: make_solver(game, setup) }),
+        (None, OpponentKind::Expected) => Box::new(ExpectedOpponent { solver: Solver::new(max_lies).with_variant(game.variant()) }),
+        (None, OpponentKind::Program) => Box::new(ProgramOpponent::new(setup.program.clone().expect("Program opponents have a program"))),
+        (None, OpponentKind::Plugin) => Box::new(load_plugin(setup.program.as_deref().expect("Plugin opponents have a library"))),
+        (None, OpponentKind::Learned) => {
+            // The model was read when parsed, so can only fail if it has since changed
+            let table = read_model(setup.program.as_deref().expect("Learned opponents have a model")).unwrap_or_else(|err| {
+                eprintln!("error: {}", err);
+                exit(1);
+            });
+            Box::new(Learned { table, fallback: Adversary { value } })
+        }
+        (None, OpponentKind::Script) => {
+            // Likewise the script
+            let script = read_script(setup.program.as_deref().expect("Script opponents have a script")).unwrap_or_else(|err| {
+                eprintln!("error: {}", err);
+                exit(1);
+            });
+            Box::new(Scripted { script, fallback: Adversary { value } })
+        }
+        (None, OpponentKind::Honest) => Box::new(Honest {
+            secret,
+            lie_positions: setup.lie_positions.clone().expect("Opponent setup is resolved"),
+        }),
+        (None, OpponentKind::Random) => Box::new(RandomLiar {
+            secret,
+            horizon: volume_bound(game.count(game.range()), max_lies),
+            rng,
+        }),
+    }
+}
+
+/// The guesser --guesser names, for `game`.
+pub fn make_guesser(game: &GameState, setup: &OpponentSetup, guesser_name: &str) -> Box<dyn Guesser> {
+    let value = value_function(&setup.value).expect("Value function names are checked when parsed");
+    match (game.lie_probability(), guesser_name) {
+        (Some(_), _) => Box::new(Median),
+        (None, "greedy") => Box::new(Greedy { value }),
+        (None, "mcts") => Box::new(Mcts {
+            playouts: setup.playouts,
+            exploration: setup.exploration,
+            greedy: Greedy { value },
+            rng: Rng::stream(setup.seed.unwrap_or_else(|| Rng::from_time().next_u64()), GUESSER_STREAM),
+        }),
+        (None, "minimax") => match setup.cost {
+            Some(ref cost) => Box::new(CheapestGuesser {
+                solver: CostSolver::new(game.max_lies(), cost.clone()).with_variant(game.variant()),
+            }),
+            None => Box::new(MinimaxGuesser { solver: make_solver(game, setup) }),
+        },
+        // Any other name is a plugin, found when parsed
+        (None, name) => {
+            let dir = setup.strategy_path.as_deref().expect("Plugin guessers come with --strategy-path");
+            Box::new(load_plugin(&plugin::find(dir, name).expect("Plugin guessers are found when parsed")))
+        }
+    }
+}
+
+// The plugin at `path`, which was loaded when parsed, so can only fail if it has
+// since been removed.
+fn load_plugin(path: &str) -> Plugin {
+    Plugin::load(path).unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        exit(1);
+    })
+}
+
+/// The learned opponent's model, as train saved it.
+pub fn read_model(path: &str) -> Result<QTable, String> {
+    let bytes = fs::read(path).map_err(|err| format!("Couldn't read the model {}: {}", path, err))?;
+    QTable::from_bytes(&bytes).map_err(|err| format!("Couldn't read the model {}: {}", path, err))
+}
+
+/// The script opponent's script.
+pub fn read_script(path: &str) -> Result<Script, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Couldn't read the script {}: {}", path, err))?;
+    text.parse().map_err(|err| format!("Couldn't read the script {}: {}", path, err))
+}
@@ -0,0 +1,245 @@
+//! Many games played unwatched, for statistics, for comparing value functions,
+//! and for training the learned opponent.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::Path;
+
+use crate::frontend::Silent;
+use crate::game::GameResult::*;
+use crate::guesser::{worst_case, Greedy};
+use crate::learned::QTable;
+use crate::modes::run_solver;
+use crate::rng::Rng;
+use crate::setup::{make_guesser, make_opponent, read_model, OpponentKind, OpponentSetup};
+use crate::style::Style;
+use crate::value::{value_function, VALUE_FUNCTIONS};
+use crate::{GameState, Guesser, Number, Solver};
+
+/// Plays `games` unwatched games, each with its own seed drawn from the setup's,
+/// returning each finished game with the opponent setup it was played against.
+pub fn play_many(
+    game: &GameState,
+    setup: &OpponentSetup,
+    guesser: &mut dyn Guesser,
+    games: u64,
+) -> Result<Vec<(GameState, OpponentSetup)>, String> {
+    let mut seeds = Rng::new(setup.seed.unwrap_or_else(|| Rng::from_time().next_u64()));
+    let mut played = vec![];
+    // Opponents that search decide everything from the position alone, so one can
+    // answer in every game and keep what it has learned, as can a program or a
+    // plugin, which is told the whole position each time
+    let searches = game.lie_probability().is_none()
+        && matches!(setup.kind, OpponentKind::Minimax | OpponentKind::Expected | OpponentKind::Program | OpponentKind::Plugin | OpponentKind::Learned);
+    let mut searching = None;
+    for _ in 0..games {
+        let mut setup = setup.clone();
+        setup.seed = Some(seeds.next_u64());
+        setup.resolve(game)?;
+        let mut opponent = searching.take().unwrap_or_else(|| make_opponent(game, &setup));
+        // No one is watching to take an answer back
+        let finished = run_solver(game.clone(), guesser, &mut *opponent, &setup, &mut Silent, &mut VecDeque::new(), Style::plain(), false);
+        played.push((finished, setup));
+        if searches {
+            searching = Some(opponent);
+        }
+    }
+    Ok(played)
+}
+
+// One row per game. The lies are the questions answered falsely about the secret,
+// or about the number the guesser settled on when the opponent never had one, and
+// are separated by semicolons.
+fn write_csv(
+    path: &str,
+    played: &[(GameState, OpponentSetup)],
+    guesser_name: &str,
+    opponent_name: &str,
+) -> Result<(), std::io::Error> {
+    let mut csv = "seed,lower,limit,lies,guesser,opponent,questions,result,secret,lie_positions\n".to_string();
+    for (game, setup) in played {
+        let (result, settled) = match game.status() {
+            Finished(answer) => ("finished".to_string(), Some(answer)),
+            status => (format!("{:?}", status).to_lowercase(), None),
+        };
+        let lies = match setup.secret.or(settled) {
+            None => String::new(),
+            Some(number) => game.history().iter().enumerate()
+                .filter(|&(_, &(ref question, response))| question.is_lie(number, response))
+                .map(|(index, _)| index.to_string())
+                .collect::<Vec<_>>()
+                .join(";"),
+        };
+        let max_lies = match game.lie_probability() {
+            None => game.max_lies().to_string(),
+            Some(lie_probability) => format!("p={}", lie_probability),
+        };
+        csv += &format!(
+            "{},{},{},{},{},{},{},{},{},{}\n",
+            setup.seed.expect("Opponent setup is resolved"),
+            game.lower_limit(),
+            game.upper_limit(),
+            max_lies,
+            guesser_name,
+            opponent_name,
+            game.history().len(),
+            result,
+            setup.secret.map_or(String::new(), |secret| secret.to_string()),
+            lies
+        );
+    }
+    fs::write(path, csv)
+}
+
+/// Plays many games between the computer and an automated opponent, then sums up
+/// how many questions they took.
+pub fn simulate_games(
+    game: GameState,
+    setup: OpponentSetup,
+    guesser_name: &str,
+    games: u64,
+    csv: Option<&str>,
+) -> Result<(), String> {
+    let mut setup = setup;
+    let seed = *setup.seed.get_or_insert_with(|| Rng::from_time().next_u64());
+    // Built once, so the minimax guesser keeps what it has learned between games
+    let mut guesser = make_guesser(&game, &setup, guesser_name);
+    let played = play_many(&game, &setup, &mut *guesser, games)?;
+    // Noisy games always pit the median guesser against a noisy liar
+    let (guesser_name, opponent_name) = match game.lie_probability() {
+        Some(_) => ("median", "noisy"),
+        None => (guesser_name, setup.kind.name()),
+    };
+    if let Some(path) = csv {
+        write_csv(path, &played, guesser_name, opponent_name)
+            .map_err(|err| format!("Couldn't write {}: {}", path, err))?;
+    }
+    let mut counts: BTreeMap<usize, u64> = BTreeMap::new();
+    let mut wrong = 0;
+    let mut impossible = 0;
+    let mut lost = 0;
+    for (played, setup) in &played {
+        match played.status() {
+            Finished(answer) => {
+                if setup.secret.is_some_and(|secret| secret != answer) {
+                    wrong += 1;
+                }
+            }
+            Impossible => impossible += 1,
+            Lost => lost += 1,
+            Ongoing => unreachable!(),
+        }
+        *counts.entry(played.history().len()).or_insert(0) += 1;
+    }
+    println!(
+        "Played {} games with {}, {}: the {} guesser against the {} opponent",
+        games,
+        game.describe(),
+        game.describe_range(),
+        guesser_name,
+        opponent_name
+    );
+    if setup.randomised(&game) {
+        println!("Drawn from seed {}, so --seed {} replays these games", seed, seed);
+    }
+    let total: usize = counts.iter().map(|(&questions, &count)| questions * count as usize).sum();
+    let most = counts.values().cloned().max().unwrap_or(0);
+    println!(
+        "Questions: min {}, mean {:.2}, max {}",
+        counts.keys().next().unwrap_or(&0),
+        total as f64 / games as f64,
+        counts.keys().next_back().unwrap_or(&0)
+    );
+    for (questions, &count) in &counts {
+        let bar = "#".repeat((count * 40).div_ceil(most) as usize);
+        println!("{:>4} | {:<40} {}", questions, bar, count);
+    }
+    if let Some(ref cost) = setup.cost {
+        let costs: Vec<f64> = played.iter().map(|(played, _)| cost.total(played.history())).collect();
+        println!(
+            "Cost: min {:.2}, mean {:.2}, max {:.2}",
+            costs.iter().cloned().fold(f64::INFINITY, f64::min),
+            costs.iter().sum::<f64>() / games as f64,
+            costs.iter().cloned().fold(0.0, f64::max)
+        );
+    }
+    if wrong > 0 {
+        println!("Settled on the wrong number in {} game(s)", wrong);
+    }
+    if impossible > 0 {
+        println!("Caught the opponent out in {} game(s)", impossible);
+    }
+    if lost > 0 {
+        println!("Ran out of questions in {} game(s)", lost);
+    }
+    Ok(())
+}
+
+/// Pits the greedy guesser with each value function against the others, over a
+/// sweep of limits, with the perfect guesser's worst case for reference.
+pub fn benchmark_values(limits: &[Number], max_lies: usize, games: u64, setup: &OpponentSetup) -> Result<(), String> {
+    // One seed for every value function, so that each plays the same games
+    let mut setup = setup.clone();
+    let seed = *setup.seed.get_or_insert_with(|| Rng::from_time().next_u64());
+    println!(
+        "Questions needed with {}: the worst case, then the mean over {} games against the {} opponent, from seed {}",
+        GameState::new(1, max_lies).describe(),
+        games,
+        setup.kind.name(),
+        seed
+    );
+    let mut header = format!("{:>7} {:>8}", "limit", "optimal");
+    for name in VALUE_FUNCTIONS.iter() {
+        header += &format!(" {:>14}", name);
+    }
+    println!("{}", header);
+    let mut solver = Solver::new(max_lies);
+    for &upper_limit in limits {
+        let game = GameState::new(upper_limit, max_lies);
+        let optimal = solver.questions_needed(&game);
+        let mut row = format!("{:>7} {:>8}", upper_limit, optimal);
+        for name in VALUE_FUNCTIONS.iter() {
+            let mut greedy = Greedy {
+                value: value_function(name).expect("Every listed value function exists"),
+            };
+            // Searching every line of a hopeless strategy takes too long to be worth it
+            let cap = 2 * optimal;
+            let worst = worst_case(&mut greedy, &game, cap).map_or(format!(">{}", cap), |worst| worst.to_string());
+            let played = play_many(&game, &setup, &mut greedy, games)?;
+            let total: usize = played.iter().map(|(finished, _)| finished.history().len()).sum();
+            row += &format!(" {:>14}", format!("{} / {:.2}", worst, total as f64 / games as f64));
+        }
+        println!("{}", row);
+    }
+    Ok(())
+}
+
+/// Trains the learned opponent by self-play, carrying on from the model at `out`
+/// if there is one.
+pub fn train_model(out: &str, limits: &[Number], max_lies: usize, episodes: u64, seed: Option<u64>) -> Result<(), String> {
+    let mut table = if Path::new(out).exists() { read_model(out)? } else { QTable::new(max_lies) };
+    if table.max_lies() != max_lies {
+        return Err(format!("{} was trained with up to {} lies, not {}", out, table.max_lies(), max_lies));
+    }
+    let seed = seed.unwrap_or_else(|| Rng::from_time().next_u64());
+    println!(
+        "Training with {} over {} games out of {}, from seed {}",
+        GameState::new(1, max_lies).describe(),
+        episodes,
+        limits.iter().map(Number::to_string).collect::<Vec<_>>().join(", "),
+        seed
+    );
+    let mut rng = Rng::new(seed);
+    // Report every tenth of the way, to show it is getting somewhere
+    let step = episodes.div_ceil(10).max(1);
+    let mut trained = 0;
+    while trained < episodes {
+        let batch = step.min(episodes - trained);
+        table.train(limits, batch, &mut rng);
+        trained += batch;
+        println!("{} games played, {} questions learned", trained, table.len());
+    }
+    fs::write(out, table.to_bytes()).map_err(|err| format!("Couldn't write {}: {}", out, err))?;
+    println!("Saved the model to {}, play against it with --opponent learned:{}", out, out);
+    Ok(())
+}
@@ -0,0 +1,842 @@
+use std::cmp::{max, min};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::game::Response::*;
+use crate::game::{GameResult, GameState, Count, Number, Question, Response, Variant};
+use crate::guesser::Guesser;
+use crate::opponent::Opponent;
+use crate::value::{better_value, lie_patterns, lie_placements};
+
+// A game's canonical form, from GameState::canonical.
+pub(crate) type State = Vec<(Count, usize)>;
+
+fn push_run(state: &mut State, len: Count, lies: usize) {
+    if let Some(last) = state.last_mut() {
+        if last.1 == lies {
+            last.0 += len;
+            return;
+        }
+    }
+    state.push((len, lies));
+}
+
+pub(crate) fn count(state: &[(Count, usize)]) -> Count {
+    state.iter().map(|&(len, _)| len).sum()
+}
+
+pub(crate) fn capacity(questions: usize) -> u128 {
+    if questions >= 127 {
+        u128::MAX
+    } else {
+        1 << questions
+    }
+}
+
+// What the solver has learned about one canonical state.
+#[derive(Clone, Copy)]
+struct Entry {
+    // The most questions known not to suffice
+    insufficient: usize,
+    // The fewest questions known to suffice, and the first question, in the order
+    // they are tried, that finishes within them
+    sufficient: usize,
+    best: Option<Count>,
+}
+
+const UNKNOWN: Entry = Entry {
+    insufficient: 0,
+    sufficient: usize::MAX,
+    best: None,
+};
+
+impl Entry {
+    // Combines what two searches learned about the same state.
+    fn merge(&mut self, other: Entry) {
+        self.insufficient = max(self.insufficient, other.insufficient);
+        if other.sufficient < self.sufficient {
+            self.sufficient = other.sufficient;
+            self.best = other.best;
+        }
+    }
+}
+
+// The positions of a window from its centre outwards, in the order centre,
+// centre + 1, centre - 1, centre + 2 and so on, skipping any that have left the
+// window as it shrinks.
+struct Outwards {
+    up: Option<Count>,
+    down: Option<Count>,
+    upwards: bool,
+}
+
+impl Outwards {
+    fn new(centre: Count) -> Outwards {
+        Outwards {
+            up: centre.checked_add(1),
+            down: Some(centre),
+            upwards: false,
+        }
+    }
+
+    fn next(&mut self, first: Count, last: Count) -> Option<Count> {
+        let in_window = |position: Option<Count>| position.is_some_and(|position| first <= position && position <= last);
+        if !in_window(self.up) && !in_window(self.down) {
+            return None;
+        }
+        if !in_window(if self.upwards { self.up } else { self.down }) {
+            self.upwards = !self.upwards;
+        }
+        let position = if self.upwards { self.up } else { self.down }.expect("Positions in the window exist");
+        if self.upwards {
+            self.up = position.checked_add(1);
+        } else {
+            self.down = position.checked_sub(1);
+        }
+        self.upwards = !self.upwards;
+        Some(position)
+    }
+}
+
+/// Exact worst-case search over the game tree, as long as the number of answer
+/// sequences to tell apart fits in a u128, which it does for up to about 2^120
+/// numbers with one lie.
+///
+/// Positions are remembered by their [`GameState::canonical`] form in a
+/// transposition table, so work done for one branch or one turn is reused by
+/// every other that reaches the same position. Keep a solver for as long as the
+/// number of lies stays the same to make the most of it.
+pub struct Solver {
+    max_lies: usize,
+    variant: Variant,
+    table: HashMap<State, Entry>,
+    // What was known when a parallel search started, shared by its threads, each of
+    // which keeps what it learns in its own table until the search ends
+    shared: Arc<HashMap<State, Entry>>,
+    threads: usize,
+    expected: HashMap<State, f64>,
+    // How long the anytime searches may take, when they are told to stop
+    time_limit: Option<Duration>,
+    deadline: Option<Instant>,
+    timed_out: bool,
+    steps: u32,
+}
+
+/// The best question an anytime search found before its time ran out, with bounds
+/// on how good it is.
+pub struct Anytime {
+    pub guess: Number,
+    /// No question finishes in fewer questions than this, counting itself.
+    pub at_least: usize,
+    /// The questions, counting itself, that `guess` is known to finish within, if
+    /// the search finished in time. It then equals `at_least`, as the question is
+    /// the best there is.
+    pub at_most: Option<usize>,
+}
+
+impl Solver {
+    pub fn new(max_lies: usize) -> Solver {
+        Solver {
+            max_lies,
+            variant: Variant::Standard,
+            table: HashMap::new(),
+            shared: Arc::new(HashMap::new()),
+            threads: 1,
+            expected: HashMap::new(),
+            time_limit: None,
+            deadline: None,
+            timed_out: false,
+            steps: 0,
+        }
+    }
+
+    /// The same solver, with the anytime searches, [`Solver::best_question_in_time`]
+    /// and [`Solver::questions_bound`], stopping after `time_limit`.
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Solver {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    /// The same solver, trying the candidate questions in each position it is
+    /// asked about on `threads` threads at once. Answers are the same as with one.
+    pub fn with_threads(mut self, threads: usize) -> Solver {
+        self.threads = max(threads, 1);
+        self
+    }
+
+    /// The same solver, for games where the opponent only tells the lies `variant`
+    /// allows. Directional games can't be solved, as positions don't record which
+    /// way each number's lies went.
+    pub fn with_variant(mut self, variant: Variant) -> Solver {
+        self.variant = variant;
+        self
+    }
+
+    // The answers a question may get: No (High) and Yes (Low), and in three-way
+    // games Equal.
+    pub(crate) fn responses(&self) -> &'static [Response] {
+        match self.variant {
+            Variant::ThreeWay => &[No, Yes, Equal],
+            _ => &[No, Yes],
+        }
+    }
+
+    // The state after `response` to the question about the number at `position`:
+    // whether the secret is less than it, or in three-way games how it compares.
+    // The numbers the answer rules out gain a lie, or are dropped if they have none
+    // left or the variant forbids the lie.
+    pub(crate) fn split(&self, state: &[(Count, usize)], position: Count, response: Response) -> State {
+        let equal = if self.variant == Variant::ThreeWay { 1 } else { 0 };
+        let mut next = vec![];
+        let mut start = 0;
+        for &(len, lies) in state {
+            let below = min(len, position.saturating_sub(start));
+            let through = min(len, position.saturating_add(equal).saturating_sub(start));
+            for &(part, truth) in &[(below, Yes), (through - below, Equal), (len - through, No)] {
+                if part == 0 {
+                    continue;
+                }
+                if truth == response {
+                    push_run(&mut next, part, lies);
+                } else if lies < self.max_lies && self.variant.allows_lie(truth) {
+                    push_run(&mut next, part, lies + 1);
+                }
+            }
+            start += len;
+        }
+        next
+    }
+
+    // Whether `response` to the question at `position` ends a truthful-last game,
+    // leaving at most one number if it is true. The numbers it is true for are
+    // those below the position for Yes and the rest for No.
+    pub(crate) fn finishes(&self, state: &[(Count, usize)], position: Count, response: Response) -> bool {
+        let below = min(position, count(state));
+        self.variant == Variant::TruthfulLast && if response == Yes { below } else { count(state) - below } <= 1
+    }
+
+    // How many answer sequences `questions` questions can give.
+    pub(crate) fn capacity(&self, questions: usize) -> u128 {
+        match self.variant {
+            Variant::ThreeWay => 3u128.checked_pow(questions as u32).unwrap_or(u128::MAX),
+            _ => capacity(questions),
+        }
+    }
+
+    // How many answer sequences the state needs to tell apart with `questions`
+    // questions left: each number, once for every way of placing the lies it has
+    // left, and in three-way games of choosing which wrong answer each lie gives.
+    // With half lies not every placement is open, and in truthful-last games the
+    // guesser may stop before the lies are placed, so each number is only sure to
+    // need one.
+    pub(crate) fn volume(&self, state: &[(Count, usize)], questions: usize) -> u128 {
+        state.iter()
+            .map(|&(len, lies)| match self.variant {
+                Variant::Standard => len.saturating_mul(lie_placements(questions, self.max_lies - lies)),
+                Variant::HalfLie | Variant::TruthfulLast | Variant::Directional => len,
+                Variant::ThreeWay => len.saturating_mul(lie_patterns(questions, self.max_lies - lies, 2)),
+            })
+            .fold(0, u128::saturating_add)
+    }
+
+    fn lookup(&self, state: &[(Count, usize)]) -> Option<Entry> {
+        match self.table.get(state) {
+            Some(&entry) => Some(entry),
+            None if self.shared.is_empty() => None,
+            None => self.shared.get(state).cloned(),
+        }
+    }
+
+    // Whether an anytime search has run past its deadline. The clock is only read
+    // every so often, as that is slow next to a step of the search.
+    fn out_of_time(&mut self) -> bool {
+        if let Some(deadline) = self.deadline {
+            self.steps = self.steps.wrapping_add(1);
+            if self.steps & 1023 == 0 && Instant::now() >= deadline {
+                self.timed_out = true;
+            }
+        }
+        self.timed_out
+    }
+
+    // Whether the guesser can always finish from `state` within `questions` questions.
+    fn can_finish(&mut self, state: &[(Count, usize)], questions: usize) -> bool {
+        if count(state) <= 1 {
+            return true;
+        }
+        if self.volume(state, questions) > self.capacity(questions) {
+            return false;
+        }
+        if let Some(entry) = self.lookup(state) {
+            if questions >= entry.sufficient {
+                return true;
+            }
+            if questions <= entry.insufficient {
+                return false;
+            }
+        }
+        if self.out_of_time() {
+            return false;
+        }
+        let position = self.finishing_position(state, questions);
+        // A search cut short proves nothing
+        if self.timed_out {
+            return false;
+        }
+        let mut entry = self.lookup(state).unwrap_or(UNKNOWN);
+        match position {
+            Some(position) => {
+                entry.sufficient = questions;
+                entry.best = Some(position);
+            }
+            None => entry.insufficient = max(entry.insufficient, questions),
+        }
+        self.table.insert(state.to_vec(), entry);
+        position.is_some()
+    }
+
+    // The positions whose High and Low answers both leave few enough answer
+    // sequences for the remaining questions. Every other question is hopeless, and
+    // since moving the position up only shrinks the High volume and grows the Low
+    // one, these are contiguous.
+    pub(crate) fn window(&self, state: &[(Count, usize)], questions: usize) -> Option<(Count, Count)> {
+        let capacity = self.capacity(questions - 1);
+        let fits = |position| {
+            (
+                self.finishes(state, position, No) || self.volume(&self.split(state, position, No), questions - 1) <= capacity,
+                self.finishes(state, position, Yes) || self.volume(&self.split(state, position, Yes), questions - 1) <= capacity,
+            )
+        };
+        let total = count(state);
+        if total < 2 {
+            return None;
+        }
+        // First position where the High answer fits
+        let (mut lower, mut upper) = (self.first_position(), total - 1);
+        while lower < upper {
+            let mid = lower + (upper - lower) / 2;
+            if fits(mid).0 {
+                upper = mid;
+            } else {
+                lower = mid + 1;
+            }
+        }
+        let first = lower;
+        if fits(first) != (true, true) {
+            return None;
+        }
+        // Last position where the Low answer fits
+        let (mut lower, mut upper) = (first, total - 1);
+        while lower < upper {
+            let mid = upper - (upper - lower) / 2;
+            if fits(mid).1 {
+                lower = mid;
+            } else {
+                upper = mid - 1;
+            }
+        }
+        Some((first, lower))
+    }
+
+    // The lowest position worth asking about. Asking whether the secret is less
+    // than the lowest number learns nothing, but comparing with it can.
+    fn first_position(&self) -> Count {
+        match self.variant {
+            Variant::ThreeWay => 0,
+            _ => 1,
+        }
+    }
+
+    // The position in the window that most evenly splits the volume between the
+    // High and Low answers, since good questions are usually near there.
+    pub(crate) fn centre(&self, state: &[(Count, usize)], questions: usize, (first, last): (Count, Count)) -> Count {
+        let high_heavier = |position| {
+            self.volume(&self.split(state, position, No), questions - 1)
+                > self.volume(&self.split(state, position, Yes), questions - 1)
+        };
+        let (mut lower, mut upper) = (first, last);
+        while lower < upper {
+            let mid = lower + (upper - lower) / 2;
+            if high_heavier(mid) {
+                lower = mid + 1;
+            } else {
+                upper = mid;
+            }
+        }
+        lower
+    }
+
+    // The first position whose answers both leave states that the remaining
+    // questions finish, trying the window from the centre outwards.
+    //
+    // This prunes like alpha-beta search. Moving a question down only adds numbers
+    // to the High state or takes lies away from them, so if the High state can't be
+    // finished, neither can it for any lower position, and likewise for the Low
+    // state and higher positions. Each failure cuts off one side of the window, and
+    // the heavier state is tried first as the one more likely to fail. An Equal
+    // answer that can't be finished only rules out its own position.
+    fn finishing_position(&mut self, state: &[(Count, usize)], questions: usize) -> Option<Count> {
+        let (mut first, mut last) = self.window(state, questions)?;
+        let mut order = Outwards::new(self.centre(state, questions, (first, last)));
+        if self.threads > 1 {
+            return self.finishing_position_parallel(state, questions, (first, last), order);
+        }
+        while let Some(position) = order.next(first, last) {
+            match self.failing_answer(state, position, questions) {
+                Some(No) => first = position + 1,
+                Some(Yes) => last = position.checked_sub(1)?,
+                Some(Equal | Pass) => {}
+                None => return Some(position),
+            }
+        }
+        None
+    }
+
+    // An answer to the question at `position` that leaves a state the remaining
+    // questions can't finish, or None if every answer's can be.
+    fn failing_answer(&mut self, state: &[(Count, usize)], position: Count, questions: usize) -> Option<Response> {
+        let mut answers: Vec<(Response, State, u128)> = self.responses().iter()
+            .filter(|&&response| !self.finishes(state, position, response))
+            .map(|&response| {
+                let next = self.split(state, position, response);
+                let volume = self.volume(&next, questions - 1);
+                (response, next, volume)
+            })
+            .collect();
+        // Heaviest first, and on a tie High before Low
+        answers.sort_by_key(|&(_, _, volume)| std::cmp::Reverse(volume));
+        answers.iter().find(|(_, next, _)| !self.can_finish(next, questions - 1)).map(|&(answer, _, _)| answer)
+    }
+
+    // The same search with the positions tried a batch at a time, one per thread.
+    // Each thread searches with a solver of its own, and the results are taken in
+    // order, so the answer is the one a single thread would give.
+    fn finishing_position_parallel(
+        &mut self,
+        state: &[(Count, usize)],
+        questions: usize,
+        (mut first, mut last): (Count, Count),
+        mut order: Outwards,
+    ) -> Option<Count> {
+        let shared = Arc::make_mut(&mut self.shared);
+        for (known, entry) in self.table.drain() {
+            shared.entry(known).or_insert(UNKNOWN).merge(entry);
+        }
+        let mut workers: Vec<Solver> = (0..self.threads)
+            .map(|_| Solver {
+                max_lies: self.max_lies,
+                variant: self.variant,
+                table: HashMap::new(),
+                shared: Arc::clone(&self.shared),
+                threads: 1,
+                expected: HashMap::new(),
+                time_limit: self.time_limit,
+                deadline: self.deadline,
+                timed_out: false,
+                steps: 0,
+            })
+            .collect();
+        let mut found = None;
+        'batches: loop {
+            let batch: Vec<Count> = (0..self.threads).map_while(|_| order.next(first, last)).collect();
+            if batch.is_empty() {
+                break;
+            }
+            let answers: Vec<Option<Response>> = thread::scope(|scope| {
+                let handles: Vec<_> = batch.iter().zip(workers.iter_mut())
+                    .map(|(&position, worker)| scope.spawn(move || worker.failing_answer(state, position, questions)))
+                    .collect();
+                handles.into_iter().map(|handle| handle.join().expect("Search threads don't panic")).collect()
+            });
+            if workers.iter().any(|worker| worker.timed_out) {
+                self.timed_out = true;
+                break;
+            }
+            for (position, answer) in batch.into_iter().zip(answers) {
+                // Ruled out by an earlier answer in the batch
+                if position < first || position > last {
+                    continue;
+                }
+                match answer {
+                    Some(No) => first = position + 1,
+                    Some(Yes) if position == 0 => break 'batches,
+                    Some(Yes) => last = position - 1,
+                    Some(Equal | Pass) => {}
+                    None => {
+                        found = Some(position);
+                        break 'batches;
+                    }
+                }
+            }
+        }
+        for worker in workers {
+            for (known, entry) in worker.table {
+                self.table.entry(known).or_insert(UNKNOWN).merge(entry);
+            }
+        }
+        found
+    }
+
+    /// Whether `questions` questions always find the secret among `numbers`
+    /// numbers, when the opponent has already used `lies_used` of its lies.
+    pub(crate) fn can_always_finish(&mut self, numbers: Count, lies_used: usize, questions: usize) -> bool {
+        self.can_finish(&[(numbers, lies_used)], questions)
+    }
+
+    /// The fewest questions that guarantee finishing from a position given as runs
+    /// of live numbers, as in [`GameState::canonical`].
+    pub(crate) fn run_questions(&mut self, runs: &[(Count, usize)]) -> usize {
+        self.state_value(runs)
+    }
+
+    fn state_value(&mut self, state: &[(Count, usize)]) -> usize {
+        // Start past any number of questions already known not to suffice
+        let mut questions = self.lookup(state).map_or(0, |entry| entry.insufficient + 1);
+        while !self.can_finish(state, questions) {
+            questions += 1;
+        }
+        questions
+    }
+
+    /// The fewest questions that guarantee finishing from this position.
+    pub fn questions_needed(&mut self, game: &GameState) -> usize {
+        if ended(game) {
+            return 0;
+        }
+        self.state_value(&game.canonical())
+    }
+
+    // The position of a question that finishes soonest in the worst case, and how
+    // many questions (including it) that takes. For states with two or more numbers.
+    fn best_position(&mut self, state: &[(Count, usize)]) -> (Count, usize) {
+        let needed = self.state_value(state);
+        let position = match self.lookup(state) {
+            Some(Entry { sufficient, best: Some(best), .. }) if sufficient == needed => best,
+            _ => self.finishing_position(state, needed).expect("A solvable state has a good question"),
+        };
+        (position, needed)
+    }
+
+    /// The question to ask next, and how many questions (including it) it guarantees
+    /// finishing in. None once the game is over.
+    pub fn best_question(&mut self, game: &GameState) -> Option<(Number, usize)> {
+        let state = game.canonical();
+        if count(&state) <= 1 || ended(game) {
+            return None;
+        }
+        let (position, needed) = self.best_position(&state);
+        Some((number_at(game, position), needed))
+    }
+
+    // Iterative deepening within the time limit: the fewest questions that finish
+    // from `state`, and true, or if time runs out the fewest that might, and false.
+    fn deepen(&mut self, state: &[(Count, usize)]) -> (usize, bool) {
+        self.deadline = self.time_limit.map(|time_limit| Instant::now() + time_limit);
+        let mut questions = self.lookup(state).map_or(0, |entry| entry.insufficient + 1);
+        let finished = loop {
+            if self.can_finish(state, questions) {
+                break true;
+            }
+            if self.timed_out {
+                break false;
+            }
+            questions += 1;
+        };
+        self.deadline = None;
+        self.timed_out = false;
+        (questions, finished)
+    }
+
+    /// Like [`Solver::questions_needed`], but giving up at the time limit: the
+    /// fewest questions that could finish from this position, and whether they are
+    /// known to.
+    pub fn questions_bound(&mut self, game: &GameState) -> (usize, bool) {
+        if ended(game) {
+            return (0, true);
+        }
+        self.deepen(&game.canonical())
+    }
+
+    /// Like [`Solver::best_question`], but giving up at the time limit. The answer
+    /// is then the question that most evenly splits the answer sequences left,
+    /// which is usually best or nearly so.
+    pub fn best_question_in_time(&mut self, game: &GameState) -> Option<Anytime> {
+        let state = game.canonical();
+        if count(&state) <= 1 || ended(game) {
+            return None;
+        }
+        let (at_least, finished) = self.deepen(&state);
+        if finished {
+            let (position, needed) = self.best_position(&state);
+            return Some(Anytime {
+                guess: number_at(game, position),
+                at_least: needed,
+                at_most: Some(needed),
+            });
+        }
+        let window = self.window(&state, at_least).unwrap_or((self.first_position(), count(&state) - 1));
+        let position = self.centre(&state, at_least, window);
+        Some(Anytime {
+            guess: number_at(game, position),
+            at_least,
+            at_most: None,
+        })
+    }
+
+    /// How many more questions perfect play takes on average, counting every way
+    /// the opponent could still answer as equally likely: each live number once for
+    /// every way of placing its remaining lies among the questions left.
+    pub fn expected_questions(&mut self, game: &GameState) -> f64 {
+        if ended(game) {
+            return 0.0;
+        }
+        self.state_expected(&game.canonical())
+    }
+
+    fn state_expected(&mut self, state: &[(Count, usize)]) -> f64 {
+        if count(state) <= 1 {
+            return 0.0;
+        }
+        if let Some(&expected) = self.expected.get(state) {
+            return expected;
+        }
+        let (position, needed) = self.best_position(state);
+        let (mut total, mut total_weight) = (0.0, 0.0);
+        for &response in self.responses() {
+            let next = self.split(state, position, response);
+            let weight = self.volume(&next, needed - 1) as f64;
+            if !self.finishes(state, position, response) {
+                total += weight * self.state_expected(&next);
+            }
+            total_weight += weight;
+        }
+        let expected = 1.0 + total / total_weight;
+        self.expected.insert(state.to_vec(), expected);
+        expected
+    }
+
+    /// The best question in every position that perfect play can reach from `game`,
+    /// whatever the answers.
+    pub fn strategy(&mut self, game: &GameState) -> Strategy {
+        let start = game.canonical();
+        let mut moves = vec![];
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(start.clone());
+        queue.push_back(start);
+        while let Some(state) = queue.pop_front() {
+            if count(&state) <= 1 {
+                continue;
+            }
+            let (below, needed) = self.best_position(&state);
+            for &response in self.responses() {
+                let next = self.split(&state, below, response);
+                if !self.finishes(&state, below, response) && seen.insert(next.clone()) {
+                    queue.push_back(next);
+                }
+            }
+            moves.push(Move {
+                state,
+                below,
+                needed,
+            });
+        }
+        Strategy {
+            upper_limit: game.upper_limit,
+            max_lies: self.max_lies,
+            moves,
+        }
+    }
+}
+
+/// A complete plan for the guesser, for other programs to follow without searching.
+///
+/// Positions are written as the live numbers in order, grouped into runs that
+/// contradict the same number of answers: `[(3, 0), (2, 1)]` is three numbers
+/// that fit every answer followed by two that fit all but one. A question is
+/// written as how many live numbers lie below the guess, so asking about the
+/// `below`th live number, counting from 0. Positions with one number or none left
+/// are finished, and left out.
+pub struct Strategy {
+    pub upper_limit: Number,
+    pub max_lies: usize,
+    /// The starting position comes first.
+    pub moves: Vec<Move>,
+}
+
+/// The question to ask in one position of a [`Strategy`].
+pub struct Move {
+    pub state: Vec<(Count, usize)>,
+    pub below: Count,
+    /// Questions left, counting this one, however the opponent answers.
+    pub needed: usize,
+}
+
+// Whether a truthful-last game is over though numbers are left, which its
+// canonical form doesn't show.
+pub(crate) fn ended(game: &GameState) -> bool {
+    game.variant() == Variant::TruthfulLast && game.status() != GameResult::Ongoing
+}
+
+// The live number with `position` live numbers below it.
+pub(crate) fn number_at(game: &GameState, position: Count) -> Number {
+    let mut remaining = position;
+    for (range, lies) in game.contradictions() {
+        if lies <= game.max_lies {
+            match game.nth(range, remaining) {
+                Some(number) => return number,
+                None => remaining -= game.count(range),
+            }
+        }
+    }
+    game.upper_limit
+}
+
+// How many live numbers are below `guess`, which number_at undoes.
+pub(crate) fn live_below(game: &GameState, guess: Number) -> Count {
+    game.contradictions().into_iter()
+        .filter(|&(_, lies)| lies <= game.max_lies)
+        .map(|(range, _)| game.count(range.clamp_higher(guess)))
+        .sum()
+}
+
+/// Gives whichever answer leaves perfect play the most questions on average, as
+/// given by [`Solver::expected_questions`], rather than in the worst case.
+pub struct ExpectedOpponent {
+    pub solver: Solver,
+}
+
+impl Opponent for ExpectedOpponent {
+    fn respond(&mut self, game: &GameState, question: &Question) -> Response {
+        let mut best: Option<(Response, f64)> = None;
+        for (response, next) in answers(game, question) {
+            let expected = self.solver.expected_questions(&next);
+            match best {
+                Some((_, best_expected)) if expected <= best_expected => {}
+                _ => best = Some((response, expected)),
+            }
+        }
+        best.map_or(Response::Yes, |(response, _)| response)
+    }
+}
+
+// The games after each answer to `question` that leaves some number possible, in
+// the order of [`Question::responses`], so Yes comes first. If every answer
+// contradicts every number, that is just Yes.
+fn answers(game: &GameState, question: &Question) -> Vec<(Response, GameState)> {
+    let mut answers: Vec<(Response, GameState)> = question.responses().iter()
+        .map(|&response| {
+            let mut next = game.clone();
+            next.ask(question.clone(), response).unwrap();
+            (response, next)
+        })
+        .collect();
+    if answers.iter().any(|(_, next)| count(&next.canonical()) > 0) {
+        answers.retain(|(_, next)| count(&next.canonical()) > 0);
+    } else {
+        answers.truncate(1);
+    }
+    answers
+}
+
+/// Plays perfectly: always asks a question that finishes soonest in the worst case,
+/// unless its solver has a time limit and runs out of time.
+pub struct MinimaxGuesser {
+    pub solver: Solver,
+}
+
+impl Guesser for MinimaxGuesser {
+    fn choose(&mut self, game: &GameState) -> Number {
+        self.solver.best_question_in_time(game).map_or(0, |question| question.guess)
+    }
+}
+
+/// Gives whichever answer leaves the most questions under perfect play, breaking
+/// ties with `better_value`. If its solver has a time limit and runs out of time,
+/// the fewest questions that might do stand in for those needed.
+pub struct MinimaxOpponent {
+    pub solver: Solver,
+}
+
+impl Opponent for MinimaxOpponent {
+    fn respond(&mut self, game: &GameState, question: &Question) -> Response {
+        let mut best: Option<(Response, (usize, Count))> = None;
+        for (response, next) in answers(game, question) {
+            let remaining = (self.solver.questions_bound(&next).0, better_value(&next));
+            match best {
+                Some((_, best_remaining)) if remaining <= best_remaining => {}
+                _ => best = Some((response, remaining)),
+            }
+        }
+        best.map_or(Response::Yes, |(response, _)| response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whether each position, written as its hypotheses, finishes within so many questions
+    type Seen = HashMap<(Vec<(Number, Number, usize)>, usize), bool>;
+
+    // Whether some way of asking "less than" questions always finishes `game`
+    // within `questions`, tried every way there is, trusting only the rules.
+    fn brute_force(game: &GameState, questions: usize, seen: &mut Seen) -> bool {
+        match game.status() {
+            GameResult::Finished(_) | GameResult::Impossible => return true,
+            _ if questions == 0 => return false,
+            _ => {}
+        }
+        let key = (game.hypotheses().map(|(range, lies)| (range.lower, range.higher, lies.len())).collect(), questions);
+        if let Some(&known) = seen.get(&key) {
+            return known;
+        }
+        let range = game.range();
+        let finishes = (range.lower + 1..range.higher).any(|guess| {
+            [Yes, No].iter().all(|&response| {
+                let mut next = game.clone();
+                next.ask(Question::Less(guess), response).unwrap();
+                brute_force(&next, questions - 1, seen)
+            })
+        });
+        seen.insert(key, finishes);
+        finishes
+    }
+
+    #[test]
+    fn solver_matches_brute_force() {
+        for lies in 0..=2 {
+            for limit in 2..=10 {
+                let game = GameState::new(limit, lies);
+                let mut seen = HashMap::new();
+                let needed = (0..).find(|&questions| brute_force(&game, questions, &mut seen)).unwrap();
+                let mut solver = Solver::new(lies);
+                assert_eq!(solver.questions_needed(&game), needed, "{} numbers, {} lies", limit, lies);
+                assert_eq!(solver.best_question(&game).map(|(_, needed)| needed), Some(needed));
+            }
+        }
+    }
+
+    // Playing the solver's questions against every answer must finish in the
+    // questions it says it needs.
+    #[test]
+    fn best_questions_keep_their_promise() {
+        fn play(solver: &mut Solver, game: &GameState, left: usize) {
+            let Some((guess, needed)) = solver.best_question(game) else { return };
+            assert!(needed <= left, "needs {} with {} left", needed, left);
+            for response in [Yes, No] {
+                let mut next = game.clone();
+                next.ask(Question::Less(guess), response).unwrap();
+                if next.status() == GameResult::Ongoing {
+                    play(solver, &next, needed - 1);
+                }
+            }
+        }
+        let game = GameState::new(20, 2);
+        let mut solver = Solver::new(2);
+        let needed = solver.questions_needed(&game);
+        play(&mut solver, &game, needed);
+    }
+}
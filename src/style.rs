@@ -0,0 +1,52 @@
+//! Colours for terminal output, which turn into plain text when colour is off.
+
+use std::env;
+use std::io::{stdout, IsTerminal};
+
+use crate::game::Response::{self, *};
+
+#[derive(Clone, Copy)]
+pub struct Style {
+    color: bool,
+}
+
+impl Style {
+    /// Colours only when writing to a terminal, and neither --no-color nor the
+    /// NO_COLOR environment variable asks otherwise.
+    pub fn detect(no_color: bool) -> Style {
+        let turned_off = no_color || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Style {
+            color: !turned_off && stdout().is_terminal(),
+        }
+    }
+
+    pub fn plain() -> Style {
+        Style { color: false }
+    }
+
+    fn paint(self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Yellow for no, cyan for yes, magenta for equal and dim for a pass.
+    pub fn response(self, response: Response, text: &str) -> String {
+        match response {
+            No => self.paint("33", text),
+            Yes => self.paint("36", text),
+            Equal => self.paint("35", text),
+            Pass => self.dim(text),
+        }
+    }
+
+    pub fn answer(self, text: &str) -> String {
+        self.paint("1;32", text)
+    }
+
+    pub fn dim(self, text: &str) -> String {
+        self.paint("2", text)
+    }
+}
@@ -0,0 +1,45 @@
+//! The classic tables of searching with lies: for each number of questions, the
+//! most numbers that many questions can always find the secret among.
+//!
+//! Tables are built bottom up, from no questions upwards. Each answer the guesser
+//! can get takes it to a game with fewer numbers or fewer lies left, so the
+//! solver's memory of every smaller game is shared by the larger ones above it.
+
+use crate::game::Count;
+use crate::solver::Solver;
+use crate::value::volume_limit;
+
+/// The most numbers `questions` questions can always find the secret among, for
+/// every number of questions up to `max_questions`. Each row has a column for each
+/// number of lies from 0 to `max_lies`.
+pub fn largest_limits(max_lies: usize, max_questions: usize) -> Vec<Vec<Count>> {
+    let mut solver = Solver::new(max_lies);
+    let mut rows: Vec<Vec<Count>> = vec![];
+    for questions in 0..=max_questions {
+        let row = (0..=max_lies)
+            .map(|lies| {
+                // One more question never hurts, and the volume bound rules out any more
+                let solvable = rows.last().map_or(1, |last| last[lies]);
+                let ruled_out = volume_limit(questions, lies).saturating_add(1);
+                largest(solvable, ruled_out, |numbers| solver.can_always_finish(numbers, max_lies - lies, questions))
+            })
+            .collect();
+        rows.push(row);
+    }
+    rows
+}
+
+// The largest number of numbers below `ruled_out` that `finishes`, given that
+// `solvable` does and more numbers are never easier.
+fn largest(solvable: Count, ruled_out: Count, mut finishes: impl FnMut(Count) -> bool) -> Count {
+    let (mut lower, mut upper) = (solvable, ruled_out);
+    while upper - lower > 1 {
+        let mid = lower + (upper - lower) / 2;
+        if finishes(mid) {
+            lower = mid;
+        } else {
+            upper = mid;
+        }
+    }
+    lower
+}
@@ -0,0 +1,376 @@
+//! Round-robin tournaments: every guesser plays every opponent at each limit, over
+//! the same games, so that strategies can be compared pairing by pairing. Each game
+//! also rates the pair, Elo style, so that strategies can be followed from one
+//! tournament to the next by a single number.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::json::Json;
+use crate::rng::Rng;
+use crate::setup::{make_guesser, OpponentSetup};
+use crate::simulate::play_many;
+use crate::{GameState, Number, Solver};
+
+pub struct Tournament {
+    /// Each as --guesser names it, or as greedy:VALUE for the greedy guesser with
+    /// its own value function
+    pub guessers: Vec<String>,
+    /// Each as --opponent names it, with its setup
+    pub opponents: Vec<(String, OpponentSetup)>,
+    pub limits: Vec<Number>,
+    pub max_lies: usize,
+    pub games: u64,
+    /// The guessers' value function, threads, time limit and strategy path, and
+    /// the seed every pairing shares
+    pub setup: OpponentSetup,
+    /// Where the ratings are kept between tournaments
+    pub ratings: Option<String>,
+}
+
+/// The options a tournament's config sets, each key being an option's name
+/// without its dashes, as in games = 50 for --games 50, and arrays being lists,
+/// as in limits = [10, 100] for --limits 10,100.
+pub fn config_options(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut options = vec![];
+    for (key, value) in read_config(text)? {
+        let value = match value {
+            Json::Array(items) => items.iter().map(option_value).collect::<Result<Vec<String>, String>>()?.join(","),
+            value => option_value(&value)?,
+        };
+        options.push((format!("--{}", key), value));
+    }
+    Ok(options)
+}
+
+fn option_value(value: &Json) -> Result<String, String> {
+    match *value {
+        Json::Str(ref text) => Ok(text.clone()),
+        Json::Int(_) | Json::UInt(_) | Json::Float(_) => Ok(value.to_string()),
+        _ => Err(format!("{} can't be an option's value", value)),
+    }
+}
+
+/// Splits a list at the commas outside brackets, so that a value expression such as
+/// min(total, 8) stays whole.
+pub fn split_list(text: &str) -> Vec<&str> {
+    let (mut items, mut start, mut depth) = (vec![], 0, 0);
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(text[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(text[start..].trim());
+    items
+}
+
+// Reads the flat subset of TOML a tournament is configured with: a key = value
+// on each line, with # starting a comment. Values are double quoted strings,
+// numbers and arrays of them, written as JSON writes them, except that arrays
+// may run over several lines and end with a comma.
+fn read_config(text: &str) -> Result<Vec<(String, Json)>, String> {
+    let mut fields = vec![];
+    let mut lines = text.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let line = without_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line.split_once('=').ok_or_else(|| format!("Line {} isn't key = value", index + 1))?;
+        let mut value = value.trim().to_string();
+        while depth(&value) > 0 {
+            match lines.next() {
+                Some((_, line)) => {
+                    value.push(' ');
+                    value += without_comment(line).trim();
+                }
+                None => return Err(format!("The array on line {} never ends", index + 1)),
+            }
+        }
+        let value = Json::parse(&without_trailing_commas(&value)).map_err(|err| format!("Line {}: {}", index + 1, err))?;
+        fields.push((key.trim().to_string(), value));
+    }
+    Ok(fields)
+}
+
+// Calls `f` with each character of `text` and whether it is inside a string.
+fn scan(text: &str, mut f: impl FnMut(usize, char, bool) -> bool) {
+    let (mut quoted, mut escaped) = (false, false);
+    for (index, c) in text.char_indices() {
+        if !f(index, c, quoted) {
+            return;
+        }
+        if quoted && c == '\\' && !escaped {
+            escaped = true;
+            continue;
+        }
+        if c == '"' && !escaped {
+            quoted = !quoted;
+        }
+        escaped = false;
+    }
+}
+
+fn without_comment(line: &str) -> &str {
+    let mut end = line.len();
+    scan(line, |index, c, quoted| {
+        if c == '#' && !quoted {
+            end = index;
+            return false;
+        }
+        true
+    });
+    &line[..end]
+}
+
+// How many more arrays are opened than closed.
+fn depth(text: &str) -> i32 {
+    let mut depth = 0;
+    scan(text, |_, c, quoted| {
+        match (c, quoted) {
+            ('[', false) => depth += 1,
+            (']', false) => depth -= 1,
+            _ => {}
+        }
+        true
+    });
+    depth
+}
+
+fn without_trailing_commas(text: &str) -> String {
+    let mut kept = String::new();
+    let mut comma = None;
+    scan(text, |_, c, quoted| {
+        match (c, quoted) {
+            (',', false) => {
+                kept.extend(comma.take());
+                comma = Some(c);
+            }
+            (']', false) => {
+                comma = None;
+                kept.push(c);
+            }
+            (c, false) if c.is_whitespace() => kept.push(c),
+            (c, _) => {
+                kept.extend(comma.take());
+                kept.push(c);
+            }
+        }
+        true
+    });
+    kept
+}
+
+// How the pairing of one guesser and one opponent went over every limit, in
+// questions beyond the best guesser's worst case.
+#[derive(Clone, Default)]
+struct Pairing {
+    games: u64,
+    excess: i64,
+    most: Option<i64>,
+    beyond: u64,
+}
+
+const RATINGS_FORMAT: &str = "one-lie ratings";
+const RATINGS_VERSION: u64 = 1;
+// Where every strategy starts, and how far one game can move it
+const INITIAL_RATING: f64 = 1500.0;
+const K_FACTOR: f64 = 16.0;
+
+#[derive(Clone, Copy)]
+struct Rating {
+    rating: f64,
+    games: u64,
+}
+
+impl Default for Rating {
+    fn default() -> Rating {
+        Rating { rating: INITIAL_RATING, games: 0 }
+    }
+}
+
+// Guessers and opponents are rated against each other but listed apart, as a
+// guesser and an opponent are never compared directly. Each game is scored as
+// golf is, with the optimal worst case as par: the guesser wins by finishing
+// under it, draws by finishing on it and loses by going over.
+#[derive(Clone, Default)]
+struct Ratings {
+    guessers: BTreeMap<String, Rating>,
+    opponents: BTreeMap<String, Rating>,
+}
+
+impl Ratings {
+    // The ratings kept at `path`, or none yet if there is no file.
+    fn load(path: &str) -> Result<Ratings, String> {
+        if !Path::new(path).exists() {
+            return Ok(Ratings::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| format!("Couldn't read {}: {}", path, err))?;
+        let json = Json::parse(&text).map_err(|err| format!("{} in {}", err, path))?;
+        if json.get("format").and_then(|format| format.as_str().ok()) != Some(RATINGS_FORMAT) {
+            return Err(format!("{} isn't a ratings file", path));
+        }
+        if json.field("version")?.as_u64()? != RATINGS_VERSION {
+            return Err(format!("{} is from another version of one-lie", path));
+        }
+        let table = |key: &str| -> Result<BTreeMap<String, Rating>, String> {
+            match *json.field(key)? {
+                Json::Object(ref fields) => fields.iter()
+                    .map(|(name, rating)| {
+                        let rating = Rating { rating: rating.field("rating")?.as_f64()?, games: rating.field("games")?.as_u64()? };
+                        Ok((name.clone(), rating))
+                    })
+                    .collect(),
+                ref other => Err(format!("Expected an object, found {}", other)),
+            }
+        };
+        Ok(Ratings { guessers: table("guessers")?, opponents: table("opponents")? })
+    }
+
+    fn save(&self, path: &str) -> Result<(), String> {
+        let table = |ratings: &BTreeMap<String, Rating>| {
+            Json::Object(ratings.iter()
+                .map(|(name, rating)| {
+                    let fields = vec![("rating", Json::Float(rating.rating)), ("games", Json::Int(i128::from(rating.games)))];
+                    (name.clone(), Json::object(fields))
+                })
+                .collect())
+        };
+        let json = Json::object(vec![
+            ("format", Json::Str(RATINGS_FORMAT.to_string())),
+            ("version", Json::Int(i128::from(RATINGS_VERSION))),
+            ("guessers", table(&self.guessers)),
+            ("opponents", table(&self.opponents)),
+        ]);
+        fs::write(path, format!("{}\n", json)).map_err(|err| format!("Couldn't write {}: {}", path, err))
+    }
+
+    // Rates one game, which went `excess` questions past par.
+    fn record(&mut self, guesser: &str, opponent: &str, excess: i64) {
+        let score = match excess {
+            ..=-1 => 1.0,
+            0 => 0.5,
+            _ => 0.0,
+        };
+        let guesser = self.guessers.entry(guesser.to_string()).or_default();
+        let opponent = self.opponents.entry(opponent.to_string()).or_default();
+        let expected = 1.0 / (1.0 + 10f64.powf((opponent.rating - guesser.rating) / 400.0));
+        let change = K_FACTOR * (score - expected);
+        guesser.rating += change;
+        opponent.rating -= change;
+        guesser.games += 1;
+        opponent.games += 1;
+    }
+}
+
+// The ratings of those named, best first, with how each changed since `before`.
+fn print_ratings(what: &str, names: &[&String], now: &BTreeMap<String, Rating>, before: &BTreeMap<String, Rating>, width: usize) {
+    let mut rated: Vec<(&String, Rating)> = names.iter().map(|&name| (name, now[name])).collect();
+    rated.sort_by(|a, b| b.1.rating.total_cmp(&a.1.rating));
+    println!("{}:", what);
+    for (name, rating) in rated {
+        let previous = before.get(name).copied().unwrap_or_default();
+        println!(
+            "{:>width$} {:>6.0} ({:+.0}) over {} games",
+            name,
+            rating.rating,
+            rating.rating - previous.rating,
+            rating.games
+        );
+    }
+}
+
+pub fn run_tournament(tournament: &Tournament) -> Result<(), String> {
+    let mut ratings = match tournament.ratings {
+        Some(ref path) => Ratings::load(path)?,
+        None => Ratings::default(),
+    };
+    let before = ratings.clone();
+    // One seed for every pairing, so that each guesser plays the same games
+    let mut setup = tournament.setup.clone();
+    let seed = *setup.seed.get_or_insert_with(|| Rng::from_time().next_u64());
+    println!(
+        "{} guessers against {} opponents with {}, {} games a pairing at each limit, from seed {}",
+        tournament.guessers.len(),
+        tournament.opponents.len(),
+        GameState::new(1, tournament.max_lies).describe(),
+        tournament.games,
+        seed
+    );
+    let mut pairings = vec![vec![Pairing::default(); tournament.opponents.len()]; tournament.guessers.len()];
+    let mut solver = Solver::new(tournament.max_lies);
+    // Wide enough for every name, such as a long greedy:VALUE
+    let width = tournament.guessers.iter().map(String::len).max().unwrap_or(0).max(16);
+    let columns: Vec<usize> = tournament.opponents.iter().map(|(name, _)| name.len().max(14)).collect();
+    for &upper_limit in &tournament.limits {
+        let game = GameState::new(upper_limit, tournament.max_lies);
+        let optimal = solver.questions_needed(&game);
+        println!();
+        println!("Out of {}, where the best guesser needs {} questions at worst: the mean and the most", upper_limit, optimal);
+        let mut header = format!("{:>width$}", "");
+        for ((name, _), &column) in tournament.opponents.iter().zip(&columns) {
+            header += &format!(" {:>column$}", name);
+        }
+        println!("{}", header);
+        for (name, row) in tournament.guessers.iter().zip(&mut pairings) {
+            let mut guesser_setup = setup.clone();
+            let guesser_name = match name.split_once(':') {
+                Some((guesser_name, value)) => {
+                    guesser_setup.value = value.to_string();
+                    guesser_name
+                }
+                None => name,
+            };
+            let mut guesser = make_guesser(&game, &guesser_setup, guesser_name);
+            let mut line = format!("{:>width$}", name);
+            for (((opponent, opponent_setup), pairing), &column) in tournament.opponents.iter().zip(row.iter_mut()).zip(&columns) {
+                let opponent_setup = OpponentSetup { seed: Some(seed), ..opponent_setup.clone() };
+                let played = play_many(&game, &opponent_setup, &mut *guesser, tournament.games)?;
+                let questions: Vec<usize> = played.iter().map(|(finished, _)| finished.history().len()).collect();
+                let most = questions.iter().copied().max().unwrap_or(0);
+                let total: usize = questions.iter().sum();
+                line += &format!(" {:>column$}", format!("{:.2} / {}", total as f64 / tournament.games as f64, most));
+                for &asked in &questions {
+                    let excess = asked as i64 - optimal as i64;
+                    pairing.games += 1;
+                    pairing.excess += excess;
+                    pairing.most = Some(pairing.most.map_or(excess, |most| most.max(excess)));
+                    pairing.beyond += u64::from(excess > 0);
+                    ratings.record(name, opponent, excess);
+                }
+            }
+            println!("{}", line);
+        }
+    }
+    println!();
+    println!("Each pairing over every limit, in questions beyond the best guesser's worst case");
+    for (name, row) in tournament.guessers.iter().zip(&pairings) {
+        for ((opponent, _), pairing) in tournament.opponents.iter().zip(row) {
+            println!(
+                "{:>width$} against {:<12} mean {:+.2}, most {:+}, beyond it in {:.1}% of {} games",
+                name,
+                opponent,
+                pairing.excess as f64 / pairing.games as f64,
+                pairing.most.unwrap_or(0),
+                100.0 * pairing.beyond as f64 / pairing.games as f64,
+                pairing.games
+            );
+        }
+    }
+    println!();
+    let guessers: Vec<&String> = tournament.guessers.iter().collect();
+    let opponents: Vec<&String> = tournament.opponents.iter().map(|(name, _)| name).collect();
+    print_ratings("Guessers' ratings", &guessers, &ratings.guessers, &before.guessers, width);
+    print_ratings("Opponents' ratings", &opponents, &ratings.opponents, &before.opponents, width);
+    match tournament.ratings {
+        Some(ref path) => ratings.save(path),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,363 @@
+//! A compact textual record of a game, in the spirit of chess's PGN.
+//!
+//! Tags in square brackets give the rules and the state of play, then the moves
+//! follow as numbered questions, each the guess with `L` if the answer was "less
+//! than" or `H` if it was "greater than or equal". "Is it exactly 5?" is written
+//! `=5` and "is it in 3..7?" `3..7`, either followed by `Y` or `N`. Numbering starts at 0, matching the question
+//! numbers in lie hypotheses. Text in braces is a comment.
+//!
+//! ```text
+//! [Limit "10"]
+//! [Lies "1"]
+//! [Result "Finished 3"]
+//! [Hypotheses "1 3..4"]
+//!
+//! 0. 5L 1. 3L 2. 2H 3. 3H 4. 4L 5. 3H
+//! ```
+
+use crate::game::{CandidateSet, Count, Dir, GameError, GameResult, GameState, Number, Prior, Question, Response, Variant, CONFIDENCE};
+
+/// A game's status as written in the Result tag, like `Finished 3`.
+pub fn format_result(result: GameResult) -> String {
+    match result {
+        GameResult::Ongoing => "Ongoing".to_string(),
+        GameResult::Finished(answer) => format!("Finished {}", answer),
+        GameResult::Impossible => "Impossible".to_string(),
+        GameResult::Lost => "Lost".to_string(),
+    }
+}
+
+fn format_hypotheses(game: &GameState) -> String {
+    game.hypotheses()
+        .map(|(range, lies)| {
+            let lies = if lies.is_empty() {
+                "-".to_string()
+            } else {
+                lies.iter().map(|lie| lie.to_string()).collect::<Vec<_>>().join(",")
+            };
+            format!("{} {}..{}", lies, range.lower, range.higher)
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// A question and its answer as written in a transcript, like `5L`, `=5N`, `3..7Y`,
+/// `~5E`, or `5P` for a pass.
+pub fn format_move(question: &Question, response: Response) -> String {
+    match (question, response) {
+        (_, Response::Pass) => format!("{}P", question),
+        (&Question::Compare(_), Response::Equal) => format!("{}E", question),
+        (&Question::Less(_) | &Question::Compare(_), _) if Dir::from(response) == Dir::Low => format!("{}L", question),
+        (&Question::Less(_) | &Question::Compare(_), _) => format!("{}H", question),
+        (_, _) => format!("{}{}", question, if response == Response::Yes { 'Y' } else { 'N' }),
+    }
+}
+
+/// Reads a question and its answer as [`format_move`] writes them.
+pub fn parse_move(text: &str) -> Result<(Question, Response), GameError> {
+    let bad_move = || GameError::ParseError(format!(
+        "Question {} should be a number ending in L or H, ~ and a number ending in L, E or H, \
+         or = and a number or some ranges ending in Y or N, or any of them ending in P for a pass",
+        text
+    ));
+    let (guess, response) = match text.char_indices().last() {
+        Some((end, answer @ ('L' | 'H' | 'E' | 'Y' | 'N' | 'P'))) => (&text[..end], answer),
+        _ => return Err(bad_move()),
+    };
+    let question: Question = guess.parse()?;
+    match (&question, response) {
+        (_, 'P') => Ok((question, Response::Pass)),
+        (Question::Less(_) | Question::Compare(_), 'L') => Ok((question, Response::Yes)),
+        (Question::Less(_) | Question::Compare(_), 'H') => Ok((question, Response::No)),
+        (Question::Compare(_), 'E') => Ok((question, Response::Equal)),
+        (Question::Less(_) | Question::Compare(_), _) | (_, 'L' | 'H' | 'E') => Err(bad_move()),
+        (_, 'Y') => Ok((question, Response::Yes)),
+        (_, _) => Ok((question, Response::No)),
+    }
+}
+
+fn parse_tag(line: &str) -> Result<(&str, &str), GameError> {
+    let malformed = || GameError::ParseError(format!("Malformed tag {}", line));
+    let inner = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')).ok_or_else(malformed)?;
+    let (name, value) = inner.split_once(' ').ok_or_else(malformed)?;
+    let value = value.trim().strip_prefix('"').and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(|| GameError::ParseError(format!("Tag {} needs a quoted value", name)))?;
+    Ok((name, value))
+}
+
+fn strip_comments(text: &str) -> Result<String, GameError> {
+    let mut stripped = String::new();
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Err(GameError::ParseError("Unmatched } in transcript".to_string())),
+            '}' => depth -= 1,
+            c if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return Err(GameError::ParseError("Unclosed { in transcript".to_string()));
+    }
+    Ok(stripped)
+}
+
+impl GameState {
+    /// Writes the game in transcript notation.
+    ///
+    /// ```
+    /// use one_lie::{Dir, GameState};
+    ///
+    /// let mut game = GameState::new(100, 1);
+    /// game.store_guess(50, Dir::Low).unwrap();
+    /// game.store_guess(25, Dir::High).unwrap();
+    /// let transcript = game.to_transcript();
+    /// assert!(transcript.ends_with("0. 50L 1. 25H\n"));
+    /// let replayed = GameState::from_transcript(&transcript).unwrap();
+    /// assert_eq!(replayed.history(), game.history());
+    /// assert_eq!(replayed.to_transcript(), transcript);
+    /// ```
+    pub fn to_transcript(&self) -> String {
+        let mut transcript = String::new();
+        if self.lower_limit != 0 {
+            transcript += &format!("[Lower \"{}\"]\n", self.lower_limit);
+        }
+        transcript += &format!("[Limit \"{}\"]\n", self.upper_limit);
+        match self.lie_probability {
+            None => transcript += &format!("[Lies \"{}\"]\n", self.max_lies),
+            Some(lie_probability) => transcript += &format!("[Noise \"{}\"]\n", lie_probability),
+        }
+        if self.lie_probability.is_some() && self.confidence != CONFIDENCE {
+            transcript += &format!("[Confidence \"{}\"]\n", self.confidence);
+        }
+        if let Some(candidate_set) = self.candidate_set() {
+            let members: Vec<String> = candidate_set.members().iter().map(|member| member.to_string()).collect();
+            transcript += &format!("[Candidates \"{}\"]\n", members.join(" "));
+        }
+        if let Some(prior) = self.prior() {
+            transcript += &format!("[Prior \"{}\"]\n", prior.spec());
+        }
+        if let Some(max_questions) = self.max_questions {
+            transcript += &format!("[MaxQuestions \"{}\"]\n", max_questions);
+        }
+        if self.variant != Variant::Standard {
+            transcript += &format!("[Variant \"{}\"]\n", self.variant);
+        }
+        if self.delay > 0 {
+            transcript += &format!("[Delay \"{}\"]\n", self.delay);
+        }
+        if let Some(window) = self.lie_window {
+            transcript += &format!("[LieWindow \"{}\"]\n", window);
+        }
+        if let Some(width) = self.moving {
+            transcript += &format!("[Corner \"{}\"]\n", width);
+        }
+        if self.max_passes > 0 {
+            transcript += &format!("[Passes \"{}\"]\n", self.max_passes);
+        }
+        if self.final_guess {
+            transcript += "[FinalGuess \"yes\"]\n";
+        }
+        if self.waits > 0 {
+            transcript += &format!("[Waits \"{}\"]\n", self.waits);
+        }
+        transcript += &format!("[Result \"{}\"]\n", format_result(self.status()));
+        if self.lie_probability.is_none() {
+            transcript += &format!("[Hypotheses \"{}\"]\n", format_hypotheses(self));
+        }
+        transcript += "\n";
+        let mut line = String::new();
+        for (index, &(ref question, response)) in self.history.iter().enumerate() {
+            let entry = format!("{}. {}", index, format_move(question, response));
+            if !line.is_empty() && line.len() + entry.len() >= 80 {
+                transcript += &line;
+                transcript += "\n";
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line += &entry;
+        }
+        if !line.is_empty() {
+            transcript += &line;
+            transcript += "\n";
+        }
+        transcript
+    }
+
+    /// Reads a game in transcript notation. The Result and Hypotheses tags are
+    /// optional, but if present they must agree with the moves.
+    pub fn from_transcript(text: &str) -> Result<GameState, GameError> {
+        let text = strip_comments(text)?;
+        let mut lower = None;
+        let mut limit = None;
+        let mut lies = None;
+        let mut noise = None;
+        let mut confidence = None;
+        let mut max_questions = None;
+        let mut candidates = None;
+        let mut prior = None;
+        let mut variant = None;
+        let mut delay = None;
+        let mut waits = 0;
+        let mut corner = None;
+        let mut lie_window = None;
+        let mut final_guess = false;
+        let mut passes = None;
+        let mut expected_result = None;
+        let mut expected_hypotheses = None;
+        let mut moves = vec![];
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if line.starts_with('[') {
+                let (name, value) = parse_tag(line)?;
+                let number_error = |_| GameError::ParseError(format!("Tag {} has a bad value {}", name, value));
+                match name {
+                    "Lower" => lower = Some(value.parse::<Number>().map_err(number_error)?),
+                    "Limit" => limit = Some(value.parse::<Number>().map_err(number_error)?),
+                    "Lies" => lies = Some(value.parse::<usize>().map_err(number_error)?),
+                    "Noise" => noise = Some(value.parse::<f64>().map_err(|_| GameError::ParseError(format!("Tag Noise has a bad value {}", value)))?),
+                    "Confidence" => confidence = Some(value.parse::<f64>().map_err(|_| GameError::ParseError(format!("Tag Confidence has a bad value {}", value)))?),
+                    "Candidates" => candidates = Some(value.split_whitespace()
+                        .map(|member| member.parse::<Number>().map_err(|_| GameError::ParseError(format!("Tag Candidates has a bad value {}", member))))
+                        .collect::<Result<Vec<Number>, GameError>>()?),
+                    "Prior" => prior = Some(Prior::parse(value)?),
+                    "MaxQuestions" => max_questions = Some(value.parse::<usize>().map_err(number_error)?),
+                    "Variant" => variant = Some(Variant::parse(value)?),
+                    "Delay" => delay = Some(value.parse::<usize>().map_err(number_error)?),
+                    "LieWindow" => lie_window = Some(value.parse::<usize>().map_err(number_error)?),
+                    "FinalGuess" if value == "yes" => final_guess = true,
+                    "FinalGuess" => return Err(GameError::ParseError(format!("Tag FinalGuess has a bad value {}", value))),
+                    "Passes" => passes = Some(value.parse::<usize>().map_err(number_error)?),
+                    "Corner" => corner = Some(value.parse::<Count>().map_err(number_error)?),
+                    "Waits" => waits = value.parse::<usize>().map_err(number_error)?,
+                    "Result" => expected_result = Some(value.to_string()),
+                    "Hypotheses" => expected_hypotheses = Some(value.to_string()),
+                    // Unknown tags are kept for people, not the engine
+                    _ => {}
+                }
+            } else {
+                moves.extend(line.split_whitespace().map(str::to_string));
+            }
+        }
+        let limit = limit.ok_or_else(|| GameError::ParseError("Transcript has no Limit tag".to_string()))?;
+        let mut game = match (lies, noise) {
+            (Some(lies), None) => GameState::new(limit, lies),
+            (None, Some(noise)) if (0.0..0.5).contains(&noise) => GameState::noisy(limit, noise),
+            (None, Some(noise)) => return Err(GameError::InvalidRules(format!("Noise {} is not in [0, 0.5)", noise))),
+            _ => return Err(GameError::ParseError("Transcript needs exactly one of the Lies and Noise tags".to_string())),
+        };
+        match confidence {
+            None => {}
+            Some(_) if game.lie_probability.is_none() => {
+                return Err(GameError::InvalidRules("Tag Confidence is for noisy games".to_string()));
+            }
+            Some(confidence) if (0.5..1.0).contains(&confidence) => game = game.with_confidence(confidence),
+            Some(confidence) => return Err(GameError::InvalidRules(format!("Confidence {} is not in [0.5, 1)", confidence))),
+        }
+        if let Some(lower) = lower {
+            if lower >= limit {
+                return Err(GameError::InvalidRules(format!("Lower {} must be less than Limit {}", lower, limit)));
+            }
+            game = game.with_lower_limit(lower);
+        }
+        if let Some(candidates) = candidates {
+            if candidates.is_empty() {
+                return Err(GameError::InvalidRules("Tag Candidates lists no numbers".to_string()));
+            }
+            game = game.with_candidates(CandidateSet::new(candidates));
+        }
+        if let Some(prior) = prior {
+            game = game.with_prior(prior);
+        }
+        if let Some(max_questions) = max_questions {
+            game = game.with_max_questions(max_questions);
+        }
+        if let Some(variant) = variant {
+            game = game.with_variant(variant);
+        }
+        if let Some(delay) = delay {
+            game = game.with_delay(delay);
+        }
+        game.waits = waits;
+        if let Some(window) = lie_window {
+            game = game.with_lie_window(window);
+        }
+        if let Some(width) = corner {
+            game = game.with_moving_target(width);
+        }
+        if final_guess {
+            game = game.with_final_guess();
+        }
+        if let Some(passes) = passes {
+            game = game.with_passes(passes);
+        }
+        let mut tokens = moves.iter();
+        while let Some(number) = tokens.next() {
+            let expected = format!("{}.", game.history.len());
+            if *number != expected {
+                return Err(GameError::ParseError(format!("Expected question number {} but found {}", expected, number)));
+            }
+            let question = tokens.next().ok_or_else(|| GameError::ParseError(format!("Question {} is missing", number)))?;
+            let (question, response) = parse_move(question)?;
+            game.ask(question, response)?;
+        }
+        if let Some(expected) = expected_result {
+            let actual = format_result(game.status());
+            if expected != actual {
+                return Err(GameError::InconsistentHistory(format!("Result tag says {} but the moves give {}", expected, actual)));
+            }
+        }
+        if let Some(expected) = expected_hypotheses {
+            let actual = format_hypotheses(&game);
+            if expected != actual {
+                return Err(GameError::InconsistentHistory(format!("Hypotheses tag says {} but the moves give {}", expected, actual)));
+            }
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::ToJson;
+
+    fn play(mut game: GameState, moves: &[(&str, Response)]) -> GameState {
+        for &(question, response) in moves {
+            let question = match question.parse().unwrap() {
+                Question::Less(guess) => game.guess(guess),
+                question => question,
+            };
+            game.ask(question, response).unwrap();
+        }
+        game
+    }
+
+    // Saving and loading must give back the same game, rules and answers alike.
+    fn round_trip(game: &GameState) {
+        let loaded = GameState::from_transcript(&game.to_transcript()).unwrap();
+        assert_eq!(loaded.to_json(), game.to_json());
+        assert_eq!(loaded.status(), game.status());
+    }
+
+    #[test]
+    fn transcripts_round_trip() {
+        use Response::*;
+        round_trip(&GameState::new(100, 1));
+        round_trip(&play(GameState::new(100, 2), &[("50", Yes), ("=20", No), ("30..40", Yes), ("odd", No), ("bit 1", Yes)]));
+        round_trip(&play(GameState::new(50, 1).with_lower_limit(-50).with_max_questions(12), &[("0", No), ("25", Yes)]));
+        round_trip(&play(GameState::new(16, 1).with_variant(Variant::ThreeWay), &[("8", Equal)]));
+        round_trip(&play(GameState::new(32, 1).with_passes(2), &[("16", Pass), ("16", Yes)]));
+        round_trip(&play(GameState::new(4, 0), &[("2", Yes), ("1", No)]));
+    }
+
+    #[test]
+    fn moves_round_trip() {
+        for text in ["50L", "25H", "=7Y", "10..20N", "b3N", "~8E", "~8L", "12P"] {
+            let (question, response) = parse_move(text).unwrap();
+            assert_eq!(format_move(&question, response), text);
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Twenty questions with a lie: the secret is one of a list of items, each with
+//! yes or no attributes, and each question asks whether it has one of them.
+//!
+//! Items are numbered in the order they are listed, and the items with an
+//! attribute are kept as a [`Bitset`], so that asking about the attribute is asking
+//! whether the secret is in the [`Question::subset`] the set's runs make. The game
+//! itself is then an ordinary [`GameState`] over the item numbers, which the
+//! opponents and value functions play as they would any other.
+
+use std::fmt;
+
+use crate::game::{Count, GameError, GameResult, GameState, Number, Question, Range, Response};
+use crate::value::ValueFunction;
+
+/// The most items a database may list, which keeps the runs of items with an
+/// attribute within [`MAX_QUESTION_PARTS`].
+///
+/// [`MAX_QUESTION_PARTS`]: crate::game::MAX_QUESTION_PARTS
+pub const MAX_ITEMS: usize = 8000;
+
+/// A set of items, one bit for each.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitset {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bitset {
+    /// No items out of `len`.
+    pub fn new(len: usize) -> Bitset {
+        Bitset { words: vec![0; len.div_ceil(64)], len }
+    }
+    /// How many items the set is drawn from.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.count() == 0
+    }
+    pub fn insert(&mut self, item: usize) {
+        self.words[item / 64] |= 1 << (item % 64);
+    }
+    pub fn contains(&self, item: usize) -> bool {
+        item < self.len && self.words[item / 64] & (1 << (item % 64)) != 0
+    }
+    /// How many items are in the set.
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+    /// The items in the set, in order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(move |&item| self.contains(item))
+    }
+    /// The items in both sets.
+    pub fn and(&self, other: &Bitset) -> Bitset {
+        Bitset {
+            words: self.words.iter().zip(&other.words).map(|(word, other)| word & other).collect(),
+            len: self.len,
+        }
+    }
+    /// The runs of items next to each other in the set, as ranges of item numbers.
+    pub fn runs(&self) -> Vec<Range> {
+        let mut runs: Vec<Range> = vec![];
+        for item in self.iter() {
+            match runs.last_mut() {
+                Some(run) if run.higher == item as Number => run.higher += 1,
+                _ => runs.push(Range::new(item as Number, item as Number + 1)),
+            }
+        }
+        runs
+    }
+}
+
+/// Items and the attributes each has, as read by [`ItemDatabase::parse`].
+#[derive(Debug, Clone)]
+pub struct ItemDatabase {
+    items: Vec<String>,
+    attributes: Vec<String>,
+    // The items with each attribute, in the same order as the attributes
+    having: Vec<Bitset>,
+}
+
+impl ItemDatabase {
+    /// Reads a table with a header line naming the attributes after a first
+    /// column, and a line for each item giving its name and then whether it has
+    /// each attribute, as yes or no, y or n, true or false, or 1 or 0. Fields are
+    /// separated by commas, and blank lines and lines starting with # are skipped.
+    pub fn parse(text: &str) -> Result<ItemDatabase, GameError> {
+        let mut lines = text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(index, line)| (index + 1, line.split(',').map(str::trim).collect::<Vec<&str>>()));
+        let (_, header) = lines.next().ok_or_else(|| GameError::ParseError("There's no header line".to_string()))?;
+        let attributes: Vec<String> = header[1..].iter().map(|attribute| attribute.to_string()).collect();
+        if attributes.is_empty() {
+            return Err(GameError::ParseError("The header line names no attributes".to_string()));
+        }
+        if attributes.iter().any(|attribute| attribute.is_empty()) {
+            return Err(GameError::ParseError("The header line has an attribute with no name".to_string()));
+        }
+        let mut items = vec![];
+        let mut rows = vec![];
+        for (line, fields) in lines {
+            if fields.len() != attributes.len() + 1 {
+                return Err(GameError::ParseError(format!(
+                    "Line {} has {} fields, but the header has {}",
+                    line,
+                    fields.len(),
+                    attributes.len() + 1
+                )));
+            }
+            if fields[0].is_empty() || items.contains(&fields[0].to_string()) {
+                return Err(GameError::ParseError(format!("Line {} needs an item name not already listed", line)));
+            }
+            let row = fields[1..].iter()
+                .map(|field| match field.to_lowercase().as_str() {
+                    "yes" | "y" | "true" | "1" => Ok(true),
+                    "no" | "n" | "false" | "0" => Ok(false),
+                    _ => Err(GameError::ParseError(format!("Line {} has {}, which isn't yes or no", line, field))),
+                })
+                .collect::<Result<Vec<bool>, GameError>>()?;
+            items.push(fields[0].to_string());
+            rows.push(row);
+        }
+        if items.len() < 2 || items.len() > MAX_ITEMS {
+            return Err(GameError::InvalidRules(format!("A database must list from 2 to {} items", MAX_ITEMS)));
+        }
+        let having = (0..attributes.len())
+            .map(|attribute| {
+                let mut set = Bitset::new(items.len());
+                for (item, row) in rows.iter().enumerate() {
+                    if row[attribute] {
+                        set.insert(item);
+                    }
+                }
+                set
+            })
+            .collect();
+        Ok(ItemDatabase { items, attributes, having })
+    }
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+    pub fn attributes(&self) -> &[String] {
+        &self.attributes
+    }
+    /// The items with `attribute`.
+    pub fn having(&self, attribute: usize) -> &Bitset {
+        &self.having[attribute]
+    }
+    /// The number of the attribute called `name`, ignoring case.
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.attributes.iter().position(|attribute| attribute.eq_ignore_ascii_case(name.trim()))
+    }
+    /// A game over the items, with up to `max_lies` lies.
+    pub fn game(&self, max_lies: usize) -> GameState {
+        GameState::new(self.items.len() as Number, max_lies)
+    }
+    /// Whether the secret has `attribute`, asked as whether it is one of the items
+    /// that do.
+    pub fn question(&self, attribute: usize) -> Question {
+        Question::subset(self.having[attribute].runs())
+    }
+    /// The attribute `question` asks about, if it asks about one.
+    pub fn attribute(&self, question: &Question) -> Option<usize> {
+        (0..self.attributes.len()).find(|&attribute| self.question(attribute) == *question)
+    }
+    /// The items the secret could still be.
+    pub fn candidates(&self, game: &GameState) -> Bitset {
+        let mut candidates = Bitset::new(self.items.len());
+        for range in game.candidates().ranges() {
+            for item in range.lower..range.higher {
+                candidates.insert(item as usize);
+            }
+        }
+        candidates
+    }
+    /// The item the game has settled on, once it has.
+    pub fn found(&self, game: &GameState) -> Option<&str> {
+        match game.status() {
+            GameResult::Finished(item) => Some(&self.items[item as usize]),
+            _ => None,
+        }
+    }
+    /// The attribute whose worst answer leaves the smallest `value`, among those
+    /// some but not all of the items still possible have, the first of them if
+    /// several tie. None if no attribute tells those items apart.
+    pub fn best_attribute(&self, game: &GameState, value: &ValueFunction) -> Option<usize> {
+        let candidates = self.candidates(game);
+        let live = candidates.count();
+        let mut best: Option<(Count, usize)> = None;
+        for attribute in 0..self.attributes.len() {
+            let split = self.having[attribute].and(&candidates).count();
+            if split == 0 || split == live {
+                continue;
+            }
+            let question = self.question(attribute);
+            let worst = [Response::Yes, Response::No]
+                .iter()
+                .map(|&response| {
+                    let mut next = game.clone();
+                    next.ask(question.clone(), response).expect("Attribute questions are legal");
+                    value.value(&next)
+                })
+                .max()
+                .unwrap_or(0);
+            let better = match best {
+                Some((best, _)) => worst < best,
+                None => true,
+            };
+            if better {
+                best = Some((worst, attribute));
+            }
+        }
+        best.map(|(_, attribute)| attribute)
+    }
+}
+
+impl fmt::Display for ItemDatabase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} items with {} attributes", self.items.len(), self.attributes.len())
+    }
+}
@@ -0,0 +1,175 @@
+//! Value functions score how much work a position leaves the guesser. Opponents
+//! steer towards high values, and guessers towards low ones.
+//!
+//! Each scores ranges by [`GameState::mass`], so in games with a prior a position
+//! is worth the weight left in it rather than the number of numbers, and guessers
+//! aim to finish quickly on average rather than in the worst case.
+//!
+//! Values are worked out in integers, saturating rather than overflowing, so they
+//! stay exact for limits far beyond what f64 can count in ones.
+
+use alloc::format;
+use alloc::string::String;
+use core::cmp::min;
+
+use crate::expression::Expression;
+use crate::float::{log2, powi};
+use crate::game::{GameState, Count, Variant};
+
+/// The number of candidates, counted once per lie hypothesis.
+pub fn simple_value(game: &GameState) -> Count {
+    game.hypotheses().map(|(range, _)| game.mass(range)).fold(0, Count::saturating_add)
+}
+
+/// Each unused lie multiplies a range's weight by roughly the number of questions left.
+pub fn better_value(game: &GameState) -> Count {
+    // Near the end the estimate drops to 1 or below, which would stop unused lies
+    // counting for anything, so it is kept at 2 or more.
+    let candidates = game.hypotheses().map(|(range, _)| game.count(range)).fold(0, Count::saturating_add);
+    let multiplier = (log2(candidates as f64) - 1.0).max(2.0);
+    // Whole parts and fractions are added up separately, so that the total is as
+    // exact as if it were added up in f64 for small games, but doesn't lose the
+    // low digits of enormous ones
+    let (mut whole, mut fractions): (Count, u128) = (0, 0);
+    for (range, lies) in game.hypotheses() {
+        let unused_lies = game.lies_left(lies.len()) as i32;
+        let (term, fraction) = times(game.mass(range), powi(multiplier, unused_lies));
+        whole = whole.saturating_add(term);
+        fractions += fraction as u128;
+    }
+    whole.saturating_add(fractions >> 64)
+}
+
+// `count` times `factor`, which must be at least 1, worked out exactly rather than
+// through f64: the whole part, saturating, and the fraction in units of 2^-64.
+fn times(count: Count, factor: f64) -> (Count, u64) {
+    if count == 0 {
+        return (0, 0);
+    }
+    if !factor.is_finite() {
+        return (Count::MAX, 0);
+    }
+    // factor is mantissa·2^exponent exactly, as it is a normal float
+    let bits = factor.to_bits();
+    let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+    let exponent = ((bits >> 52) & 0x7ff) as i32 - 1075;
+    // count·mantissa is high·2^64 + low
+    let low = (count & u64::MAX as Count) * mantissa as Count;
+    let high = (count >> 64) * mantissa as Count + (low >> 64);
+    let low = low as u64;
+    if exponent >= 0 {
+        let shift = exponent as u32;
+        if high.leading_zeros() < 64 + shift {
+            return (Count::MAX, 0);
+        }
+        ((high << 64 | low as Count) << shift, 0)
+    } else {
+        // Between 1 and 52, as factor is at least 1
+        let shift = exponent.unsigned_abs();
+        if high.leading_zeros() < 64 - shift {
+            return (Count::MAX, 0);
+        }
+        ((high << (64 - shift)) | (low >> shift) as Count, low << (64 - shift))
+    }
+}
+
+/// The number of ways to place up to `max_lies` lies among `questions` answers.
+pub fn lie_placements(questions: usize, max_lies: usize) -> u128 {
+    lie_patterns(questions, max_lies, 1)
+}
+
+/// The same, when each lie may be any of `wrong_answers` answers, as with the two
+/// wrong answers to a three-way question.
+pub fn lie_patterns(questions: usize, max_lies: usize, wrong_answers: u128) -> u128 {
+    let mut patterns: u128 = 0;
+    let mut binomial: u128 = 1;
+    let mut choices: u128 = 1;
+    for lies in 0..=min(max_lies, questions) {
+        patterns = patterns.saturating_add(binomial.saturating_mul(choices));
+        binomial = binomial.saturating_mul((questions - lies) as u128) / (lies as u128 + 1);
+        choices = choices.saturating_mul(wrong_answers);
+    }
+    patterns
+}
+
+/// The fewest questions that could possibly suffice: each of the `upper_limit` numbers,
+/// combined with each way of placing up to `max_lies` lies among q answers, must
+/// lead to a different sequence of answers, and there are only 2^q of those.
+pub fn volume_bound(upper_limit: Count, max_lies: usize) -> usize {
+    let mut questions = 0;
+    while questions < 127 && volume_limit(questions, max_lies) < upper_limit {
+        questions += 1;
+    }
+    questions
+}
+
+/// The other way round: the most numbers `questions` questions could possibly
+/// find the secret among with up to `max_lies` lies, sharing the 2^q sequences of
+/// answers out among each number's placements of its lies.
+pub fn volume_limit(questions: usize, max_lies: usize) -> Count {
+    let sequences = if questions >= 127 { Count::MAX } else { 1 << questions };
+    sequences / lie_placements(questions, max_lies)
+}
+
+/// Berlekamp's weight: with q questions left in a perfectly played game, a number
+/// that can still be explained by a lies counts once for each way those lies could
+/// fall among the q answers, so for one lie V = a·(q+1) + b. In directional games
+/// each way's lies can only fall where the truth is the other answer, which is
+/// taken to be half the answers. Passes tell the guesser nothing, so they don't
+/// count as questions used.
+// No question can make both answers leave more than half the weight, so this is
+// the natural measure of how much work is left.
+pub fn berlekamp_value(game: &GameState) -> Count {
+    let answered = game.history.len() - game.passes();
+    let questions = volume_bound(game.count(game.range()), game.max_lies).saturating_sub(answered);
+    game.hypotheses()
+        .map(|(range, lies)| {
+            let placements = match game.variant() {
+                Variant::Directional => {
+                    let (yeses, nos) = game.lies_left_each_way(lies);
+                    lie_placements(questions / 2, yeses).saturating_mul(lie_placements(questions - questions / 2, nos))
+                }
+                _ => lie_placements(questions, game.lies_left(lies.len())),
+            };
+            game.mass(range).saturating_mul(placements)
+        })
+        .fold(0, Count::saturating_add)
+}
+
+/// A value function: one of those above, or an [`Expression`].
+#[derive(Clone)]
+pub enum ValueFunction {
+    Named(fn(&GameState) -> Count),
+    Expression(Expression),
+}
+
+impl ValueFunction {
+    /// How much work `game` leaves the guesser.
+    pub fn value(&self, game: &GameState) -> Count {
+        match *self {
+            ValueFunction::Named(value) => value(game),
+            ValueFunction::Expression(ref expression) => expression.value(game),
+        }
+    }
+}
+
+/// The command line names of every value function.
+pub const VALUE_FUNCTIONS: [&str; 3] = ["simple", "better", "berlekamp"];
+
+/// Looks up a value function by its command line name, or failing that, reads it
+/// as an expression.
+pub fn value_function(name: &str) -> Result<ValueFunction, String> {
+    match name {
+        "simple" => Ok(ValueFunction::Named(simple_value)),
+        "better" => Ok(ValueFunction::Named(better_value)),
+        "berlekamp" => Ok(ValueFunction::Named(berlekamp_value)),
+        _ => name.parse().map(ValueFunction::Expression).map_err(|err| {
+            // A misspelt name is more likely than an expression of one unknown word
+            if err.starts_with("Unknown statistic") && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                format!("must be simple, better, berlekamp or an expression, not {}", name)
+            } else {
+                format!("can't be {}: {}", name, err)
+            }
+        }),
+    }
+}
@@ -0,0 +1,172 @@
+//! Checking a [`Strategy`], as `export` writes them, by playing it out. Every
+//! secret is tried with every placement of up to `max_lies` lies among the
+//! answers, following the strategy's question in each position it reaches, and
+//! each game must name the secret within the questions the strategy claims for its
+//! starting position.
+//!
+//! This is the slow, obvious way to check, on purpose: it trusts nothing the
+//! [`Solver`](crate::solver::Solver) works out, only the rules of the game, so a
+//! strategy it passes is right whatever wrote it.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::game::{Count, GameResult, GameState, Number, Question, Response};
+use crate::solver::{number_at, Move, Strategy};
+use crate::transcript::format_move;
+
+// How many counterexamples a report keeps, though every one is counted
+const KEPT: usize = 10;
+
+/// One secret and placement of lies that a strategy fails on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counterexample {
+    pub secret: Number,
+    /// The questions the strategy asked and the answers they got, each with
+    /// whether it was a lie.
+    pub moves: Vec<(Question, Response, bool)>,
+    /// What went wrong at the end of them.
+    pub problem: String,
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "secret {}:", self.secret)?;
+        for (question, response, lie) in &self.moves {
+            write!(f, " {}{}", format_move(question, *response), if *lie { "*" } else { "" })?;
+        }
+        write!(f, " {}", self.problem)
+    }
+}
+
+/// What playing out a strategy found.
+pub struct Report {
+    /// The questions the strategy claims to need.
+    pub claimed: usize,
+    /// How many games were played out, one for each secret and placement of lies.
+    pub games: u64,
+    /// How many of them failed.
+    pub failures: u64,
+    /// The first few that failed.
+    pub counterexamples: Vec<Counterexample>,
+}
+
+struct Verifier<'a> {
+    moves: HashMap<&'a [(Count, usize)], &'a Move>,
+    claimed: usize,
+    report: Report,
+}
+
+/// Plays `strategy` out against every secret and placement of lies. Strategies
+/// that don't start from the position of their own game can't be checked.
+pub fn verify(strategy: &Strategy) -> Result<Report, String> {
+    let game = GameState::new(strategy.upper_limit, strategy.max_lies);
+    let claimed = match strategy.moves.first() {
+        Some(start) if start.state == game.canonical() => start.needed,
+        Some(_) => return Err("The strategy doesn't start from the position of its own game".to_string()),
+        // Games with one number or none are over before they start
+        None if strategy.upper_limit <= 1 => 0,
+        None => return Err("The strategy has no questions".to_string()),
+    };
+    let mut verifier = Verifier {
+        moves: strategy.moves.iter().map(|step| (step.state.as_slice(), step)).collect(),
+        claimed,
+        report: Report { claimed, games: 0, failures: 0, counterexamples: vec![] },
+    };
+    for secret in 0..strategy.upper_limit.max(0) {
+        verifier.play(&game, secret, strategy.max_lies, &mut vec![]);
+    }
+    Ok(verifier.report)
+}
+
+impl Verifier<'_> {
+    // Plays on from `game` with `lies` lies left, both answering truthfully and
+    // lying if there are lies to tell, having asked `moves` so far.
+    fn play(&mut self, game: &GameState, secret: Number, lies: usize, moves: &mut Vec<(Question, Response, bool)>) {
+        let problem = match game.status() {
+            GameResult::Finished(number) if number == secret => None,
+            GameResult::Finished(number) => Some(format!("names {} instead", number)),
+            GameResult::Ongoing if moves.len() >= self.claimed => {
+                Some(format!("is still going after the {} questions claimed", self.claimed))
+            }
+            GameResult::Ongoing => match self.moves.get(game.canonical().as_slice()) {
+                Some(step) => {
+                    let question = Question::Less(number_at(game, step.below));
+                    let truth = question.truth(secret);
+                    let lie = if truth == Response::Yes { Response::No } else { Response::Yes };
+                    let answers = if lies > 0 { vec![(truth, false), (lie, true)] } else { vec![(truth, false)] };
+                    for (response, lied) in answers {
+                        let mut next = game.clone();
+                        moves.push((question.clone(), response, lied));
+                        match next.ask(question.clone(), response) {
+                            Ok(()) => self.play(&next, secret, lies - lied as usize, moves),
+                            Err(err) => self.fail(secret, moves, format!("asks a question the game rejects: {}", err)),
+                        }
+                        moves.pop();
+                    }
+                    return;
+                }
+                None => Some("reaches a position the strategy has no question for".to_string()),
+            },
+            GameResult::Impossible => Some("finds the answers contradict each other".to_string()),
+            GameResult::Lost => Some("runs out of questions".to_string()),
+        };
+        match problem {
+            None => self.report.games += 1,
+            Some(problem) => self.fail(secret, moves, problem),
+        }
+    }
+
+    fn fail(&mut self, secret: Number, moves: &[(Question, Response, bool)], problem: String) {
+        self.report.games += 1;
+        self.report.failures += 1;
+        if self.report.counterexamples.len() < KEPT {
+            self.report.counterexamples.push(Counterexample { secret, moves: moves.to_vec(), problem });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::Solver;
+
+    fn strategy(limit: Number, lies: usize) -> Strategy {
+        Solver::new(lies).strategy(&GameState::new(limit, lies))
+    }
+
+    #[test]
+    fn solver_strategies_pass() {
+        for (limit, lies) in [(2, 0), (10, 1), (30, 2)] {
+            let report = verify(&strategy(limit, lies)).unwrap();
+            assert_eq!(report.failures, 0, "{} numbers, {} lies", limit, lies);
+            assert!(report.games > 0);
+        }
+    }
+
+    #[test]
+    fn tampered_strategies_fail() {
+        // Claiming a question fewer than it takes
+        let mut short = strategy(30, 1);
+        short.moves[0].needed -= 1;
+        let report = verify(&short).unwrap();
+        assert!(report.failures > 0);
+        assert!(report.counterexamples[0].problem.contains("still going"));
+
+        // Asking a worse first question
+        let mut worse = strategy(30, 1);
+        worse.moves[0].below = 1;
+        assert!(verify(&worse).unwrap().failures > 0);
+
+        // Losing a position the strategy reaches
+        let mut missing = strategy(30, 1);
+        missing.moves.pop();
+        let report = verify(&missing).unwrap();
+        assert!(report.counterexamples.iter().any(|counterexample| counterexample.problem.contains("no question")));
+
+        // Starting somewhere else
+        let mut moved = strategy(30, 1);
+        moved.upper_limit = 31;
+        assert!(verify(&moved).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! A game played through strings of JSON, for JavaScript to drive once the
+//! library is compiled to WebAssembly. Every argument and result is a string or
+//! a number, and every error a message, so a wasm-bindgen layer need only wrap
+//! each method of [`WebGame`] as it is. Nothing here reads the clock, starts a
+//! thread or touches the filesystem, none of which `wasm32-unknown-unknown` has.
+
+use crate::game::{check_rules, GameResult, GameState, Question, Variant};
+use crate::guesser::Median;
+use crate::json::{possibilities_to_json, response_to_json, Json, ToJson};
+use crate::opponent::{Adversary, NoisyLiar, Opponent, RandomLiar};
+use crate::rng::Rng;
+use crate::solver::Solver;
+use crate::value::{value_function, volume_bound};
+use crate::Guesser;
+
+/// A game against one of the computer's opponents, with the solver for hints.
+pub struct WebGame {
+    game: GameState,
+    opponent: Box<dyn Opponent>,
+    solver: Solver,
+}
+
+impl WebGame {
+    /// Starts a game from options such as `{"limit": 100, "lies": 1}`, which may
+    /// also give the `lower` limit, the `variant`, and the `opponent`: either
+    /// `adversarial`, the default, which answers by the `value` function named,
+    /// or `random`, which picks its secret and lies from `seed`. Noisy games set
+    /// `p`, the chance of each answer being a lie, in place of `lies`.
+    pub fn new(options: &str) -> Result<WebGame, String> {
+        let options = Json::parse(options)?;
+        let limit = options.field("limit")?.as_number()?;
+        let lower = options.get("lower").map_or(Ok(0), Json::as_number)?;
+        let lies = options.get("lies").map_or(Ok(1), Json::as_u64)? as usize;
+        check_rules(lower, limit, lies)?;
+        let variant = match options.get("variant") {
+            Some(variant) => Variant::parse(variant.as_str()?)?,
+            None => Variant::Standard,
+        };
+        let game = match options.get("p") {
+            Some(p) => match p.as_f64()? {
+                p if (0.0..0.5).contains(&p) => GameState::noisy(limit, p),
+                _ => return Err("p must be at least 0 and less than 0.5".to_string()),
+            },
+            // lies is how many go each way for directional lies, as on the command line
+            None if variant == Variant::Directional => GameState::new(limit, 2 * lies).with_variant(variant),
+            None => GameState::new(limit, lies).with_variant(variant),
+        };
+        let game = game.with_lower_limit(lower);
+        let mut rng = Rng::new(options.get("seed").map_or(Ok(0), Json::as_u64)?);
+        let opponent: Box<dyn Opponent> = match options.get("opponent").map_or(Ok("adversarial"), Json::as_str)? {
+            "adversarial" if game.lie_probability().is_none() => {
+                let name = options.get("value").map_or(Ok("better"), Json::as_str)?;
+                let value = value_function(name).map_err(|err| format!("value {}", err))?;
+                Box::new(Adversary { value })
+            }
+            "adversarial" => return Err("Noisy games need the random opponent".to_string()),
+            "random" => {
+                let range = game.range();
+                let secret = game.nth(range, rng.below_u128(game.count(range))).expect("The index is below the count");
+                match game.lie_probability() {
+                    Some(lie_probability) => Box::new(NoisyLiar { secret, lie_probability, rng }),
+                    None => Box::new(RandomLiar { secret, horizon: volume_bound(game.count(range), game.max_lies()), rng }),
+                }
+            }
+            name => return Err(format!("opponent must be adversarial or random, not {}", name)),
+        };
+        let solver = Solver::new(game.max_lies()).with_variant(game.variant());
+        Ok(WebGame { game, opponent, solver })
+    }
+
+    /// Asks the opponent `question`, written as a player would type it, such as
+    /// `37` or `=42`, and replies with the `response`, the surviving
+    /// `possibilities` and the `status`.
+    pub fn ask(&mut self, question: &str) -> Result<String, String> {
+        let game = &mut self.game;
+        let question = match question.trim().parse::<Question>()? {
+            // A number on its own asks whatever a guess asks in this game
+            Question::Less(guess) => game.guess(guess),
+            question => question,
+        };
+        if game.status() != GameResult::Ongoing {
+            return Err("The game is over".to_string());
+        }
+        game.check_question(&question)?;
+        let response = self.opponent.respond(game, &question);
+        game.ask(question.clone(), response)?;
+        Ok(Json::object(vec![
+            ("question", question.to_json()),
+            ("response", response_to_json(&question, response)),
+            ("possibilities", possibilities_to_json(game)),
+            ("status", game.status().to_json()),
+        ])
+        .to_string())
+    }
+
+    /// The best question to ask next, with how many more questions it guarantees
+    /// finishing in, or `null` once the game is over. Noisy games suggest the
+    /// question that splits the probability in half, with no guarantee.
+    pub fn hint(&mut self) -> String {
+        let game = &self.game;
+        if game.status() != GameResult::Ongoing {
+            return Json::Null.to_string();
+        }
+        let (guess, needed) = match game.lie_probability() {
+            Some(_) => (Median.choose(game), Json::Null),
+            None => match self.solver.best_question(game) {
+                Some((guess, needed)) => (guess, Json::Int(needed as i128)),
+                None => return Json::Null.to_string(),
+            },
+        };
+        Json::object(vec![("question", Json::Str(game.guess(guess).to_string())), ("questions_needed", needed)]).to_string()
+    }
+
+    /// What the number could still be, and how the game stands.
+    pub fn possibilities(&self) -> String {
+        Json::object(vec![("possibilities", possibilities_to_json(&self.game)), ("status", self.game.status().to_json())]).to_string()
+    }
+
+    /// The whole game, as the json module writes it.
+    pub fn game(&self) -> String {
+        self.game.to_json().to_string()
+    }
+}
@@ -0,0 +1,203 @@
+//! Just enough of the WebSocket protocol (RFC 6455) for serve to talk to browsers:
+//! the opening handshake, and text messages each way. Pings are answered, and
+//! binary messages are read as text. There are no extensions or subprotocols.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const TEXT: u8 = 0x1;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xA;
+
+fn sha1(message: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+    for block in padded.chunks(64) {
+        let mut words = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            words[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, &word) in words.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let next = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = next;
+        }
+        for (part, added) in state.iter_mut().zip([a, b, c, d, e]) {
+            *part = part.wrapping_add(added);
+        }
+    }
+    let mut digest = [0; 20];
+    for (i, part) in state.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&part.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| bits | u32::from(byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+// The Sec-WebSocket-Accept a server replies with to a client's key.
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key, GUID).as_bytes()))
+}
+
+/// A connection that has finished the handshake.
+pub struct WebSocket {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl WebSocket {
+    /// Reads the client's HTTP upgrade request and agrees to it, or refuses it with
+    /// a 400 if it isn't one.
+    pub fn accept(stream: TcpStream) -> Result<WebSocket, String> {
+        let mut writer = stream.try_clone().map_err(|err| err.to_string())?;
+        let mut reader = BufReader::new(stream);
+        let mut key = None;
+        loop {
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(0) => return Err("The connection closed during the handshake".to_string()),
+                Ok(_) => {}
+                Err(err) => return Err(err.to_string()),
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("Sec-WebSocket-Key") {
+                    key = Some(value.trim().to_string());
+                }
+            }
+        }
+        let key = match key {
+            Some(key) => key,
+            None => {
+                let _ = write!(writer, "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                return Err("Not a WebSocket handshake".to_string());
+            }
+        };
+        write!(
+            writer,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(&key)
+        )
+        .map_err(|err| err.to_string())?;
+        Ok(WebSocket { reader, writer })
+    }
+
+    fn write_frame(&mut self, opcode: u8, payload: &[u8]) -> std::io::Result<()> {
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            length @ 0..=125 => frame.push(length as u8),
+            length @ 126..=0xFFFF => {
+                frame.push(126);
+                frame.extend_from_slice(&(length as u16).to_be_bytes());
+            }
+            length => {
+                frame.push(127);
+                frame.extend_from_slice(&(length as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(payload);
+        self.writer.write_all(&frame)
+    }
+
+    /// Sends one text message.
+    pub fn send(&mut self, text: &str) -> std::io::Result<()> {
+        self.write_frame(TEXT, text.as_bytes())
+    }
+
+    // The opcode and unmasked payload of the next frame.
+    fn read_frame(&mut self) -> std::io::Result<(bool, u8, Vec<u8>)> {
+        let mut header = [0; 2];
+        self.reader.read_exact(&mut header)?;
+        let length = match header[1] & 0x7F {
+            126 => {
+                let mut length = [0; 2];
+                self.reader.read_exact(&mut length)?;
+                u64::from(u16::from_be_bytes(length))
+            }
+            127 => {
+                let mut length = [0; 8];
+                self.reader.read_exact(&mut length)?;
+                u64::from_be_bytes(length)
+            }
+            length => u64::from(length),
+        };
+        // Nothing a player sends needs more than this, so longer is a mistake or worse
+        if length > 1 << 20 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Message too long"));
+        }
+        let mut mask = [0; 4];
+        if header[1] & 0x80 != 0 {
+            self.reader.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0; length as usize];
+        self.reader.read_exact(&mut payload)?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+        Ok((header[0] & 0x80 != 0, header[0] & 0x0F, payload))
+    }
+
+    /// The next whole message, or None once the client has closed the connection
+    /// or it has failed.
+    pub fn receive(&mut self) -> Option<String> {
+        let mut message = vec![];
+        loop {
+            let (last, opcode, payload) = self.read_frame().ok()?;
+            match opcode {
+                CLOSE => {
+                    let _ = self.write_frame(CLOSE, &payload);
+                    return None;
+                }
+                PING => {
+                    self.write_frame(PONG, &payload).ok()?;
+                    continue;
+                }
+                PONG => continue,
+                // Text, binary, or the rest of either
+                _ => message.extend_from_slice(&payload),
+            }
+            if last {
+                return Some(String::from_utf8_lossy(&message).into_owned());
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Guessing a word from a wordlist, asking whether it comes before another word
+//! in dictionary order.
+//!
+//! Once the list is sorted, each word is its place in it, and "is it before
+//! melon?" is "is it less than the place melon has, or would have?", so the game
+//! is an ordinary [`GameState`] over the places, and every opponent and guesser
+//! plays it as it would numbers. Only the questions and answers are put in words.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::game::{GameError, GameResult, GameState, Number, Question, Range};
+
+/// Sorted words with no repeats, as read by [`Wordlist::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wordlist {
+    words: Vec<String>,
+}
+
+impl Wordlist {
+    /// Sorts the words and drops repeats.
+    pub fn new(mut words: Vec<String>) -> Result<Wordlist, GameError> {
+        words.sort_unstable();
+        words.dedup();
+        if words.len() < 2 {
+            return Err(GameError::InvalidRules("A wordlist needs at least two different words".to_string()));
+        }
+        Ok(Wordlist { words })
+    }
+    /// Reads words separated by whitespace, usually one per line, ignoring lines
+    /// starting with #. Words are compared as written, so capitals come first.
+    pub fn parse(text: &str) -> Result<Wordlist, GameError> {
+        let words = text.lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(str::split_whitespace)
+            .map(str::to_string)
+            .collect();
+        Wordlist::new(words)
+    }
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+    /// The word in place `place`, if there is one.
+    pub fn word(&self, place: Number) -> Option<&str> {
+        usize::try_from(place).ok().and_then(|place| self.words.get(place)).map(String::as_str)
+    }
+    /// How many words in the list come before `word`, which need not be in it.
+    pub fn rank(&self, word: &str) -> Number {
+        self.words.partition_point(|listed| listed.as_str() < word) as Number
+    }
+    /// The place of `word` in the list, if it is in it.
+    pub fn find(&self, word: &str) -> Option<Number> {
+        self.words.binary_search_by(|listed| listed.as_str().cmp(word)).ok().map(|place| place as Number)
+    }
+    /// A game over the words, with up to `max_lies` lies.
+    pub fn game(&self, max_lies: usize) -> GameState {
+        GameState::new(self.words.len() as Number, max_lies)
+    }
+    /// Whether the secret comes before `word`.
+    pub fn before(&self, word: &str) -> Question {
+        Question::Less(self.rank(word))
+    }
+    /// `question` in words, as in "before melon", for questions about places.
+    /// Others are described as they would be for numbers.
+    pub fn describe(&self, question: &Question) -> String {
+        match *question {
+            Question::Less(place) => match self.word(place) {
+                Some(word) => format!("before {}", word),
+                None => "before the end".to_string(),
+            },
+            Question::Equal(place) => self.word(place).unwrap_or("nothing").to_string(),
+            ref question => question.describe(),
+        }
+    }
+    /// The words in `range`, by their first and last.
+    pub fn describe_range(&self, range: Range) -> String {
+        match (self.word(range.lower), self.word(range.higher - 1)) {
+            _ if range.is_empty() => "nothing".to_string(),
+            (Some(first), Some(_)) if range.higher - range.lower == 1 => first.to_string(),
+            (Some(first), Some(last)) => format!("{} to {}", first, last),
+            _ => "nothing".to_string(),
+        }
+    }
+    /// The word the game has settled on, once it has.
+    pub fn found(&self, game: &GameState) -> Option<&str> {
+        match game.status() {
+            GameResult::Finished(place) => self.word(place),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Wordlist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} words from {} to {}", self.words.len(), self.words[0], self.words[self.words.len() - 1])
+    }
+}